@@ -0,0 +1,3 @@
+ALTER TABLE devices ADD COLUMN dither TEXT NOT NULL DEFAULT 'threshold';
+ALTER TABLE devices ADD COLUMN dither_threshold INTEGER NOT NULL DEFAULT 127;
+ALTER TABLE devices ADD COLUMN gamma REAL NOT NULL DEFAULT 1.0;
@@ -0,0 +1,8 @@
+CREATE TABLE fonts (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    family     TEXT NOT NULL,
+    filename   TEXT NOT NULL UNIQUE,
+    data       BLOB NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at DATETIME NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,12 @@
+CREATE TABLE assets (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    template_id  INTEGER NOT NULL REFERENCES templates(id),
+    name         TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    data         BLOB NOT NULL,
+    created_at   DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at   DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX assets_template_id ON assets(template_id);
+CREATE UNIQUE INDEX assets_name_template_id ON assets(name, template_id);
@@ -0,0 +1 @@
+ALTER TABLE devices ADD COLUMN keep_last_image_on_error BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+CREATE TABLE source_snapshots (
+    kind TEXT NOT NULL,
+    source_id INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, source_id)
+);
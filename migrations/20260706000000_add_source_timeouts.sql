@@ -0,0 +1,3 @@
+ALTER TABLE prometheus_queries ADD COLUMN timeout_secs INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE range_queries ADD COLUMN timeout_secs INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE http_sources ADD COLUMN timeout_secs INTEGER NOT NULL DEFAULT 5;
@@ -0,0 +1,17 @@
+ALTER TABLE templates ADD COLUMN kind TEXT NOT NULL DEFAULT 'liquid';
+
+CREATE TABLE layout_regions (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    layout_id   INTEGER NOT NULL REFERENCES templates(id),
+    template_id INTEGER NOT NULL REFERENCES templates(id),
+    region      TEXT NOT NULL DEFAULT 'full',
+    x           INTEGER NOT NULL DEFAULT 0,
+    y           INTEGER NOT NULL DEFAULT 0,
+    width       INTEGER NOT NULL DEFAULT 0,
+    height      INTEGER NOT NULL DEFAULT 0,
+    created_at  DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at  DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX layout_regions_layout_id ON layout_regions(layout_id);
+CREATE INDEX layout_regions_template_id ON layout_regions(template_id);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS calendar_sources (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    template_id  INTEGER REFERENCES templates(id),
+    name         TEXT NOT NULL,
+    url          TEXT NOT NULL DEFAULT '',
+    content      TEXT NOT NULL DEFAULT '',
+    days         INTEGER NOT NULL DEFAULT 7,
+    timeout_secs INTEGER NOT NULL DEFAULT 5,
+    created_at   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS calendar_sources_template_id ON calendar_sources(template_id);
+CREATE UNIQUE INDEX IF NOT EXISTS calendar_sources_name_template_id ON calendar_sources(name, template_id);
@@ -0,0 +1,12 @@
+CREATE TABLE template_revisions (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    template_id   INTEGER NOT NULL REFERENCES templates(id),
+    content       TEXT NOT NULL,
+    -- JSON snapshot of the template's data sources and layout regions.
+    sources       TEXT NOT NULL,
+    author        TEXT,
+    restored_from INTEGER,
+    created_at    DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX template_revisions_template_id ON template_revisions(template_id);
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::sync::OnceLock;
 
 use dioxus::prelude::*;
@@ -7,7 +8,10 @@ use sqlx::{
 };
 
 use crate::models::{
-    Device, DeviceLog, DeviceLogEntry, HttpSource, PrometheusQuery, RangeQuery, Template, User,
+    Asset, BUNDLE_VERSION, BundleAsset, CalendarSource, Device, DeviceLog, DeviceLogEntry, Dither,
+    Font, HttpSource, ImportConflict, ImportOptions, ImportReport, LayoutRegion, Partial,
+    PrometheusQuery, RangeQuery, RevisionSources, SourceKind, Template, TemplateBundle,
+    TemplateKind, TemplateRevision, User,
 };
 
 static POOL: OnceLock<SqlitePool> = OnceLock::new();
@@ -42,7 +46,7 @@ pub async fn get_default_template() -> Result<Template, sqlx::error::Error> {
     let conn = get();
 
     match sqlx::query_as(
-        "SELECT id, name, content, updated_at, created_at FROM templates ORDER BY id ASC LIMIT 1",
+        "SELECT id, name, content, kind, updated_at, created_at FROM templates ORDER BY id ASC LIMIT 1",
     )
     .fetch_optional(conn)
     .await
@@ -61,7 +65,7 @@ pub async fn get_default_template() -> Result<Template, sqlx::error::Error> {
             .await;
 
             sqlx::query_as(
-                "SELECT id, name, content, updated_at, created_at FROM templates ORDER BY id ASC LIMIT 1",
+                "SELECT id, name, content, kind, updated_at, created_at FROM templates ORDER BY id ASC LIMIT 1",
             )
             .fetch_one(conn)
             .await
@@ -74,21 +78,25 @@ pub async fn get_default_template() -> Result<Template, sqlx::error::Error> {
 }
 
 pub async fn get_templates() -> Result<Vec<Template>, sqlx::error::Error> {
-    sqlx::query_as("SELECT id, name, content, updated_at, created_at FROM templates ORDER BY name")
-        .fetch_all(get())
-        .await
+    sqlx::query_as(
+        "SELECT id, name, content, kind, updated_at, created_at FROM templates ORDER BY name",
+    )
+    .fetch_all(get())
+    .await
 }
 
 pub async fn get_template_by_id(id: i64) -> Result<Template, sqlx::error::Error> {
-    sqlx::query_as("SELECT id, name, content, updated_at, created_at FROM templates WHERE id = ?")
-        .bind(id)
-        .fetch_one(get())
-        .await
+    sqlx::query_as(
+        "SELECT id, name, content, kind, updated_at, created_at FROM templates WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_one(get())
+    .await
 }
 
 pub async fn get_template_for_device(device_id: i64) -> Result<Template, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT t.id, t.name, t.content, t.updated_at, t.created_at \
+        "SELECT t.id, t.name, t.content, t.kind, t.updated_at, t.created_at \
          FROM templates t \
          JOIN devices d ON d.template_id = t.id \
          WHERE d.id = ?",
@@ -98,14 +106,19 @@ pub async fn get_template_for_device(device_id: i64) -> Result<Template, sqlx::e
     .await
 }
 
-pub async fn create_template(name: &str, content: &str) -> Result<Template, sqlx::error::Error> {
+pub async fn create_template(
+    name: &str,
+    content: &str,
+    kind: TemplateKind,
+) -> Result<Template, sqlx::error::Error> {
     let row: SqliteRow = sqlx::query(
-        "INSERT INTO templates (name, content, updated_at, created_at) \
-         VALUES (?, ?, datetime('now'), datetime('now')) \
+        "INSERT INTO templates (name, content, kind, updated_at, created_at) \
+         VALUES (?, ?, ?, datetime('now'), datetime('now')) \
          RETURNING *",
     )
     .bind(name)
     .bind(content)
+    .bind(kind)
     .fetch_one(get())
     .await?;
 
@@ -117,6 +130,10 @@ pub async fn delete_template(id: i64) -> Result<(), sqlx::error::Error> {
         delete_http_source(http.id.unwrap()).await?;
     }
 
+    for calendar in get_calendar_sources(id).await? {
+        delete_calendar_source(calendar.id.unwrap()).await?;
+    }
+
     for prom in get_prometheus_queries(id).await? {
         delete_prometheus_query(prom.id.unwrap()).await?;
     }
@@ -125,6 +142,23 @@ pub async fn delete_template(id: i64) -> Result<(), sqlx::error::Error> {
         delete_range_query(range.id.unwrap()).await?;
     }
 
+    sqlx::query("DELETE FROM assets WHERE template_id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+
+    sqlx::query("DELETE FROM template_revisions WHERE template_id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+
+    // The layout's own regions, and the regions of any layout showing it.
+    sqlx::query("DELETE FROM layout_regions WHERE layout_id = ? OR template_id = ?")
+        .bind(id)
+        .bind(id)
+        .execute(get())
+        .await?;
+
     sqlx::query("DELETE FROM templates WHERE id = ?")
         .bind(id)
         .execute(get())
@@ -156,6 +190,34 @@ pub async fn update_device_maximum_compatibility(
     Ok(())
 }
 
+pub async fn update_device_keep_last_image_on_error(
+    device_id: i64,
+    keep_last_image_on_error: bool,
+) -> Result<(), sqlx::error::Error> {
+    sqlx::query("UPDATE devices SET keep_last_image_on_error = ? WHERE id = ?")
+        .bind(keep_last_image_on_error)
+        .bind(device_id)
+        .execute(get())
+        .await?;
+    Ok(())
+}
+
+pub async fn update_device_dithering(
+    device_id: i64,
+    dither: Dither,
+    dither_threshold: i64,
+    gamma: f64,
+) -> Result<(), sqlx::error::Error> {
+    sqlx::query("UPDATE devices SET dither = ?, dither_threshold = ?, gamma = ? WHERE id = ?")
+        .bind(dither)
+        .bind(dither_threshold)
+        .bind(gamma)
+        .bind(device_id)
+        .execute(get())
+        .await?;
+    Ok(())
+}
+
 pub async fn get_device_logs(
     device_id: i64,
     limit: i64,
@@ -230,7 +292,7 @@ pub async fn delete_device(device_id: i64) -> Result<(), sqlx::error::Error> {
 
 pub async fn get_device(device_id: i64) -> Result<Device, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT id, access_token, mac_address, model, friendly_id, fw_version, width, height, battery_voltage, rssi, template_id, maximum_compatibility, last_seen_at, created_at \
+        "SELECT id, access_token, mac_address, model, friendly_id, fw_version, width, height, battery_voltage, rssi, template_id, maximum_compatibility, dither, dither_threshold, gamma, keep_last_image_on_error, last_seen_at, created_at \
          FROM devices
          WHERE id = $1
          ORDER BY last_seen_at DESC"
@@ -242,7 +304,7 @@ pub async fn get_device(device_id: i64) -> Result<Device, sqlx::error::Error> {
 
 pub async fn get_devices() -> Result<Vec<Device>, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT id, access_token, mac_address, model, friendly_id, fw_version, width, height, battery_voltage, rssi, template_id, maximum_compatibility, last_seen_at, created_at \
+        "SELECT id, access_token, mac_address, model, friendly_id, fw_version, width, height, battery_voltage, rssi, template_id, maximum_compatibility, dither, dither_threshold, gamma, keep_last_image_on_error, last_seen_at, created_at \
          FROM devices ORDER BY last_seen_at DESC"
     )
         .fetch_all(get())
@@ -329,26 +391,43 @@ pub async fn create_device(
     Device::from_row(&device_id)
 }
 
+/// Updates a Prometheus query. Changing what it fetches drops its snapshot, so
+/// a failed fetch never falls back to the old query's result.
 pub async fn update_prometheus_query(
     id: i64,
     name: &str,
     addr: &str,
     query: &str,
+    timeout_secs: i64,
 ) -> Result<(), sqlx::error::Error> {
+    let changed: Option<bool> = sqlx::query_scalar(
+        "SELECT addr IS NOT ? OR query IS NOT ? FROM prometheus_queries WHERE id = ?",
+    )
+    .bind(addr)
+    .bind(query)
+    .bind(id)
+    .fetch_optional(get())
+    .await?;
     sqlx::query(
-        "UPDATE prometheus_queries SET name = ?, addr = ?, query = ?, updated_at = datetime('now') WHERE id = ?",
+        "UPDATE prometheus_queries SET name = ?, addr = ?, query = ?, timeout_secs = ?, \
+         updated_at = datetime('now') WHERE id = ?",
     )
     .bind(name)
     .bind(addr)
     .bind(query)
+    .bind(timeout_secs)
     .bind(id)
     .execute(get())
     .await?;
+    if changed == Some(true) {
+        delete_source_snapshot(SourceKind::Prometheus, id).await?;
+    }
 
     Ok(())
 }
 
 pub async fn delete_prometheus_query(id: i64) -> Result<(), sqlx::error::Error> {
+    delete_source_snapshot(SourceKind::Prometheus, id).await?;
     sqlx::query("DELETE FROM prometheus_queries WHERE id = ?")
         .bind(id)
         .execute(get())
@@ -362,16 +441,18 @@ pub async fn create_prometheus_query(
     name: &str,
     addr: &str,
     query: &str,
+    timeout_secs: i64,
 ) -> Result<PrometheusQuery, sqlx::error::Error> {
     let r = sqlx::query(
-        "INSERT INTO prometheus_queries (template_id, name, addr, query, created_at, updated_at) \
-         VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))
+        "INSERT INTO prometheus_queries (template_id, name, addr, query, timeout_secs, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, datetime('now'), datetime('now'))
          RETURNING *",
     )
     .bind(template_id)
     .bind(name)
     .bind(addr)
     .bind(query)
+    .bind(timeout_secs)
     .fetch_one(get())
     .await?;
 
@@ -382,7 +463,7 @@ pub async fn get_prometheus_queries(
     template_id: i64,
 ) -> Result<Vec<PrometheusQuery>, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT id, template_id, name, addr, query, created_at, updated_at \
+        "SELECT id, template_id, name, addr, query, timeout_secs, created_at, updated_at \
          FROM prometheus_queries
          WHERE template_id = ?
          ORDER BY name",
@@ -394,7 +475,7 @@ pub async fn get_prometheus_queries(
 
 pub async fn get_range_queries(template_id: i64) -> Result<Vec<RangeQuery>, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT id, template_id, name, addr, query, duration, step, created_at, updated_at \
+        "SELECT id, template_id, name, addr, query, duration, step, timeout_secs, created_at, updated_at \
          FROM range_queries
          WHERE template_id = ?
          ORDER BY name",
@@ -411,10 +492,11 @@ pub async fn create_range_query(
     query: &str,
     duration: &str,
     step: &str,
+    timeout_secs: i64,
 ) -> Result<RangeQuery, sqlx::error::Error> {
     let r = sqlx::query(
-        "INSERT INTO range_queries (template_id, name, addr, query, duration, step, created_at, updated_at) \
-         VALUES (?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+        "INSERT INTO range_queries (template_id, name, addr, query, duration, step, timeout_secs, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
          RETURNING *",
     )
     .bind(template_id)
@@ -423,12 +505,14 @@ pub async fn create_range_query(
     .bind(query)
     .bind(duration)
     .bind(step)
+    .bind(timeout_secs)
     .fetch_one(get())
     .await?;
 
     RangeQuery::from_row(&r)
 }
 
+/// Updates a range query, dropping its snapshot when what it fetches changes.
 pub async fn update_range_query(
     id: i64,
     name: &str,
@@ -436,24 +520,41 @@ pub async fn update_range_query(
     query: &str,
     duration: &str,
     step: &str,
+    timeout_secs: i64,
 ) -> Result<(), sqlx::error::Error> {
+    let changed: Option<bool> = sqlx::query_scalar(
+        "SELECT addr IS NOT ? OR query IS NOT ? OR duration IS NOT ? OR step IS NOT ? \
+         FROM range_queries WHERE id = ?",
+    )
+    .bind(addr)
+    .bind(query)
+    .bind(duration)
+    .bind(step)
+    .bind(id)
+    .fetch_optional(get())
+    .await?;
     sqlx::query(
         "UPDATE range_queries SET name = ?, addr = ?, query = ?, duration = ?, step = ?, \
-         updated_at = datetime('now') WHERE id = ?",
+         timeout_secs = ?, updated_at = datetime('now') WHERE id = ?",
     )
     .bind(name)
     .bind(addr)
     .bind(query)
     .bind(duration)
     .bind(step)
+    .bind(timeout_secs)
     .bind(id)
     .execute(get())
     .await?;
+    if changed == Some(true) {
+        delete_source_snapshot(SourceKind::PrometheusRange, id).await?;
+    }
 
     Ok(())
 }
 
 pub async fn delete_range_query(id: i64) -> Result<(), sqlx::error::Error> {
+    delete_source_snapshot(SourceKind::PrometheusRange, id).await?;
     sqlx::query("DELETE FROM range_queries WHERE id = ?")
         .bind(id)
         .execute(get())
@@ -464,7 +565,7 @@ pub async fn delete_range_query(id: i64) -> Result<(), sqlx::error::Error> {
 
 pub async fn get_http_sources(template_id: i64) -> Result<Vec<HttpSource>, sqlx::error::Error> {
     sqlx::query_as(
-        "SELECT id, template_id, name, url, created_at, updated_at \
+        "SELECT id, template_id, name, url, timeout_secs, created_at, updated_at \
          FROM http_sources
          WHERE template_id = ?
          ORDER BY name",
@@ -478,35 +579,55 @@ pub async fn create_http_source(
     template_id: i64,
     name: &str,
     url: &str,
+    timeout_secs: i64,
 ) -> Result<HttpSource, sqlx::error::Error> {
     let r = sqlx::query(
-        "INSERT INTO http_sources (template_id, name, url, created_at, updated_at) \
-         VALUES (?, ?, ?, datetime('now'), datetime('now'))
+        "INSERT INTO http_sources (template_id, name, url, timeout_secs, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))
          RETURNING *",
     )
     .bind(template_id)
     .bind(name)
     .bind(url)
+    .bind(timeout_secs)
     .fetch_one(get())
     .await?;
 
     HttpSource::from_row(&r)
 }
 
-pub async fn update_http_source(id: i64, name: &str, url: &str) -> Result<(), sqlx::error::Error> {
+/// Updates an HTTP source, dropping its snapshot when its URL changes.
+pub async fn update_http_source(
+    id: i64,
+    name: &str,
+    url: &str,
+    timeout_secs: i64,
+) -> Result<(), sqlx::error::Error> {
+    let changed: Option<bool> =
+        sqlx::query_scalar("SELECT url IS NOT ? FROM http_sources WHERE id = ?")
+            .bind(url)
+            .bind(id)
+            .fetch_optional(get())
+            .await?;
     sqlx::query(
-        "UPDATE http_sources SET name = ?, url = ?, updated_at = datetime('now') WHERE id = ?",
+        "UPDATE http_sources SET name = ?, url = ?, timeout_secs = ?, updated_at = datetime('now') \
+         WHERE id = ?",
     )
     .bind(name)
     .bind(url)
+    .bind(timeout_secs)
     .bind(id)
     .execute(get())
     .await?;
+    if changed == Some(true) {
+        delete_source_snapshot(SourceKind::Http, id).await?;
+    }
 
     Ok(())
 }
 
 pub async fn delete_http_source(id: i64) -> Result<(), sqlx::error::Error> {
+    delete_source_snapshot(SourceKind::Http, id).await?;
     sqlx::query("DELETE FROM http_sources WHERE id = ?")
         .bind(id)
         .execute(get())
@@ -515,6 +636,278 @@ pub async fn delete_http_source(id: i64) -> Result<(), sqlx::error::Error> {
     Ok(())
 }
 
+pub async fn get_calendar_sources(
+    template_id: i64,
+) -> Result<Vec<CalendarSource>, sqlx::error::Error> {
+    sqlx::query_as(
+        "SELECT id, template_id, name, url, length(CAST(content AS BLOB)) AS size, days, timeout_secs, \
+         created_at, updated_at \
+         FROM calendar_sources
+         WHERE template_id = ?
+         ORDER BY name",
+    )
+    .bind(template_id)
+    .fetch_all(get())
+    .await
+}
+
+/// The uploaded iCalendar file of a calendar source; empty for a feed.
+pub async fn get_calendar_content(id: i64) -> Result<String, sqlx::error::Error> {
+    sqlx::query_scalar("SELECT content FROM calendar_sources WHERE id = ?")
+        .bind(id)
+        .fetch_one(get())
+        .await
+}
+
+pub async fn create_calendar_source(
+    template_id: i64,
+    name: &str,
+    url: &str,
+    content: &str,
+    days: i64,
+    timeout_secs: i64,
+) -> Result<CalendarSource, sqlx::error::Error> {
+    let r = sqlx::query(
+        "INSERT INTO calendar_sources \
+         (template_id, name, url, content, days, timeout_secs, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+         RETURNING id, template_id, name, url, length(CAST(content AS BLOB)) AS size, days, timeout_secs, \
+         created_at, updated_at",
+    )
+    .bind(template_id)
+    .bind(name)
+    .bind(url)
+    .bind(content)
+    .bind(days)
+    .bind(timeout_secs)
+    .fetch_one(get())
+    .await?;
+
+    CalendarSource::from_row(&r)
+}
+
+/// Updates a calendar source, replacing its uploaded file only when `content`
+/// is given. Changing its URL, file or days drops its snapshot.
+pub async fn update_calendar_source(
+    id: i64,
+    name: &str,
+    url: &str,
+    content: Option<&str>,
+    days: i64,
+    timeout_secs: i64,
+) -> Result<(), sqlx::error::Error> {
+    let changed: Option<bool> = sqlx::query_scalar(
+        "SELECT url IS NOT ? OR COALESCE(?, content) IS NOT content OR days IS NOT ? \
+         FROM calendar_sources WHERE id = ?",
+    )
+    .bind(url)
+    .bind(content)
+    .bind(days)
+    .bind(id)
+    .fetch_optional(get())
+    .await?;
+    sqlx::query(
+        "UPDATE calendar_sources SET name = ?, url = ?, content = COALESCE(?, content), days = ?, \
+         timeout_secs = ?, updated_at = datetime('now') \
+         WHERE id = ?",
+    )
+    .bind(name)
+    .bind(url)
+    .bind(content)
+    .bind(days)
+    .bind(timeout_secs)
+    .bind(id)
+    .execute(get())
+    .await?;
+    if changed == Some(true) {
+        delete_source_snapshot(SourceKind::Calendar, id).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn delete_calendar_source(id: i64) -> Result<(), sqlx::error::Error> {
+    delete_source_snapshot(SourceKind::Calendar, id).await?;
+    sqlx::query("DELETE FROM calendar_sources WHERE id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+
+    Ok(())
+}
+
+/// Stores the latest successful result of a data source, serialized as JSON,
+/// replacing the previous one.
+pub async fn save_source_snapshot(
+    kind: SourceKind,
+    source_id: i64,
+    value: &str,
+    fetched_at: i64,
+) -> Result<(), sqlx::error::Error> {
+    sqlx::query(
+        "INSERT INTO source_snapshots (kind, source_id, value, fetched_at) VALUES (?, ?, ?, ?) \
+         ON CONFLICT (kind, source_id) DO UPDATE SET value = excluded.value, fetched_at = excluded.fetched_at",
+    )
+    .bind(kind)
+    .bind(source_id)
+    .bind(value)
+    .bind(fetched_at)
+    .execute(get())
+    .await?;
+    Ok(())
+}
+
+/// The last stored result of a data source and when it was fetched (Unix
+/// seconds), if it ever succeeded.
+pub async fn get_source_snapshot(
+    kind: SourceKind,
+    source_id: i64,
+) -> Result<Option<(String, i64)>, sqlx::error::Error> {
+    sqlx::query_as(
+        "SELECT value, fetched_at FROM source_snapshots WHERE kind = ? AND source_id = ?",
+    )
+    .bind(kind)
+    .bind(source_id)
+    .fetch_optional(get())
+    .await
+}
+
+async fn delete_source_snapshot(
+    kind: SourceKind,
+    source_id: i64,
+) -> Result<(), sqlx::error::Error> {
+    sqlx::query("DELETE FROM source_snapshots WHERE kind = ? AND source_id = ?")
+        .bind(kind)
+        .bind(source_id)
+        .execute(get())
+        .await?;
+    Ok(())
+}
+
+pub async fn get_assets(template_id: i64) -> Result<Vec<Asset>, sqlx::error::Error> {
+    sqlx::query_as(
+        "SELECT id, template_id, name, content_type, length(data) AS size, created_at, updated_at \
+         FROM assets
+         WHERE template_id = ?
+         ORDER BY name",
+    )
+    .bind(template_id)
+    .fetch_all(get())
+    .await
+}
+
+/// `(name, content_type, data)` of every asset of a template, for rendering.
+pub async fn get_asset_data(
+    template_id: i64,
+) -> Result<Vec<(String, String, Vec<u8>)>, sqlx::error::Error> {
+    sqlx::query_as("SELECT name, content_type, data FROM assets WHERE template_id = ?")
+        .bind(template_id)
+        .fetch_all(get())
+        .await
+}
+
+/// `(content_type, data)` of a single asset.
+pub async fn get_asset_data_by_id(id: i64) -> Result<(String, Vec<u8>), sqlx::error::Error> {
+    sqlx::query_as("SELECT content_type, data FROM assets WHERE id = ?")
+        .bind(id)
+        .fetch_one(get())
+        .await
+}
+
+pub async fn create_asset(
+    template_id: i64,
+    name: &str,
+    content_type: &str,
+    data: &[u8],
+) -> Result<Asset, sqlx::error::Error> {
+    let r = sqlx::query(
+        "INSERT INTO assets (template_id, name, content_type, data, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))
+         RETURNING id, template_id, name, content_type, length(data) AS size, created_at, updated_at",
+    )
+    .bind(template_id)
+    .bind(name)
+    .bind(content_type)
+    .bind(data)
+    .fetch_one(get())
+    .await?;
+
+    Asset::from_row(&r)
+}
+
+pub async fn delete_asset(id: i64) -> Result<(), sqlx::error::Error> {
+    sqlx::query("DELETE FROM assets WHERE id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_layout_regions(layout_id: i64) -> Result<Vec<LayoutRegion>, sqlx::error::Error> {
+    sqlx::query_as(
+        "SELECT id, layout_id, template_id, region, x, y, width, height, created_at, updated_at \
+         FROM layout_regions WHERE layout_id = ? ORDER BY id",
+    )
+    .bind(layout_id)
+    .fetch_all(get())
+    .await
+}
+
+/// Adds `region`'s placement to the layout `layout_id`.
+pub async fn create_layout_region(
+    layout_id: i64,
+    region: &LayoutRegion,
+) -> Result<LayoutRegion, sqlx::error::Error> {
+    let r = sqlx::query(
+        "INSERT INTO layout_regions (layout_id, template_id, region, x, y, width, height, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+         RETURNING *",
+    )
+    .bind(layout_id)
+    .bind(region.template_id)
+    .bind(region.region)
+    .bind(region.x)
+    .bind(region.y)
+    .bind(region.width)
+    .bind(region.height)
+    .fetch_one(get())
+    .await?;
+
+    LayoutRegion::from_row(&r)
+}
+
+pub async fn update_layout_region(
+    id: i64,
+    region: &LayoutRegion,
+) -> Result<(), sqlx::error::Error> {
+    sqlx::query(
+        "UPDATE layout_regions \
+         SET template_id = ?, region = ?, x = ?, y = ?, width = ?, height = ?, updated_at = datetime('now') \
+         WHERE id = ?",
+    )
+    .bind(region.template_id)
+    .bind(region.region)
+    .bind(region.x)
+    .bind(region.y)
+    .bind(region.width)
+    .bind(region.height)
+    .bind(id)
+    .execute(get())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_layout_region(id: i64) -> Result<(), sqlx::error::Error> {
+    sqlx::query("DELETE FROM layout_regions WHERE id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+
+    Ok(())
+}
+
 pub async fn copy_template(source_id: i64) -> Result<Template, sqlx::error::Error> {
     let source = get_template_by_id(source_id).await?;
 
@@ -523,11 +916,18 @@ pub async fn copy_template(source_id: i64) -> Result<Template, sqlx::error::Erro
         source.name,
         chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
     );
-    let new_template = create_template(&new_name, &source.content).await?;
+    let new_template = create_template(&new_name, &source.content, source.kind).await?;
 
     let prom_queries = get_prometheus_queries(source_id).await?;
     for pq in prom_queries {
-        create_prometheus_query(new_template.id, &pq.name, &pq.addr, &pq.query).await?;
+        create_prometheus_query(
+            new_template.id,
+            &pq.name,
+            &pq.addr,
+            &pq.query,
+            pq.timeout_secs,
+        )
+        .await?;
     }
 
     let range_queries = get_range_queries(source_id).await?;
@@ -539,13 +939,39 @@ pub async fn copy_template(source_id: i64) -> Result<Template, sqlx::error::Erro
             &rq.query,
             &rq.duration,
             &rq.step,
+            rq.timeout_secs,
         )
         .await?;
     }
 
     let http_sources = get_http_sources(source_id).await?;
     for hs in http_sources {
-        create_http_source(new_template.id, &hs.name, &hs.url).await?;
+        create_http_source(new_template.id, &hs.name, &hs.url, hs.timeout_secs).await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO calendar_sources \
+         (template_id, name, url, content, days, timeout_secs, created_at, updated_at) \
+         SELECT ?, name, url, content, days, timeout_secs, datetime('now'), datetime('now') \
+         FROM calendar_sources WHERE template_id = ?",
+    )
+    .bind(new_template.id)
+    .bind(source_id)
+    .execute(get())
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO assets (template_id, name, content_type, data, created_at, updated_at) \
+         SELECT ?, name, content_type, data, datetime('now'), datetime('now') \
+         FROM assets WHERE template_id = ?",
+    )
+    .bind(new_template.id)
+    .bind(source_id)
+    .execute(get())
+    .await?;
+
+    for region in get_layout_regions(source_id).await? {
+        create_layout_region(new_template.id, &region).await?;
     }
 
     Ok(new_template)
@@ -615,29 +1041,585 @@ pub async fn delete_user(id: i64) -> Result<(), sqlx::error::Error> {
     Ok(())
 }
 
-#[cfg(test)]
-pub(crate) mod test_support {
-    use super::*;
-    use tokio::sync::OnceCell;
+pub async fn get_fonts() -> Result<Vec<Font>, sqlx::error::Error> {
+    sqlx::query_as(
+        "SELECT id, family, filename, length(data) AS size, created_at, updated_at \
+         FROM fonts ORDER BY family, filename",
+    )
+    .fetch_all(get())
+    .await
+}
 
-    static INIT: OnceCell<()> = OnceCell::const_new();
+/// Raw data of every uploaded font, for loading into the font database.
+pub async fn get_font_data() -> Result<Vec<Vec<u8>>, sqlx::error::Error> {
+    let rows: Vec<(Vec<u8>,)> = sqlx::query_as("SELECT data FROM fonts ORDER BY id")
+        .fetch_all(get())
+        .await?;
+    Ok(rows.into_iter().map(|(data,)| data).collect())
+}
 
-    /// Initialize a process-wide in-memory SQLite pool with migrations applied,
-    /// stored in the same global `POOL` that `db::get()` reads. Idempotent and
-    /// safe to call from every test; the first caller wins and the rest reuse it.
-    pub async fn init_test_db() {
-        INIT.get_or_init(|| async {
-            let pool = SqlitePoolOptions::new()
-                .max_connections(1)
+pub async fn create_font(
+    family: &str,
+    filename: &str,
+    data: &[u8],
+) -> Result<Font, sqlx::error::Error> {
+    let row: SqliteRow = sqlx::query(
+        "INSERT INTO fonts (family, filename, data, created_at, updated_at) \
+         VALUES (?, ?, ?, datetime('now'), datetime('now')) \
+         RETURNING id, family, filename, length(data) AS size, created_at, updated_at",
+    )
+    .bind(family)
+    .bind(filename)
+    .bind(data)
+    .fetch_one(get())
+    .await?;
+
+    Font::from_row(&row)
+}
+
+pub async fn delete_font(id: i64) -> Result<(), sqlx::error::Error> {
+    sqlx::query("DELETE FROM fonts WHERE id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+    Ok(())
+}
+
+pub async fn get_partials() -> Result<Vec<Partial>, sqlx::error::Error> {
+    sqlx::query_as("SELECT id, name, content, created_at, updated_at FROM partials ORDER BY name")
+        .fetch_all(get())
+        .await
+}
+
+pub async fn create_partial(name: &str, content: &str) -> Result<Partial, sqlx::error::Error> {
+    let row: SqliteRow = sqlx::query(
+        "INSERT INTO partials (name, content, created_at, updated_at) \
+         VALUES (?, ?, datetime('now'), datetime('now')) \
+         RETURNING id, name, content, created_at, updated_at",
+    )
+    .bind(name)
+    .bind(content)
+    .fetch_one(get())
+    .await?;
+
+    Partial::from_row(&row)
+}
+
+pub async fn update_partial(
+    id: i64,
+    name: &str,
+    content: &str,
+) -> Result<Partial, sqlx::error::Error> {
+    let row: SqliteRow = sqlx::query(
+        "UPDATE partials SET name = ?, content = ?, updated_at = datetime('now') WHERE id = ? \
+         RETURNING id, name, content, created_at, updated_at",
+    )
+    .bind(name)
+    .bind(content)
+    .bind(id)
+    .fetch_one(get())
+    .await?;
+
+    Partial::from_row(&row)
+}
+
+pub async fn delete_partial(id: i64) -> Result<(), sqlx::error::Error> {
+    sqlx::query("DELETE FROM partials WHERE id = ?")
+        .bind(id)
+        .execute(get())
+        .await?;
+    Ok(())
+}
+
+/// The template's data sources and layout regions, as a revision keeps them.
+pub async fn get_revision_sources(template_id: i64) -> Result<RevisionSources, sqlx::error::Error> {
+    Ok(RevisionSources {
+        prometheus_queries: get_prometheus_queries(template_id).await?,
+        range_queries: get_range_queries(template_id).await?,
+        http_sources: get_http_sources(template_id).await?,
+        calendar_sources: get_calendar_sources(template_id).await?,
+        regions: get_layout_regions(template_id).await?,
+    })
+}
+
+type RevisionRow = (
+    i64,
+    i64,
+    String,
+    String,
+    Option<String>,
+    Option<i64>,
+    chrono::NaiveDateTime,
+);
+
+const REVISION_COLUMNS: &str =
+    "id, template_id, content, sources, author, restored_from, created_at";
+
+fn revision_from_row(row: RevisionRow) -> Result<TemplateRevision, sqlx::error::Error> {
+    let (id, template_id, content, sources, author, restored_from, created_at) = row;
+    let sources =
+        serde_json::from_str(&sources).map_err(|e| sqlx::error::Error::Decode(Box::new(e)))?;
+    Ok(TemplateRevision {
+        id,
+        template_id,
+        content,
+        sources,
+        author,
+        restored_from,
+        created_at,
+    })
+}
+
+pub async fn create_template_revision(
+    template_id: i64,
+    content: &str,
+    sources: &RevisionSources,
+    author: Option<&str>,
+    restored_from: Option<i64>,
+) -> Result<TemplateRevision, sqlx::error::Error> {
+    let sources =
+        serde_json::to_string(sources).map_err(|e| sqlx::error::Error::Encode(Box::new(e)))?;
+    let row: RevisionRow = sqlx::query_as(&format!(
+        "INSERT INTO template_revisions \
+         (template_id, content, sources, author, restored_from, created_at) \
+         VALUES (?, ?, ?, ?, ?, datetime('now')) \
+         RETURNING {REVISION_COLUMNS}"
+    ))
+    .bind(template_id)
+    .bind(content)
+    .bind(sources)
+    .bind(author)
+    .bind(restored_from)
+    .fetch_one(get())
+    .await?;
+
+    revision_from_row(row)
+}
+
+/// A template's revisions, newest first.
+pub async fn get_template_revisions(
+    template_id: i64,
+) -> Result<Vec<TemplateRevision>, sqlx::error::Error> {
+    let rows: Vec<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {REVISION_COLUMNS} FROM template_revisions WHERE template_id = ? ORDER BY id DESC"
+    ))
+    .bind(template_id)
+    .fetch_all(get())
+    .await?;
+
+    rows.into_iter().map(revision_from_row).collect()
+}
+
+pub async fn get_latest_template_revision(
+    template_id: i64,
+) -> Result<Option<TemplateRevision>, sqlx::error::Error> {
+    let row: Option<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {REVISION_COLUMNS} FROM template_revisions WHERE template_id = ? \
+         ORDER BY id DESC LIMIT 1"
+    ))
+    .bind(template_id)
+    .fetch_optional(get())
+    .await?;
+
+    row.map(revision_from_row).transpose()
+}
+
+pub async fn get_template_revision(id: i64) -> Result<TemplateRevision, sqlx::error::Error> {
+    let row: RevisionRow = sqlx::query_as(&format!(
+        "SELECT {REVISION_COLUMNS} FROM template_revisions WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_one(get())
+    .await?;
+
+    revision_from_row(row)
+}
+
+/// Makes the template's data sources and layout regions match `sources`:
+/// sources still present are updated in place, keeping their stored results
+/// and uploaded calendar files, removed ones are recreated and the rest
+/// deleted. A recreated calendar without a URL gets its file from
+/// `calendar_files`, by source name. Regions showing a template that no
+/// longer exists are skipped. Returns a warning for each calendar recreated
+/// without its file.
+pub async fn restore_revision_sources(
+    template_id: i64,
+    sources: &RevisionSources,
+    calendar_files: &BTreeMap<String, String>,
+) -> Result<Vec<String>, sqlx::error::Error> {
+    let mut warnings = vec![];
+    let current = get_revision_sources(template_id).await?;
+
+    for query in &current.prometheus_queries {
+        if !sources.prometheus_queries.iter().any(|q| q.id == query.id) {
+            delete_prometheus_query(query.id.unwrap()).await?;
+        }
+    }
+    for query in &sources.prometheus_queries {
+        match query
+            .id
+            .filter(|_| current.prometheus_queries.iter().any(|q| q.id == query.id))
+        {
+            Some(id) => {
+                update_prometheus_query(
+                    id,
+                    &query.name,
+                    &query.addr,
+                    &query.query,
+                    query.timeout_secs,
+                )
+                .await?
+            }
+            None => {
+                create_prometheus_query(
+                    template_id,
+                    &query.name,
+                    &query.addr,
+                    &query.query,
+                    query.timeout_secs,
+                )
+                .await?;
+            }
+        }
+    }
+
+    for query in &current.range_queries {
+        if !sources.range_queries.iter().any(|q| q.id == query.id) {
+            delete_range_query(query.id.unwrap()).await?;
+        }
+    }
+    for query in &sources.range_queries {
+        match query
+            .id
+            .filter(|_| current.range_queries.iter().any(|q| q.id == query.id))
+        {
+            Some(id) => {
+                update_range_query(
+                    id,
+                    &query.name,
+                    &query.addr,
+                    &query.query,
+                    &query.duration,
+                    &query.step,
+                    query.timeout_secs,
+                )
+                .await?
+            }
+            None => {
+                create_range_query(
+                    template_id,
+                    &query.name,
+                    &query.addr,
+                    &query.query,
+                    &query.duration,
+                    &query.step,
+                    query.timeout_secs,
+                )
+                .await?;
+            }
+        }
+    }
+
+    for source in &current.http_sources {
+        if !sources.http_sources.iter().any(|s| s.id == source.id) {
+            delete_http_source(source.id.unwrap()).await?;
+        }
+    }
+    for source in &sources.http_sources {
+        match source
+            .id
+            .filter(|_| current.http_sources.iter().any(|s| s.id == source.id))
+        {
+            Some(id) => {
+                update_http_source(id, &source.name, &source.url, source.timeout_secs).await?
+            }
+            None => {
+                create_http_source(template_id, &source.name, &source.url, source.timeout_secs)
+                    .await?;
+            }
+        }
+    }
+
+    for source in &current.calendar_sources {
+        if !sources.calendar_sources.iter().any(|s| s.id == source.id) {
+            delete_calendar_source(source.id.unwrap()).await?;
+        }
+    }
+    for source in &sources.calendar_sources {
+        match source
+            .id
+            .filter(|_| current.calendar_sources.iter().any(|s| s.id == source.id))
+        {
+            Some(id) => {
+                update_calendar_source(
+                    id,
+                    &source.name,
+                    &source.url,
+                    None,
+                    source.days,
+                    source.timeout_secs,
+                )
+                .await?
+            }
+            None => {
+                let content = match calendar_files.get(&source.name) {
+                    Some(content) => content.as_str(),
+                    None if source.url.is_empty() => {
+                        warnings.push(format!(
+                            "The calendar '{}' had an uploaded file that no longer exists; upload it again",
+                            source.name
+                        ));
+                        ""
+                    }
+                    None => "",
+                };
+                create_calendar_source(
+                    template_id,
+                    &source.name,
+                    &source.url,
+                    content,
+                    source.days,
+                    source.timeout_secs,
+                )
+                .await?;
+            }
+        }
+    }
+
+    let templates = get_templates().await?;
+    for region in &current.regions {
+        if !sources.regions.iter().any(|r| r.id == region.id) {
+            delete_layout_region(region.id.unwrap()).await?;
+        }
+    }
+    for region in &sources.regions {
+        if !templates.iter().any(|t| t.id == region.template_id) {
+            continue;
+        }
+        match region
+            .id
+            .filter(|_| current.regions.iter().any(|r| r.id == region.id))
+        {
+            Some(id) => update_layout_region(id, region).await?,
+            None => {
+                create_layout_region(template_id, region).await?;
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Everything needed to recreate a template on another server: what
+/// `copy_template` copies, plus the partials the template uses by name and
+/// the names of the templates in a layout's regions.
+pub async fn export_template(template_id: i64) -> Result<TemplateBundle, sqlx::error::Error> {
+    use base64::Engine;
+
+    let template = get_template_by_id(template_id).await?;
+    let sources = get_revision_sources(template_id).await?;
+
+    let mut calendar_files = BTreeMap::new();
+    for source in &sources.calendar_sources {
+        if source.size > 0 {
+            let content = get_calendar_content(source.id.unwrap()).await?;
+            calendar_files.insert(source.name.clone(), content);
+        }
+    }
+
+    let mut assets: Vec<BundleAsset> = get_asset_data(template_id)
+        .await?
+        .into_iter()
+        .map(|(name, content_type, data)| BundleAsset {
+            name,
+            content_type,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+        })
+        .collect();
+    assets.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let saved = get_partials().await?;
+    let mut pending = crate::device::partials::references(&template.content);
+    let mut partials: Vec<Partial> = vec![];
+    while let Some(name) = pending.pop() {
+        if partials.iter().any(|p| p.name == name) {
+            continue;
+        }
+        if let Some(partial) = saved.iter().find(|p| p.name == name) {
+            pending.extend(crate::device::partials::references(&partial.content));
+            partials.push(partial.clone());
+        }
+    }
+    partials.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let templates = get_templates().await?;
+    let region_templates = sources
+        .regions
+        .iter()
+        .filter_map(|region| {
+            let shown = templates.iter().find(|t| t.id == region.template_id)?;
+            Some((shown.id, shown.name.clone()))
+        })
+        .collect();
+
+    Ok(TemplateBundle {
+        version: BUNDLE_VERSION,
+        name: template.name,
+        kind: template.kind,
+        content: template.content,
+        sources,
+        calendar_files,
+        assets,
+        partials,
+        region_templates,
+    })
+}
+
+/// Creates the template in `bundle` with its data sources, assets and
+/// partials. When its name is taken, the template is imported under a new
+/// name or replaces the existing one, as `options.conflict` says; partials
+/// that differ from saved ones of the same name are kept or overwritten the
+/// same way. Anything not imported as it was is reported as a warning.
+pub async fn import_template(
+    bundle: &TemplateBundle,
+    options: &ImportOptions,
+) -> Result<ImportReport, sqlx::error::Error> {
+    use base64::Engine;
+
+    let replace = options.conflict == ImportConflict::Replace;
+    let mut warnings = vec![];
+    let templates = get_templates().await?;
+
+    let template = match templates.iter().find(|t| t.name == bundle.name) {
+        Some(existing) if replace && existing.kind == bundle.kind => {
+            update_template(existing.id, &existing.name, &bundle.content).await?;
+            for asset in get_assets(existing.id).await? {
+                delete_asset(asset.id).await?;
+            }
+            get_template_by_id(existing.id).await?
+        }
+        existing => {
+            let name = match existing {
+                Some(_) => format!(
+                    "{} (Imported {})",
+                    bundle.name,
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+                ),
+                None => bundle.name.clone(),
+            };
+            if existing.is_some() {
+                warnings.push(format!(
+                    "A template named '{}' exists, so this one was imported as '{name}'",
+                    bundle.name
+                ));
+            }
+            create_template(&name, &bundle.content, bundle.kind).await?
+        }
+    };
+
+    let mut sources = bundle.sources.clone();
+    for query in &mut sources.prometheus_queries {
+        query.id = None;
+        if let Some(addr) = &options.prometheus_addr {
+            query.addr = addr.clone();
+        }
+    }
+    for query in &mut sources.range_queries {
+        query.id = None;
+        if let Some(addr) = &options.prometheus_addr {
+            query.addr = addr.clone();
+        }
+    }
+    for source in &mut sources.http_sources {
+        source.id = None;
+    }
+    for source in &mut sources.calendar_sources {
+        source.id = None;
+    }
+    sources.regions.retain_mut(|region| {
+        region.id = None;
+        let name = bundle.region_templates.get(&region.template_id);
+        let shown = name.and_then(|name| {
+            templates
+                .iter()
+                .find(|t| &t.name == name && t.kind == TemplateKind::Liquid)
+        });
+        match shown {
+            Some(shown) => {
+                region.template_id = shown.id;
+                true
+            }
+            None => {
+                warnings.push(format!(
+                    "Left out a region showing '{}', which isn't a template here",
+                    name.map(String::as_str).unwrap_or("an unknown template")
+                ));
+                false
+            }
+        }
+    });
+    warnings.extend(restore_revision_sources(template.id, &sources, &bundle.calendar_files).await?);
+
+    for asset in &bundle.assets {
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&asset.data)
+            .map_err(|e| sqlx::error::Error::Decode(Box::new(e)))?;
+        create_asset(template.id, &asset.name, &asset.content_type, &data).await?;
+    }
+
+    let saved = get_partials().await?;
+    for partial in &bundle.partials {
+        match saved.iter().find(|p| p.name == partial.name) {
+            None => {
+                create_partial(&partial.name, &partial.content).await?;
+            }
+            Some(existing) if existing.content == partial.content => {}
+            Some(existing) if replace => {
+                update_partial(existing.id.unwrap(), &existing.name, &partial.content).await?;
+                warnings.push(format!(
+                    "Replaced the partial '{}', which other templates may also use",
+                    existing.name
+                ));
+            }
+            Some(existing) => warnings.push(format!(
+                "Kept the existing partial '{}', which differs from the one in the bundle",
+                existing.name
+            )),
+        }
+    }
+
+    Ok(ImportReport { template, warnings })
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use tokio::sync::OnceCell;
+
+    static INIT: OnceCell<()> = OnceCell::const_new();
+
+    /// Initialize a process-wide SQLite pool with migrations applied, stored in
+    /// the same global `POOL` that `db::get()` reads. Idempotent and safe to call
+    /// from every test; the first caller wins and the rest reuse it.
+    ///
+    /// The database is a per-process file in the temp dir rather than
+    /// `sqlite::memory:`: each `#[tokio::test]` runs on its own runtime, and a
+    /// pooled connection dropped with one test's runtime would otherwise take the
+    /// whole in-memory database with it.
+    pub async fn init_test_db() {
+        INIT.get_or_init(|| async {
+            let path = std::env::temp_dir().join(format!("srvr-test-{}.db", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
                 .connect_with(
-                    "sqlite::memory:"
-                        .parse::<SqliteConnectOptions>()
-                        .expect("parse in-memory sqlite url")
+                    SqliteConnectOptions::new()
+                        .filename(&path)
                         .create_if_missing(true)
                         .pragma("foreign_keys", "ON"),
                 )
                 .await
-                .expect("create in-memory test pool");
+                .expect("create test pool");
             sqlx::migrate!()
                 .run(&pool)
                 .await
@@ -651,13 +1633,14 @@ pub(crate) mod test_support {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::Region;
     use test_support::init_test_db;
 
     #[tokio::test]
     async fn test_range_query_crud_round_trip() {
         init_test_db().await;
 
-        let template = create_template("range-crud-tpl", "<svg/>")
+        let template = create_template("range-crud-tpl", "<svg/>", TemplateKind::Liquid)
             .await
             .expect("create template");
 
@@ -668,10 +1651,12 @@ mod tests {
             "rate(cpu[5m])",
             "1h",
             "60s",
+            5,
         )
         .await
         .expect("create range query");
         assert_eq!(created.name, "cpu");
+        assert_eq!(created.timeout_secs, 5);
         assert_eq!(created.duration, "1h");
         assert_eq!(created.step, "60s");
         let id = created.id.expect("created row has id");
@@ -682,14 +1667,23 @@ mod tests {
         assert_eq!(fetched.len(), 1);
         assert_eq!(fetched[0].query, "rate(cpu[5m])");
 
-        update_range_query(id, "cpu2", "http://prom:9090", "rate(cpu[1m])", "30m", "5m")
-            .await
-            .expect("update range query");
+        update_range_query(
+            id,
+            "cpu2",
+            "http://prom:9090",
+            "rate(cpu[1m])",
+            "30m",
+            "5m",
+            2,
+        )
+        .await
+        .expect("update range query");
         let after = get_range_queries(template.id).await.expect("re-get");
         assert_eq!(after[0].name, "cpu2");
         assert_eq!(after[0].duration, "30m");
         assert_eq!(after[0].step, "5m");
         assert_eq!(after[0].query, "rate(cpu[1m])");
+        assert_eq!(after[0].timeout_secs, 2);
 
         delete_range_query(id).await.expect("delete range query");
         let empty = get_range_queries(template.id)
@@ -697,4 +1691,511 @@ mod tests {
             .expect("get after delete");
         assert!(empty.is_empty(), "range query should be gone after delete");
     }
+
+    #[tokio::test]
+    async fn test_changing_what_a_source_fetches_drops_its_snapshot() {
+        init_test_db().await;
+
+        let template = create_template("snapshot-edit-tpl", "<svg/>", TemplateKind::Liquid)
+            .await
+            .expect("create template");
+        let http = create_http_source(template.id, "weather", "http://a/1", 5)
+            .await
+            .expect("create http source");
+        let http_id = http.id.expect("created row has id");
+        save_source_snapshot(SourceKind::Http, http_id, "{}", 1)
+            .await
+            .expect("save snapshot");
+
+        // Renaming or a new timeout keeps the last good result.
+        update_http_source(http_id, "weather2", "http://a/1", 9)
+            .await
+            .expect("update http source");
+        assert!(
+            get_source_snapshot(SourceKind::Http, http_id)
+                .await
+                .expect("get snapshot")
+                .is_some()
+        );
+
+        update_http_source(http_id, "weather2", "http://a/2", 9)
+            .await
+            .expect("update http source");
+        assert!(
+            get_source_snapshot(SourceKind::Http, http_id)
+                .await
+                .expect("get snapshot")
+                .is_none(),
+            "a new URL must not fall back to the old URL's result"
+        );
+
+        let calendar = create_calendar_source(template.id, "cal", "", "BEGIN:VCALENDAR", 7, 5)
+            .await
+            .expect("create calendar source");
+        let calendar_id = calendar.id.expect("created row has id");
+        save_source_snapshot(SourceKind::Calendar, calendar_id, "{}", 1)
+            .await
+            .expect("save snapshot");
+        update_calendar_source(calendar_id, "cal", "", None, 7, 9)
+            .await
+            .expect("update calendar source");
+        assert!(
+            get_source_snapshot(SourceKind::Calendar, calendar_id)
+                .await
+                .expect("get snapshot")
+                .is_some(),
+            "keeping the uploaded file keeps the snapshot"
+        );
+        update_calendar_source(calendar_id, "cal", "", None, 14, 9)
+            .await
+            .expect("update calendar source");
+        assert!(
+            get_source_snapshot(SourceKind::Calendar, calendar_id)
+                .await
+                .expect("get snapshot")
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_device_dithering_round_trip() {
+        init_test_db().await;
+
+        let device = create_device(
+            "dither-token",
+            Some("dither-mac"),
+            Some("og"),
+            "dither-device",
+            None,
+            Some(800),
+            Some(480),
+            None,
+            None,
+        )
+        .await
+        .expect("create device");
+        assert_eq!(
+            device.dither,
+            Dither::Threshold,
+            "new devices default to threshold"
+        );
+        assert_eq!(device.dither_threshold, 127);
+        assert_eq!(device.gamma, 1.0);
+
+        update_device_dithering(device.id, Dither::Atkinson, 100, 1.8)
+            .await
+            .expect("update dithering");
+        let after = get_device(device.id).await.expect("get device");
+        assert_eq!(after.dither, Dither::Atkinson);
+        assert_eq!(after.dither_threshold, 100);
+        assert_eq!(after.gamma, 1.8);
+    }
+
+    #[tokio::test]
+    async fn test_device_keep_last_image_on_error_round_trip() {
+        init_test_db().await;
+
+        let device = create_device(
+            "fallback-token",
+            Some("fallback-mac"),
+            Some("og"),
+            "fallback-device",
+            None,
+            Some(800),
+            Some(480),
+            None,
+            None,
+        )
+        .await
+        .expect("create device");
+        assert!(
+            !device.keep_last_image_on_error,
+            "new devices show the error screen"
+        );
+
+        update_device_keep_last_image_on_error(device.id, true)
+            .await
+            .expect("update setting");
+        let after = get_device(device.id).await.expect("get device");
+        assert!(after.keep_last_image_on_error);
+    }
+
+    #[tokio::test]
+    async fn test_font_crud_round_trip() {
+        init_test_db().await;
+
+        let font = create_font("Crud Sans", "crud-sans.ttf", &[1, 2, 3, 4])
+            .await
+            .expect("create font");
+        assert_eq!(font.family, "Crud Sans");
+        assert_eq!(font.size, 4, "size is reported without loading the data");
+
+        let listed = get_fonts().await.expect("get fonts");
+        assert!(listed.iter().any(|f| f.id == font.id));
+        let data = get_font_data().await.expect("get font data");
+        assert!(data.contains(&vec![1, 2, 3, 4]));
+
+        assert!(
+            create_font("Crud Sans", "crud-sans.ttf", &[5])
+                .await
+                .is_err(),
+            "filenames are unique"
+        );
+
+        delete_font(font.id).await.expect("delete font");
+        let after = get_fonts().await.expect("get fonts after delete");
+        assert!(after.iter().all(|f| f.id != font.id));
+    }
+
+    #[tokio::test]
+    async fn test_partial_crud_round_trip() {
+        init_test_db().await;
+
+        let partial = create_partial("crud-header", "<text>{{ title }}</text>")
+            .await
+            .expect("create partial");
+        assert_eq!(partial.name, "crud-header");
+
+        let updated = update_partial(partial.id.unwrap(), "crud-top", "<text/>")
+            .await
+            .expect("update partial");
+        assert_eq!(updated.name, "crud-top");
+        assert_eq!(updated.content, "<text/>");
+        assert!(
+            create_partial("crud-top", "").await.is_err(),
+            "names are unique"
+        );
+
+        delete_partial(updated.id.unwrap())
+            .await
+            .expect("delete partial");
+        let after = get_partials().await.expect("get partials after delete");
+        assert!(after.iter().all(|p| p.id != updated.id));
+    }
+
+    #[tokio::test]
+    async fn test_layout_regions_are_copied_and_deleted_with_templates() {
+        init_test_db().await;
+
+        let child = create_template("region-child", "<svg/>", TemplateKind::Liquid)
+            .await
+            .expect("create child");
+        let layout = create_template("region-layout", "", TemplateKind::Layout)
+            .await
+            .expect("create layout");
+        assert_eq!(layout.kind, TemplateKind::Layout);
+
+        let mut region = LayoutRegion::new(layout.id, child.id);
+        region.region = Region::TopHalf;
+        let created = create_layout_region(layout.id, &region)
+            .await
+            .expect("create region");
+        region.region = Region::Custom;
+        (region.x, region.y, region.width, region.height) = (10, 20, 300, 200);
+        update_layout_region(created.id.expect("created row has id"), &region)
+            .await
+            .expect("update region");
+        let regions = get_layout_regions(layout.id).await.expect("get regions");
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].region, Region::Custom);
+        assert_eq!(regions[0].rect(800, 480), (10, 20, 300, 200));
+
+        let copy = copy_template(layout.id).await.expect("copy layout");
+        assert_eq!(copy.kind, TemplateKind::Layout);
+        assert_eq!(get_layout_regions(copy.id).await.expect("get").len(), 1);
+
+        delete_template(child.id).await.expect("delete child");
+        assert!(
+            get_layout_regions(layout.id)
+                .await
+                .expect("get regions")
+                .is_empty(),
+            "layouts stop showing a deleted template"
+        );
+        delete_template(layout.id).await.expect("delete layout");
+    }
+
+    #[tokio::test]
+    async fn test_assets_are_copied_and_deleted_with_template() {
+        init_test_db().await;
+
+        let template = create_template("asset-tpl", "<svg/>", TemplateKind::Liquid)
+            .await
+            .expect("create template");
+        let asset = create_asset(template.id, "logo", "image/png", &[1, 2, 3])
+            .await
+            .expect("create asset");
+        assert_eq!(asset.size, 3);
+        assert!(
+            create_asset(template.id, "logo", "image/png", &[4])
+                .await
+                .is_err(),
+            "names are unique per template"
+        );
+
+        let copy = copy_template(template.id).await.expect("copy template");
+        let copied = get_assets(copy.id).await.expect("get copied assets");
+        assert_eq!(copied.len(), 1);
+        assert_eq!(copied[0].name, "logo");
+        assert_ne!(copied[0].id, asset.id);
+        let data = get_asset_data(copy.id).await.expect("get copied data");
+        assert_eq!(data[0].2, vec![1, 2, 3]);
+
+        delete_template(template.id).await.expect("delete template");
+        assert!(
+            get_assets(template.id)
+                .await
+                .expect("get assets")
+                .is_empty()
+        );
+        assert_eq!(
+            get_assets(copy.id)
+                .await
+                .expect("copy keeps its assets")
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revisions_record_and_restore_content_and_sources() {
+        init_test_db().await;
+
+        let template = create_template("revision-tpl", "v1", TemplateKind::Liquid)
+            .await
+            .expect("create template");
+        let kept = create_http_source(template.id, "kept", "http://a/1", 5)
+            .await
+            .expect("create kept source");
+        let removed = create_http_source(template.id, "removed", "http://a/2", 5)
+            .await
+            .expect("create removed source");
+        let sources = get_revision_sources(template.id)
+            .await
+            .expect("get sources");
+        let first = create_template_revision(template.id, "v1", &sources, Some("alice"), None)
+            .await
+            .expect("create revision");
+        assert_eq!(first.sources.http_sources.len(), 2);
+        assert_eq!(first.author.as_deref(), Some("alice"));
+
+        update_template(template.id, "revision-tpl", "v2")
+            .await
+            .expect("update template");
+        update_http_source(kept.id.unwrap(), "kept", "http://b/1", 9)
+            .await
+            .expect("update kept source");
+        delete_http_source(removed.id.unwrap())
+            .await
+            .expect("delete source");
+        create_http_source(template.id, "added", "http://a/3", 5)
+            .await
+            .expect("create added source");
+        let sources = get_revision_sources(template.id)
+            .await
+            .expect("get sources");
+        let second = create_template_revision(template.id, "v2", &sources, None, None)
+            .await
+            .expect("create revision");
+
+        let listed = get_template_revisions(template.id)
+            .await
+            .expect("get revisions");
+        assert_eq!(
+            listed.iter().map(|r| r.id).collect::<Vec<_>>(),
+            [second.id, first.id],
+            "newest first"
+        );
+        assert_eq!(
+            get_latest_template_revision(template.id)
+                .await
+                .expect("get latest"),
+            Some(second)
+        );
+
+        let warnings = restore_revision_sources(template.id, &first.sources, &BTreeMap::new())
+            .await
+            .expect("restore sources");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let restored = get_http_sources(template.id)
+            .await
+            .expect("get restored sources");
+        let mut names: Vec<_> = restored
+            .iter()
+            .map(|s| (s.name.as_str(), s.url.as_str(), s.timeout_secs))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [("kept", "http://a/1", 5), ("removed", "http://a/2", 5)]
+        );
+        assert!(
+            restored.iter().any(|s| s.id == kept.id),
+            "sources still present are updated in place"
+        );
+
+        delete_template(template.id).await.expect("delete template");
+        assert!(
+            get_template_revisions(template.id)
+                .await
+                .expect("get revisions")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restoring_a_deleted_uploaded_calendar_warns_without_its_file() {
+        init_test_db().await;
+
+        let template = create_template("revision-calendar", "", TemplateKind::Liquid)
+            .await
+            .expect("create template");
+        let uploaded = create_calendar_source(template.id, "holidays", "", "BEGIN:VCALENDAR", 7, 5)
+            .await
+            .expect("create calendar");
+        let sources = get_revision_sources(template.id)
+            .await
+            .expect("get sources");
+        delete_calendar_source(uploaded.id.unwrap())
+            .await
+            .expect("delete calendar");
+
+        let warnings = restore_revision_sources(template.id, &sources, &BTreeMap::new())
+            .await
+            .expect("restore sources");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'holidays'"), "{warnings:?}");
+
+        let restored = get_calendar_sources(template.id)
+            .await
+            .expect("get calendars");
+        delete_calendar_source(restored[0].id.unwrap())
+            .await
+            .expect("delete calendar");
+        let files = BTreeMap::from([("holidays".to_string(), "BEGIN:VCALENDAR".to_string())]);
+        let warnings = restore_revision_sources(template.id, &sources, &files)
+            .await
+            .expect("restore sources");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let restored = get_calendar_sources(template.id)
+            .await
+            .expect("get calendars");
+        assert_eq!(
+            get_calendar_content(restored[0].id.unwrap())
+                .await
+                .expect("get content"),
+            "BEGIN:VCALENDAR"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundle_export_and_import_recreate_template() {
+        init_test_db().await;
+
+        let template = create_template(
+            "bundle-tpl",
+            r#"{% render "bundle-header" %}"#,
+            TemplateKind::Liquid,
+        )
+        .await
+        .expect("create template");
+        create_partial("bundle-header", r#"{% include "bundle-logo" %}"#)
+            .await
+            .expect("create header");
+        let logo = create_partial("bundle-logo", "<g/>")
+            .await
+            .expect("create logo");
+        create_partial("bundle-unused", "")
+            .await
+            .expect("create unused partial");
+        create_prometheus_query(template.id, "up", "http://staging:9090", "up", 5)
+            .await
+            .expect("create query");
+        create_range_query(
+            template.id,
+            "load",
+            "http://staging:9090",
+            "load1",
+            "1h",
+            "5m",
+            5,
+        )
+        .await
+        .expect("create range query");
+        create_calendar_source(template.id, "holidays", "", "BEGIN:VCALENDAR", 7, 5)
+            .await
+            .expect("create calendar");
+        create_asset(template.id, "logo", "image/png", &[1, 2, 3])
+            .await
+            .expect("create asset");
+
+        let bundle = export_template(template.id).await.expect("export");
+        assert_eq!(
+            bundle
+                .partials
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            ["bundle-header", "bundle-logo"],
+            "only partials the template uses, directly or not"
+        );
+        assert_eq!(bundle.calendar_files["holidays"], "BEGIN:VCALENDAR");
+        assert_eq!(bundle.assets[0].data, "AQID");
+
+        let options = ImportOptions {
+            conflict: ImportConflict::Rename,
+            prometheus_addr: Some("http://prod:9090".to_string()),
+        };
+        let report = import_template(&bundle, &options).await.expect("import");
+        let copy = report.template;
+        assert_ne!(copy.id, template.id);
+        assert!(copy.name.starts_with("bundle-tpl (Imported "));
+        assert_eq!(report.warnings.len(), 1, "{:?}", report.warnings);
+        let queries = get_prometheus_queries(copy.id).await.expect("get queries");
+        assert_eq!(queries[0].addr, "http://prod:9090");
+        let range_queries = get_range_queries(copy.id).await.expect("get range queries");
+        assert_eq!(range_queries[0].addr, "http://prod:9090");
+        let calendars = get_calendar_sources(copy.id).await.expect("get calendars");
+        assert_eq!(
+            get_calendar_content(calendars[0].id.unwrap())
+                .await
+                .expect("get calendar content"),
+            "BEGIN:VCALENDAR"
+        );
+        assert_eq!(
+            get_asset_data(copy.id).await.expect("get assets"),
+            [("logo".to_string(), "image/png".to_string(), vec![1, 2, 3])]
+        );
+
+        update_partial(logo.id.unwrap(), "bundle-logo", "<g>changed</g>")
+            .await
+            .expect("update logo");
+        let kept = import_template(&bundle, &options).await.expect("import");
+        assert!(
+            kept.warnings
+                .iter()
+                .any(|w| w.contains("Kept the existing partial 'bundle-logo'")),
+            "{:?}",
+            kept.warnings
+        );
+
+        let options = ImportOptions {
+            conflict: ImportConflict::Replace,
+            prometheus_addr: None,
+        };
+        let replaced = import_template(&bundle, &options).await.expect("import");
+        assert_eq!(replaced.template.id, template.id);
+        assert_eq!(
+            get_prometheus_queries(template.id)
+                .await
+                .expect("get queries")
+                .len(),
+            1,
+            "sources are replaced, not added to"
+        );
+        assert_eq!(get_assets(template.id).await.expect("get assets").len(), 1);
+        let partials = get_partials().await.expect("get partials");
+        let logo = partials.iter().find(|p| p.name == "bundle-logo").unwrap();
+        assert_eq!(logo.content, "<g/>");
+    }
 }
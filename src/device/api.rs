@@ -24,10 +24,14 @@ use tracing::{error, info};
 use crate::time::Clock;
 use crate::{
     db::{get_device_id_by_access_token, insert_device_logs},
-    device::{create_device_from_headers, get_and_update_device_from_headers, renderer},
+    device::{
+        create_device_from_headers,
+        error_screen::{RenderFailure, render_error_screen},
+        get_and_update_device_from_headers, renderer,
+    },
     frontend::server_fns::get_render_context,
     hmac::{generate_signature_bytes, validate_signature},
-    models::{DeviceLog, DeviceLogEntry},
+    models::{DeviceLog, DeviceLogEntry, ImageFormat},
     time::RealClock,
 };
 
@@ -73,6 +77,14 @@ fn get_effective_host(headers: &HeaderMap) -> Cow<'_, str> {
     )
 }
 
+fn get_scheme() -> &'static str {
+    if *TLS_ENABLED.get().unwrap_or(&false) {
+        "https"
+    } else {
+        "http"
+    }
+}
+
 async fn connection_close(req: Request, next: Next) -> Response {
     let mut res = next.run(req).await;
     res.headers_mut().insert(
@@ -91,7 +103,8 @@ pub fn router<T: Clone + Send + Sync + 'static>(tls_enabled: bool) -> Router<T>
         .route("/api/display", get(display_handler))
         .route("/api/log", post(log_handler))
         .route("/api/setup", get(setup_handler))
-        .route("/render/screen.bmp", get(render_screen_handler))
+        .route("/render/screen.bmp", get(render_bmp_handler))
+        .route("/render/screen.png", get(render_png_handler))
         .layer(TimeoutLayer::with_status_code(axum::http::StatusCode::REQUEST_TIMEOUT, Duration::from_secs(30)))
         .layer(middleware::from_fn(connection_close));
 
@@ -205,11 +218,7 @@ async fn display_handler(headers: HeaderMap) -> impl IntoResponse {
     // Add timestamp for cache busting and device dimensions
     let real_clock = RealClock;
     let timestamp = real_clock.now_secs();
-    let scheme = if *TLS_ENABLED.get().unwrap_or(&false) {
-        "https"
-    } else {
-        "http"
-    };
+    let scheme = get_scheme();
 
     // Generate HMAC signature for the image URL
     let secret = std::env::var("IMAGE_SIGNATURE_SECRET")
@@ -217,14 +226,15 @@ async fn display_handler(headers: HeaderMap) -> impl IntoResponse {
     let signed_bytes = generate_signature_bytes(&secret, device.id, real_clock.clone());
     let sig_encoded = URL_SAFE_NO_PAD.encode(&signed_bytes);
 
+    let extension = device.image_format().extension();
     let image_url = format!(
-        "{}://{}/render/screen.bmp?device_id={}&t={}&sig={}",
-        scheme, host, device.id, timestamp, sig_encoded
+        "{}://{}/render/screen.{}?device_id={}&t={}&sig={}",
+        scheme, host, extension, device.id, timestamp, sig_encoded
     );
 
     let response = DisplayResponse {
         image_url: Some(image_url),
-        filename: Some(format!("screen_{}.bmp", timestamp)),
+        filename: Some(format!("screen_{}.{}", timestamp, extension)),
         refresh_rate: (60 - Local::now().second()) as u32,
         update_firmware: false,
         maximum_compatibility: device.maximum_compatibility,
@@ -328,11 +338,7 @@ async fn setup_handler(headers: HeaderMap) -> impl IntoResponse {
     // Add timestamp for cache busting and device dimensions
     let real_clock = RealClock;
     let timestamp = real_clock.now_secs();
-    let scheme = if *TLS_ENABLED.get().unwrap_or(&false) {
-        "https"
-    } else {
-        "http"
-    };
+    let scheme = get_scheme();
 
     // Generate HMAC signature for the image URL
     let secret = std::env::var("IMAGE_SIGNATURE_SECRET")
@@ -341,8 +347,13 @@ async fn setup_handler(headers: HeaderMap) -> impl IntoResponse {
     let sig_encoded = URL_SAFE_NO_PAD.encode(&signed_bytes);
 
     let image_url = format!(
-        "{}://{}/render/screen.bmp?device_id={}&t={}&sig={}",
-        scheme, host, device.id, timestamp, sig_encoded
+        "{}://{}/render/screen.{}?device_id={}&t={}&sig={}",
+        scheme,
+        host,
+        device.image_format().extension(),
+        device.id,
+        timestamp,
+        sig_encoded
     );
 
     let response = SetupResponse {
@@ -365,8 +376,21 @@ struct RenderQuery {
     sig: Option<String>,
 }
 
-// GET /render/screen.bmp - Render screen image with HMAC validation
-async fn render_screen_handler(Query(params): Query<RenderQuery>) -> impl IntoResponse {
+// GET /render/screen.bmp - Render screen image as BMP with HMAC validation
+async fn render_bmp_handler(headers: HeaderMap, Query(params): Query<RenderQuery>) -> Response {
+    render_screen_handler(&headers, params, ImageFormat::Bmp).await
+}
+
+// GET /render/screen.png - Render screen image as PNG with HMAC validation
+async fn render_png_handler(headers: HeaderMap, Query(params): Query<RenderQuery>) -> Response {
+    render_screen_handler(&headers, params, ImageFormat::Png).await
+}
+
+async fn render_screen_handler(
+    headers: &HeaderMap,
+    params: RenderQuery,
+    format: ImageFormat,
+) -> Response {
     // Validate HMAC signature
     let timestamp = match params.t {
         Some(t) => t,
@@ -419,15 +443,66 @@ async fn render_screen_handler(Query(params): Query<RenderQuery>) -> impl IntoRe
         }
     };
 
-    match renderer::render_screen(&render_context).await {
-        Ok(image) => (StatusCode::OK, [("Content-Type", "image/bmp")], image).into_response(),
+    let error = match renderer::render_screen(&render_context, format).await {
+        Ok(image) => return image_response(format, std::sync::Arc::unwrap_or_clone(image)),
+        Err(e) => e,
+    };
+    error!(
+        "Error rendering template {} for device {}: {:?}",
+        render_context.template.id, params.device_id, error
+    );
+
+    // Devices keep whatever they last downloaded, so send an image either way
+    // rather than a 500 that leaves a stale screen with no hint of a problem.
+    if render_context.device.keep_last_image_on_error
+        && let Some(image) = renderer::last_image(params.device_id, format)
+    {
+        return image_response(format, std::sync::Arc::unwrap_or_clone(image));
+    }
+
+    let editor_url = format!(
+        "{}://{}/template/{}",
+        get_scheme(),
+        get_effective_host(headers),
+        render_context.template.id
+    );
+    let device = render_context.device.clone();
+    let template_name = render_context.template.name.clone();
+    let message = error.to_string();
+    // Drawing the error screen is a full rasterize and encode, so it runs on
+    // the blocking pool like any other render.
+    let error_screen = tokio::task::spawn_blocking(move || {
+        let failure = RenderFailure {
+            template_name: &template_name,
+            error: &message,
+            editor_url: &editor_url,
+        };
+        render_error_screen(&device, &failure, format)
+    })
+    .await
+    .map_err(renderer::Error::from)
+    .and_then(|screen| screen);
+    match error_screen {
+        Ok(image) => image_response(format, image),
         Err(e) => {
-            error!("Error: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e)).into_response()
+            error!("Error rendering error screen: {:?}", e);
+            match renderer::last_image(params.device_id, format) {
+                Some(image) => image_response(format, std::sync::Arc::unwrap_or_clone(image)),
+                None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
         }
     }
 }
 
+fn image_response(format: ImageFormat, image: Vec<u8>) -> Response {
+    (
+        StatusCode::OK,
+        [("Content-Type", format.content_type())],
+        image,
+    )
+        .into_response()
+}
+
 // GET /api/devices/:id/logs/stream - SSE stream of new logs for a device
 async fn log_stream_handler(
     Path(device_id): Path<i64>,
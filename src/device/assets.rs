@@ -0,0 +1,411 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use base64::Engine;
+use image::{DynamicImage, GrayImage, imageops::FilterType};
+
+use crate::device::dither::{DitherOptions, dither};
+use crate::device::render_limits::render_limits;
+use crate::models::Dither;
+
+pub const SVG_CONTENT_TYPE: &str = "image/svg+xml";
+
+/// Works out what kind of image an upload is. Only formats `usvg` can embed
+/// are accepted.
+pub fn detect_content_type(data: &[u8]) -> Option<&'static str> {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Png) => Some("image/png"),
+        Ok(image::ImageFormat::Jpeg) => Some("image/jpeg"),
+        Ok(_) => None,
+        Err(_) => usvg::Tree::from_data(data, &usvg::Options::default())
+            .ok()
+            .map(|_| SVG_CONTENT_TYPE),
+    }
+}
+
+/// How the `asset` filter prepares an image before embedding it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EmbedOptions {
+    /// Target width in pixels; with only one of width/height the other follows
+    /// the aspect ratio, with both the image is fitted inside the box.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Pre-dither to `levels` grays instead of leaving it to the screen's
+    /// dithering, so photos can use error diffusion while text stays sharp.
+    pub dither: Option<Dither>,
+    pub levels: u8,
+}
+
+impl EmbedOptions {
+    /// These options with width and height no larger than `max` pixels.
+    fn within(self, max: u32) -> Self {
+        Self {
+            width: self.width.map(|w| w.clamp(1, max)),
+            height: self.height.map(|h| h.clamp(1, max)),
+            ..self
+        }
+    }
+}
+
+/// Returns a `data:` URI for an image, scaled and dithered as requested. SVG
+/// images are embedded as-is since they scale losslessly. Neither side is
+/// scaled beyond the render limits' `max_raster_dimension`.
+pub fn data_uri(content_type: &str, data: &[u8], options: &EmbedOptions) -> Result<String, String> {
+    if is_untouched(content_type, options) {
+        return Ok(encode_uri(content_type, data));
+    }
+
+    let max = render_limits().max_raster_dimension;
+    let options = options.within(max);
+    let image = image::load_from_memory(data).map_err(|e| e.to_string())?;
+    let image = resize(image, options.width, options.height, max);
+
+    let encoded = match options.dither {
+        Some(algorithm) => encode_png(&dither_image(&image, algorithm, options.levels).into())?,
+        None => encode_png(&image)?,
+    };
+    Ok(encode_uri("image/png", &encoded))
+}
+
+/// Whether `data_uri` embeds the image as it is.
+fn is_untouched(content_type: &str, options: &EmbedOptions) -> bool {
+    content_type == SVG_CONTENT_TYPE
+        || (options.width.is_none() && options.height.is_none() && options.dither.is_none())
+}
+
+fn encode_uri(content_type: &str, data: &[u8]) -> String {
+    format!(
+        "data:{content_type};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(data)
+    )
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(out.into_inner())
+}
+
+/// Scales to `width` and/or `height`. A side worked out from the aspect
+/// ratio is kept to `max` pixels.
+fn resize(image: DynamicImage, width: Option<u32>, height: Option<u32>, max: u32) -> DynamicImage {
+    let (w, h) = (image.width().max(1), image.height().max(1));
+    let scaled = |to: u32, from: u32, other: u32| {
+        (to as u64 * other as u64 / from as u64).clamp(1, max as u64) as u32
+    };
+    match (width, height) {
+        (Some(width), Some(height)) => image.resize(width, height, FilterType::Lanczos3),
+        (Some(width), None) => image.resize_exact(width, scaled(width, w, h), FilterType::Lanczos3),
+        (None, Some(height)) => {
+            image.resize_exact(scaled(height, h, w), height, FilterType::Lanczos3)
+        }
+        (None, None) => image,
+    }
+}
+
+/// Flattens onto white (transparent areas would otherwise turn black) and
+/// dithers to `levels` evenly spaced grays.
+fn dither_image(image: &DynamicImage, algorithm: Dither, levels: u8) -> GrayImage {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let gray: Vec<f32> = rgba
+        .pixels()
+        .map(|p| {
+            let [r, g, b, a] = p.0;
+            let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            let alpha = a as f32 / 255.0;
+            luma * alpha + 255.0 * (1.0 - alpha)
+        })
+        .collect();
+
+    let levels = levels.clamp(2, 16);
+    let options = DitherOptions {
+        algorithm,
+        ..Default::default()
+    };
+    let step = 255 / (levels - 1);
+    let pixels = dither(&gray, width as usize, height as usize, levels, &options)
+        .into_iter()
+        .map(|level| level * step)
+        .collect();
+    GrayImage::from_raw(width, height, pixels).expect("one level per pixel")
+}
+
+/// One template's images, keyed by asset name.
+#[derive(Clone, Default)]
+pub struct RenderAssets(Arc<HashMap<String, (String, Vec<u8>)>>);
+
+impl RenderAssets {
+    pub async fn load(template_id: i64) -> Result<Self, sqlx::Error> {
+        let assets = crate::db::get_asset_data(template_id)
+            .await?
+            .into_iter()
+            .map(|(name, content_type, data)| (name, (content_type, data)))
+            .collect();
+        Ok(Self(Arc::new(assets)))
+    }
+
+    /// Makes these assets visible to the `asset` filter while `f` runs.
+    /// Liquid rendering is synchronous, so a thread-local is enough to hand
+    /// them to the filter without putting image data in the template globals.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|c| c.replace(Some(self.clone())));
+        let _restore = Restore(previous);
+        f()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<RenderAssets>> = const { RefCell::new(None) };
+}
+
+struct Restore(Option<RenderAssets>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+/// Data URI for the named asset of the template being rendered. Scaled or
+/// dithered images are prepared once and then served from memory.
+pub fn embed(name: &str, options: &EmbedOptions) -> Result<String, String> {
+    CURRENT.with(|current| {
+        let current = current.borrow();
+        let (content_type, data) = current
+            .as_ref()
+            .and_then(|assets| assets.0.get(name))
+            .ok_or_else(|| format!("no asset named {name:?}"))?;
+        if is_untouched(content_type, options) {
+            return data_uri(content_type, data, options);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let key = (
+            hasher.finish(),
+            options.within(render_limits().max_raster_dimension),
+        );
+        if let Some(uri) = prepared_images()
+            .lock()
+            .expect("prepared images lock poisoned")
+            .get(&key)
+        {
+            return Ok(uri.to_string());
+        }
+        let uri = data_uri(content_type, data, options)?;
+        prepared_images()
+            .lock()
+            .expect("prepared images lock poisoned")
+            .insert(key, Arc::from(uri.as_str()));
+        Ok(uri)
+    })
+}
+
+/// Largest total size of the data URIs `embed` keeps.
+const MAX_PREPARED_BYTES: usize = 16 * 1024 * 1024;
+
+type PreparedKey = (u64, EmbedOptions);
+
+/// Data URIs of scaled or dithered images, keyed by a hash of the image data
+/// and the options, dropping the oldest past `MAX_PREPARED_BYTES`.
+#[derive(Default)]
+struct PreparedImages {
+    uris: HashMap<PreparedKey, Arc<str>>,
+    order: VecDeque<PreparedKey>,
+    bytes: usize,
+}
+
+impl PreparedImages {
+    fn get(&self, key: &PreparedKey) -> Option<Arc<str>> {
+        self.uris.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: PreparedKey, uri: Arc<str>) {
+        if uri.len() > MAX_PREPARED_BYTES || self.uris.contains_key(&key) {
+            return;
+        }
+        self.bytes += uri.len();
+        self.uris.insert(key, uri);
+        self.order.push_back(key);
+        while self.bytes > MAX_PREPARED_BYTES {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(uri) = self.uris.remove(&oldest) {
+                self.bytes -= uri.len();
+            }
+        }
+    }
+}
+
+fn prepared_images() -> &'static Mutex<PreparedImages> {
+    static PREPARED: OnceLock<Mutex<PreparedImages>> = OnceLock::new();
+    PREPARED.get_or_init(Default::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x2 PNG: left half black, right half white.
+    fn png() -> Vec<u8> {
+        let image = GrayImage::from_fn(4, 2, |x, _| image::Luma([if x < 2 { 0 } else { 255 }]));
+        encode_png(&image.into()).unwrap()
+    }
+
+    fn decode(uri: &str) -> DynamicImage {
+        let data = uri
+            .strip_prefix("data:image/png;base64,")
+            .expect("PNG data URI");
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .unwrap();
+        image::load_from_memory(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_content_type_is_sniffed_from_data() {
+        assert_eq!(detect_content_type(&png()), Some("image/png"));
+        assert_eq!(
+            detect_content_type(
+                br#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"/>"#
+            ),
+            Some(SVG_CONTENT_TYPE)
+        );
+        assert_eq!(detect_content_type(b"GIF89a not really"), None);
+        assert_eq!(detect_content_type(b"plain text"), None);
+    }
+
+    #[test]
+    fn test_unchanged_image_is_embedded_as_is() {
+        let data = png();
+        let uri = data_uri("image/png", &data, &EmbedOptions::default()).unwrap();
+        assert_eq!(uri, encode_uri("image/png", &data));
+    }
+
+    #[test]
+    fn test_width_keeps_aspect_ratio() {
+        let options = EmbedOptions {
+            width: Some(8),
+            ..Default::default()
+        };
+        let image = decode(&data_uri("image/png", &png(), &options).unwrap());
+        assert_eq!((image.width(), image.height()), (8, 4));
+    }
+
+    #[test]
+    fn test_sizes_are_capped() {
+        let wide = DynamicImage::from(GrayImage::new(4, 2));
+        let image = resize(wide, Some(100), None, 16);
+        assert_eq!((image.width(), image.height()), (100, 16));
+
+        let options = EmbedOptions {
+            width: Some(100_000),
+            height: Some(0),
+            ..Default::default()
+        };
+        let capped = options.within(16);
+        assert_eq!((capped.width, capped.height), (Some(16), Some(1)));
+    }
+
+    #[test]
+    fn test_predithered_image_uses_only_panel_grays() {
+        let options = EmbedOptions {
+            width: Some(16),
+            dither: Some(Dither::FloydSteinberg),
+            levels: 2,
+            ..Default::default()
+        };
+        let image = decode(&data_uri("image/png", &png(), &options).unwrap()).to_luma8();
+        assert!(image.pixels().all(|p| p.0[0] == 0 || p.0[0] == 255));
+    }
+
+    #[test]
+    fn test_embed_only_sees_assets_in_scope() {
+        let assets = RenderAssets(Arc::new(HashMap::from([(
+            "logo".to_string(),
+            ("image/png".to_string(), png()),
+        )])));
+        let options = EmbedOptions::default();
+
+        assert!(
+            embed("logo", &options).is_err(),
+            "no assets outside a render"
+        );
+        assets.scope(|| {
+            assert!(
+                embed("logo", &options)
+                    .unwrap()
+                    .starts_with("data:image/png;base64,")
+            );
+            assert!(embed("missing", &options).is_err());
+        });
+        assert!(
+            embed("logo", &options).is_err(),
+            "scope is restored afterwards"
+        );
+    }
+
+    #[test]
+    fn test_prepared_images_are_reused() {
+        let assets = RenderAssets(Arc::new(HashMap::from([(
+            "prepared".to_string(),
+            ("image/png".to_string(), png()),
+        )])));
+        let options = EmbedOptions {
+            width: Some(6),
+            dither: Some(Dither::Atkinson),
+            levels: 2,
+            ..Default::default()
+        };
+        let uri = assets.scope(|| embed("prepared", &options)).unwrap();
+
+        let mut hasher = DefaultHasher::new();
+        png().hash(&mut hasher);
+        let cached = prepared_images()
+            .lock()
+            .unwrap()
+            .get(&(hasher.finish(), options));
+        assert_eq!(cached.as_deref(), Some(uri.as_str()));
+    }
+
+    #[test]
+    fn test_prepared_images_drop_the_oldest_past_the_size_cap() {
+        let mut prepared = PreparedImages::default();
+        let uri: Arc<str> = Arc::from("x".repeat(MAX_PREPARED_BYTES / 2));
+        for hash in 0..3 {
+            prepared.insert((hash, EmbedOptions::default()), uri.clone());
+        }
+        assert!(prepared.get(&(0, EmbedOptions::default())).is_none());
+        assert!(prepared.get(&(2, EmbedOptions::default())).is_some());
+        assert_eq!(prepared.bytes, MAX_PREPARED_BYTES);
+    }
+
+    #[test]
+    fn test_asset_filter_renders_data_uri_in_template() {
+        let assets = RenderAssets(Arc::new(HashMap::from([(
+            "logo".to_string(),
+            ("image/png".to_string(), png()),
+        )])));
+        let template = crate::models::server::template_with(
+            r#"<image href="{{ "logo" | asset: width: 8, dither: "bayer" }}"/>"#,
+        );
+
+        let svg = assets
+            .scope(|| template.render(liquid::object!({})))
+            .expect("render");
+        assert!(svg.starts_with(r#"<image href="data:image/png;base64,"#));
+
+        let err = template
+            .render(liquid::object!({}))
+            .expect_err("asset is unknown outside the scope");
+        assert!(err.to_string().contains("no asset named"), "{err}");
+    }
+}
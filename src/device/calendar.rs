@@ -0,0 +1,1226 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use chrono::offset::LocalResult;
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use liquid::Object;
+use liquid::model::ValueView;
+
+use crate::device::charts::num;
+use crate::device::text_layout::{self, TextStyle, escape_xml};
+
+// ─── Parsing ──────────────────────────────────────────────────────────────────
+
+/// Time zone an event time is written in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Zone {
+    Utc,
+    Named(Tz),
+    /// No zone, or one we don't know: read in the render timezone.
+    Floating,
+}
+
+/// A `DTSTART`-style value: a whole day or a wall time in a zone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EventTime {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime, Zone),
+}
+
+impl EventTime {
+    fn naive(&self) -> NaiveDateTime {
+        match self {
+            EventTime::Date(date) => date.and_time(NaiveTime::MIN),
+            EventTime::DateTime(naive, _) => *naive,
+        }
+    }
+
+    /// The same kind of time at another wall time.
+    fn with_naive(&self, naive: NaiveDateTime) -> Self {
+        match self {
+            EventTime::Date(_) => EventTime::Date(naive.date()),
+            EventTime::DateTime(_, zone) => EventTime::DateTime(naive, *zone),
+        }
+    }
+
+    /// The instant, reading dates and floating times in `tz`.
+    fn instant(&self, tz: &Tz) -> DateTime<Tz> {
+        match self {
+            EventTime::Date(date) => local(tz, date.and_time(NaiveTime::MIN)),
+            EventTime::DateTime(naive, Zone::Utc) => Utc.from_utc_datetime(naive).with_timezone(tz),
+            EventTime::DateTime(naive, Zone::Named(zone)) => local(zone, *naive).with_timezone(tz),
+            EventTime::DateTime(naive, Zone::Floating) => local(tz, *naive),
+        }
+    }
+
+    /// Whether two times name the same start; a date matches any time that
+    /// day.
+    fn matches(&self, other: &EventTime, tz: &Tz) -> bool {
+        match (self, other) {
+            (EventTime::Date(_), _) | (_, EventTime::Date(_)) => {
+                self.naive().date() == other.naive().date()
+            }
+            _ => self.instant(tz) == other.instant(tz),
+        }
+    }
+}
+
+/// `naive` as a wall time in `tz`. Times skipped by a DST change move forward
+/// an hour; repeated ones take the first.
+fn local(tz: &Tz, naive: NaiveDateTime) -> DateTime<Tz> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time,
+        LocalResult::None => local(tz, naive + Duration::hours(1)),
+    }
+}
+
+/// Parameters of a property, with upper-cased names, as in
+/// `DTSTART;TZID=Europe/Berlin`.
+type Params = Vec<(String, String)>;
+
+/// A `VEVENT`, with its recurrence rule unexpanded.
+#[derive(Clone, Debug, Default)]
+pub struct Event {
+    uid: String,
+    summary: String,
+    location: String,
+    description: String,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+    duration: Option<Duration>,
+    rrule: Option<String>,
+    rdates: Vec<EventTime>,
+    exdates: Vec<EventTime>,
+    /// Set on an override of one instance of a recurring event.
+    recurrence_id: Option<EventTime>,
+    cancelled: bool,
+}
+
+impl Event {
+    fn set(&mut self, name: &str, params: &[(String, String)], value: &str) {
+        let times = || value.split(',').filter_map(|v| parse_time(params, v));
+        match name {
+            "UID" => self.uid = value.to_string(),
+            "SUMMARY" => self.summary = unescape(value),
+            "LOCATION" => self.location = unescape(value),
+            "DESCRIPTION" => self.description = unescape(value),
+            "DTSTART" => self.start = parse_time(params, value),
+            "DTEND" => self.end = parse_time(params, value),
+            "DURATION" => self.duration = parse_duration(value),
+            "RRULE" => self.rrule = Some(value.to_string()),
+            "RDATE" => self.rdates.extend(times()),
+            "EXDATE" => self.exdates.extend(times()),
+            "RECURRENCE-ID" => self.recurrence_id = parse_time(params, value),
+            "STATUS" => self.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+}
+
+/// Reads the events of an iCalendar file. Properties that can't be read are
+/// ignored rather than failing the whole calendar, and events without a start
+/// are dropped.
+pub fn parse(ics: &str) -> Result<Vec<Event>, String> {
+    // Unfold continuation lines, which start with a space or tab.
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+            }
+        } else if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    let starts_calendar = lines.first().is_some_and(|line| {
+        line.trim_start_matches('\u{feff}')
+            .eq_ignore_ascii_case("BEGIN:VCALENDAR")
+    });
+    if !starts_calendar {
+        return Err("not an iCalendar file (expected BEGIN:VCALENDAR)".to_string());
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    // Depth of components nested in the current event, such as alarms.
+    let mut nested = 0usize;
+    for line in &lines {
+        let Some((name, params, value)) = split_property(line) else {
+            continue;
+        };
+        let component = value.to_ascii_uppercase();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if component == "VEVENT" => current = Some(Event::default()),
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if component == "VEVENT" => {
+                if let Some(event) = current.take().filter(|e| e.start.is_some()) {
+                    events.push(event);
+                }
+            }
+            (_, Some(event)) if nested == 0 => event.set(&name, &params, value),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Splits a content line into its upper-cased name, parameters and value.
+fn split_property(line: &str) -> Option<(String, Params, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        (c == ':' && !quoted).then_some(i)
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+    Some((name, params, value))
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+fn parse_time(params: &[(String, String)], value: &str) -> Option<EventTime> {
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    let value = value.trim();
+    if param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(EventTime::Date);
+    }
+    let (value, zone) = match value.strip_suffix(['Z', 'z']) {
+        Some(value) => (value, Zone::Utc),
+        None => {
+            let zone = param("TZID")
+                .and_then(|tzid| tzid.trim_start_matches('/').parse().ok())
+                .map_or(Zone::Floating, Zone::Named);
+            (value, zone)
+        }
+    };
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .map(|naive| EventTime::DateTime(naive, zone))
+}
+
+/// Longest event drawn, about ten years. Longer durations in a feed are
+/// ignored and longer spans cut short, so date arithmetic can't overflow.
+const MAX_EVENT_DAYS: i64 = 3_660;
+
+/// Reads an RFC 5545 duration such as `PT1H30M`, `P1D` or `-P1W`. `None` if
+/// it's malformed or longer than `MAX_EVENT_DAYS`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut rest = value.strip_prefix('P')?.chars();
+    let (mut total, mut number, mut time) = (Duration::zero(), 0i64, false);
+    for c in rest.by_ref() {
+        let part = match c {
+            '0'..='9' => {
+                number = number
+                    .checked_mul(10)?
+                    .checked_add(c.to_digit(10)? as i64)?;
+                continue;
+            }
+            'T' => {
+                time = true;
+                continue;
+            }
+            'W' => Duration::try_weeks(std::mem::take(&mut number)),
+            'D' => Duration::try_days(std::mem::take(&mut number)),
+            'H' if time => Duration::try_hours(std::mem::take(&mut number)),
+            'M' if time => Duration::try_minutes(std::mem::take(&mut number)),
+            'S' if time => Duration::try_seconds(std::mem::take(&mut number)),
+            _ => return None,
+        };
+        total = total.checked_add(&part?)?;
+    }
+    (total <= Duration::days(MAX_EVENT_DAYS)).then_some(total * sign)
+}
+
+// ─── Recurrence ───────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The supported part of an `RRULE`.
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    freq: Freq,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<EventTime>,
+    /// Weekdays, with an ordinal within the month or year (`2TU`, `-1FR`).
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+/// Periods expanded past the start of the window before giving up, so a
+/// rule that never matches can't loop forever.
+const MAX_PERIODS: u32 = 10_000;
+
+impl Rule {
+    /// `None` for frequencies below a day, which are drawn as a single event.
+    fn parse(rule: &str) -> Option<Self> {
+        let mut parsed = Rule {
+            freq: Freq::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: vec![],
+            by_month_day: vec![],
+            by_month: vec![],
+        };
+        let mut freq = None;
+        for part in rule.split(';') {
+            let Some((key, value)) = part.split_once('=') else {
+                continue;
+            };
+            let list = || value.split(',').map(str::trim);
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => parsed.interval = value.parse().unwrap_or(1).max(1),
+                "COUNT" => parsed.count = value.parse().ok(),
+                "UNTIL" => parsed.until = parse_time(&[], value),
+                "BYDAY" => parsed.by_day = list().filter_map(parse_by_day).collect(),
+                "BYMONTHDAY" => {
+                    parsed.by_month_day = list().filter_map(|v| v.parse().ok()).collect()
+                }
+                "BYMONTH" => parsed.by_month = list().filter_map(|v| v.parse().ok()).collect(),
+                _ => {}
+            }
+        }
+        parsed.freq = freq?;
+        Some(parsed)
+    }
+
+    /// Starts from `start` up to `until`, in order.
+    fn expand(&self, start: EventTime, from: DateTime<Tz>, until: DateTime<Tz>) -> Vec<EventTime> {
+        let tz = from.timezone();
+        let first = start.naive();
+        let (first_date, time) = (first.date(), first.time());
+        let rule_end = self.until.map(|u| u.instant(&tz));
+
+        // Without a count, periods before the window can be skipped.
+        let skip = match self.count {
+            Some(_) => 0,
+            None => {
+                (self.periods_between(first_date, from.date_naive()) / self.interval as i64 - 1)
+                    .max(0) as u32
+            }
+        };
+
+        let mut starts = Vec::new();
+        let mut count = 0;
+        for period in skip..skip.saturating_add(MAX_PERIODS) {
+            let n = period.saturating_mul(self.interval);
+            let Some(period_start) = self.period_start(first_date, n) else {
+                break;
+            };
+            if local(&tz, period_start.and_time(NaiveTime::MIN)) > until {
+                break;
+            }
+            for date in self.dates_in_period(first_date, period_start) {
+                if date < first_date {
+                    continue;
+                }
+                let occurrence = start.with_naive(date.and_time(time));
+                let instant = occurrence.instant(&tz);
+                if rule_end.is_some_and(|end| instant > end) || instant > until {
+                    return starts;
+                }
+                count += 1;
+                if self.count.is_some_and(|max| count > max) {
+                    return starts;
+                }
+                starts.push(occurrence);
+            }
+        }
+        starts
+    }
+
+    /// Whole periods from `first` to `date`.
+    fn periods_between(&self, first: NaiveDate, date: NaiveDate) -> i64 {
+        match self.freq {
+            Freq::Daily => (date - first).num_days(),
+            Freq::Weekly => (date - first).num_days() / 7,
+            Freq::Monthly => {
+                (date.year() as i64 * 12 + date.month0() as i64)
+                    - (first.year() as i64 * 12 + first.month0() as i64)
+            }
+            Freq::Yearly => (date.year() - first.year()) as i64,
+        }
+    }
+
+    /// First day of the `n`th period after the one holding `first`: the day
+    /// itself, its week from Monday, its month or its year.
+    fn period_start(&self, first: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self.freq {
+            Freq::Daily => first.checked_add_days(Days::new(n as u64)),
+            Freq::Weekly => first
+                .checked_sub_days(Days::new(first.weekday().num_days_from_monday() as u64))?
+                .checked_add_days(Days::new(n as u64 * 7)),
+            Freq::Monthly => first.with_day(1)?.checked_add_months(Months::new(n)),
+            Freq::Yearly => NaiveDate::from_ymd_opt(first.year() + n as i32, 1, 1),
+        }
+    }
+
+    fn dates_in_period(&self, first: NaiveDate, period: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = match self.freq {
+            Freq::Daily => vec![period],
+            Freq::Weekly => {
+                let weekdays = match self.by_day.is_empty() {
+                    true => vec![first.weekday()],
+                    false => self.by_day.iter().map(|(_, day)| *day).collect(),
+                };
+                weekdays
+                    .iter()
+                    .filter_map(|day| {
+                        period.checked_add_days(Days::new(day.num_days_from_monday() as u64))
+                    })
+                    .collect()
+            }
+            Freq::Monthly => self.days_in_month(period, first.day()),
+            Freq::Yearly => {
+                let months = match self.by_month.is_empty() {
+                    true => vec![first.month()],
+                    false => self.by_month.clone(),
+                };
+                months
+                    .iter()
+                    .filter_map(|&month| NaiveDate::from_ymd_opt(period.year(), month, 1))
+                    .flat_map(|month| self.days_in_month(month, first.day()))
+                    .collect()
+            }
+        };
+        // BYxxx parts that don't pick the days limit them instead.
+        dates.retain(|date| {
+            (self.by_month.is_empty() || self.by_month.contains(&date.month()))
+                && (self.freq != Freq::Daily
+                    || self.by_day.is_empty()
+                    || self.by_day.iter().any(|(_, day)| *day == date.weekday()))
+        });
+        dates.sort();
+        dates.dedup();
+        dates
+    }
+
+    /// Days of the month starting `month` picked by BYMONTHDAY or BYDAY, or
+    /// `default_day` when the rule has neither.
+    fn days_in_month(&self, month: NaiveDate, default_day: u32) -> Vec<NaiveDate> {
+        let Some(next) = month.checked_add_months(Months::new(1)) else {
+            return vec![];
+        };
+        let last = next.pred_opt().map_or(28, |d| d.day()) as i32;
+        let day = |d: i32| {
+            let d = if d < 0 { last + 1 + d } else { d };
+            (1..=last)
+                .contains(&d)
+                .then(|| month.with_day(d as u32))
+                .flatten()
+        };
+
+        if !self.by_month_day.is_empty() {
+            return self.by_month_day.iter().filter_map(|&d| day(d)).collect();
+        }
+        if self.by_day.is_empty() {
+            return day(default_day as i32).into_iter().collect();
+        }
+        self.by_day
+            .iter()
+            .flat_map(|&(ordinal, weekday)| {
+                let matching: Vec<NaiveDate> = (1..=last)
+                    .filter_map(day)
+                    .filter(|date| date.weekday() == weekday)
+                    .collect();
+                match ordinal {
+                    None => matching,
+                    Some(n) if n > 0 => matching.get(n as usize - 1).copied().into_iter().collect(),
+                    Some(n) => matching
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .and_then(|i| matching.get(i).copied())
+                        .into_iter()
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_by_day(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let (ordinal, day) = value.split_at(split);
+    let day = match day.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(n.parse().ok()?),
+    };
+    Some((ordinal, day))
+}
+
+// ─── Occurrences ──────────────────────────────────────────────────────────────
+
+/// One occurrence of an event, in the render timezone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occurrence {
+    pub title: String,
+    pub location: String,
+    pub description: String,
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    pub all_day: bool,
+}
+
+impl Occurrence {
+    /// The event object templates see in `calendar.<name>.events`. Times are
+    /// in a form the `date` filter accepts.
+    pub fn to_object(&self, now: DateTime<Tz>) -> Object {
+        const FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+        // An event ending at midnight doesn't cover the day it ends on.
+        let last_day = (self.end - Duration::seconds(1))
+            .date_naive()
+            .max(self.start.date_naive());
+        liquid::object!({
+            "title": self.title,
+            "location": self.location,
+            "description": self.description,
+            "start": self.start.format(FORMAT).to_string(),
+            "end": self.end.format(FORMAT).to_string(),
+            "date": self.start.format("%Y-%m-%d").to_string(),
+            "end_date": last_day.format("%Y-%m-%d").to_string(),
+            "start_time": self.start.format("%H:%M").to_string(),
+            "end_time": self.end.format("%H:%M").to_string(),
+            "all_day": self.all_day,
+            "ongoing": self.start <= now && now < self.end,
+            "ended": self.end <= now,
+        })
+    }
+}
+
+/// Occurrences of `events` overlapping `from`..`to`, sorted by start.
+/// Recurring events are expanded in the zone they were written in, so a 9:00
+/// meeting stays at 9:00 across DST changes, then shown in `from`'s timezone.
+pub fn occurrences(events: &[Event], from: DateTime<Tz>, to: DateTime<Tz>) -> Vec<Occurrence> {
+    let tz = from.timezone();
+    // Instances replaced or cancelled by an override, by UID.
+    let overridden: Vec<(&str, EventTime)> = events
+        .iter()
+        .filter_map(|e| Some((e.uid.as_str(), e.recurrence_id?)))
+        .collect();
+
+    let mut occurrences = Vec::new();
+    for event in events.iter().filter(|e| !e.cancelled) {
+        let Some(start) = event.start else {
+            continue;
+        };
+        let starts = match (
+            &event.recurrence_id,
+            event.rrule.as_deref().and_then(Rule::parse),
+        ) {
+            (None, Some(rule)) => {
+                // Reach back far enough to catch occurrences still running.
+                let reach = (length(event, &tz) + Duration::days(1)).max(Duration::days(1));
+                rule.expand(start, from.checked_sub_signed(reach).unwrap_or(from), to)
+            }
+            _ => vec![start],
+        };
+        let mut starts: Vec<EventTime> = starts
+            .into_iter()
+            .chain(event.rdates.iter().copied())
+            .filter(|s| !event.exdates.iter().any(|x| x.matches(s, &tz)))
+            .collect();
+        if event.recurrence_id.is_none() {
+            starts.retain(|s| {
+                !overridden
+                    .iter()
+                    .any(|(uid, original)| *uid == event.uid && original.matches(s, &tz))
+            });
+        }
+
+        let all_day = matches!(start, EventTime::Date(_));
+        for start in starts {
+            let (start, end) = if all_day {
+                let days = length(event, &tz).num_days().max(1) as u64;
+                let date = start.naive().date();
+                let end = date.checked_add_days(Days::new(days)).unwrap_or(date);
+                (
+                    local(&tz, date.and_time(NaiveTime::MIN)),
+                    local(&tz, end.and_time(NaiveTime::MIN)),
+                )
+            } else {
+                let start = start.instant(&tz);
+                let Some(end) = start.checked_add_signed(length(event, &tz)) else {
+                    continue;
+                };
+                (start, end)
+            };
+            let overlaps = if start == end {
+                from <= start && start < to
+            } else {
+                start < to && end > from
+            };
+            if overlaps {
+                occurrences.push(Occurrence {
+                    title: event.summary.clone(),
+                    location: event.location.clone(),
+                    description: event.description.clone(),
+                    start,
+                    end,
+                    all_day,
+                });
+            }
+        }
+    }
+    occurrences.sort_by(|a, b| (a.start, &a.title).cmp(&(b.start, &b.title)));
+    occurrences
+}
+
+/// Occurrences from the start of `now`'s day through the next `days` days.
+pub fn upcoming(events: &[Event], now: DateTime<Tz>, days: u64) -> Vec<Occurrence> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let end = today.checked_add_days(Days::new(days)).unwrap_or(today);
+    occurrences(
+        events,
+        local(&tz, today.and_time(NaiveTime::MIN)),
+        local(&tz, end.and_time(NaiveTime::MIN)),
+    )
+}
+
+/// How long each occurrence of `event` lasts: to `DTEND`, for `DURATION`, or
+/// a day for dates and no time for date-times given neither. At most
+/// `MAX_EVENT_DAYS`.
+fn length(event: &Event, tz: &Tz) -> Duration {
+    let Some(start) = event.start else {
+        return Duration::zero();
+    };
+    let length = match (start, event.end, event.duration) {
+        (EventTime::Date(start), Some(EventTime::Date(end)), _) => end - start,
+        (_, Some(end), _) => end.instant(tz) - start.instant(tz),
+        (_, None, Some(duration)) => duration,
+        (EventTime::Date(_), None, None) => Duration::days(1),
+        (EventTime::DateTime(..), None, None) => Duration::zero(),
+    };
+    length.clamp(Duration::zero(), Duration::days(MAX_EVENT_DAYS))
+}
+
+// ─── Drawing ──────────────────────────────────────────────────────────────────
+
+/// The parts of a `calendar.<name>.events` entry the calendar filters draw.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarEntry {
+    pub date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_time: String,
+    pub all_day: bool,
+    pub ended: bool,
+    pub title: String,
+}
+
+impl CalendarEntry {
+    /// Reads an event object, or `None` if it has no valid `date`.
+    pub fn from_value(value: &dyn ValueView) -> Option<Self> {
+        let event = value.as_object()?;
+        let text = |key: &str| {
+            event
+                .get(key)
+                .map(|v| v.to_kstr().to_string())
+                .unwrap_or_default()
+        };
+        let flag = |key: &str| {
+            event
+                .get(key)
+                .and_then(|v| v.as_scalar()?.to_bool())
+                .unwrap_or(false)
+        };
+        let date = parse_date(&text("date"))?;
+        Some(Self {
+            date,
+            end_date: parse_date(&text("end_date")).unwrap_or(date).max(date),
+            start_time: text("start_time"),
+            all_day: flag("all_day"),
+            ended: flag("ended"),
+            title: text("title"),
+        })
+    }
+}
+
+/// Reads the date at the start of `text`, as in `2026-10-18` or
+/// `2026-10-18 09:00:00 +0200`.
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgendaOptions {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    /// Lines stop before passing this height.
+    pub height: Option<f64>,
+    pub limit: Option<usize>,
+    /// Include events that have already ended.
+    pub include_ended: bool,
+    /// Group events under a heading for each day.
+    pub day_headings: bool,
+    /// Text shown when there are no events.
+    pub empty: String,
+    pub style: TextStyle,
+}
+
+/// Draws `entries` as lines of `<text>`: start time (or "All day") and title,
+/// grouped under "Today", "Tomorrow" or the date. Events that started on an
+/// earlier day are listed under today. Titles are cut to fit the width.
+pub fn agenda_svg(entries: &[CalendarEntry], today: NaiveDate, options: &AgendaOptions) -> String {
+    let size = options.style.size as f64;
+    let line_height = (size * 1.4).round();
+    let family = escape_xml(&options.style.family);
+    let text = |x: f64, y: f64, weight: u16, content: &str| {
+        format!(
+            r#"<text x="{}" y="{}" font-family="{family}" font-size="{}" font-weight="{weight}" fill="black">{}</text>"#,
+            num(x),
+            num(y),
+            num(size),
+            escape_xml(content)
+        )
+    };
+
+    let shown: Vec<&CalendarEntry> = entries
+        .iter()
+        .filter(|e| options.include_ended || !e.ended)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    let mut svg = format!(
+        r#"<g transform="translate({} {})">"#,
+        num(options.x),
+        num(options.y)
+    );
+    if shown.is_empty() {
+        svg.push_str(&text(0.0, size, 400, &options.empty));
+        svg.push_str("</g>");
+        return svg;
+    }
+
+    let body = text_layout::with_measure_or_estimate(&options.style, |measure| {
+        let time_width = measure("00:00").max(measure("All day")) as f64 + size * 0.75;
+        let title_width = (options.width - time_width).max(0.0) as f32;
+        let fits = |bottom: f64| options.height.is_none_or(|height| bottom <= height);
+
+        let mut body = String::new();
+        let mut bottom = 0.0;
+        let mut heading = None;
+        for entry in &shown {
+            let day = entry.date.max(today);
+            if options.day_headings && heading != Some(day) {
+                // Keep a heading with at least one event under it.
+                if !fits(bottom + line_height * 2.0) {
+                    break;
+                }
+                heading = Some(day);
+                let label = match (day - today).num_days() {
+                    0 => "Today".to_string(),
+                    1 => "Tomorrow".to_string(),
+                    _ => day.format("%a %-d %b").to_string(),
+                };
+                body.push_str(&text(0.0, bottom + size, 700, &label));
+                bottom += line_height;
+            }
+            if !fits(bottom + line_height) {
+                break;
+            }
+            let time = match entry.all_day {
+                true => "All day",
+                false => entry.start_time.as_str(),
+            };
+            let title = text_layout::truncate(&entry.title, title_width, "…", measure);
+            body.push_str(&text(0.0, bottom + size, 400, time));
+            body.push_str(&text(time_width.round(), bottom + size, 400, &title));
+            bottom += line_height;
+        }
+        body
+    });
+    svg.push_str(&body);
+    svg.push_str("</g>");
+    svg
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthOptions {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub week_start: Weekday,
+    /// Show the month and year above the grid.
+    pub title: bool,
+    pub font_family: String,
+    /// Size of the day numbers; fitted to the cells when not given.
+    pub font_size: Option<f64>,
+}
+
+/// Draws the month holding `month` as a grid of weeks. `today` is drawn white
+/// on black and days in `marked` get a bar under their number.
+pub fn month_svg(
+    month: NaiveDate,
+    today: Option<NaiveDate>,
+    marked: &HashSet<NaiveDate>,
+    options: &MonthOptions,
+) -> String {
+    let first = month.with_day(1).unwrap_or(month);
+    let days = first
+        .checked_add_months(Months::new(1))
+        .map_or(30, |next| (next - first).num_days()) as u32;
+    let lead = (7 + first.weekday().num_days_from_monday()
+        - options.week_start.num_days_from_monday())
+        % 7;
+    let weeks = (lead + days).div_ceil(7);
+    let rows = weeks + 1 + u32::from(options.title);
+
+    let cell_width = (options.width / 7.0).floor().max(1.0);
+    let cell_height = (options.height / rows as f64).floor().max(1.0);
+    let size = options
+        .font_size
+        .unwrap_or_else(|| (cell_height * 0.5).min(cell_width * 0.45).round().max(6.0));
+    let family = escape_xml(&options.font_family);
+    let text = |x: f64, row: u32, weight: u16, fill: &str, content: &str| {
+        format!(
+            r#"<text x="{}" y="{}" text-anchor="middle" font-family="{family}" font-size="{}" font-weight="{weight}" fill="{fill}">{}</text>"#,
+            num(x),
+            num((row as f64 * cell_height + cell_height / 2.0 + size * 0.35).round()),
+            num(size),
+            escape_xml(content)
+        )
+    };
+
+    let mut svg = format!(
+        r#"<g transform="translate({} {})">"#,
+        num(options.x),
+        num(options.y)
+    );
+    let mut row = 0;
+    if options.title {
+        svg.push_str(&text(
+            cell_width * 3.5,
+            row,
+            700,
+            "black",
+            &first.format("%B %Y").to_string(),
+        ));
+        row += 1;
+    }
+    let mut weekday = options.week_start;
+    for column in 0..7 {
+        let name = weekday.to_string();
+        svg.push_str(&text(
+            cell_width * (column as f64 + 0.5),
+            row,
+            700,
+            "black",
+            &name[..2],
+        ));
+        weekday = weekday.succ();
+    }
+    row += 1;
+
+    let bar_height = (size / 8.0).round().max(1.0);
+    for day in 0..days {
+        let Some(date) = first.checked_add_days(Days::new(day as u64)) else {
+            break;
+        };
+        let (column, week) = ((lead + day) % 7, (lead + day) / 7);
+        let (left, top) = (
+            column as f64 * cell_width,
+            (row + week) as f64 * cell_height,
+        );
+        let center = left + cell_width / 2.0;
+        let fill = if today == Some(date) {
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black" shape-rendering="crispEdges"/>"#,
+                num(left + 1.0),
+                num(top + 1.0),
+                num(cell_width - 2.0),
+                num(cell_height - 2.0)
+            );
+            "white"
+        } else {
+            "black"
+        };
+        svg.push_str(&text(center, row + week, 400, fill, &(day + 1).to_string()));
+        if marked.contains(&date) {
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{bar}" fill="{fill}" shape-rendering="crispEdges"/>"#,
+                num((center - size * 0.4).round()),
+                num((top + cell_height / 2.0 + size * 0.35).round() + bar_height + 1.0),
+                num((size * 0.8).round()),
+                bar = num(bar_height),
+            );
+        }
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tz() -> Tz {
+        "Europe/Berlin".parse().unwrap()
+    }
+
+    fn at(date: &str) -> DateTime<Tz> {
+        local(
+            &tz(),
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+        )
+    }
+
+    fn calendar(events: &str) -> Vec<Event> {
+        parse(&format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n"
+        ))
+        .unwrap()
+    }
+
+    fn titles_and_starts(occurrences: &[Occurrence]) -> Vec<(String, String)> {
+        occurrences
+            .iter()
+            .map(|o| {
+                (
+                    o.title.clone(),
+                    o.start.format("%a %Y-%m-%d %H:%M").to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_reads_folded_escaped_and_zoned_properties() {
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\n",
+            "UID:1\r\n",
+            "SUMMARY:Planning\\, part\r\n",
+            " 2\r\n",
+            "LOCATION;LANGUAGE=en:\"Room: A\"\r\n",
+            "DTSTART;TZID=America/New_York:20261019T090000\r\n",
+            "DURATION:PT1H30M\r\n",
+            "BEGIN:VALARM\r\n",
+            "SUMMARY:not the event\r\n",
+            "END:VALARM\r\n",
+            "END:VEVENT\r\n",
+            "BEGIN:VEVENT\r\n",
+            "SUMMARY:no start\r\n",
+            "END:VEVENT\r\n",
+        ));
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.summary, "Planning, part2");
+        assert_eq!(event.location, "\"Room: A\"");
+        assert_eq!(event.duration, Some(Duration::minutes(90)));
+
+        let found = occurrences(&events, at("2026-10-19 00:00"), at("2026-10-20 00:00"));
+        // 9:00 in New York is 15:00 in Berlin.
+        assert_eq!(found[0].start, at("2026-10-19 15:00"));
+        assert_eq!(found[0].end, at("2026-10-19 16:30"));
+
+        assert!(parse("<html>").is_err());
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("P1W"), Some(Duration::weeks(1)));
+        assert_eq!(
+            parse_duration("P1DT2H3M4S"),
+            Some(Duration::seconds(86_400 + 7_384))
+        );
+        assert_eq!(parse_duration("-PT15M"), Some(Duration::minutes(-15)));
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn test_hostile_durations_are_ignored() {
+        assert_eq!(parse_duration("P20000000W"), None);
+        assert_eq!(parse_duration("P99999999999999W"), None);
+        assert_eq!(parse_duration("PT99999999999999999999S"), None);
+        assert_eq!(parse_duration("P3660D"), Some(Duration::days(3_660)));
+
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\nUID:long\r\nSUMMARY:Long\r\n",
+            "DTSTART:20261019T090000Z\r\nDURATION:P20000000W\r\nRRULE:FREQ=DAILY\r\n",
+            "END:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:far\r\nSUMMARY:Far\r\n",
+            "DTSTART:20261019T090000Z\r\nDTEND:99991231T000000Z\r\nRRULE:FREQ=YEARLY\r\n",
+            "END:VEVENT\r\n",
+        ));
+        let found = occurrences(&events, at("2026-10-19 00:00"), at("2026-10-20 00:00"));
+        assert_eq!(found.len(), 2);
+        let long = found.iter().find(|o| o.title == "Long").expect("long");
+        assert_eq!(long.end, long.start, "unreadable duration");
+        let far = found.iter().find(|o| o.title == "Far").expect("far");
+        assert_eq!(far.end - far.start, Duration::days(MAX_EVENT_DAYS));
+    }
+
+    #[test]
+    fn test_weekly_rule_keeps_wall_time_across_dst() {
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\nUID:standup\r\nSUMMARY:Standup\r\n",
+            "DTSTART;TZID=Europe/Berlin:20261019T091500\r\nDTEND;TZID=Europe/Berlin:20261019T093000\r\n",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\nEXDATE;TZID=Europe/Berlin:20261028T091500\r\n",
+            "END:VEVENT\r\n",
+        ));
+        let found = occurrences(&events, at("2026-10-20 00:00"), at("2026-11-03 00:00"));
+        assert_eq!(
+            titles_and_starts(&found),
+            [
+                ("Standup".into(), "Wed 2026-10-21 09:15".into()),
+                ("Standup".into(), "Mon 2026-10-26 09:15".into()),
+                ("Standup".into(), "Mon 2026-11-02 09:15".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_monthly_and_yearly_rules() {
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Review\r\nDTSTART:20260101T100000Z\r\n",
+            "RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=11\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:b\r\nSUMMARY:Rent\r\nDTSTART;VALUE=DATE:20260131\r\n",
+            "RRULE:FREQ=MONTHLY\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:c\r\nSUMMARY:Birthday\r\nDTSTART;VALUE=DATE:20001030\r\n",
+            "RRULE:FREQ=YEARLY\r\nEND:VEVENT\r\n",
+        ));
+        let found = occurrences(&events, at("2026-10-01 00:00"), at("2026-12-01 00:00"));
+        assert_eq!(
+            titles_and_starts(&found),
+            [
+                ("Birthday".into(), "Fri 2026-10-30 00:00".into()),
+                // The last Friday, at 10:00 UTC; the 11th and last is in November.
+                ("Review".into(), "Fri 2026-10-30 11:00".into()),
+                ("Rent".into(), "Sat 2026-10-31 00:00".into()),
+                ("Review".into(), "Fri 2026-11-27 11:00".into()),
+            ],
+            "November has no 31st"
+        );
+        assert!(
+            found
+                .iter()
+                .filter(|o| o.title != "Review")
+                .all(|o| o.all_day)
+        );
+    }
+
+    #[test]
+    fn test_overrides_and_cancellations() {
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\nUID:sync\r\nSUMMARY:Sync\r\nDTSTART:20261019T080000Z\r\n",
+            "DURATION:PT30M\r\nRRULE:FREQ=DAILY;UNTIL=20261023T080000Z\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:sync\r\nRECURRENCE-ID:20261020T080000Z\r\nSUMMARY:Sync (moved)\r\n",
+            "DTSTART:20261020T130000Z\r\nDURATION:PT30M\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:sync\r\nRECURRENCE-ID:20261021T080000Z\r\nSTATUS:CANCELLED\r\n",
+            "DTSTART:20261021T080000Z\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:gone\r\nSUMMARY:Gone\r\nSTATUS:CANCELLED\r\nDTSTART:20261020T090000Z\r\n",
+            "END:VEVENT\r\n",
+        ));
+        let found = occurrences(&events, at("2026-10-19 00:00"), at("2026-10-31 00:00"));
+        assert_eq!(
+            titles_and_starts(&found),
+            [
+                ("Sync".into(), "Mon 2026-10-19 10:00".into()),
+                ("Sync (moved)".into(), "Tue 2026-10-20 15:00".into()),
+                ("Sync".into(), "Thu 2026-10-22 10:00".into()),
+                ("Sync".into(), "Fri 2026-10-23 10:00".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_object_and_multi_day_events() {
+        let events = calendar(concat!(
+            "BEGIN:VEVENT\r\nUID:trip\r\nSUMMARY:Trip\r\nDTSTART;VALUE=DATE:20261017\r\n",
+            "DTEND;VALUE=DATE:20261020\r\nEND:VEVENT\r\n",
+        ));
+        let found = occurrences(&events, at("2026-10-18 00:00"), at("2026-10-19 00:00"));
+        let object = found[0].to_object(at("2026-10-18 12:00"));
+        assert_eq!(object.get("date").unwrap().to_kstr(), "2026-10-17");
+        assert_eq!(object.get("end_date").unwrap().to_kstr(), "2026-10-19");
+        assert_eq!(
+            object.get("start").unwrap().to_kstr(),
+            "2026-10-17 00:00:00 +0200"
+        );
+        assert_eq!(object.get("ongoing").unwrap().to_kstr(), "true");
+        assert_eq!(object.get("ended").unwrap().to_kstr(), "false");
+    }
+
+    fn entry(date: &str, time: &str, title: &str) -> CalendarEntry {
+        let date = parse_date(date).unwrap();
+        CalendarEntry {
+            date,
+            end_date: date,
+            start_time: time.to_string(),
+            all_day: time.is_empty(),
+            ended: false,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_agenda_groups_by_day_and_stops_at_height() {
+        let options = AgendaOptions {
+            x: 0.0,
+            y: 0.0,
+            width: 300.0,
+            height: None,
+            limit: None,
+            include_ended: false,
+            day_headings: true,
+            empty: "No events".to_string(),
+            style: TextStyle {
+                family: "no-such-font".to_string(),
+                size: 10.0,
+                weight: 400,
+            },
+        };
+        let entries = [
+            entry("2026-10-17", "", "Trip"),
+            entry("2026-10-18", "09:00", "Standup"),
+            entry("2026-10-19", "10:30", "Planning"),
+            entry("2026-10-21", "12:00", "Lunch"),
+        ];
+        let today = parse_date("2026-10-18").unwrap();
+        let svg = agenda_svg(&entries, today, &options);
+        let texts: Vec<&str> = svg
+            .split("</text>")
+            .filter_map(|t| t.rsplit('>').next())
+            .filter(|t| !t.is_empty())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Today",
+                "All day",
+                "Trip",
+                "09:00",
+                "Standup",
+                "Tomorrow",
+                "10:30",
+                "Planning",
+                "Wed 21 Oct",
+                "12:00",
+                "Lunch"
+            ]
+        );
+
+        // Four 14px lines fit in 60px, but a heading needs room for an event.
+        let short = agenda_svg(
+            &entries,
+            today,
+            &AgendaOptions {
+                height: Some(60.0),
+                ..options.clone()
+            },
+        );
+        assert_eq!(short.matches("<text").count(), 5, "{short}");
+        assert!(!short.contains("Tomorrow"), "{short}");
+
+        let empty = agenda_svg(&[], today, &options);
+        assert!(empty.contains(">No events</text>"), "{empty}");
+    }
+
+    #[test]
+    fn test_month_grid_marks_today_and_event_days() {
+        let today = parse_date("2026-10-18").unwrap();
+        let marked = HashSet::from([parse_date("2026-10-20").unwrap()]);
+        let options = MonthOptions {
+            x: 0.0,
+            y: 0.0,
+            width: 280.0,
+            height: 240.0,
+            week_start: Weekday::Mon,
+            title: true,
+            font_family: "sans-serif".to_string(),
+            font_size: None,
+        };
+        let svg = month_svg(today, Some(today), &marked, &options);
+        assert!(svg.contains(">October 2026</text>"), "{svg}");
+        assert!(
+            svg.contains(">Mo</text>") && svg.contains(">Su</text>"),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(">31</text>") && !svg.contains(">32</text>"),
+            "{svg}"
+        );
+        // October 2026 starts on a Thursday and spans five weeks: 7 rows of 34px.
+        // The 18th is the Sunday of the third week.
+        assert!(
+            svg.contains(r#"<rect x="241" y="137" width="38" height="32" fill="black""#),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"fill="white">18</text>"#), "{svg}");
+        assert_eq!(svg.matches("<rect").count(), 2, "{svg}");
+    }
+}
@@ -0,0 +1,502 @@
+use std::fmt::Write;
+
+use liquid_core::{ObjectView, ValueView};
+
+use crate::device::text_layout::escape_xml;
+
+/// Charts drawn by the `sparkline`, `line_chart`, `bar_chart` and `area_chart`
+/// filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartKind {
+    Sparkline,
+    Line,
+    Bar,
+    Area,
+}
+
+impl ChartKind {
+    /// Name of the filter drawing this kind of chart.
+    pub fn filter_name(self) -> &'static str {
+        match self {
+            ChartKind::Sparkline => "sparkline",
+            ChartKind::Line => "line_chart",
+            ChartKind::Bar => "bar_chart",
+            ChartKind::Area => "area_chart",
+        }
+    }
+
+    /// Bars and areas are drawn up from zero, so their value range includes it.
+    fn starts_at_zero(self) -> bool {
+        matches!(self, ChartKind::Bar | ChartKind::Area)
+    }
+}
+
+/// One series of `(t, value)` points in time order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Series {
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartOptions {
+    pub width: f64,
+    pub height: f64,
+    /// Position of the chart's top left corner.
+    pub x: f64,
+    pub y: f64,
+    /// Values at the bottom and top of the chart; the data's range by default.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub stroke: String,
+    pub stroke_width: f64,
+    /// Fill of bars and areas.
+    pub fill: String,
+    pub fill_opacity: f64,
+    /// Draw the left and bottom edges.
+    pub axes: bool,
+    /// Number of dashed horizontal lines between the bottom and top.
+    pub gridlines: u32,
+    /// Label the top and bottom with the chart's max and min values.
+    pub labels: bool,
+    pub font_family: String,
+    pub font_size: f64,
+}
+
+impl ChartOptions {
+    /// Defaults for a `kind` chart of `width` x `height`: black, undecorated,
+    /// with hairline sparklines and 2px lines.
+    pub fn new(kind: ChartKind, width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            x: 0.0,
+            y: 0.0,
+            min: None,
+            max: None,
+            stroke: "black".to_string(),
+            stroke_width: match kind {
+                ChartKind::Sparkline | ChartKind::Bar | ChartKind::Area => 1.0,
+                ChartKind::Line => 2.0,
+            },
+            fill: "black".to_string(),
+            fill_opacity: 1.0,
+            axes: false,
+            gridlines: 0,
+            labels: false,
+            font_family: "sans-serif".to_string(),
+            font_size: 10.0,
+        }
+    }
+}
+
+/// Dash patterns telling lines apart on a 1-bit panel, by series index.
+const DASHES: [&str; 4] = ["", "6 3", "2 2", "8 3 2 3"];
+
+/// Reads a chart filter's input: a range series (`prometheus_range.cpu[0]`), a
+/// list of them (`prometheus_range.cpu`), or a list of numbers, which are
+/// spaced evenly. Points whose value isn't a finite number are skipped.
+pub fn series_from_value(value: &dyn ValueView) -> Result<Vec<Series>, String> {
+    if let Some(object) = value.as_object() {
+        return Ok(vec![series_from_object(object)?]);
+    }
+    let Some(array) = value.as_array() else {
+        return Err(format!(
+            "expected a range series or a list of them, got {}",
+            value.type_name()
+        ));
+    };
+    if array.values().all(|v| v.as_scalar().is_some()) {
+        let points = array
+            .values()
+            .enumerate()
+            .filter_map(|(i, v)| Some((i as f64, v.as_scalar()?.to_float()?)))
+            .filter(|(_, v)| v.is_finite())
+            .collect();
+        return Ok(vec![Series { points }]);
+    }
+    array
+        .values()
+        .map(|v| {
+            v.as_object()
+                .ok_or_else(|| format!("expected a range series, got {}", v.type_name()))
+                .and_then(series_from_object)
+        })
+        .collect()
+}
+
+fn series_from_object(object: &dyn ObjectView) -> Result<Series, String> {
+    let points = object
+        .get("points")
+        .and_then(|p| p.as_array())
+        .ok_or("expected a range series with points")?;
+    let float = |point: &dyn ObjectView, key: &str| point.get(key)?.as_scalar()?.to_float();
+    let points = points
+        .values()
+        .filter_map(|p| {
+            let p = p.as_object()?;
+            Some((float(p, "t")?, float(p, "value")?))
+        })
+        .filter(|(t, v)| t.is_finite() && v.is_finite())
+        .collect();
+    Ok(Series { points })
+}
+
+/// Maps times and values onto the chart's pixels, y growing downwards.
+struct Scale {
+    t0: f64,
+    t1: f64,
+    v0: f64,
+    v1: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Scale {
+    fn new(kind: ChartKind, series: &[Series], options: &ChartOptions) -> Self {
+        let points = || series.iter().flat_map(|s| s.points.iter());
+        let fold = |f: fn(f64, f64) -> f64, init: f64, pick: fn(&(f64, f64)) -> f64| {
+            points().map(pick).fold(init, f)
+        };
+        let (mut t0, mut t1) = (
+            fold(f64::min, f64::INFINITY, |p| p.0),
+            fold(f64::max, f64::NEG_INFINITY, |p| p.0),
+        );
+        if t0 > t1 {
+            (t0, t1) = (0.0, 0.0);
+        }
+
+        let (mut v0, mut v1) = (
+            fold(f64::min, f64::INFINITY, |p| p.1),
+            fold(f64::max, f64::NEG_INFINITY, |p| p.1),
+        );
+        if v0 > v1 {
+            (v0, v1) = (0.0, 0.0);
+        }
+        if kind.starts_at_zero() {
+            (v0, v1) = (v0.min(0.0), v1.max(0.0));
+        }
+        // Bounds can come from data, so NaN or infinite ones are ignored.
+        let mut v0 = options.min.filter(|v| v.is_finite()).unwrap_or(v0);
+        let mut v1 = options.max.filter(|v| v.is_finite()).unwrap_or(v1);
+        if v0 > v1 {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+        if v0 == v1 {
+            // A flat series sits in the middle.
+            (v0, v1) = (v0 - 1.0, v1 + 1.0);
+        }
+
+        Self {
+            t0,
+            t1,
+            v0,
+            v1,
+            width: options.width,
+            height: options.height,
+        }
+    }
+
+    fn x(&self, t: f64) -> f64 {
+        if self.t1 == self.t0 {
+            return self.width / 2.0;
+        }
+        (t - self.t0) / (self.t1 - self.t0) * self.width
+    }
+
+    /// Values beyond the chart's range are drawn at its edge.
+    fn y(&self, value: f64) -> f64 {
+        let value = value.clamp(self.v0, self.v1);
+        self.height - (value - self.v0) / (self.v1 - self.v0) * self.height
+    }
+}
+
+/// Formats a coordinate or label to at most two decimals.
+pub(crate) fn num(value: f64) -> String {
+    let s = format!("{value:.2}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn line_path(scale: &Scale, series: &Series) -> String {
+    series
+        .points
+        .iter()
+        .enumerate()
+        .map(|(i, &(t, v))| {
+            let command = if i == 0 { 'M' } else { 'L' };
+            format!("{command}{} {}", num(scale.x(t)), num(scale.y(v)))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Draws `series` as an SVG `<g>` for a `kind` chart. Lines after the first
+/// are dashed, and bars and areas after the first are drawn in outline, so
+/// series stay distinguishable in black and white.
+pub fn chart_svg(kind: ChartKind, series: &[Series], options: &ChartOptions) -> String {
+    let scale = Scale::new(kind, series, options);
+    let (width, height) = (num(options.width), num(options.height));
+    let stroke = escape_xml(&options.stroke);
+    let fill = escape_xml(&options.fill);
+    let stroke_width = num(options.stroke_width);
+    let fill_opacity = num(options.fill_opacity);
+
+    let mut svg = format!(
+        r#"<g transform="translate({} {})">"#,
+        num(options.x),
+        num(options.y)
+    );
+
+    for i in 1..=options.gridlines {
+        let y = num(options.height * i as f64 / (options.gridlines + 1) as f64);
+        let _ = write!(
+            svg,
+            r#"<line x1="0" y1="{y}" x2="{width}" y2="{y}" stroke="{stroke}" stroke-width="1" stroke-dasharray="2 2" shape-rendering="crispEdges"/>"#
+        );
+    }
+
+    match kind {
+        ChartKind::Sparkline | ChartKind::Line => {
+            for (i, s) in series
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| !s.points.is_empty())
+            {
+                let dash = match DASHES[i % DASHES.len()] {
+                    "" => String::new(),
+                    dash => format!(r#" stroke-dasharray="{dash}""#),
+                };
+                let _ = write!(
+                    svg,
+                    r#"<path d="{}" fill="none" stroke="{stroke}" stroke-width="{stroke_width}" stroke-linejoin="round" stroke-linecap="round"{dash}/>"#,
+                    line_path(&scale, s)
+                );
+            }
+        }
+        ChartKind::Area => {
+            let base = num(scale.y(0.0));
+            for (i, s) in series.iter().enumerate() {
+                let (Some(first), Some(last)) = (s.points.first(), s.points.last()) else {
+                    continue;
+                };
+                let line = line_path(&scale, s);
+                let area = format!(
+                    "M{} {base} {} L{} {base} Z",
+                    num(scale.x(first.0)),
+                    &line.replacen('M', "L", 1),
+                    num(scale.x(last.0))
+                );
+                if i == 0 {
+                    let _ = write!(
+                        svg,
+                        r#"<path d="{area}" fill="{fill}" fill-opacity="{fill_opacity}" stroke="none"/>"#
+                    );
+                }
+                let _ = write!(
+                    svg,
+                    r#"<path d="{line}" fill="none" stroke="{stroke}" stroke-width="{stroke_width}" stroke-linejoin="round"/>"#
+                );
+            }
+        }
+        ChartKind::Bar => {
+            // One slot per point index, holding a bar from each series.
+            let slots = series.iter().map(|s| s.points.len()).max().unwrap_or(0);
+            let slot = options.width / slots.max(1) as f64;
+            let bar = slot * 0.8 / series.len().max(1) as f64;
+            let base = scale.y(0.0);
+            for (i, s) in series.iter().enumerate() {
+                let style = if i == 0 {
+                    format!(r#"fill="{fill}" fill-opacity="{fill_opacity}""#)
+                } else {
+                    format!(r#"fill="white" stroke="{stroke}" stroke-width="{stroke_width}""#)
+                };
+                for (j, &(_, v)) in s.points.iter().enumerate() {
+                    let x = slot * j as f64 + slot * 0.1 + bar * i as f64;
+                    let y = scale.y(v);
+                    let _ = write!(
+                        svg,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" {style} shape-rendering="crispEdges"/>"#,
+                        num(x),
+                        num(y.min(base)),
+                        num(bar),
+                        num((base - y).abs())
+                    );
+                }
+            }
+        }
+    }
+
+    if options.axes {
+        let _ = write!(
+            svg,
+            r#"<path d="M0 0V{height}H{width}" fill="none" stroke="{stroke}" stroke-width="1" shape-rendering="crispEdges"/>"#
+        );
+    }
+    if options.labels {
+        let font = format!(
+            r#"font-family="{}" font-size="{}" fill="{stroke}""#,
+            escape_xml(&options.font_family),
+            num(options.font_size)
+        );
+        let _ = write!(
+            svg,
+            r#"<text x="2" y="{}" {font}>{}</text><text x="2" y="{}" {font}>{}</text>"#,
+            num(options.font_size),
+            num(scale.v1),
+            num(options.height - 2.0),
+            num(scale.v0)
+        );
+    }
+
+    svg.push_str("</g>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[f64]) -> Series {
+        Series {
+            points: values
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| (i as f64 * 60.0, v))
+                .collect(),
+        }
+    }
+
+    fn options(kind: ChartKind) -> ChartOptions {
+        ChartOptions::new(kind, 100.0, 50.0)
+    }
+
+    #[test]
+    fn test_series_are_read_from_range_objects_and_number_lists() {
+        let range = liquid::object!({
+            "points": [
+                { "t": 60.0, "value": 1.5 },
+                { "t": 120.0, "value": "NaN" },
+                { "t": 180.0, "value": 3 },
+            ],
+            "min": 1.5,
+        });
+        assert_eq!(
+            series_from_value(&range).unwrap(),
+            vec![Series {
+                points: vec![(60.0, 1.5), (180.0, 3.0)]
+            }],
+            "non-finite values are skipped"
+        );
+
+        let ranges = liquid::model::Value::Array(vec![range.clone().into(), range.into()]);
+        assert_eq!(series_from_value(&ranges).unwrap().len(), 2);
+
+        let numbers = liquid::model::Value::Array(vec![
+            liquid::model::Value::scalar(4),
+            liquid::model::Value::scalar(2.5),
+        ]);
+        assert_eq!(
+            series_from_value(&numbers).unwrap()[0].points,
+            vec![(0.0, 4.0), (1.0, 2.5)]
+        );
+
+        let err = series_from_value(&liquid::model::Value::scalar("cpu")).unwrap_err();
+        assert!(err.contains("expected a range series"), "{err}");
+    }
+
+    #[test]
+    fn test_line_spans_the_chart_between_min_and_max() {
+        let svg = chart_svg(
+            ChartKind::Line,
+            &[series(&[10.0, 30.0, 20.0])],
+            &options(ChartKind::Line),
+        );
+        assert!(svg.contains(r#"d="M0 50 L50 0 L100 25""#), "{svg}");
+        assert!(svg.contains(r#"stroke-width="2""#), "{svg}");
+        assert!(!svg.contains("NaN"));
+
+        let mut fixed = options(ChartKind::Line);
+        (fixed.min, fixed.max) = (Some(0.0), Some(20.0));
+        let svg = chart_svg(ChartKind::Line, &[series(&[10.0, 30.0])], &fixed);
+        assert!(svg.contains(r#"d="M0 25 L100 0""#), "clamped to max: {svg}");
+    }
+
+    #[test]
+    fn test_nan_and_infinite_bounds_are_ignored() {
+        let mut bounds = options(ChartKind::Line);
+        (bounds.min, bounds.max) = (Some(f64::NAN), Some(f64::INFINITY));
+        let svg = chart_svg(ChartKind::Line, &[series(&[10.0, 30.0, 20.0])], &bounds);
+        assert!(svg.contains(r#"d="M0 50 L50 0 L100 25""#), "{svg}");
+    }
+
+    #[test]
+    fn test_flat_and_empty_series_stay_finite() {
+        let svg = chart_svg(
+            ChartKind::Sparkline,
+            &[series(&[5.0])],
+            &options(ChartKind::Sparkline),
+        );
+        assert!(svg.contains(r#"d="M50 25""#), "{svg}");
+        let svg = chart_svg(
+            ChartKind::Area,
+            &[Series::default()],
+            &options(ChartKind::Area),
+        );
+        assert_eq!(svg, r#"<g transform="translate(0 0)"></g>"#);
+    }
+
+    #[test]
+    fn test_bars_grow_from_zero_and_later_series_are_outlined() {
+        let svg = chart_svg(
+            ChartKind::Bar,
+            &[series(&[10.0, 5.0]), series(&[2.0, 8.0])],
+            &options(ChartKind::Bar),
+        );
+        // Two slots of 50px; each holds two 20px bars after a 5px gap.
+        assert!(
+            svg.contains(r#"<rect x="5" y="0" width="20" height="50" fill="black""#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(
+                r#"<rect x="75" y="10" width="20" height="40" fill="white" stroke="black""#
+            ),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn test_decorations_and_second_series_dashes() {
+        let mut decorated = options(ChartKind::Line);
+        (decorated.axes, decorated.gridlines, decorated.labels) = (true, 1, true);
+        let svg = chart_svg(
+            ChartKind::Line,
+            &[series(&[0.0, 1.0]), series(&[1.0, 0.5])],
+            &decorated,
+        );
+        assert!(
+            svg.contains(r#"<line x1="0" y1="25" x2="100" y2="25""#),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"d="M0 0V50H100""#), "{svg}");
+        assert!(svg.contains(r#"stroke-dasharray="6 3""#), "{svg}");
+        assert!(
+            svg.contains(">1</text>") && svg.contains(">0</text>"),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn test_area_closes_on_the_zero_line() {
+        let svg = chart_svg(
+            ChartKind::Area,
+            &[series(&[10.0, 20.0])],
+            &options(ChartKind::Area),
+        );
+        assert!(svg.contains(r#"d="M0 50 L0 25 L100 0 L100 50 Z""#), "{svg}");
+    }
+}
@@ -0,0 +1,318 @@
+use crate::models::{Device, Dither};
+
+/// Per-device settings controlling how a grayscale raster is reduced to the
+/// panel's black and white pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DitherOptions {
+    pub algorithm: Dither,
+    /// Luminance (0-255) at or above which a pixel is white.
+    pub threshold: u8,
+    /// Gamma applied to luminance before quantizing; 1.0 leaves it unchanged.
+    pub gamma: f32,
+}
+
+impl Default for DitherOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Dither::Threshold,
+            threshold: 127,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl DitherOptions {
+    pub fn for_device(device: &Device) -> Self {
+        Self {
+            algorithm: device.dither,
+            threshold: device.dither_threshold.clamp(0, 255) as u8,
+            gamma: device.gamma as f32,
+        }
+    }
+}
+
+/// 8x8 Bayer index matrix; entries are the order (0-63) in which cells turn white.
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Reduces a row-major luminance buffer (0.0-255.0 per pixel) to `levels`
+/// evenly spaced grays. Returns one level index per pixel, 0 being black and
+/// `levels - 1` white.
+///
+/// The threshold only applies to two-level output; with more levels each
+/// pixel snaps to its nearest gray.
+pub fn dither(
+    gray: &[f32],
+    width: usize,
+    height: usize,
+    levels: u8,
+    options: &DitherOptions,
+) -> Vec<u8> {
+    let mut gray: Vec<f32> = gray
+        .iter()
+        .map(|&v| apply_gamma(v, options.gamma))
+        .collect();
+    let quantizer = Quantizer::new(levels, options.threshold);
+
+    match options.algorithm {
+        Dither::Threshold => gray.iter().map(|&v| quantizer.level(v)).collect(),
+        Dither::Bayer => gray
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let (x, y) = (i % width, i / width);
+                let offset = (BAYER_8X8[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5;
+                quantizer.level(v + offset * quantizer.step)
+            })
+            .collect(),
+        Dither::FloydSteinberg => diffuse(
+            &mut gray,
+            width,
+            height,
+            &quantizer,
+            &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)],
+            16.0,
+        ),
+        // Atkinson only propagates 6/8 of the error, which keeps highlights and
+        // shadows clean at the cost of some detail in the extremes.
+        Dither::Atkinson => diffuse(
+            &mut gray,
+            width,
+            height,
+            &quantizer,
+            &[
+                (1, 0, 1.0),
+                (2, 0, 1.0),
+                (-1, 1, 1.0),
+                (0, 1, 1.0),
+                (1, 1, 1.0),
+                (0, 2, 1.0),
+            ],
+            8.0,
+        ),
+    }
+}
+
+/// Maps luminance to one of `levels` evenly spaced grays.
+struct Quantizer {
+    levels: u8,
+    threshold: f32,
+    /// Luminance distance between adjacent levels.
+    step: f32,
+}
+
+impl Quantizer {
+    fn new(levels: u8, threshold: u8) -> Self {
+        let levels = levels.max(2);
+        Self {
+            levels,
+            threshold: threshold as f32,
+            step: 255.0 / (levels - 1) as f32,
+        }
+    }
+
+    fn level(&self, value: f32) -> u8 {
+        if self.levels == 2 {
+            return (value >= self.threshold) as u8;
+        }
+        (value / self.step)
+            .round()
+            .clamp(0.0, (self.levels - 1) as f32) as u8
+    }
+
+    fn value(&self, level: u8) -> f32 {
+        level as f32 * self.step
+    }
+}
+
+fn apply_gamma(value: f32, gamma: f32) -> f32 {
+    if gamma <= 0.0 || gamma == 1.0 {
+        return value;
+    }
+    255.0 * (value / 255.0).clamp(0.0, 1.0).powf(1.0 / gamma)
+}
+
+/// Error diffusion: quantize each pixel in scan order and push the
+/// quantization error onto not-yet-visited neighbours using `kernel`
+/// entries of `(dx, dy, weight)`, each divided by `divisor`.
+fn diffuse(
+    gray: &mut [f32],
+    width: usize,
+    height: usize,
+    quantizer: &Quantizer,
+    kernel: &[(isize, usize, f32)],
+    divisor: f32,
+) -> Vec<u8> {
+    let mut out = vec![0; width * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let old = gray[i];
+            let level = quantizer.level(old);
+            out[i] = level;
+
+            let error = old - quantizer.value(level);
+            for &(dx, dy, weight) in kernel {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+                if nx < 0 || nx >= width as isize || ny >= height {
+                    continue;
+                }
+                gray[ny * width + nx as usize] += error * weight / divisor;
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 16;
+
+    /// A left-to-right black-to-white ramp with a mid-gray disc in the middle,
+    /// covering both smooth gradients and a flat area with hard edges.
+    fn test_image() -> Vec<f32> {
+        let mut gray = Vec::with_capacity(WIDTH * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let (dx, dy) = (x as f32 - 31.5, (y as f32 - 7.5) * 2.0);
+                if dx * dx + dy * dy < 100.0 {
+                    gray.push(128.0);
+                } else {
+                    gray.push(x as f32 * 255.0 / (WIDTH - 1) as f32);
+                }
+            }
+        }
+        gray
+    }
+
+    /// One character per pixel, darkest first: `#` black through `.` white.
+    fn to_ascii(pixels: &[u8], levels: u8) -> String {
+        const RAMP: &[u8; 16] = b"#@&%*+=o~-:;,'`.";
+        pixels
+            .chunks(WIDTH)
+            .map(|row| {
+                let mut line: String = row
+                    .iter()
+                    .map(|&l| RAMP[l as usize * 15 / (levels as usize - 1)] as char)
+                    .collect();
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+
+    /// Compares against `src/device/testdata/dither/<name>.txt`. Run with
+    /// `UPDATE_GOLDEN=1` to regenerate after an intentional change.
+    fn assert_golden(name: &str, levels: u8, options: DitherOptions) {
+        let actual = to_ascii(
+            &dither(&test_image(), WIDTH, HEIGHT, levels, &options),
+            levels,
+        );
+        let path = format!(
+            "{}/src/device/testdata/dither/{name}.txt",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&path, &actual).expect("write golden file");
+        }
+        let expected = std::fs::read_to_string(&path).expect("read golden file");
+        assert_eq!(actual, expected, "{name} output differs from {path}");
+    }
+
+    fn options(algorithm: Dither) -> DitherOptions {
+        DitherOptions {
+            algorithm,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_threshold_matches_golden() {
+        assert_golden("threshold", 2, options(Dither::Threshold));
+    }
+
+    #[test]
+    fn test_floyd_steinberg_matches_golden() {
+        assert_golden("floyd_steinberg", 2, options(Dither::FloydSteinberg));
+    }
+
+    #[test]
+    fn test_atkinson_matches_golden() {
+        assert_golden("atkinson", 2, options(Dither::Atkinson));
+    }
+
+    #[test]
+    fn test_bayer_matches_golden() {
+        assert_golden("bayer", 2, options(Dither::Bayer));
+    }
+
+    #[test]
+    fn test_threshold_and_gamma_shift_the_cut_off() {
+        let gray = vec![100.0];
+        let white = |threshold, gamma| {
+            dither(
+                &gray,
+                1,
+                1,
+                2,
+                &DitherOptions {
+                    algorithm: Dither::Threshold,
+                    threshold,
+                    gamma,
+                },
+            )[0] == 1
+        };
+        assert!(!white(127, 1.0), "100 is below the default cut-off");
+        assert!(white(90, 1.0), "a lower threshold turns 100 white");
+        assert!(white(127, 2.0), "gamma 2.0 lifts 100 to ~160");
+    }
+
+    #[test]
+    fn test_dithering_preserves_average_brightness() {
+        let gray = vec![64.0; WIDTH * HEIGHT];
+        for algorithm in [Dither::FloydSteinberg, Dither::Bayer] {
+            let bits = dither(&gray, WIDTH, HEIGHT, 2, &options(algorithm));
+            let white = bits.iter().filter(|&&l| l == 1).count() as f32 / bits.len() as f32;
+            assert!(
+                (white - 0.25).abs() < 0.03,
+                "{algorithm:?}: expected ~25% white for 25% gray, got {white}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_four_level_threshold_matches_golden() {
+        assert_golden("threshold_4", 4, options(Dither::Threshold));
+    }
+
+    #[test]
+    fn test_four_level_floyd_steinberg_matches_golden() {
+        assert_golden("floyd_steinberg_4", 4, options(Dither::FloydSteinberg));
+    }
+
+    #[test]
+    fn test_sixteen_level_bayer_matches_golden() {
+        assert_golden("bayer_16", 16, options(Dither::Bayer));
+    }
+
+    #[test]
+    fn test_multi_level_snaps_to_nearest_gray() {
+        let gray = vec![0.0, 80.0, 90.0, 170.0, 255.0];
+        let levels = dither(&gray, 5, 1, 4, &options(Dither::Threshold));
+        assert_eq!(levels, vec![0, 1, 1, 2, 3]);
+    }
+}
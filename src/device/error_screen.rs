@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use qrcode::QrCode;
+
+use crate::device::dither::DitherOptions;
+use crate::device::fonts::ui_family;
+use crate::device::liquid_filters::qrcode_to_svg_group;
+use crate::device::render_limits::render_limits;
+use crate::device::renderer::{Error, render_timezone, svg_to_image};
+use crate::device::text_layout::{self, TextStyle, escape_xml};
+use crate::models::{Device, ImageFormat};
+
+/// What went wrong, shown on the screen served in place of a failed render.
+pub struct RenderFailure<'a> {
+    pub template_name: &'a str,
+    pub error: &'a str,
+    /// Link to the template editor, encoded in the QR code.
+    pub editor_url: &'a str,
+}
+
+/// Renders the error screen at the device's size and gray levels, encoded as
+/// `format`. Threshold dithering keeps the text readable on every panel.
+pub fn render_error_screen(
+    device: &Device,
+    failure: &RenderFailure,
+    format: ImageFormat,
+) -> Result<Vec<u8>, Error> {
+    let tz = render_timezone().unwrap_or(Tz::UTC);
+    let now = Utc::now().with_timezone(&tz);
+    let svg = error_screen_svg(device, failure, now, ui_family().as_deref());
+    svg_to_image(
+        &svg,
+        device.gray_levels(),
+        &DitherOptions::default(),
+        format,
+        render_limits().raster_size(device.width, device.height),
+    )
+}
+
+fn error_screen_svg(
+    device: &Device,
+    failure: &RenderFailure,
+    now: DateTime<Tz>,
+    family: Option<&str>,
+) -> String {
+    let (width, height) = match (device.width, device.height) {
+        (w, h) if w > 0 && h > 0 => (w as f32, h as f32),
+        _ => (800.0, 480.0),
+    };
+    let margin = (width.max(height) / 40.0).round().max(8.0);
+    let title_size = (height / 12.0).round().clamp(16.0, 48.0);
+    let body_size = (title_size / 2.0).round().max(12.0);
+    let line_height = (body_size * 1.3).round();
+    let family = family.unwrap_or("sans-serif");
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}"><rect width="100%" height="100%" fill="white"/>"#
+    );
+
+    // QR code to the template editor in the top-right corner.
+    let mut text_width = width - 2.0 * margin;
+    if let Ok(code) = QrCode::new(failure.editor_url.as_bytes()) {
+        let modules = code.width() as f32 + 8.0;
+        let module_size = ((width.min(height) / 3.0) / modules).floor().max(1.0);
+        let qr_size = modules * module_size;
+        if let Ok(group) = qrcode_to_svg_group(failure.editor_url, module_size as u32) {
+            let x = width - margin - qr_size;
+            svg.push_str(&format!(
+                r#"<g transform="translate({x} {margin})">{group}</g><text x="{}" y="{}" font-family="{}" font-size="{body_size}" text-anchor="middle">Edit template</text>"#,
+                x + qr_size / 2.0,
+                margin + qr_size + body_size,
+                escape_xml(family),
+            ));
+            text_width -= qr_size + margin;
+        }
+    }
+
+    // Fall back to an average glyph width if the font can't be measured; the
+    // text is then drawn with whatever usvg resolves.
+    let style = TextStyle {
+        family: family.to_string(),
+        size: body_size,
+        weight: 400,
+    };
+    let wrap = |text: &str, max_lines| {
+        text_layout::with_measure(&style, |measure| {
+            text_layout::wrap_lines(text, text_width, Some(max_lines), measure)
+        })
+        .unwrap_or_else(|_| {
+            let estimate = |s: &str| s.chars().count() as f32 * body_size * 0.6;
+            text_layout::wrap_lines(text, text_width, Some(max_lines), &estimate)
+        })
+    };
+
+    let mut y = margin + title_size;
+    let mut text = |content: &str, size: f32, weight: u16, y: f32| {
+        svg.push_str(&format!(
+            r#"<text x="{margin}" y="{y}" font-family="{}" font-size="{size}" font-weight="{weight}">{}</text>"#,
+            escape_xml(family),
+            escape_xml(content),
+        ));
+    };
+    text("Render error", title_size, 700, y);
+    y += line_height * 1.5;
+    for line in wrap(&format!("Template: {}", failure.template_name), 2) {
+        text(&line, body_size, 700, y);
+        y += line_height;
+    }
+    text(
+        &now.format("%Y-%m-%d %H:%M %Z").to_string(),
+        body_size,
+        400,
+        y,
+    );
+    y += line_height * 1.5;
+
+    let max_lines = (((height - margin - y) / line_height).floor() as usize + 1).max(1);
+    for line in wrap(failure.error, max_lines) {
+        text(&line, body_size, 400, y);
+        y += line_height;
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure() -> RenderFailure<'static> {
+        RenderFailure {
+            template_name: "Weather <beta>",
+            error: "liquid: Unknown variable\nrequested variable=forecast",
+            editor_url: "http://localhost:8080/template/7",
+        }
+    }
+
+    fn now() -> DateTime<Tz> {
+        DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .with_timezone(&Tz::UTC)
+    }
+
+    #[test]
+    fn test_error_screen_shows_template_error_time_and_qr() {
+        let svg = error_screen_svg(&Device::virtual_device(), &failure(), now(), None);
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="800" height="480""#)
+        );
+        assert!(svg.contains("Template: Weather &lt;beta&gt;"), "{svg}");
+        assert!(svg.contains("liquid: Unknown variable"), "{svg}");
+        assert!(svg.contains("requested variable=forecast"), "{svg}");
+        assert!(svg.contains("2023-11-14 22:13 UTC"), "{svg}");
+        assert!(svg.contains("Edit template"), "{svg}");
+        usvg::Tree::from_str(&svg, &usvg::Options::default()).expect("valid SVG");
+    }
+
+    #[test]
+    fn test_error_screen_renders_at_device_size_and_depth() {
+        let mut device = Device::virtual_device();
+        device.width = 400;
+        device.height = 300;
+        let bmp = render_error_screen(&device, &failure(), ImageFormat::Bmp).expect("render");
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(i32::from_le_bytes(bmp[18..22].try_into().unwrap()), 400);
+        assert_eq!(i32::from_le_bytes(bmp[22..26].try_into().unwrap()), 300);
+        assert_eq!(
+            u16::from_le_bytes([bmp[28], bmp[29]]),
+            1,
+            "1-bit for og panels"
+        );
+    }
+}
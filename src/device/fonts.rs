@@ -0,0 +1,118 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use usvg::fontdb::{Database, Family, Query};
+
+use crate::device::render_cache::render_cache;
+
+/// Font database shared by every render. Starts out with the system fonts and
+/// is swapped for a new one whenever uploaded fonts change, so renders never
+/// scan the filesystem themselves.
+fn shared() -> &'static RwLock<Arc<Database>> {
+    static FONTS: OnceLock<RwLock<Arc<Database>>> = OnceLock::new();
+    FONTS.get_or_init(|| RwLock::new(system_fonts()))
+}
+
+/// System fonts are scanned once per process; rebuilding after an upload
+/// starts from a clone of this.
+fn system_fonts() -> Arc<Database> {
+    static SYSTEM: OnceLock<Arc<Database>> = OnceLock::new();
+    SYSTEM
+        .get_or_init(|| {
+            let mut db = Database::new();
+            db.load_system_fonts();
+            tracing::info!("Loaded {} system font faces", db.len());
+            Arc::new(db)
+        })
+        .clone()
+}
+
+pub fn font_database() -> Arc<Database> {
+    shared()
+        .read()
+        .expect("font database lock poisoned")
+        .clone()
+}
+
+/// Rebuilds the shared font database from the system fonts plus every font
+/// uploaded to the database, and drops cached screens that may have been
+/// rendered with the previous set.
+pub async fn reload_fonts() -> Result<(), sqlx::Error> {
+    let uploaded = crate::db::get_font_data().await?;
+
+    let mut db = (*system_fonts()).clone();
+    for data in uploaded {
+        db.load_font_data(data);
+    }
+    tracing::info!("Font database has {} faces", db.len());
+
+    *shared().write().expect("font database lock poisoned") = Arc::new(db);
+    render_cache().clear();
+    Ok(())
+}
+
+/// Sorted, de-duplicated family names available to templates.
+pub fn font_families() -> Vec<String> {
+    families(&font_database())
+}
+
+/// A family that resolves in the shared database, for screens the server draws
+/// itself: the generic sans-serif face if installed, otherwise any face.
+pub fn ui_family() -> Option<String> {
+    let db = font_database();
+    let id = db
+        .query(&Query {
+            families: &[Family::SansSerif],
+            ..Default::default()
+        })
+        .or_else(|| db.faces().next().map(|face| face.id))?;
+    db.face(id)
+        .and_then(|face| face.families.first())
+        .map(|(name, _)| name.clone())
+}
+
+/// Family names contained in a TTF/OTF/TTC file, or an empty list if it isn't
+/// a font `usvg` can use.
+pub fn families_in_font_data(data: &[u8]) -> Vec<String> {
+    let mut db = Database::new();
+    db.load_font_data(data.to_vec());
+    families(&db)
+}
+
+fn families(db: &Database) -> Vec<String> {
+    let mut families: Vec<String> = db
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+        .collect();
+    families.sort();
+    families.dedup();
+    families
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_font_data_has_no_families() {
+        assert!(families_in_font_data(b"").is_empty());
+        assert!(families_in_font_data(b"<svg xmlns='http://www.w3.org/2000/svg'/>").is_empty());
+    }
+
+    #[test]
+    fn test_families_are_read_from_font_files() {
+        let path = system_fonts()
+            .faces()
+            .find_map(|face| match &face.source {
+                usvg::fontdb::Source::File(path) => Some(path.clone()),
+                _ => None,
+            })
+            .expect("a system font is installed, such as fonts-dejavu");
+        let data = std::fs::read(&path).expect("read system font");
+        let families = families_in_font_data(&data);
+        assert!(!families.is_empty(), "{path:?} should name a family");
+        assert!(
+            families.iter().all(|f| font_families().contains(f)),
+            "system font families should be in the shared database"
+        );
+    }
+}
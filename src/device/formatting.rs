@@ -0,0 +1,343 @@
+use chrono::DateTime;
+
+/// The number formatting filters, each named after its kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatKind {
+    /// Fixed decimals with thousands separators.
+    Number,
+    /// Powers of 1000 with SI prefixes: `3.4 kW`, `12 mA`.
+    Si,
+    /// Powers of 1024 with IEC prefixes: `1.2 GiB`.
+    Iec,
+    /// A number of seconds as `3h 12m`.
+    Duration,
+    /// A timestamp relative to now: `5 min ago`.
+    TimeAgo,
+    Percent,
+    /// Between units of the same quantity, such as °C and °F.
+    Convert,
+}
+
+impl FormatKind {
+    pub fn filter_name(self) -> &'static str {
+        match self {
+            FormatKind::Number => "number",
+            FormatKind::Si => "si",
+            FormatKind::Iec => "iec",
+            FormatKind::Duration => "duration",
+            FormatKind::TimeAgo => "time_ago",
+            FormatKind::Percent => "percent",
+            FormatKind::Convert => "convert",
+        }
+    }
+}
+
+/// `value` to `decimals` places, with `separator` between groups of three
+/// digits: `1,234,567.89`.
+pub fn number(value: f64, decimals: usize, separator: &str, decimal_point: &str) -> String {
+    let fixed = format!("{:.decimals$}", value.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    // No "-0" when a small negative rounds away.
+    let negative = value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let sign = if negative { "-" } else { "" };
+    match fraction {
+        "" => format!("{sign}{grouped}"),
+        fraction => format!("{sign}{grouped}{decimal_point}{fraction}"),
+    }
+}
+
+const SI_PREFIXES: [(i32, &str); 9] = [
+    (-3, "n"),
+    (-2, "µ"),
+    (-1, "m"),
+    (0, ""),
+    (1, "k"),
+    (2, "M"),
+    (3, "G"),
+    (4, "T"),
+    (5, "P"),
+];
+const IEC_PREFIXES: [&str; 7] = ["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+
+/// `value` scaled to between 1 and 1000 and written with an SI prefix and
+/// `unit`. Without `decimals`, keeps up to one decimal place.
+pub fn si(value: f64, decimals: Option<usize>, unit: &str) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return with_unit(&scaled(value, decimals), "", unit);
+    }
+    let mut power = (value.abs().log10() / 3.0).floor() as i32;
+    power = power.clamp(SI_PREFIXES[0].0, SI_PREFIXES[SI_PREFIXES.len() - 1].0);
+    let mut text = scaled(value / 1000f64.powi(power), decimals);
+    // 999.96 rounds up to the next prefix.
+    if text.trim_start_matches('-').starts_with("1000") && power < SI_PREFIXES[8].0 {
+        power += 1;
+        text = scaled(value / 1000f64.powi(power), decimals);
+    }
+    let prefix = SI_PREFIXES
+        .iter()
+        .find(|(p, _)| *p == power)
+        .map_or("", |(_, prefix)| prefix);
+    with_unit(&text, prefix, unit)
+}
+
+/// `value` scaled by powers of 1024 and written with an IEC prefix and
+/// `unit`. Values below 1024 are written as they are.
+pub fn iec(value: f64, decimals: Option<usize>, unit: &str) -> String {
+    let mut power = 0;
+    if value.is_finite() && value.abs() >= 1.0 {
+        power = ((value.abs().log2() / 10.0).floor() as usize).min(IEC_PREFIXES.len() - 1);
+    }
+    let mut text = scaled(value / 1024f64.powi(power as i32), decimals);
+    if text.trim_start_matches('-').starts_with("1024") && power + 1 < IEC_PREFIXES.len() {
+        power += 1;
+        text = scaled(value / 1024f64.powi(power as i32), decimals);
+    }
+    with_unit(&text, IEC_PREFIXES[power], unit)
+}
+
+fn scaled(value: f64, decimals: Option<usize>) -> String {
+    match decimals {
+        Some(decimals) => format!("{value:.decimals$}"),
+        None => {
+            let text = format!("{value:.1}");
+            text.strip_suffix(".0").unwrap_or(&text).to_string()
+        }
+    }
+}
+
+/// `1.2 kW`, or `1.2k` without a unit.
+fn with_unit(number: &str, prefix: &str, unit: &str) -> String {
+    match unit {
+        "" => format!("{number}{prefix}"),
+        unit => format!("{number} {prefix}{unit}"),
+    }
+}
+
+/// `secs` as its `parts` largest units of days, hours, minutes and seconds:
+/// `3h 12m`, `2d 4h`. Durations under a second are written in milliseconds.
+pub fn duration(secs: f64, parts: usize) -> String {
+    if !secs.is_finite() {
+        return "–".to_string();
+    }
+    let sign = if secs < 0.0 { "-" } else { "" };
+    let secs = secs.abs();
+    if secs > 0.0 && secs < 1.0 {
+        return format!("{sign}{}ms", (secs * 1000.0).round());
+    }
+    let mut rest = secs.round() as u64;
+    let mut units = Vec::new();
+    for (size, suffix) in [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")] {
+        if rest >= size {
+            units.push(format!("{}{suffix}", rest / size));
+            rest %= size;
+        } else if !units.is_empty() {
+            // Keep parts adjacent: `1h 0m`, never `1h 5s`.
+            units.push(format!("0{suffix}"));
+        }
+    }
+    units.truncate(parts.max(1));
+    // Drop trailing zero parts kept for adjacency.
+    while units.len() > 1 && units.last().is_some_and(|u| u.starts_with('0')) {
+        units.pop();
+    }
+    match units.is_empty() {
+        true => "0s".to_string(),
+        false => format!("{sign}{}", units.join(" ")),
+    }
+}
+
+/// How long before or after `now` the unix time `timestamp` is, in its largest
+/// unit: `just now`, `5 min ago`, `3 h ago`, `in 2 days`.
+pub fn relative_time(timestamp: f64, now: f64) -> String {
+    let delta = now - timestamp;
+    let secs = delta.abs();
+    let amount = |n: f64, one: &str, many: &str| {
+        let n = n.round().max(1.0);
+        match n == 1.0 {
+            true => format!("1 {one}"),
+            false => format!("{n} {many}"),
+        }
+    };
+    let text = match secs {
+        s if s < 45.0 => return "just now".to_string(),
+        s if s < 45.0 * 60.0 => amount(s / 60.0, "min", "min"),
+        s if s < 22.0 * 3_600.0 => amount(s / 3_600.0, "h", "h"),
+        s if s < 26.0 * 86_400.0 => amount(s / 86_400.0, "day", "days"),
+        s if s < 320.0 * 86_400.0 => amount(s / (30.0 * 86_400.0), "month", "months"),
+        s => amount(s / (365.0 * 86_400.0), "year", "years"),
+    };
+    match delta < 0.0 {
+        true => format!("in {text}"),
+        false => format!("{text} ago"),
+    }
+}
+
+/// Reads a unix time from a number, or from a time as the `date` filter
+/// writes it (`2026-10-18 09:00:00 +0200`) or RFC 3339.
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<f64>() {
+        return Some(secs);
+    }
+    DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S %z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .map(|time| time.timestamp() as f64)
+}
+
+/// `part / whole` in percent, to `decimals` places: `42%`.
+pub fn percent(part: f64, whole: f64, decimals: usize) -> String {
+    if whole == 0.0 || !part.is_finite() || !whole.is_finite() {
+        return "–".to_string();
+    }
+    format!("{:.decimals$}%", part / whole * 100.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Quantity {
+    Temperature,
+    Speed,
+    Length,
+    Pressure,
+}
+
+/// Units `convert` knows, as names and the linear map to the quantity's base
+/// unit (kelvin, m/s, metres, pascals): `base = value * scale + offset`.
+const UNITS: &[(&[&str], Quantity, f64, f64)] = &[
+    (&["c", "°c", "celsius"], Quantity::Temperature, 1.0, 273.15),
+    (
+        &["f", "°f", "fahrenheit"],
+        Quantity::Temperature,
+        5.0 / 9.0,
+        459.67 * 5.0 / 9.0,
+    ),
+    (&["k", "kelvin"], Quantity::Temperature, 1.0, 0.0),
+    (&["m/s", "mps"], Quantity::Speed, 1.0, 0.0),
+    (&["km/h", "kmh", "kph"], Quantity::Speed, 1.0 / 3.6, 0.0),
+    (&["mph"], Quantity::Speed, 0.44704, 0.0),
+    (
+        &["kn", "kt", "knots"],
+        Quantity::Speed,
+        1852.0 / 3600.0,
+        0.0,
+    ),
+    (&["mm"], Quantity::Length, 0.001, 0.0),
+    (&["cm"], Quantity::Length, 0.01, 0.0),
+    (&["m"], Quantity::Length, 1.0, 0.0),
+    (&["km"], Quantity::Length, 1000.0, 0.0),
+    (&["in"], Quantity::Length, 0.0254, 0.0),
+    (&["ft"], Quantity::Length, 0.3048, 0.0),
+    (&["mi"], Quantity::Length, 1609.344, 0.0),
+    (&["pa"], Quantity::Pressure, 1.0, 0.0),
+    (&["hpa", "mbar"], Quantity::Pressure, 100.0, 0.0),
+    (&["kpa"], Quantity::Pressure, 1000.0, 0.0),
+    (&["bar"], Quantity::Pressure, 100_000.0, 0.0),
+    (&["inhg"], Quantity::Pressure, 3386.389, 0.0),
+    (&["psi"], Quantity::Pressure, 6894.757, 0.0),
+];
+
+/// `value` in `from` units converted to `to` units. Unit names are case
+/// insensitive.
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, String> {
+    let unit = |name: &str| {
+        let lower = name.trim().to_lowercase();
+        UNITS
+            .iter()
+            .find(|(names, ..)| names.contains(&lower.as_str()))
+            .ok_or_else(|| format!("unknown unit '{name}'"))
+    };
+    let (_, from_quantity, from_scale, from_offset) = unit(from)?;
+    let (_, to_quantity, to_scale, to_offset) = unit(to)?;
+    if from_quantity != to_quantity {
+        return Err(format!("can't convert {from} to {to}"));
+    }
+    let base = value * from_scale + from_offset;
+    Ok((base - to_offset) / to_scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_groups_thousands() {
+        assert_eq!(number(1234567.891, 2, ",", "."), "1,234,567.89");
+        assert_eq!(number(-1234.6, 0, ",", "."), "-1,235");
+        assert_eq!(number(999.0, 1, ",", "."), "999.0");
+        assert_eq!(number(1234.5, 1, ".", ","), "1.234,5");
+        assert_eq!(number(-0.004, 2, ",", "."), "0.00");
+    }
+
+    #[test]
+    fn test_si_and_iec_prefixes() {
+        assert_eq!(si(1_234_000_000.0, None, "B"), "1.2 GB");
+        assert_eq!(si(3400.0, None, "W"), "3.4 kW");
+        assert_eq!(si(0.012, None, "A"), "12 mA");
+        assert_eq!(si(950.0, None, ""), "950");
+        assert_eq!(si(999_960.0, None, "W"), "1 MW");
+        assert_eq!(si(-2500.0, Some(2), ""), "-2.50k");
+        assert_eq!(si(0.0, None, "W"), "0 W");
+
+        assert_eq!(iec(1_288_490_189.0, None, "B"), "1.2 GiB");
+        assert_eq!(iec(512.0, None, "B"), "512 B");
+        assert_eq!(iec(1_048_575.0, None, "B"), "1 MiB");
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(duration(11_520.0, 2), "3h 12m");
+        assert_eq!(duration(187_200.0, 2), "2d 4h");
+        assert_eq!(duration(3_605.0, 2), "1h");
+        assert_eq!(duration(3_605.0, 3), "1h 0m 5s");
+        assert_eq!(duration(45.0, 2), "45s");
+        assert_eq!(duration(0.35, 2), "350ms");
+        assert_eq!(duration(0.0, 2), "0s");
+        assert_eq!(duration(-90.0, 1), "-1m");
+    }
+
+    #[test]
+    fn test_relative_time() {
+        let now = 1_760_000_000.0;
+        assert_eq!(relative_time(now - 10.0, now), "just now");
+        assert_eq!(relative_time(now - 300.0, now), "5 min ago");
+        assert_eq!(relative_time(now - 3.0 * 3_600.0, now), "3 h ago");
+        assert_eq!(relative_time(now - 30.0 * 3_600.0, now), "1 day ago");
+        assert_eq!(relative_time(now + 2.0 * 86_400.0, now), "in 2 days");
+        assert_eq!(relative_time(now - 400.0 * 86_400.0, now), "1 year ago");
+
+        assert_eq!(parse_timestamp("1760000000"), Some(now));
+        assert_eq!(
+            parse_timestamp("2026-10-18 09:00:00 +0200"),
+            parse_timestamp("2026-10-18T07:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_percent_and_conversions() {
+        assert_eq!(percent(0.423, 1.0, 0), "42%");
+        assert_eq!(percent(3.0, 8.0, 1), "37.5%");
+        assert_eq!(percent(1.0, 0.0, 0), "–");
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-3;
+        assert!(close(convert(100.0, "C", "F").unwrap(), 212.0));
+        assert!(close(convert(-40.0, "°F", "celsius").unwrap(), -40.0));
+        assert!(close(convert(0.0, "c", "k").unwrap(), 273.15));
+        assert!(close(convert(10.0, "m/s", "mph").unwrap(), 22.369));
+        assert!(close(convert(1013.25, "hPa", "inHg").unwrap(), 29.921));
+        assert_eq!(
+            convert(1.0, "c", "mph").unwrap_err(),
+            "can't convert c to mph"
+        );
+        assert_eq!(
+            convert(1.0, "c", "furlong").unwrap_err(),
+            "unknown unit 'furlong'"
+        );
+    }
+}
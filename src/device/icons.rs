@@ -0,0 +1,254 @@
+use crate::device::charts::num;
+use crate::device::text_layout::escape_xml;
+
+/// Bundled icons, drawn with round-capped strokes on a 24×24 grid, sorted by
+/// name.
+const ICONS: &[(&str, &str)] = &[
+    ("arrow-down", r#"<path d="M12 5v14M19 12l-7 7-7-7"/>"#),
+    ("arrow-down-left", r#"<path d="M17 7L7 17M17 17H7V7"/>"#),
+    ("arrow-down-right", r#"<path d="M7 7l10 10M17 7v10H7"/>"#),
+    ("arrow-left", r#"<path d="M19 12H5M12 19l-7-7 7-7"/>"#),
+    ("arrow-right", r#"<path d="M5 12h14M12 5l7 7-7 7"/>"#),
+    ("arrow-up", r#"<path d="M12 19V5M5 12l7-7 7 7"/>"#),
+    ("arrow-up-left", r#"<path d="M17 17L7 7M7 17V7h10"/>"#),
+    ("arrow-up-right", r#"<path d="M7 17L17 7M7 7h10v10"/>"#),
+    (
+        "battery-charging",
+        r#"<rect x="2" y="7" width="17" height="10" rx="2"/><path d="M22 11v2M11 9l-2 3h3l-2 3"/>"#,
+    ),
+    (
+        "battery-empty",
+        r#"<rect x="2" y="7" width="17" height="10" rx="2"/><path d="M22 11v2"/>"#,
+    ),
+    (
+        "battery-full",
+        r#"<rect x="2" y="7" width="17" height="10" rx="2"/><path d="M22 11v2M6 10v4M10 10v4M14 10v4"/>"#,
+    ),
+    (
+        "battery-half",
+        r#"<rect x="2" y="7" width="17" height="10" rx="2"/><path d="M22 11v2M6 10v4M10 10v4"/>"#,
+    ),
+    (
+        "battery-low",
+        r#"<rect x="2" y="7" width="17" height="10" rx="2"/><path d="M22 11v2M6 10v4"/>"#,
+    ),
+    (
+        "bell",
+        r#"<path d="M18 8A6 6 0 0 0 6 8c0 7-3 9-3 9h18s-3-2-3-9M13.73 21a2 2 0 0 1-3.46 0"/>"#,
+    ),
+    ("bolt", r#"<path d="M13 2L3 14h9l-1 8 10-12h-9z"/>"#),
+    (
+        "calendar",
+        r#"<rect x="3" y="4" width="18" height="18" rx="2"/><path d="M16 2v4M8 2v4M3 10h18"/>"#,
+    ),
+    ("check", r#"<path d="M20 6L9 17l-5-5"/>"#),
+    (
+        "check-circle",
+        r#"<circle cx="12" cy="12" r="10"/><path d="M8 12l3 3 5-6"/>"#,
+    ),
+    ("chevron-down", r#"<path d="M6 9l6 6 6-6"/>"#),
+    ("chevron-left", r#"<path d="M15 18l-6-6 6-6"/>"#),
+    ("chevron-right", r#"<path d="M9 18l6-6-6-6"/>"#),
+    ("chevron-up", r#"<path d="M6 15l6-6 6 6"/>"#),
+    (
+        "clock",
+        r#"<circle cx="12" cy="12" r="10"/><path d="M12 6v6l4 2"/>"#,
+    ),
+    (
+        "cloud",
+        r#"<path d="M7 19a4 4 0 0 1-.5-7.97A6 6 0 0 1 18 10a4.5 4.5 0 0 1 0 9z"/>"#,
+    ),
+    (
+        "cloud-drizzle",
+        r#"<path d="M7 15a4 4 0 0 1-.5-7.97A6 6 0 0 1 18 6a4.5 4.5 0 0 1 0 9zM8 18v1M8 21v1M12 19v1M12 22v1M16 18v1M16 21v1"/>"#,
+    ),
+    (
+        "cloud-lightning",
+        r#"<path d="M7 15a4 4 0 0 1-.5-7.97A6 6 0 0 1 18 6a4.5 4.5 0 0 1 0 9zM13 15l-3 4h4l-3 4"/>"#,
+    ),
+    (
+        "cloud-rain",
+        r#"<path d="M7 15a4 4 0 0 1-.5-7.97A6 6 0 0 1 18 6a4.5 4.5 0 0 1 0 9zM8 18v3M12 18v4M16 18v3"/>"#,
+    ),
+    (
+        "cloud-snow",
+        r#"<path d="M7 15a4 4 0 0 1-.5-7.97A6 6 0 0 1 18 6a4.5 4.5 0 0 1 0 9zM8 18h.01M12 19h.01M16 18h.01M8 22h.01M12 23h.01M16 22h.01"/>"#,
+    ),
+    (
+        "cloud-sun",
+        r#"<path d="M8 1v2M1 9h2M3.05 4.05l1.41 1.41M12.95 4.05l-1.41 1.41M5.17 11.83A4 4 0 1 1 11.46 7M9 21a3.5 3.5 0 0 1-.4-6.97A5 5 0 0 1 18 13.5a3.75 3.75 0 0 1 0 7.5z"/>"#,
+    ),
+    (
+        "droplet",
+        r#"<path d="M12 2.7l5.66 5.66a8 8 0 1 1-11.31 0z"/>"#,
+    ),
+    (
+        "error",
+        r#"<circle cx="12" cy="12" r="10"/><path d="M15 9l-6 6M9 9l6 6"/>"#,
+    ),
+    ("fog", r#"<path d="M4 8h16M2 12h20M4 16h16M7 20h10"/>"#),
+    (
+        "heart",
+        r#"<path d="M20.84 4.61a5.5 5.5 0 0 0-7.78 0L12 5.67l-1.06-1.06a5.5 5.5 0 0 0-7.78 7.78L12 21.23l8.84-8.84a5.5 5.5 0 0 0 0-7.78z"/>"#,
+    ),
+    (
+        "home",
+        r#"<path d="M3 10l9-7 9 7v11a1 1 0 0 1-1 1H4a1 1 0 0 1-1-1zM9 22V12h6v10"/>"#,
+    ),
+    (
+        "info",
+        r#"<circle cx="12" cy="12" r="10"/><path d="M12 16v-4M12 8h.01"/>"#,
+    ),
+    (
+        "lock",
+        r#"<rect x="3" y="11" width="18" height="11" rx="2"/><path d="M7 11V7a5 5 0 0 1 10 0v4"/>"#,
+    ),
+    (
+        "mail",
+        r#"<rect x="2" y="4" width="20" height="16" rx="2"/><path d="M22 6l-10 7L2 6"/>"#,
+    ),
+    (
+        "moon",
+        r#"<path d="M21 12.79A9 9 0 1 1 11.21 3 7 7 0 0 0 21 12.79z"/>"#,
+    ),
+    (
+        "power",
+        r#"<path d="M18.36 6.64a9 9 0 1 1-12.73 0M12 2v10"/>"#,
+    ),
+    (
+        "refresh",
+        r#"<path d="M21 12a9 9 0 1 1-2.64-6.36L21 8M21 3v5h-5"/>"#,
+    ),
+    (
+        "snowflake",
+        r#"<path d="M12 2v20M3.34 7l17.32 10M3.34 17l17.32-10"/>"#,
+    ),
+    (
+        "star",
+        r#"<path d="M12 2l3.09 6.26L22 9.27l-5 4.87 1.18 6.88L12 17.77l-6.18 3.25L7 14.14 2 9.27l6.91-1.01z"/>"#,
+    ),
+    (
+        "sun",
+        r#"<circle cx="12" cy="12" r="4"/><path d="M12 2v2M12 20v2M4.93 4.93l1.41 1.41M17.66 17.66l1.41 1.41M2 12h2M20 12h2M6.34 17.66l-1.41 1.41M19.07 4.93l-1.41 1.41"/>"#,
+    ),
+    (
+        "sunrise",
+        r#"<path d="M17 18a5 5 0 0 0-10 0M12 2v7M4.22 10.22l1.42 1.42M1 18h2M21 18h2M18.36 11.64l1.42-1.42M23 22H1M8 6l4-4 4 4"/>"#,
+    ),
+    (
+        "sunset",
+        r#"<path d="M17 18a5 5 0 0 0-10 0M12 9V2M4.22 10.22l1.42 1.42M1 18h2M21 18h2M18.36 11.64l1.42-1.42M23 22H1M16 5l-4 4-4-4"/>"#,
+    ),
+    (
+        "thermometer",
+        r#"<path d="M14 14.76V4.5a2.5 2.5 0 0 0-5 0v10.26a4.5 4.5 0 1 0 5 0z"/>"#,
+    ),
+    (
+        "trash",
+        r#"<path d="M3 6h18M8 6V4a2 2 0 0 1 2-2h4a2 2 0 0 1 2 2v2M19 6l-1 14a2 2 0 0 1-2 2H8a2 2 0 0 1-2-2L5 6"/>"#,
+    ),
+    (
+        "trending-down",
+        r#"<path d="M22 17l-8.5-8.5-5 5L2 7M16 17h6v-6"/>"#,
+    ),
+    (
+        "trending-up",
+        r#"<path d="M22 7l-8.5 8.5-5-5L2 17M16 7h6v6"/>"#,
+    ),
+    (
+        "umbrella",
+        r#"<path d="M22 12a10 10 0 0 0-20 0zM12 12v7a2 2 0 0 1-4 0"/>"#,
+    ),
+    (
+        "user",
+        r#"<circle cx="12" cy="7" r="4"/><path d="M20 21v-2a4 4 0 0 0-4-4H8a4 4 0 0 0-4 4v2"/>"#,
+    ),
+    ("warning", r#"<path d="M12 3L2 20h20zM12 9v5M12 17h.01"/>"#),
+    (
+        "wifi",
+        r#"<path d="M5 12.55a11 11 0 0 1 14.08 0M1.42 9a16 16 0 0 1 21.16 0M8.53 16.11a6 6 0 0 1 6.95 0M12 20h.01"/>"#,
+    ),
+    (
+        "wifi-off",
+        r#"<path d="M5 12.55a11 11 0 0 1 14.08 0M1.42 9a16 16 0 0 1 21.16 0M8.53 16.11a6 6 0 0 1 6.95 0M12 20h.01M2 2l20 20"/>"#,
+    ),
+    (
+        "wind",
+        r#"<path d="M3 8h10a3 3 0 1 0-3-3M3 12h15a3 3 0 1 1-3 3M3 16h7"/>"#,
+    ),
+    ("x", r#"<path d="M18 6L6 18M6 6l12 12"/>"#),
+];
+
+/// Side of the grid icons are drawn on.
+const GRID: f64 = 24.0;
+
+/// Names of the bundled icons, in alphabetical order.
+pub fn icon_names() -> impl Iterator<Item = &'static str> {
+    ICONS.iter().map(|(name, _)| *name)
+}
+
+/// Draws icon `name` as an SVG `<g>` scaled to `size` pixels with its top left
+/// corner at `x`,`y`, or `None` if there is no such icon. `stroke_width` is in
+/// grid units, so lines thicken with the icon.
+pub fn icon_svg(
+    name: &str,
+    x: f64,
+    y: f64,
+    size: f64,
+    color: &str,
+    stroke_width: f64,
+) -> Option<String> {
+    let index = ICONS.binary_search_by(|(n, _)| (*n).cmp(name)).ok()?;
+    Some(format!(
+        r#"<g transform="translate({} {}) scale({})" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round">{}</g>"#,
+        num(x),
+        num(y),
+        size / GRID,
+        escape_xml(color),
+        num(stroke_width),
+        ICONS[index].1
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icons_are_sorted_and_parse() {
+        assert!(
+            ICONS.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "icons must be sorted by name for lookup"
+        );
+        for name in icon_names() {
+            let svg = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24">{}</svg>"#,
+                icon_svg(name, 0.0, 0.0, 24.0, "black", 2.0).unwrap()
+            );
+            let tree = usvg::Tree::from_str(&svg, &usvg::Options::default())
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert!(tree.root().has_children(), "{name} draws nothing");
+            let bounds = tree.root().abs_stroke_bounding_box();
+            assert!(
+                bounds.left() >= -1.0
+                    && bounds.top() >= -1.0
+                    && bounds.right() <= 25.0
+                    && bounds.bottom() <= 25.0,
+                "{name} leaves the grid: {bounds:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_icon_is_placed_and_scaled() {
+        let svg = icon_svg("check", 10.0, 20.0, 48.0, "black", 2.0).unwrap();
+        assert!(
+            svg.starts_with(
+                r#"<g transform="translate(10 20) scale(2)" fill="none" stroke="black""#
+            ),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"<path d="M20 6L9 17l-5-5"/>"#), "{svg}");
+        assert_eq!(icon_svg("no-such-icon", 0.0, 0.0, 24.0, "black", 2.0), None);
+    }
+}
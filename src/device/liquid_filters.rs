@@ -2,15 +2,27 @@ use std::fmt;
 
 use liquid_core::{
     Error, Expression, Filter, Result, Runtime, Value, ValueView,
+    model::{ScalarCow, State},
     parser::{FilterArguments, FilterReflection, ParameterReflection, ParseFilter},
 };
 use qrcode::{Color, QrCode};
 
+use crate::device::assets::EmbedOptions;
+use crate::device::calendar::{self, AgendaOptions, CalendarEntry, MonthOptions};
+use crate::device::charts::{self, ChartKind, ChartOptions};
+use crate::device::formatting::{self, FormatKind};
+use crate::device::icons;
+use crate::device::locale;
+use crate::device::renderer::render_timezone;
+use crate::device::text_layout::{self, TextStyle};
+use crate::device::widgets::{self, BigNumberOptions, Frame, GaugeOptions, WidgetKind};
+use crate::time::{Clock, RealClock};
+
 // ─── QR code SVG generation ───────────────────────────────────────────────────
 
 /// Generates an inline SVG `<g>` element for a QR code.
 /// `module_size` is the pixel size of each module (dark/light square).
-fn qrcode_to_svg_group(
+pub(crate) fn qrcode_to_svg_group(
     data: &str,
     module_size: u32,
 ) -> std::result::Result<String, qrcode::types::QrError> {
@@ -75,6 +87,77 @@ fn eval_u32(expr: &Expression, runtime: &dyn Runtime, default: u32) -> Result<u3
         .unwrap_or(default))
 }
 
+fn eval_f32(expr: &Expression, runtime: &dyn Runtime, default: f32) -> Result<f32> {
+    Ok(expr
+        .evaluate(runtime)?
+        .as_scalar()
+        .and_then(|s| s.to_float())
+        .map(|n| n as f32)
+        .unwrap_or(default))
+}
+
+fn eval_opt_f32(expr: &Option<Expression>, runtime: &dyn Runtime) -> Result<Option<f32>> {
+    match expr {
+        Some(expr) => Ok(expr
+            .evaluate(runtime)?
+            .as_scalar()
+            .and_then(|s| s.to_float())
+            .map(|n| n as f32)),
+        None => Ok(None),
+    }
+}
+
+/// Font keyword arguments shared by the text layout filters.
+#[derive(Debug, Default)]
+struct FontArgs {
+    font_family: Option<Expression>,
+    font_size: Option<Expression>,
+    font_weight: Option<Expression>,
+}
+
+impl FontArgs {
+    /// Claims `key` if it is one of the font arguments.
+    fn take(&mut self, key: &str, expr: Expression) -> Option<Expression> {
+        match key {
+            "font_family" => self.font_family = Some(expr),
+            "font_size" => self.font_size = Some(expr),
+            "font_weight" => self.font_weight = Some(expr),
+            _ => return Some(expr),
+        }
+        None
+    }
+
+    fn style(&self, runtime: &dyn Runtime) -> Result<TextStyle> {
+        let mut style = TextStyle::default();
+        if let Some(expr) = &self.font_family {
+            style.family = eval_str(expr, runtime)?;
+        }
+        if let Some(expr) = &self.font_size {
+            style.size = eval_f32(expr, runtime, style.size)?.max(1.0);
+        }
+        if let Some(expr) = &self.font_weight {
+            style.weight = eval_u32(expr, runtime, 400)?.clamp(1, 1000) as u16;
+        }
+        Ok(style)
+    }
+}
+
+const FONT_FAMILY_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "font_family",
+    description: "Font family list, as in font-family (default: sans-serif)",
+    is_optional: true,
+};
+const FONT_SIZE_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "font_size",
+    description: "Font size in pixels (default: 16)",
+    is_optional: true,
+};
+const FONT_WEIGHT_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "font_weight",
+    description: "Numeric font weight (default: 400)",
+    is_optional: true,
+};
+
 // ─── `qrcode` filter ──────────────────────────────────────────────────────────
 //
 // Usage:
@@ -253,3 +336,1900 @@ impl ParseFilter for QrcodeWifiFilterParser {
         self
     }
 }
+
+// ─── `asset` filter ───────────────────────────────────────────────────────────
+//
+// Usage:
+//   <image href="{{ "logo" | asset }}" width="120" height="40"/>
+//   <image href="{{ "photo" | asset: width: 400, dither: "floyd_steinberg" }}" width="400" height="300"/>
+
+#[derive(Debug)]
+pub struct AssetFilter {
+    width: Option<Expression>,
+    height: Option<Expression>,
+    dither: Option<Expression>,
+    levels: Option<Expression>,
+}
+
+impl fmt::Display for AssetFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "asset")
+    }
+}
+
+impl Filter for AssetFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let eval_size = |expr: &Option<Expression>| -> Result<Option<u32>> {
+            match expr {
+                Some(expr) => Ok(Some(eval_u32(expr, runtime, 1)?)),
+                None => Ok(None),
+            }
+        };
+        let dither = match &self.dither {
+            Some(expr) => Some(
+                eval_str(expr, runtime)?
+                    .parse()
+                    .map_err(|e| Error::with_msg(format!("asset filter: {e}")))?,
+            ),
+            None => None,
+        };
+        let levels = match &self.levels {
+            Some(expr) => eval_u32(expr, runtime, 2)?.clamp(2, 16) as u8,
+            None => 2,
+        };
+        let options = EmbedOptions {
+            width: eval_size(&self.width)?,
+            height: eval_size(&self.height)?,
+            dither,
+            levels,
+        };
+
+        let uri = crate::device::assets::embed(&input.to_kstr(), &options)
+            .map_err(|e| Error::with_msg(format!("asset filter: {e}")))?;
+        Ok(Value::scalar(uri))
+    }
+}
+
+#[derive(Clone)]
+pub struct AssetFilterParser;
+
+impl FilterReflection for AssetFilterParser {
+    fn name(&self) -> &str {
+        "asset"
+    }
+    fn description(&self) -> &str {
+        "Returns a data URI for one of the template's uploaded images, for use as an <image> href."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[
+            ParameterReflection {
+                name: "width",
+                description: "Scale to this width in pixels (PNG/JPEG only)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "height",
+                description: "Scale to this height in pixels (PNG/JPEG only)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "dither",
+                description: "Pre-dither with threshold, floyd_steinberg, atkinson or bayer",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "levels",
+                description: "Gray levels to pre-dither to (default: 2)",
+                is_optional: true,
+            },
+        ]
+    }
+}
+
+impl ParseFilter for AssetFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut height = None;
+        let mut dither = None;
+        let mut levels = None;
+
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "width" => width = Some(expr),
+                "height" => height = Some(expr),
+                "dither" => dither = Some(expr),
+                "levels" => levels = Some(expr),
+                _ => return Err(Error::with_msg(format!("asset: unknown argument '{key}'"))),
+            }
+        }
+
+        Ok(Box::new(AssetFilter {
+            width,
+            height,
+            dither,
+            levels,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── `wrap_text` filter ───────────────────────────────────────────────────────
+//
+// Usage:
+//   <text x="10" y="30" font-size="20">{{ body | wrap_text: width: 380, font_size: 20, x: 10 }}</text>
+//   {{ title | wrap_text: width: 200, font_family: "Inter", max_lines: 2 }}
+
+#[derive(Debug)]
+pub struct WrapTextFilter {
+    width: Expression,
+    font: FontArgs,
+    line_height: Option<Expression>,
+    x: Option<Expression>,
+    max_lines: Option<Expression>,
+}
+
+impl fmt::Display for WrapTextFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wrap_text")
+    }
+}
+
+impl Filter for WrapTextFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let width = eval_f32(&self.width, runtime, 0.0)?;
+        let style = self.font.style(runtime)?;
+        let line_height = eval_opt_f32(&self.line_height, runtime)?.unwrap_or(style.size * 1.2);
+        let x = eval_opt_f32(&self.x, runtime)?.unwrap_or(0.0);
+        let max_lines = match &self.max_lines {
+            Some(expr) => Some(eval_u32(expr, runtime, 1)? as usize),
+            None => None,
+        };
+
+        let text = text_layout::unescape_xml(&input.to_kstr());
+        let lines = text_layout::with_measure(&style, |measure| {
+            text_layout::wrap_lines(&text, width, max_lines, measure)
+        })
+        .map_err(|e| Error::with_msg(format!("wrap_text filter: {e}")))?;
+
+        // The first line sits on the <text> element's baseline.
+        let svg = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let dy = if i == 0 { 0.0 } else { line_height };
+                format!(
+                    r#"<tspan x="{x}" dy="{dy}">{}</tspan>"#,
+                    text_layout::escape_xml(line)
+                )
+            })
+            .collect::<String>();
+        Ok(Value::scalar(svg))
+    }
+}
+
+#[derive(Clone)]
+pub struct WrapTextFilterParser;
+
+impl FilterReflection for WrapTextFilterParser {
+    fn name(&self) -> &str {
+        "wrap_text"
+    }
+    fn description(&self) -> &str {
+        "Wraps text to a pixel width and returns one <tspan> per line, for use inside <text>."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        const PARAMETERS: [ParameterReflection; 7] = [
+            ParameterReflection {
+                name: "width",
+                description: "Maximum line width in pixels",
+                is_optional: false,
+            },
+            FONT_FAMILY_PARAMETER,
+            FONT_SIZE_PARAMETER,
+            FONT_WEIGHT_PARAMETER,
+            ParameterReflection {
+                name: "line_height",
+                description: "Distance between baselines in pixels (default: 1.2 × font_size)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "x",
+                description: "x position of each line (default: 0)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "max_lines",
+                description: "Stop after this many lines, ending the last with an ellipsis",
+                is_optional: true,
+            },
+        ];
+        &PARAMETERS
+    }
+}
+
+impl ParseFilter for WrapTextFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut font = FontArgs::default();
+        let mut line_height = None;
+        let mut x = None;
+        let mut max_lines = None;
+
+        for (key, expr) in &mut arguments.keyword {
+            let Some(expr) = font.take(key, expr) else {
+                continue;
+            };
+            match key {
+                "width" => width = Some(expr),
+                "line_height" => line_height = Some(expr),
+                "x" => x = Some(expr),
+                "max_lines" => max_lines = Some(expr),
+                _ => {
+                    return Err(Error::with_msg(format!(
+                        "wrap_text: unknown argument '{key}'"
+                    )));
+                }
+            }
+        }
+
+        Ok(Box::new(WrapTextFilter {
+            width: width.ok_or_else(|| Error::with_msg("wrap_text: 'width' is required"))?,
+            font,
+            line_height,
+            x,
+            max_lines,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── `fit_text` filter ────────────────────────────────────────────────────────
+//
+// Usage:
+//   {% assign size = temperature | fit_text: width: 200, height: 80 %}
+//   <text font-size="{{ size }}">{{ temperature }}</text>
+
+#[derive(Debug)]
+pub struct FitTextFilter {
+    width: Expression,
+    height: Option<Expression>,
+    font: FontArgs,
+    min_size: Option<Expression>,
+    max_size: Option<Expression>,
+}
+
+impl fmt::Display for FitTextFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fit_text")
+    }
+}
+
+impl Filter for FitTextFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let width = eval_f32(&self.width, runtime, 0.0)?;
+        let height = eval_opt_f32(&self.height, runtime)?;
+        let min_size = eval_opt_f32(&self.min_size, runtime)?.unwrap_or(6.0);
+        let max_size = eval_opt_f32(&self.max_size, runtime)?.unwrap_or(64.0);
+        let style = TextStyle {
+            size: 1.0,
+            ..self.font.style(runtime)?
+        };
+
+        let text = text_layout::unescape_xml(&input.to_kstr());
+        let size = text_layout::with_measure(&style, |measure| {
+            text_layout::fit_size(&text, width, height, min_size, max_size, measure)
+        })
+        .map_err(|e| Error::with_msg(format!("fit_text filter: {e}")))?;
+        Ok(Value::scalar(size as i64))
+    }
+}
+
+#[derive(Clone)]
+pub struct FitTextFilterParser;
+
+impl FilterReflection for FitTextFilterParser {
+    fn name(&self) -> &str {
+        "fit_text"
+    }
+    fn description(&self) -> &str {
+        "Returns the largest whole font size at which the text fits on one line in a box."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[
+            ParameterReflection {
+                name: "width",
+                description: "Box width in pixels",
+                is_optional: false,
+            },
+            ParameterReflection {
+                name: "height",
+                description: "Box height in pixels; the size never exceeds it",
+                is_optional: true,
+            },
+            FONT_FAMILY_PARAMETER,
+            FONT_WEIGHT_PARAMETER,
+            ParameterReflection {
+                name: "min_size",
+                description: "Smallest size to return, even if the text overflows (default: 6)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "max_size",
+                description: "Largest size to return (default: 64)",
+                is_optional: true,
+            },
+        ]
+    }
+}
+
+impl ParseFilter for FitTextFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut height = None;
+        let mut font = FontArgs::default();
+        let mut min_size = None;
+        let mut max_size = None;
+
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "width" => width = Some(expr),
+                "height" => height = Some(expr),
+                "font_family" | "font_weight" => {
+                    font.take(key, expr);
+                }
+                "min_size" => min_size = Some(expr),
+                "max_size" => max_size = Some(expr),
+                _ => {
+                    return Err(Error::with_msg(format!(
+                        "fit_text: unknown argument '{key}'"
+                    )));
+                }
+            }
+        }
+
+        Ok(Box::new(FitTextFilter {
+            width: width.ok_or_else(|| Error::with_msg("fit_text: 'width' is required"))?,
+            height,
+            font,
+            min_size,
+            max_size,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── `truncate_px` filter ─────────────────────────────────────────────────────
+//
+// Usage:
+//   <text font-size="18">{{ event.title | truncate_px: width: 240, font_size: 18 }}</text>
+
+#[derive(Debug)]
+pub struct TruncatePxFilter {
+    width: Expression,
+    font: FontArgs,
+    ellipsis: Option<Expression>,
+}
+
+impl fmt::Display for TruncatePxFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "truncate_px")
+    }
+}
+
+impl Filter for TruncatePxFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let width = eval_f32(&self.width, runtime, 0.0)?;
+        let style = self.font.style(runtime)?;
+        let ellipsis = match &self.ellipsis {
+            Some(expr) => eval_str(expr, runtime)?,
+            None => "…".to_string(),
+        };
+
+        let text = text_layout::unescape_xml(&input.to_kstr());
+        let truncated = text_layout::with_measure(&style, |measure| {
+            text_layout::truncate(&text, width, &ellipsis, measure)
+        })
+        .map_err(|e| Error::with_msg(format!("truncate_px filter: {e}")))?;
+        Ok(Value::scalar(text_layout::escape_xml(&truncated)))
+    }
+}
+
+#[derive(Clone)]
+pub struct TruncatePxFilterParser;
+
+impl FilterReflection for TruncatePxFilterParser {
+    fn name(&self) -> &str {
+        "truncate_px"
+    }
+    fn description(&self) -> &str {
+        "Shortens text to a pixel width, ending it with an ellipsis when cut."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        const PARAMETERS: [ParameterReflection; 5] = [
+            ParameterReflection {
+                name: "width",
+                description: "Maximum width in pixels",
+                is_optional: false,
+            },
+            FONT_FAMILY_PARAMETER,
+            FONT_SIZE_PARAMETER,
+            FONT_WEIGHT_PARAMETER,
+            ParameterReflection {
+                name: "ellipsis",
+                description: "Text appended when cut (default: …)",
+                is_optional: true,
+            },
+        ];
+        &PARAMETERS
+    }
+}
+
+impl ParseFilter for TruncatePxFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut font = FontArgs::default();
+        let mut ellipsis = None;
+
+        for (key, expr) in &mut arguments.keyword {
+            let Some(expr) = font.take(key, expr) else {
+                continue;
+            };
+            match key {
+                "width" => width = Some(expr),
+                "ellipsis" => ellipsis = Some(expr),
+                _ => {
+                    return Err(Error::with_msg(format!(
+                        "truncate_px: unknown argument '{key}'"
+                    )));
+                }
+            }
+        }
+
+        Ok(Box::new(TruncatePxFilter {
+            width: width.ok_or_else(|| Error::with_msg("truncate_px: 'width' is required"))?,
+            font,
+            ellipsis,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── Chart filters ────────────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ prometheus_range.cpu[0] | sparkline: width: 120, height: 24 }}
+//   {{ prometheus_range.cpu | line_chart: width: 400, height: 120, axes: true, labels: true }}
+//   {{ prometheus_range.requests[0] | bar_chart: width: 300, height: 80, gridlines: 3 }}
+//   {{ prometheus_range.memory[0] | area_chart: width: 300, height: 80, min: 0, max: 100 }}
+
+#[derive(Debug)]
+pub struct ChartFilter {
+    kind: ChartKind,
+    width: Expression,
+    height: Expression,
+    /// The optional keyword arguments, checked against `CHART_PARAMETERS`.
+    options: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for ChartFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.filter_name())
+    }
+}
+
+impl Filter for ChartFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let name = self.kind.filter_name();
+        let series = charts::series_from_value(input)
+            .map_err(|e| Error::with_msg(format!("{name} filter: {e}")))?;
+
+        let mut options = ChartOptions::new(
+            self.kind,
+            eval_f32(&self.width, runtime, 0.0)?.max(0.0) as f64,
+            eval_f32(&self.height, runtime, 0.0)?.max(0.0) as f64,
+        );
+        for (key, expr) in &self.options {
+            let float =
+                |default: f64| Ok::<_, Error>(eval_f32(expr, runtime, default as f32)? as f64);
+            let opt_float = || {
+                Ok::<_, Error>(
+                    expr.evaluate(runtime)?
+                        .as_scalar()
+                        .and_then(|s| s.to_float()),
+                )
+            };
+            let truthy = || Ok::<_, Error>(expr.evaluate(runtime)?.query_state(State::Truthy));
+            match key.as_str() {
+                "x" => options.x = float(options.x)?,
+                "y" => options.y = float(options.y)?,
+                "min" => options.min = opt_float()?,
+                "max" => options.max = opt_float()?,
+                "stroke" => options.stroke = eval_str(expr, runtime)?,
+                "stroke_width" => options.stroke_width = float(options.stroke_width)?.max(0.0),
+                "fill" => options.fill = eval_str(expr, runtime)?,
+                "fill_opacity" => {
+                    options.fill_opacity = float(options.fill_opacity)?.clamp(0.0, 1.0)
+                }
+                "axes" => options.axes = truthy()?,
+                "gridlines" => options.gridlines = float(0.0)?.clamp(0.0, 20.0) as u32,
+                "labels" => options.labels = truthy()?,
+                "font_family" => options.font_family = eval_str(expr, runtime)?,
+                "font_size" => options.font_size = float(options.font_size)?.max(1.0),
+                _ => {}
+            }
+        }
+
+        Ok(Value::scalar(charts::chart_svg(
+            self.kind, &series, &options,
+        )))
+    }
+}
+
+/// Parses one of the chart filters, named after its kind.
+#[derive(Clone)]
+pub struct ChartFilterParser(pub ChartKind);
+
+const CHART_PARAMETERS: [ParameterReflection; 15] = [
+    ParameterReflection {
+        name: "width",
+        description: "Chart width in pixels",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "height",
+        description: "Chart height in pixels",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "x",
+        description: "Left edge of the chart (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "y",
+        description: "Top edge of the chart (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "min",
+        description: "Value at the bottom of the chart (default: the lowest value, or 0 for bars and areas)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "max",
+        description: "Value at the top of the chart (default: the highest value)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "stroke",
+        description: "Line, axis and label color (default: black)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "stroke_width",
+        description: "Line width in pixels (default: 1, or 2 for line_chart)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "fill",
+        description: "Bar and area color (default: black)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "fill_opacity",
+        description: "Bar and area opacity from 0 to 1 (default: 1)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "axes",
+        description: "Draw the left and bottom axes (default: false)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "gridlines",
+        description: "Number of dashed horizontal gridlines (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "labels",
+        description: "Label the top and bottom with the max and min values (default: false)",
+        is_optional: true,
+    },
+    FONT_FAMILY_PARAMETER,
+    ParameterReflection {
+        name: "font_size",
+        description: "Label font size in pixels (default: 10)",
+        is_optional: true,
+    },
+];
+
+impl FilterReflection for ChartFilterParser {
+    fn name(&self) -> &str {
+        self.0.filter_name()
+    }
+    fn description(&self) -> &str {
+        match self.0 {
+            ChartKind::Sparkline => "Draws a range series as a small undecorated line.",
+            ChartKind::Line => "Draws one or more range series as lines.",
+            ChartKind::Bar => "Draws one or more range series as bars.",
+            ChartKind::Area => "Draws one or more range series as filled areas.",
+        }
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &CHART_PARAMETERS
+    }
+}
+
+impl ParseFilter for ChartFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let name = self.0.filter_name();
+        let mut width = None;
+        let mut height = None;
+        let mut options = Vec::new();
+
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "width" => width = Some(expr),
+                "height" => height = Some(expr),
+                _ if CHART_PARAMETERS.iter().any(|p| p.name == key) => {
+                    options.push((key.to_string(), expr));
+                }
+                _ => {
+                    return Err(Error::with_msg(format!("{name}: unknown argument '{key}'")));
+                }
+            }
+        }
+
+        Ok(Box::new(ChartFilter {
+            kind: self.0,
+            width: width.ok_or_else(|| Error::with_msg(format!("{name}: 'width' is required")))?,
+            height: height
+                .ok_or_else(|| Error::with_msg(format!("{name}: 'height' is required")))?,
+            options,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── Widget filters ───────────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ prometheus.cpu[0].value | gauge: x: 10, y: 10, width: 120, height: 120, thresholds: "70,90", unit: "%" }}
+//   {{ job.percent | progress_bar: x: 10, y: 150, width: 200, height: 12 }}
+//   {{ device.battery_percent_charged | battery: x: 740, y: 8 }}
+//   {{ device.rssi | wifi_bars: x: 710, y: 10 }}
+//   {{ prometheus_range.temp[0] | big_number: width: 200, height: 100, label: "Office", unit: "°", decimals: 1 }}
+
+#[derive(Debug)]
+pub struct WidgetFilter {
+    kind: WidgetKind,
+    /// The keyword arguments, checked against the kind's parameters.
+    args: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for WidgetFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.filter_name())
+    }
+}
+
+impl WidgetFilter {
+    fn arg(&self, name: &str) -> Option<&Expression> {
+        self.args
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, expr)| expr)
+    }
+
+    fn float(&self, name: &str, runtime: &dyn Runtime) -> Result<Option<f64>> {
+        match self.arg(name) {
+            Some(expr) => Ok(expr
+                .evaluate(runtime)?
+                .as_scalar()
+                .and_then(|s| s.to_float())),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, name: &str, runtime: &dyn Runtime) -> Result<Option<String>> {
+        self.arg(name)
+            .map(|expr| eval_str(expr, runtime))
+            .transpose()
+    }
+
+    fn truthy(&self, name: &str, runtime: &dyn Runtime, default: bool) -> Result<bool> {
+        match self.arg(name) {
+            Some(expr) => Ok(expr.evaluate(runtime)?.query_state(State::Truthy)),
+            None => Ok(default),
+        }
+    }
+
+    /// The value formatted to `decimals` and followed by `unit`.
+    fn label(&self, value: f64, runtime: &dyn Runtime) -> Result<String> {
+        let decimals = self
+            .float("decimals", runtime)?
+            .map(|d| d.clamp(0.0, 6.0) as usize);
+        let unit = self.string("unit", runtime)?.unwrap_or_default();
+        Ok(format!("{}{unit}", widgets::format_value(value, decimals)))
+    }
+
+    /// Threshold values, from a list or a comma-separated string.
+    fn thresholds(&self, runtime: &dyn Runtime) -> Result<Vec<f64>> {
+        let Some(expr) = self.arg("thresholds") else {
+            return Ok(vec![]);
+        };
+        let value = expr.evaluate(runtime)?;
+        Ok(match value.as_array() {
+            Some(array) => array
+                .values()
+                .filter_map(|v| v.as_scalar()?.to_float())
+                .collect(),
+            None => value
+                .to_kstr()
+                .split(',')
+                .filter_map(|s| s.trim().parse().ok())
+                .collect(),
+        })
+    }
+}
+
+impl Filter for WidgetFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let (default_width, default_height) = self.kind.default_size();
+        let frame = Frame::new(
+            self.float("x", runtime)?.unwrap_or(0.0),
+            self.float("y", runtime)?.unwrap_or(0.0),
+            self.float("width", runtime)?.unwrap_or(default_width),
+            self.float("height", runtime)?.unwrap_or(default_height),
+        );
+        let number = input
+            .as_scalar()
+            .and_then(|s| s.to_float())
+            .filter(|n| n.is_finite());
+        let font_family = self
+            .string("font_family", runtime)?
+            .unwrap_or_else(|| "sans-serif".to_string());
+        let font_size = self.float("font_size", runtime)?.map(|size| size.max(1.0));
+
+        let svg = match self.kind {
+            WidgetKind::Gauge => {
+                let label = match (self.truthy("show_value", runtime, true)?, number) {
+                    (false, _) => None,
+                    (true, Some(n)) => Some(self.label(n, runtime)?),
+                    (true, None) => Some("–".to_string()),
+                };
+                let options = GaugeOptions {
+                    min: self.float("min", runtime)?.unwrap_or(0.0),
+                    max: self.float("max", runtime)?.unwrap_or(100.0),
+                    thresholds: self.thresholds(runtime)?,
+                    label,
+                    font_family,
+                    font_size,
+                };
+                widgets::gauge_svg(number.unwrap_or(f64::NAN), frame, &options)
+            }
+            WidgetKind::ProgressBar => {
+                let min = self.float("min", runtime)?.unwrap_or(0.0);
+                let max = self.float("max", runtime)?.unwrap_or(100.0);
+                let fraction = number.map_or(0.0, |n| widgets::fraction(n, min, max));
+                widgets::progress_bar_svg(fraction, frame)
+            }
+            WidgetKind::Battery => widgets::battery_svg(number, frame),
+            WidgetKind::WifiBars => widgets::wifi_bars_svg(number, frame),
+            WidgetKind::BigNumber => {
+                // A range series shows its last value and trends from its first.
+                let series = input.as_object();
+                let field = |key: &str| {
+                    series
+                        .and_then(|s| s.get(key))
+                        .and_then(|v| v.as_scalar()?.to_float())
+                        .filter(|n| n.is_finite())
+                };
+                let value = number.or_else(|| field("last"));
+                let previous = self.float("previous", runtime)?.or_else(|| field("first"));
+                let text = match value {
+                    Some(value) => self.label(value, runtime)?,
+                    None => "–".to_string(),
+                };
+                let options = BigNumberOptions {
+                    label: self.string("label", runtime)?,
+                    trend: value.zip(previous).and_then(|(v, p)| v.partial_cmp(&p)),
+                    border: self.truthy("border", runtime, true)?,
+                    font_family,
+                    font_size,
+                };
+                widgets::big_number_svg(&text, frame, &options)
+            }
+        };
+        Ok(Value::scalar(svg))
+    }
+}
+
+/// Parses one of the widget filters, named after its kind.
+#[derive(Clone)]
+pub struct WidgetFilterParser(pub WidgetKind);
+
+const WIDGET_X_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "x",
+    description: "Left edge of the widget (default: 0)",
+    is_optional: true,
+};
+const WIDGET_Y_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "y",
+    description: "Top edge of the widget (default: 0)",
+    is_optional: true,
+};
+const WIDGET_WIDTH_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "width",
+    description: "Widget width in pixels",
+    is_optional: true,
+};
+const WIDGET_HEIGHT_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "height",
+    description: "Widget height in pixels",
+    is_optional: true,
+};
+const WIDGET_MIN_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "min",
+    description: "Value of an empty widget (default: 0)",
+    is_optional: true,
+};
+const WIDGET_MAX_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "max",
+    description: "Value of a full widget (default: 100)",
+    is_optional: true,
+};
+const WIDGET_UNIT_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "unit",
+    description: "Text after the value",
+    is_optional: true,
+};
+const WIDGET_DECIMALS_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "decimals",
+    description: "Decimal places of the value (default: up to 2)",
+    is_optional: true,
+};
+const WIDGET_FONT_SIZE_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "font_size",
+    description: "Size of the value in pixels (default: fitted to the widget)",
+    is_optional: true,
+};
+
+const GAUGE_PARAMETERS: [ParameterReflection; 12] = [
+    WIDGET_X_PARAMETER,
+    WIDGET_Y_PARAMETER,
+    WIDGET_WIDTH_PARAMETER,
+    WIDGET_HEIGHT_PARAMETER,
+    WIDGET_MIN_PARAMETER,
+    WIDGET_MAX_PARAMETER,
+    ParameterReflection {
+        name: "thresholds",
+        description: "Values to mark with ticks, as a list or comma-separated",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "show_value",
+        description: "Write the value in the middle (default: true)",
+        is_optional: true,
+    },
+    WIDGET_UNIT_PARAMETER,
+    WIDGET_DECIMALS_PARAMETER,
+    FONT_FAMILY_PARAMETER,
+    WIDGET_FONT_SIZE_PARAMETER,
+];
+const PROGRESS_BAR_PARAMETERS: [ParameterReflection; 6] = [
+    WIDGET_X_PARAMETER,
+    WIDGET_Y_PARAMETER,
+    WIDGET_WIDTH_PARAMETER,
+    WIDGET_HEIGHT_PARAMETER,
+    WIDGET_MIN_PARAMETER,
+    WIDGET_MAX_PARAMETER,
+];
+const ICON_PARAMETERS: [ParameterReflection; 4] = [
+    WIDGET_X_PARAMETER,
+    WIDGET_Y_PARAMETER,
+    WIDGET_WIDTH_PARAMETER,
+    WIDGET_HEIGHT_PARAMETER,
+];
+const BIG_NUMBER_PARAMETERS: [ParameterReflection; 11] = [
+    WIDGET_X_PARAMETER,
+    WIDGET_Y_PARAMETER,
+    WIDGET_WIDTH_PARAMETER,
+    WIDGET_HEIGHT_PARAMETER,
+    ParameterReflection {
+        name: "previous",
+        description: "Earlier value the trend arrow compares with (default: a series' first value)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "label",
+        description: "Caption above the number",
+        is_optional: true,
+    },
+    WIDGET_UNIT_PARAMETER,
+    WIDGET_DECIMALS_PARAMETER,
+    ParameterReflection {
+        name: "border",
+        description: "Outline the tile (default: true)",
+        is_optional: true,
+    },
+    FONT_FAMILY_PARAMETER,
+    WIDGET_FONT_SIZE_PARAMETER,
+];
+
+impl FilterReflection for WidgetFilterParser {
+    fn name(&self) -> &str {
+        self.0.filter_name()
+    }
+    fn description(&self) -> &str {
+        match self.0 {
+            WidgetKind::Gauge => "Draws a value as a radial gauge with threshold ticks.",
+            WidgetKind::ProgressBar => "Draws a value as a horizontal progress bar.",
+            WidgetKind::Battery => "Draws a battery icon filled to a percentage.",
+            WidgetKind::WifiBars => "Draws Wi-Fi signal bars for an RSSI in dBm.",
+            WidgetKind::BigNumber => "Draws a value in a tile, with a caption and trend arrow.",
+        }
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        match self.0 {
+            WidgetKind::Gauge => &GAUGE_PARAMETERS,
+            WidgetKind::ProgressBar => &PROGRESS_BAR_PARAMETERS,
+            WidgetKind::Battery | WidgetKind::WifiBars => &ICON_PARAMETERS,
+            WidgetKind::BigNumber => &BIG_NUMBER_PARAMETERS,
+        }
+    }
+}
+
+impl ParseFilter for WidgetFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let parameters = self.keyword_parameters();
+        let mut args = Vec::new();
+
+        for (key, expr) in &mut arguments.keyword {
+            if !parameters.iter().any(|p| p.name == key) {
+                return Err(Error::with_msg(format!(
+                    "{}: unknown argument '{key}'",
+                    self.0.filter_name()
+                )));
+            }
+            args.push((key.to_string(), expr));
+        }
+
+        Ok(Box::new(WidgetFilter { kind: self.0, args }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── `icon` filter ────────────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ "cloud-rain" | icon: size: 48, x: 10, y: 10 }}
+//   {{ "warning" | icon: size: 16, stroke_width: 2.5 }}
+
+#[derive(Debug)]
+pub struct IconFilter {
+    size: Option<Expression>,
+    x: Option<Expression>,
+    y: Option<Expression>,
+    color: Option<Expression>,
+    stroke_width: Option<Expression>,
+}
+
+impl fmt::Display for IconFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "icon")
+    }
+}
+
+impl Filter for IconFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let size = eval_opt_f32(&self.size, runtime)?.unwrap_or(24.0).max(1.0);
+        let x = eval_opt_f32(&self.x, runtime)?.unwrap_or(0.0);
+        let y = eval_opt_f32(&self.y, runtime)?.unwrap_or(0.0);
+        let stroke_width = eval_opt_f32(&self.stroke_width, runtime)?.unwrap_or(2.0);
+        let color = match &self.color {
+            Some(expr) => eval_str(expr, runtime)?,
+            None => "black".to_string(),
+        };
+
+        let name = input.to_kstr();
+        let svg = icons::icon_svg(
+            &name,
+            x as f64,
+            y as f64,
+            size as f64,
+            &color,
+            stroke_width.max(0.0) as f64,
+        )
+        .ok_or_else(|| Error::with_msg(format!("icon filter: unknown icon '{name}'")))?;
+        Ok(Value::scalar(svg))
+    }
+}
+
+#[derive(Clone)]
+pub struct IconFilterParser;
+
+impl FilterReflection for IconFilterParser {
+    fn name(&self) -> &str {
+        "icon"
+    }
+    fn description(&self) -> &str {
+        "Draws a bundled icon by name as an inline SVG group."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[
+            ParameterReflection {
+                name: "size",
+                description: "Width and height in pixels (default: 24)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "x",
+                description: "Left edge of the icon (default: 0)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "y",
+                description: "Top edge of the icon (default: 0)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "color",
+                description: "Stroke color (default: black)",
+                is_optional: true,
+            },
+            ParameterReflection {
+                name: "stroke_width",
+                description: "Line width at 24px, scaled with the icon (default: 2)",
+                is_optional: true,
+            },
+        ]
+    }
+}
+
+impl ParseFilter for IconFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut size = None;
+        let mut x = None;
+        let mut y = None;
+        let mut color = None;
+        let mut stroke_width = None;
+
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "size" => size = Some(expr),
+                "x" => x = Some(expr),
+                "y" => y = Some(expr),
+                "color" => color = Some(expr),
+                "stroke_width" => stroke_width = Some(expr),
+                _ => return Err(Error::with_msg(format!("icon: unknown argument '{key}'"))),
+            }
+        }
+
+        Ok(Box::new(IconFilter {
+            size,
+            x,
+            y,
+            color,
+            stroke_width,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── Calendar filters ─────────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ calendar.work.events | agenda: x: 10, y: 10, width: 380, height: 400 }}
+//   {{ calendar.home.events | agenda: width: 300, limit: 5, font_size: 14, empty: "Nothing planned" }}
+//   {{ calendar.work.events | month_calendar: x: 420, y: 10, width: 350, height: 300 }}
+
+/// Events from a `calendar.<name>.events` list, or the `calendar.<name>`
+/// object holding it.
+fn calendar_entries(input: &dyn ValueView, filter: &str) -> Result<Vec<CalendarEntry>> {
+    let events = match input.as_object().and_then(|o| o.get("events")) {
+        Some(events) => events,
+        None => input,
+    };
+    if events.is_nil() {
+        return Ok(vec![]);
+    }
+    let array = events.as_array().ok_or_else(|| {
+        Error::with_msg(format!(
+            "{filter} filter: expected a list of calendar events"
+        ))
+    })?;
+    Ok(array
+        .values()
+        .filter_map(CalendarEntry::from_value)
+        .collect())
+}
+
+/// The `today` argument, or else the render's `date` variable.
+fn calendar_today(
+    today: Option<&Expression>,
+    runtime: &dyn Runtime,
+) -> Result<Option<chrono::NaiveDate>> {
+    let text = match today {
+        Some(expr) => eval_str(expr, runtime)?,
+        None => runtime
+            .try_get(&[ScalarCow::new("date")])
+            .map(|date| date.to_kstr().to_string())
+            .unwrap_or_default(),
+    };
+    Ok(calendar::parse_date(&text))
+}
+
+fn find_arg<'a>(args: &'a [(String, Expression)], name: &str) -> Option<&'a Expression> {
+    args.iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, expr)| expr)
+}
+
+#[derive(Debug)]
+pub struct AgendaFilter {
+    width: Expression,
+    font: FontArgs,
+    /// The other optional keyword arguments, checked against `AGENDA_PARAMETERS`.
+    options: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for AgendaFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "agenda")
+    }
+}
+
+impl Filter for AgendaFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let entries = calendar_entries(input, "agenda")?;
+        // Without a date, events are listed under their own days.
+        let today = calendar_today(find_arg(&self.options, "today"), runtime)?
+            .or_else(|| entries.first().map(|e| e.date))
+            .unwrap_or_default();
+        let mut options = AgendaOptions {
+            x: 0.0,
+            y: 0.0,
+            width: eval_f32(&self.width, runtime, 0.0)?.max(0.0) as f64,
+            height: None,
+            limit: None,
+            include_ended: false,
+            day_headings: true,
+            empty: "No upcoming events".to_string(),
+            style: self.font.style(runtime)?,
+        };
+        for (key, expr) in &self.options {
+            let float = || eval_f32(expr, runtime, 0.0).map(|n| n as f64);
+            let truthy = || Ok::<_, Error>(expr.evaluate(runtime)?.query_state(State::Truthy));
+            match key.as_str() {
+                "x" => options.x = float()?,
+                "y" => options.y = float()?,
+                "height" => options.height = Some(float()?.max(0.0)),
+                "limit" => options.limit = Some(eval_u32(expr, runtime, 1)? as usize),
+                "include_ended" => options.include_ended = truthy()?,
+                "day_headings" => options.day_headings = truthy()?,
+                "empty" => options.empty = eval_str(expr, runtime)?,
+                _ => {}
+            }
+        }
+        Ok(Value::scalar(calendar::agenda_svg(
+            &entries, today, &options,
+        )))
+    }
+}
+
+#[derive(Clone)]
+pub struct AgendaFilterParser;
+
+const AGENDA_PARAMETERS: [ParameterReflection; 12] = [
+    ParameterReflection {
+        name: "width",
+        description: "Width of the list in pixels; titles are cut to fit",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "height",
+        description: "Stop adding events before passing this height (default: no limit)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "x",
+        description: "Left edge of the list (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "y",
+        description: "Top edge of the list (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "limit",
+        description: "Most events to list (default: no limit)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "include_ended",
+        description: "Also list events that have already ended (default: false)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "day_headings",
+        description: "Group events under a heading for each day (default: true)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "empty",
+        description: "Text shown when there are no events (default: No upcoming events)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "today",
+        description: "Date the Today and Tomorrow headings count from (default: the render date)",
+        is_optional: true,
+    },
+    FONT_FAMILY_PARAMETER,
+    FONT_SIZE_PARAMETER,
+    FONT_WEIGHT_PARAMETER,
+];
+
+impl FilterReflection for AgendaFilterParser {
+    fn name(&self) -> &str {
+        "agenda"
+    }
+    fn description(&self) -> &str {
+        "Draws calendar events as a list of times and titles grouped by day."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &AGENDA_PARAMETERS
+    }
+}
+
+impl ParseFilter for AgendaFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut font = FontArgs::default();
+        let mut options = Vec::new();
+
+        for (key, expr) in &mut arguments.keyword {
+            let Some(expr) = font.take(key, expr) else {
+                continue;
+            };
+            match key {
+                "width" => width = Some(expr),
+                _ if AGENDA_PARAMETERS.iter().any(|p| p.name == key) => {
+                    options.push((key.to_string(), expr));
+                }
+                _ => return Err(Error::with_msg(format!("agenda: unknown argument '{key}'"))),
+            }
+        }
+
+        Ok(Box::new(AgendaFilter {
+            width: width.ok_or_else(|| Error::with_msg("agenda: 'width' is required"))?,
+            font,
+            options,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MonthCalendarFilter {
+    width: Expression,
+    height: Expression,
+    /// The optional keyword arguments, checked against `MONTH_CALENDAR_PARAMETERS`.
+    options: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for MonthCalendarFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "month_calendar")
+    }
+}
+
+impl Filter for MonthCalendarFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let entries = calendar_entries(input, "month_calendar")?;
+        let today = calendar_today(find_arg(&self.options, "today"), runtime)?;
+        let mut month = today;
+        let mut options = MonthOptions {
+            x: 0.0,
+            y: 0.0,
+            width: eval_f32(&self.width, runtime, 0.0)?.max(0.0) as f64,
+            height: eval_f32(&self.height, runtime, 0.0)?.max(0.0) as f64,
+            week_start: chrono::Weekday::Mon,
+            title: true,
+            font_family: "sans-serif".to_string(),
+            font_size: None,
+        };
+        for (key, expr) in &self.options {
+            let float = || eval_f32(expr, runtime, 0.0).map(|n| n as f64);
+            match key.as_str() {
+                "x" => options.x = float()?,
+                "y" => options.y = float()?,
+                "month" => {
+                    month = calendar::parse_date(&format!("{}-01", eval_str(expr, runtime)?))
+                }
+                "week_start" => {
+                    options.week_start = match eval_str(expr, runtime)?.to_lowercase().as_str() {
+                        "monday" => chrono::Weekday::Mon,
+                        "sunday" => chrono::Weekday::Sun,
+                        other => {
+                            return Err(Error::with_msg(format!(
+                                "month_calendar filter: week_start must be monday or sunday, not '{other}'"
+                            )));
+                        }
+                    }
+                }
+                "title" => options.title = expr.evaluate(runtime)?.query_state(State::Truthy),
+                "font_family" => options.font_family = eval_str(expr, runtime)?,
+                "font_size" => options.font_size = Some(float()?.max(1.0)),
+                _ => {}
+            }
+        }
+        let month = month
+            .or_else(|| entries.first().map(|e| e.date))
+            .ok_or_else(|| {
+                Error::with_msg(
+                    "month_calendar filter: no date to show the month of; pass month: \"YYYY-MM\"",
+                )
+            })?;
+
+        let marked = entries
+            .iter()
+            .flat_map(|e| e.date.iter_days().take_while(|day| *day <= e.end_date))
+            .collect();
+        Ok(Value::scalar(calendar::month_svg(
+            month, today, &marked, &options,
+        )))
+    }
+}
+
+#[derive(Clone)]
+pub struct MonthCalendarFilterParser;
+
+const MONTH_CALENDAR_PARAMETERS: [ParameterReflection; 10] = [
+    ParameterReflection {
+        name: "width",
+        description: "Grid width in pixels",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "height",
+        description: "Grid height in pixels, including the title and weekday rows",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "x",
+        description: "Left edge of the grid (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "y",
+        description: "Top edge of the grid (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "month",
+        description: "Month to show, as YYYY-MM (default: the month of today)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "today",
+        description: "Date drawn highlighted (default: the render date)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "week_start",
+        description: "First day of the week, monday or sunday (default: monday)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "title",
+        description: "Show the month and year above the grid (default: true)",
+        is_optional: true,
+    },
+    FONT_FAMILY_PARAMETER,
+    ParameterReflection {
+        name: "font_size",
+        description: "Day number size in pixels (default: fitted to the cells)",
+        is_optional: true,
+    },
+];
+
+impl FilterReflection for MonthCalendarFilterParser {
+    fn name(&self) -> &str {
+        "month_calendar"
+    }
+    fn description(&self) -> &str {
+        "Draws a month grid marking today and the days calendar events fall on."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &MONTH_CALENDAR_PARAMETERS
+    }
+}
+
+impl ParseFilter for MonthCalendarFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let mut width = None;
+        let mut height = None;
+        let mut options = Vec::new();
+
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "width" => width = Some(expr),
+                "height" => height = Some(expr),
+                _ if MONTH_CALENDAR_PARAMETERS.iter().any(|p| p.name == key) => {
+                    options.push((key.to_string(), expr));
+                }
+                _ => {
+                    return Err(Error::with_msg(format!(
+                        "month_calendar: unknown argument '{key}'"
+                    )));
+                }
+            }
+        }
+
+        Ok(Box::new(MonthCalendarFilter {
+            width: width.ok_or_else(|| Error::with_msg("month_calendar: 'width' is required"))?,
+            height: height
+                .ok_or_else(|| Error::with_msg("month_calendar: 'height' is required"))?,
+            options,
+        }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── Formatting filters ───────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ prometheus.requests[0].value | number: decimals: 1 }}          → 12,345.6
+//   {{ prometheus.disk_used[0].value | si: unit: "B" }}               → 1.2 GB
+//   {{ prometheus.memory[0].value | iec: unit: "B", decimals: 2 }}    → 1.15 GiB
+//   {{ prometheus.uptime[0].value | duration }}                       → 3h 12m
+//   {{ prometheus_range.cpu[0].points[0].t | time_ago }}              → 5 min ago
+//   {{ prometheus.ratio[0].value | percent: decimals: 1 }}            → 42.3%
+//   {{ http.weather.temp | convert: from: "c", to: "f" | round }}     → 68
+
+#[derive(Debug)]
+pub struct FormatFilter {
+    kind: FormatKind,
+    /// The keyword arguments, checked against the kind's parameters.
+    args: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for FormatFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.filter_name())
+    }
+}
+
+impl FormatFilter {
+    fn float(&self, name: &str, runtime: &dyn Runtime) -> Result<Option<f64>> {
+        match find_arg(&self.args, name) {
+            Some(expr) => Ok(expr
+                .evaluate(runtime)?
+                .as_scalar()
+                .and_then(|s| s.to_float())),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, name: &str, runtime: &dyn Runtime) -> Result<Option<String>> {
+        find_arg(&self.args, name)
+            .map(|expr| eval_str(expr, runtime))
+            .transpose()
+    }
+
+    fn decimals(&self, runtime: &dyn Runtime) -> Result<Option<usize>> {
+        Ok(self
+            .float("decimals", runtime)?
+            .map(|d| d.clamp(0.0, 10.0) as usize))
+    }
+}
+
+impl Filter for FormatFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let name = self.kind.filter_name();
+        // Missing values, such as a query with no results, render as nothing.
+        if input.is_nil() || input.to_kstr().trim().is_empty() {
+            return Ok(Value::Nil);
+        }
+        let parsed = match self.kind {
+            FormatKind::TimeAgo => formatting::parse_timestamp(&input.to_kstr()),
+            _ => input.as_scalar().and_then(|s| s.to_float()),
+        };
+        let value = parsed.ok_or_else(|| {
+            Error::with_msg(format!(
+                "{name} filter: expected a number, got '{}'",
+                input.to_kstr()
+            ))
+        })?;
+
+        let text = match self.kind {
+            FormatKind::Number => formatting::number(
+                value,
+                self.decimals(runtime)?.unwrap_or(0),
+                &self
+                    .string("separator", runtime)?
+                    .unwrap_or_else(|| ",".to_string()),
+                &self
+                    .string("decimal_point", runtime)?
+                    .unwrap_or_else(|| ".".to_string()),
+            ),
+            FormatKind::Si | FormatKind::Iec => {
+                let unit = self.string("unit", runtime)?.unwrap_or_default();
+                let decimals = self.decimals(runtime)?;
+                match self.kind {
+                    FormatKind::Si => formatting::si(value, decimals, &unit),
+                    _ => formatting::iec(value, decimals, &unit),
+                }
+            }
+            FormatKind::Duration => {
+                let scale = match self.string("unit", runtime)?.as_deref() {
+                    None | Some("s") => 1.0,
+                    Some("ms") => 0.001,
+                    Some("m") => 60.0,
+                    Some("h") => 3_600.0,
+                    Some("d") => 86_400.0,
+                    Some(other) => {
+                        return Err(Error::with_msg(format!(
+                            "duration filter: unit must be ms, s, m, h or d, not '{other}'"
+                        )));
+                    }
+                };
+                let parts = self.float("parts", runtime)?.unwrap_or(2.0).clamp(1.0, 4.0);
+                formatting::duration(value * scale, parts as usize)
+            }
+            FormatKind::TimeAgo => {
+                let now = match self.string("now", runtime)? {
+                    Some(now) => formatting::parse_timestamp(&now).ok_or_else(|| {
+                        Error::with_msg(format!("time_ago filter: invalid now '{now}'"))
+                    })?,
+                    None => RealClock.now_secs() as f64,
+                };
+                // Millisecond timestamps, as JavaScript writes them.
+                let value = if value > 1e11 { value / 1000.0 } else { value };
+                formatting::relative_time(value, now)
+            }
+            FormatKind::Percent => formatting::percent(
+                value,
+                self.float("of", runtime)?.unwrap_or(1.0),
+                self.decimals(runtime)?.unwrap_or(0),
+            ),
+            FormatKind::Convert => {
+                let from = self.string("from", runtime)?.unwrap_or_default();
+                let to = self.string("to", runtime)?.unwrap_or_default();
+                let converted = formatting::convert(value, &from, &to)
+                    .map_err(|e| Error::with_msg(format!("convert filter: {e}")))?;
+                return Ok(Value::scalar(converted));
+            }
+        };
+        Ok(Value::scalar(text))
+    }
+}
+
+/// Parses one of the formatting filters, named after its kind.
+#[derive(Clone)]
+pub struct FormatFilterParser(pub FormatKind);
+
+const FORMAT_DECIMALS_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "decimals",
+    description: "Digits after the decimal point (default: up to 1)",
+    is_optional: true,
+};
+const FORMAT_UNIT_PARAMETER: ParameterReflection = ParameterReflection {
+    name: "unit",
+    description: "Unit written after the prefix, as in kW (default: none)",
+    is_optional: true,
+};
+
+const NUMBER_PARAMETERS: [ParameterReflection; 3] = [
+    ParameterReflection {
+        name: "decimals",
+        description: "Digits after the decimal point (default: 0)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "separator",
+        description: "Thousands separator (default: ,)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "decimal_point",
+        description: "Decimal point (default: .)",
+        is_optional: true,
+    },
+];
+
+const PREFIX_PARAMETERS: [ParameterReflection; 2] =
+    [FORMAT_UNIT_PARAMETER, FORMAT_DECIMALS_PARAMETER];
+
+const DURATION_PARAMETERS: [ParameterReflection; 2] = [
+    ParameterReflection {
+        name: "unit",
+        description: "Unit of the input: ms, s, m, h or d (default: s)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "parts",
+        description: "Number of units to show, from 1 to 4 (default: 2)",
+        is_optional: true,
+    },
+];
+
+const TIME_AGO_PARAMETERS: [ParameterReflection; 1] = [ParameterReflection {
+    name: "now",
+    description: "Time to count from, as a unix time or date (default: the render time)",
+    is_optional: true,
+}];
+
+const PERCENT_PARAMETERS: [ParameterReflection; 2] = [
+    ParameterReflection {
+        name: "of",
+        description: "Value that is 100% (default: 1)",
+        is_optional: true,
+    },
+    ParameterReflection {
+        name: "decimals",
+        description: "Digits after the decimal point (default: 0)",
+        is_optional: true,
+    },
+];
+
+const CONVERT_PARAMETERS: [ParameterReflection; 2] = [
+    ParameterReflection {
+        name: "from",
+        description: "Unit of the input, such as c, mph or hpa",
+        is_optional: false,
+    },
+    ParameterReflection {
+        name: "to",
+        description: "Unit to convert to",
+        is_optional: false,
+    },
+];
+
+impl FilterReflection for FormatFilterParser {
+    fn name(&self) -> &str {
+        self.0.filter_name()
+    }
+    fn description(&self) -> &str {
+        match self.0 {
+            FormatKind::Number => "Formats a number with fixed decimals and thousands separators.",
+            FormatKind::Si => "Formats a number with an SI prefix, as in 3.4 kW.",
+            FormatKind::Iec => "Formats a number with a binary prefix, as in 1.2 GiB.",
+            FormatKind::Duration => "Formats a number of seconds as a duration, as in 3h 12m.",
+            FormatKind::TimeAgo => "Formats a unix time relative to now, as in 5 min ago.",
+            FormatKind::Percent => "Formats a ratio as a percentage.",
+            FormatKind::Convert => "Converts a temperature, speed, length or pressure.",
+        }
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        match self.0 {
+            FormatKind::Number => &NUMBER_PARAMETERS,
+            FormatKind::Si | FormatKind::Iec => &PREFIX_PARAMETERS,
+            FormatKind::Duration => &DURATION_PARAMETERS,
+            FormatKind::TimeAgo => &TIME_AGO_PARAMETERS,
+            FormatKind::Percent => &PERCENT_PARAMETERS,
+            FormatKind::Convert => &CONVERT_PARAMETERS,
+        }
+    }
+}
+
+impl ParseFilter for FormatFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let name = self.0.filter_name();
+        let parameters = self.keyword_parameters();
+        let mut args = Vec::new();
+
+        for (key, expr) in &mut arguments.keyword {
+            if !parameters.iter().any(|p| p.name == key) {
+                return Err(Error::with_msg(format!("{name}: unknown argument '{key}'")));
+            }
+            args.push((key.to_string(), expr));
+        }
+        if let Some(missing) = parameters
+            .iter()
+            .find(|p| !p.is_optional && !args.iter().any(|(key, _)| key == p.name))
+        {
+            return Err(Error::with_msg(format!(
+                "{name}: '{}' is required",
+                missing.name
+            )));
+        }
+
+        Ok(Box::new(FormatFilter { kind: self.0, args }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+// ─── `strftime` filter ────────────────────────────────────────────────────────
+//
+// Usage:
+//   {{ now | strftime: "%A %-d %B" }}
+//   {{ calendar.work.events[0].start | strftime: "%a %H:%M", locale: "de" }}
+//   {{ prometheus_range.cpu[0].points[0].t | strftime: "%H:%M" }}
+
+#[derive(Debug)]
+pub struct StrftimeFilter {
+    format: Expression,
+    locale: Option<Expression>,
+}
+
+impl fmt::Display for StrftimeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "strftime")
+    }
+}
+
+/// The instant `input` names: a unix time in seconds (or milliseconds), an
+/// object with a `timestamp` such as `now`, `"now"`, or a date or time string.
+/// Times without an offset are read in `tz`.
+fn input_time(
+    input: &dyn ValueView,
+    tz: &chrono_tz::Tz,
+) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone};
+
+    if let Some(timestamp) = input.as_object().and_then(|o| o.get("timestamp")) {
+        return input_time(timestamp, tz);
+    }
+    let text = input.to_kstr();
+    let text = text.trim();
+    if text == "now" || text == "today" {
+        return Some(chrono::Utc::now().with_timezone(tz));
+    }
+    if let Some(secs) = crate::device::formatting::parse_timestamp(text) {
+        // Millisecond timestamps, as JavaScript writes them.
+        let secs = if secs.abs() > 1e11 {
+            secs / 1000.0
+        } else {
+            secs
+        };
+        return chrono::DateTime::from_timestamp_millis((secs * 1000.0).round() as i64)
+            .map(|time| time.with_timezone(tz));
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.and_time(Default::default()))
+        })
+        .ok()?;
+    tz.from_local_datetime(&naive).earliest()
+}
+
+impl Filter for StrftimeFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        if input.is_nil() || input.to_kstr().trim().is_empty() {
+            return Ok(Value::Nil);
+        }
+        let tz = render_timezone()
+            .map_err(|e| Error::with_msg(format!("strftime filter: invalid TZ: {e}")))?;
+        let time = input_time(input, &tz).ok_or_else(|| {
+            Error::with_msg(format!(
+                "strftime filter: expected a timestamp or date, got '{}'",
+                input.to_kstr()
+            ))
+        })?;
+        let locale = match &self.locale {
+            Some(expr) => {
+                let tag = eval_str(expr, runtime)?;
+                locale::locale(&tag).ok_or_else(|| {
+                    Error::with_msg(format!(
+                        "strftime filter: unknown locale '{tag}' (known: {})",
+                        locale::locale_tags().join(", ")
+                    ))
+                })?
+            }
+            None => locale::render_locale(),
+        };
+        let format = eval_str(&self.format, runtime)?;
+        locale::strftime(&time, &format, locale)
+            .map(Value::scalar)
+            .map_err(|e| Error::with_msg(format!("strftime filter: {e}")))
+    }
+}
+
+#[derive(Clone)]
+pub struct StrftimeFilterParser;
+
+impl FilterReflection for StrftimeFilterParser {
+    fn name(&self) -> &str {
+        "strftime"
+    }
+    fn description(&self) -> &str {
+        "Formats a timestamp or date in the render timezone, with localized names."
+    }
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[ParameterReflection {
+            name: "format",
+            description: "strftime format, such as \"%A %-d %B\"",
+            is_optional: false,
+        }]
+    }
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[ParameterReflection {
+            name: "locale",
+            description: "Language of month and weekday names, such as de or fr (default: LOCALE)",
+            is_optional: true,
+        }]
+    }
+}
+
+impl ParseFilter for StrftimeFilterParser {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let format = arguments
+            .positional
+            .next()
+            .ok_or_else(|| Error::with_msg("strftime: a format is required"))?;
+        if arguments.positional.next().is_some() {
+            return Err(Error::with_msg("strftime: takes one format"));
+        }
+        let mut locale = None;
+        for (key, expr) in &mut arguments.keyword {
+            match key {
+                "locale" => locale = Some(expr),
+                _ => {
+                    return Err(Error::with_msg(format!(
+                        "strftime: unknown argument '{key}'"
+                    )));
+                }
+            }
+        }
+
+        Ok(Box::new(StrftimeFilter { format, locale }))
+    }
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
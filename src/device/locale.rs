@@ -0,0 +1,500 @@
+use std::fmt::Write;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, TimeZone};
+
+/// Month and weekday names of a language, for `strftime`. Weekdays start on
+/// Monday.
+#[derive(Debug, PartialEq)]
+pub struct Locale {
+    pub tag: &'static str,
+    pub weekdays: [&'static str; 7],
+    pub weekdays_short: [&'static str; 7],
+    pub months: [&'static str; 12],
+    pub months_short: [&'static str; 12],
+}
+
+const LOCALES: &[Locale] = &[
+    Locale {
+        tag: "en",
+        weekdays: [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ],
+        weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        months: [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+        months_short: [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+    },
+    Locale {
+        tag: "de",
+        weekdays: [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+        weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        months: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        months_short: [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+    },
+    Locale {
+        tag: "fr",
+        weekdays: [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ],
+        weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        months: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        months_short: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+    },
+    Locale {
+        tag: "es",
+        weekdays: [
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_short: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        months: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        months_short: [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+    },
+    Locale {
+        tag: "it",
+        weekdays: [
+            "lunedì",
+            "martedì",
+            "mercoledì",
+            "giovedì",
+            "venerdì",
+            "sabato",
+            "domenica",
+        ],
+        weekdays_short: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+        months: [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+        months_short: [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+    },
+    Locale {
+        tag: "nl",
+        weekdays: [
+            "maandag",
+            "dinsdag",
+            "woensdag",
+            "donderdag",
+            "vrijdag",
+            "zaterdag",
+            "zondag",
+        ],
+        weekdays_short: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+        months: [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+    },
+    Locale {
+        tag: "pt",
+        weekdays: [
+            "segunda-feira",
+            "terça-feira",
+            "quarta-feira",
+            "quinta-feira",
+            "sexta-feira",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_short: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+        months: [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+        months_short: [
+            "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+        ],
+    },
+    Locale {
+        tag: "sv",
+        weekdays: [
+            "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag", "söndag",
+        ],
+        weekdays_short: ["mån", "tis", "ons", "tors", "fre", "lör", "sön"],
+        months: [
+            "januari",
+            "februari",
+            "mars",
+            "april",
+            "maj",
+            "juni",
+            "juli",
+            "augusti",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan", "feb", "mars", "apr", "maj", "juni", "juli", "aug", "sep", "okt", "nov", "dec",
+        ],
+    },
+    Locale {
+        tag: "da",
+        weekdays: [
+            "mandag", "tirsdag", "onsdag", "torsdag", "fredag", "lørdag", "søndag",
+        ],
+        weekdays_short: ["man", "tir", "ons", "tor", "fre", "lør", "søn"],
+        months: [
+            "januar",
+            "februar",
+            "marts",
+            "april",
+            "maj",
+            "juni",
+            "juli",
+            "august",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+    },
+    Locale {
+        tag: "nb",
+        weekdays: [
+            "mandag", "tirsdag", "onsdag", "torsdag", "fredag", "lørdag", "søndag",
+        ],
+        weekdays_short: ["man", "tir", "ons", "tor", "fre", "lør", "søn"],
+        months: [
+            "januar",
+            "februar",
+            "mars",
+            "april",
+            "mai",
+            "juni",
+            "juli",
+            "august",
+            "september",
+            "oktober",
+            "november",
+            "desember",
+        ],
+        months_short: [
+            "jan", "feb", "mar", "apr", "mai", "jun", "jul", "aug", "sep", "okt", "nov", "des",
+        ],
+    },
+    Locale {
+        tag: "fi",
+        weekdays: [
+            "maanantai",
+            "tiistai",
+            "keskiviikko",
+            "torstai",
+            "perjantai",
+            "lauantai",
+            "sunnuntai",
+        ],
+        weekdays_short: ["ma", "ti", "ke", "to", "pe", "la", "su"],
+        months: [
+            "tammikuu",
+            "helmikuu",
+            "maaliskuu",
+            "huhtikuu",
+            "toukokuu",
+            "kesäkuu",
+            "heinäkuu",
+            "elokuu",
+            "syyskuu",
+            "lokakuu",
+            "marraskuu",
+            "joulukuu",
+        ],
+        months_short: [
+            "tammi", "helmi", "maalis", "huhti", "touko", "kesä", "heinä", "elo", "syys", "loka",
+            "marras", "joulu",
+        ],
+    },
+    Locale {
+        tag: "pl",
+        weekdays: [
+            "poniedziałek",
+            "wtorek",
+            "środa",
+            "czwartek",
+            "piątek",
+            "sobota",
+            "niedziela",
+        ],
+        weekdays_short: ["pon", "wt", "śr", "czw", "pt", "sob", "niedz"],
+        months: [
+            "styczeń",
+            "luty",
+            "marzec",
+            "kwiecień",
+            "maj",
+            "czerwiec",
+            "lipiec",
+            "sierpień",
+            "wrzesień",
+            "październik",
+            "listopad",
+            "grudzień",
+        ],
+        months_short: [
+            "sty", "lut", "mar", "kwi", "maj", "cze", "lip", "sie", "wrz", "paź", "lis", "gru",
+        ],
+    },
+];
+
+/// The locale for a language tag such as `de`, `de-AT` or `de_DE.UTF-8`;
+/// only the language is used.
+pub fn locale(tag: &str) -> Option<&'static Locale> {
+    let language = tag
+        .split(['-', '_', '.'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let language = match language.as_str() {
+        "no" | "nn" => "nb",
+        "c" | "posix" => "en",
+        language => language,
+    };
+    LOCALES.iter().find(|l| l.tag == language)
+}
+
+/// Tags of every bundled locale.
+pub fn locale_tags() -> Vec<&'static str> {
+    LOCALES.iter().map(|l| l.tag).collect()
+}
+
+/// The locale renders use: `LOCALE`, else `LANG`, else English. Unknown
+/// languages fall back to English too.
+pub fn render_locale() -> &'static Locale {
+    ["LOCALE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find_map(|tag| locale(&tag))
+        .unwrap_or(&LOCALES[0])
+}
+
+/// Formats `time` with strftime-style `format`, writing `%A`, `%a`, `%B`,
+/// `%b` and `%h` in `locale`. The rest is chrono's strftime.
+pub fn strftime<Tz: TimeZone>(
+    time: &DateTime<Tz>,
+    format: &str,
+    locale: &Locale,
+) -> Result<String, String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let weekday = time.weekday().num_days_from_monday() as usize;
+    let month = time.month0() as usize;
+
+    // Substitute the names first, escaping any `%` they might contain.
+    let mut localized = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        let mut spec = String::from('%');
+        while let Some(&flag) = chars
+            .peek()
+            .filter(|f| matches!(f, '-' | '_' | '0' | '^' | '#'))
+        {
+            spec.push(flag);
+            chars.next();
+        }
+        let name = match chars.peek() {
+            Some('A') => Some(locale.weekdays[weekday]),
+            Some('a') => Some(locale.weekdays_short[weekday]),
+            Some('B') => Some(locale.months[month]),
+            Some('b' | 'h') => Some(locale.months_short[month]),
+            _ => None,
+        };
+        match name {
+            Some(name) => {
+                chars.next();
+                let name = match spec.contains('^') {
+                    true => name.to_uppercase(),
+                    false => name.to_string(),
+                };
+                localized.push_str(&name.replace('%', "%%"));
+            }
+            None => {
+                localized.push_str(&spec);
+                if let Some(next) = chars.next() {
+                    localized.push(next);
+                }
+            }
+        }
+    }
+
+    let items: Vec<Item> = StrftimeItems::new(&localized).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid format '{format}'"));
+    }
+    let mut out = String::new();
+    write!(out, "{}", time.format_with_items(items.into_iter()))
+        .map_err(|_| format!("invalid format '{format}'"))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Tz;
+
+    #[test]
+    fn test_locale_tags() {
+        assert_eq!(locale("de_DE.UTF-8").unwrap().tag, "de");
+        assert_eq!(locale("pt-BR").unwrap().tag, "pt");
+        assert_eq!(locale("no").unwrap().tag, "nb");
+        assert_eq!(locale("C.UTF-8").unwrap().tag, "en");
+        assert!(locale("xx").is_none());
+        for locale in LOCALES {
+            assert!(
+                locale
+                    .weekdays
+                    .iter()
+                    .chain(&locale.months)
+                    .all(|n| !n.is_empty()),
+                "{}",
+                locale.tag
+            );
+        }
+    }
+
+    #[test]
+    fn test_strftime_localizes_names() {
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        let time = tz.with_ymd_and_hms(2026, 3, 2, 7, 5, 0).unwrap();
+        let de = locale("de").unwrap();
+        assert_eq!(
+            strftime(&time, "%A, %-d. %B %Y %H:%M", de).unwrap(),
+            "Montag, 2. März 2026 07:05"
+        );
+        assert_eq!(
+            strftime(&time, "%a %b %^a %% %z", locale("fr").unwrap()).unwrap(),
+            "lun. mars LUN. % +0100"
+        );
+        assert_eq!(
+            strftime(&time, "%a %d %b %I:%M %p", locale("en").unwrap()).unwrap(),
+            "Mon 02 Mar 07:05 AM"
+        );
+        assert_eq!(
+            strftime(&time, "%Q", de).unwrap_err(),
+            "invalid format '%Q'"
+        );
+    }
+}
@@ -9,8 +9,25 @@ use crate::{
 use thiserror::Error;
 
 pub mod api;
+pub(crate) mod assets;
+pub(crate) mod calendar;
+pub(crate) mod charts;
+pub(crate) mod dither;
+pub(crate) mod error_screen;
+pub(crate) mod fonts;
+pub(crate) mod formatting;
+pub(crate) mod icons;
 pub(crate) mod liquid_filters;
+pub(crate) mod locale;
+pub(crate) mod partials;
+pub(crate) mod render_cache;
+pub(crate) mod render_limits;
+pub(crate) mod render_stats;
 pub(crate) mod renderer;
+pub(crate) mod snapshots;
+pub(crate) mod text_layout;
+pub(crate) mod validation;
+pub(crate) mod widgets;
 
 #[derive(Error, Debug)]
 pub enum Error {
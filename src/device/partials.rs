@@ -0,0 +1,355 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use liquid_core::parser::Language;
+use liquid_core::partials::{PartialCompiler, PartialSource};
+use liquid_core::runtime::{PartialStore, Renderable, Runtime};
+use liquid_core::{Error, Result};
+
+/// The shared snippets, keyed by name, that `{% include %}` and `{% render %}`
+/// resolve while a template renders.
+#[derive(Clone, Default)]
+pub struct RenderPartials(Arc<HashMap<String, String>>);
+
+impl RenderPartials {
+    pub async fn load() -> std::result::Result<Self, sqlx::Error> {
+        let partials = crate::db::get_partials()
+            .await?
+            .into_iter()
+            .map(|partial| (partial.name, partial.content));
+        Ok(Self::new(partials))
+    }
+
+    pub fn new(partials: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self(Arc::new(partials.into_iter().collect()))
+    }
+
+    /// Makes these partials visible to the Liquid parser's partial store
+    /// while `f` runs. As with `RenderAssets::scope`, rendering is
+    /// synchronous, so a thread-local is enough.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|c| c.replace(Some(self.clone())));
+        let _restore = Restore(previous);
+        f()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<RenderPartials>> = const { RefCell::new(None) };
+    /// Names of the partials being rendered, outermost first.
+    static STACK: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Restore(Option<RenderPartials>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+fn current_content(name: &str) -> Option<String> {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .and_then(|partials| partials.0.get(name).cloned())
+    })
+}
+
+fn current_names() -> Vec<String> {
+    CURRENT.with(|current| {
+        let mut names: Vec<String> = current
+            .borrow()
+            .iter()
+            .flat_map(|partials| partials.0.keys().cloned())
+            .collect();
+        names.sort();
+        names
+    })
+}
+
+/// Checks a partial name: letters, digits, `_`, `-`, `.` and `/`.
+pub fn check_name(name: &str) -> std::result::Result<(), String> {
+    if name.is_empty() {
+        return Err("Enter a partial name".to_string());
+    }
+    let allowed = |c: char| c.is_ascii_alphanumeric() || "_-./".contains(c);
+    match name.chars().all(allowed) {
+        true => Ok(()),
+        false => Err(format!(
+            "'{name}' may only contain letters, digits, '_', '-', '.' and '/'"
+        )),
+    }
+}
+
+/// Names of the partials `content` includes or renders by a quoted name.
+/// Names chosen at render time (`{% include name %}`) can't be known here.
+pub fn references(content: &str) -> Vec<String> {
+    let mut names = vec![];
+    for tag in content.split("{%").skip(1) {
+        let tag = tag.split("%}").next().unwrap_or_default();
+        let tag = tag.trim_start_matches('-').trim_start();
+        let Some(rest) = tag
+            .strip_prefix("include")
+            .or_else(|| tag.strip_prefix("render"))
+        else {
+            continue;
+        };
+        if !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let rest = rest.trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        if let Some((name, _)) = rest[1..].split_once(quote) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// A chain of partials that leads from `start` back to itself, such as
+/// `["header", "logo", "header"]`, if `partials` (name → content) has one.
+pub fn find_cycle(partials: &HashMap<String, String>, start: &str) -> Option<Vec<String>> {
+    fn visit(
+        partials: &HashMap<String, String>,
+        start: &str,
+        path: &mut Vec<String>,
+        seen: &mut Vec<String>,
+    ) -> bool {
+        let name = path.last().expect("path starts with the start").clone();
+        let Some(content) = partials.get(&name) else {
+            return false;
+        };
+        for next in references(content) {
+            if next == start {
+                path.push(next);
+                return true;
+            }
+            if seen.contains(&next) {
+                continue;
+            }
+            seen.push(next.clone());
+            path.push(next);
+            if visit(partials, start, path, seen) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    let mut path = vec![start.to_string()];
+    visit(partials, start, &mut path, &mut vec![]).then_some(path)
+}
+
+/// A parsed partial. Rendering it fails when it is already being rendered
+/// further out, so a partial that includes itself, directly or through
+/// others, errors instead of recursing until the stack overflows.
+#[derive(Debug)]
+struct CompiledPartial {
+    name: String,
+    content_hash: u64,
+    template: liquid_core::runtime::Template,
+}
+
+impl Renderable for CompiledPartial {
+    fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> Result<()> {
+        let cycle = STACK.with(|stack| {
+            let stack = stack.borrow();
+            stack
+                .iter()
+                .position(|name| *name == self.name)
+                .map(|start| {
+                    let mut cycle = stack[start..].to_vec();
+                    cycle.push(self.name.clone());
+                    cycle.join(" → ")
+                })
+        });
+        if let Some(cycle) = cycle {
+            return Err(Error::with_msg(format!("partial cycle: {cycle}")));
+        }
+
+        STACK.with(|stack| stack.borrow_mut().push(self.name.clone()));
+        let _pop = PopStack;
+        self.template.render_to(writer, runtime)
+    }
+}
+
+struct PopStack;
+
+impl Drop for PopStack {
+    fn drop(&mut self) {
+        STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// Parsed partials by name, reparsed when their content changes.
+fn partial_cache() -> &'static Mutex<HashMap<String, Arc<CompiledPartial>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<CompiledPartial>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Drops the parsed copy of a partial, after it is renamed or deleted.
+pub fn invalidate_partial(name: &str) {
+    partial_cache()
+        .lock()
+        .expect("partial cache lock poisoned")
+        .remove(name);
+}
+
+/// The parser's partial source: the partials of the render in progress on
+/// this thread (see `RenderPartials::scope`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CurrentPartials;
+
+impl PartialSource for CurrentPartials {
+    fn contains(&self, name: &str) -> bool {
+        current_content(name).is_some()
+    }
+
+    // The names live in a thread-local, so they can't be borrowed from `self`;
+    // `PartialStore::get` lists them in its own error instead.
+    fn names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn try_get<'a>(&'a self, name: &str) -> Option<Cow<'a, str>> {
+        current_content(name).map(Cow::Owned)
+    }
+}
+
+impl PartialCompiler for CurrentPartials {
+    fn compile(self, language: Arc<Language>) -> Result<Box<dyn PartialStore + Send + Sync>> {
+        Ok(Box::new(CurrentPartialStore { language }))
+    }
+
+    fn source(&self) -> &dyn PartialSource {
+        self
+    }
+}
+
+struct CurrentPartialStore {
+    language: Arc<Language>,
+}
+
+impl fmt::Debug for CurrentPartialStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CurrentPartialStore")
+    }
+}
+
+impl PartialStore for CurrentPartialStore {
+    fn contains(&self, name: &str) -> bool {
+        CurrentPartials.contains(name)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn try_get(&self, name: &str) -> Option<Arc<dyn Renderable>> {
+        self.get(name).ok()
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<dyn Renderable>> {
+        let Some(content) = current_content(name) else {
+            // `render` retries a missing name with `.liquid` appended.
+            let name = name.strip_suffix(".liquid").unwrap_or(name);
+            let known = current_names();
+            return Err(match known.is_empty() {
+                true => Error::with_msg(format!("unknown partial '{name}' (none saved)")),
+                false => Error::with_msg(format!(
+                    "unknown partial '{name}' (known: {})",
+                    known.join(", ")
+                )),
+            });
+        };
+
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let content_hash = hasher.finish();
+        if let Some(cached) = partial_cache()
+            .lock()
+            .expect("partial cache lock poisoned")
+            .get(name)
+            && cached.content_hash == content_hash
+        {
+            return Ok(cached.clone());
+        }
+
+        let template = liquid_core::parser::parse(&content, &self.language)
+            .map(liquid_core::runtime::Template::new)
+            .map_err(|e| Error::with_msg(format!("partial '{name}': {e}")))?;
+        let compiled = Arc::new(CompiledPartial {
+            name: name.to_string(),
+            content_hash,
+            template,
+        });
+        partial_cache()
+            .lock()
+            .expect("partial cache lock poisoned")
+            .insert(name.to_string(), compiled.clone());
+        Ok(compiled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partials(list: &[(&str, &str)]) -> HashMap<String, String> {
+        list.iter()
+            .map(|(name, content)| (name.to_string(), content.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_references_finds_quoted_include_and_render_names() {
+        let content = r#"{% include "header" %}{%- render 'footer', title: t -%}
+            {% include name %}{% rendered "x" %}"#;
+        assert_eq!(references(content), ["header", "footer"]);
+    }
+
+    #[test]
+    fn test_find_cycle_follows_references_back_to_the_start() {
+        let list = partials(&[
+            ("header", r#"{% render "logo" %}{% render "clock" %}"#),
+            ("clock", "{{ now.hour }}"),
+            ("logo", r#"{% include "badge" %}"#),
+            ("badge", r#"{% include "header" %}"#),
+        ]);
+        assert_eq!(
+            find_cycle(&list, "header"),
+            Some(vec![
+                "header".to_string(),
+                "logo".to_string(),
+                "badge".to_string(),
+                "header".to_string()
+            ])
+        );
+        assert_eq!(find_cycle(&list, "clock"), None);
+
+        let list = partials(&[("self", r#"{% include "self" %}"#)]);
+        assert_eq!(
+            find_cycle(&list, "self"),
+            Some(vec!["self".to_string(), "self".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_check_name_allows_paths_but_not_spaces_or_quotes() {
+        assert!(check_name("widgets/header-bar_2.svg").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("header bar").is_err());
+        assert!(check_name("x\"y").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use axum_prometheus::metrics;
+
+use crate::device::dither::DitherOptions;
+use crate::models::{Dither, ImageFormat};
+
+/// Identifies an encoded screen: the rendered SVG plus every output setting
+/// that affects the bytes sent to the device. Devices on the same template with
+/// the same settings share entries.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderKey {
+    svg_hash: u64,
+    levels: u8,
+    dither: Dither,
+    threshold: u8,
+    gamma_bits: u32,
+    format: ImageFormat,
+}
+
+impl RenderKey {
+    pub fn new(svg: &str, levels: u8, options: &DitherOptions, format: ImageFormat) -> Self {
+        let mut hasher = DefaultHasher::new();
+        svg.hash(&mut hasher);
+        Self {
+            svg_hash: hasher.finish(),
+            levels,
+            dither: options.algorithm,
+            threshold: options.threshold,
+            gamma_bits: options.gamma.to_bits(),
+            format,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderCacheLimits {
+    pub max_bytes: usize,
+    pub max_entries: usize,
+    pub ttl_secs: i64,
+}
+
+impl RenderCacheLimits {
+    /// Reads `RENDER_CACHE_MAX_BYTES` (default 32 MiB), `RENDER_CACHE_MAX_ENTRIES`
+    /// (default 256) and `RENDER_CACHE_TTL_SECS` (default 300). A zero size or
+    /// TTL disables caching.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+        Self {
+            max_bytes: var("RENDER_CACHE_MAX_BYTES", 32 * 1024 * 1024),
+            max_entries: var("RENDER_CACHE_MAX_ENTRIES", 256),
+            ttl_secs: var("RENDER_CACHE_TTL_SECS", 300),
+        }
+    }
+}
+
+struct Entry {
+    image: Arc<Vec<u8>>,
+    inserted_at: i64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<RenderKey, Entry>,
+    bytes: usize,
+    /// Monotonic use counter for least-recently-used eviction.
+    tick: u64,
+}
+
+/// Bounded, TTL-expiring cache of encoded screens. Times are passed in as unix
+/// seconds so the expiry logic can be tested without a clock.
+pub struct RenderCache {
+    limits: RenderCacheLimits,
+    inner: Mutex<Inner>,
+}
+
+impl RenderCache {
+    pub fn new(limits: RenderCacheLimits) -> Self {
+        Self {
+            limits,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn get(&self, key: &RenderKey, now_secs: i64) -> Option<Arc<Vec<u8>>> {
+        let mut inner = self.inner.lock().expect("render cache lock poisoned");
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let expired = match inner.entries.get_mut(key) {
+            Some(entry) if now_secs - entry.inserted_at < self.limits.ttl_secs => {
+                entry.last_used = tick;
+                metrics::counter!("render_cache_hits_total").increment(1);
+                return Some(entry.image.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        if expired {
+            inner.remove(key);
+            metrics::counter!("render_cache_evictions_total", "reason" => "expired").increment(1);
+            inner.record_size();
+        }
+        metrics::counter!("render_cache_misses_total").increment(1);
+        None
+    }
+
+    pub fn insert(&self, key: RenderKey, image: Arc<Vec<u8>>, now_secs: i64) {
+        if self.limits.ttl_secs <= 0
+            || self.limits.max_entries == 0
+            || image.len() > self.limits.max_bytes
+        {
+            return;
+        }
+
+        let mut inner = self.inner.lock().expect("render cache lock poisoned");
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        inner.remove(&key);
+        inner.bytes += image.len();
+        inner.entries.insert(
+            key,
+            Entry {
+                image,
+                inserted_at: now_secs,
+                last_used: tick,
+            },
+        );
+
+        while inner.bytes > self.limits.max_bytes || inner.entries.len() > self.limits.max_entries {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            inner.remove(&oldest);
+            metrics::counter!("render_cache_evictions_total", "reason" => "size").increment(1);
+        }
+        inner.record_size();
+    }
+
+    /// Drops every entry, e.g. after fonts change and identical SVG would
+    /// render differently.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().expect("render cache lock poisoned");
+        inner.entries.clear();
+        inner.bytes = 0;
+        inner.record_size();
+    }
+}
+
+impl Inner {
+    fn remove(&mut self, key: &RenderKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.image.len();
+        }
+    }
+
+    fn record_size(&self) {
+        metrics::gauge!("render_cache_bytes").set(self.bytes as f64);
+        metrics::gauge!("render_cache_entries").set(self.entries.len() as f64);
+    }
+}
+
+/// Process-wide cache shared by every render.
+pub fn render_cache() -> &'static RenderCache {
+    static CACHE: OnceLock<RenderCache> = OnceLock::new();
+    CACHE.get_or_init(|| RenderCache::new(RenderCacheLimits::from_env()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> RenderCacheLimits {
+        RenderCacheLimits {
+            max_bytes: 10,
+            max_entries: 3,
+            ttl_secs: 60,
+        }
+    }
+
+    fn key(svg: &str) -> RenderKey {
+        RenderKey::new(svg, 2, &DitherOptions::default(), ImageFormat::Bmp)
+    }
+
+    fn image(len: usize) -> Arc<Vec<u8>> {
+        Arc::new(vec![0; len])
+    }
+
+    #[test]
+    fn test_cached_image_is_returned_until_ttl_expires() {
+        let cache = RenderCache::new(limits());
+        cache.insert(key("<svg/>"), image(4), 1_000);
+        assert_eq!(cache.get(&key("<svg/>"), 1_059).map(|i| i.len()), Some(4));
+        assert!(
+            cache.get(&key("<svg/>"), 1_060).is_none(),
+            "entry should expire after the TTL"
+        );
+    }
+
+    #[test]
+    fn test_output_settings_are_part_of_the_key() {
+        let cache = RenderCache::new(limits());
+        cache.insert(key("<svg/>"), image(4), 0);
+
+        let png = RenderKey::new("<svg/>", 2, &DitherOptions::default(), ImageFormat::Png);
+        assert!(
+            cache.get(&png, 0).is_none(),
+            "format must not share entries"
+        );
+
+        let dithered = DitherOptions {
+            algorithm: Dither::Atkinson,
+            ..Default::default()
+        };
+        let atkinson = RenderKey::new("<svg/>", 2, &dithered, ImageFormat::Bmp);
+        assert!(
+            cache.get(&atkinson, 0).is_none(),
+            "dithering must not share entries"
+        );
+
+        assert!(
+            cache.get(&key("<svg />"), 0).is_none(),
+            "different SVG must miss"
+        );
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted_over_size_limit() {
+        let cache = RenderCache::new(limits());
+        cache.insert(key("a"), image(4), 0);
+        cache.insert(key("b"), image(4), 0);
+        cache.get(&key("a"), 0);
+        cache.insert(key("c"), image(4), 0);
+
+        assert!(
+            cache.get(&key("a"), 0).is_some(),
+            "recently used entry stays"
+        );
+        assert!(cache.get(&key("b"), 0).is_none(), "LRU entry is evicted");
+        assert!(cache.get(&key("c"), 0).is_some());
+    }
+
+    #[test]
+    fn test_entry_count_limit_and_oversized_images() {
+        let cache = RenderCache::new(limits());
+        for svg in ["a", "b", "c", "d"] {
+            cache.insert(key(svg), image(1), 0);
+        }
+        assert!(
+            cache.get(&key("a"), 0).is_none(),
+            "oldest of four is evicted"
+        );
+        assert!(cache.get(&key("d"), 0).is_some());
+
+        cache.insert(key("huge"), image(11), 0);
+        assert!(
+            cache.get(&key("huge"), 0).is_none(),
+            "images larger than the cache are not stored"
+        );
+        assert!(cache.get(&key("d"), 0).is_some(), "and evict nothing");
+    }
+}
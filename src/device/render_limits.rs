@@ -0,0 +1,620 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use liquid_core::error::ResultLiquidExt;
+use liquid_core::model::{KString, Scalar, Value, ValueCow, ValueView};
+use liquid_core::parser::{BlockElement, TryMatchToken};
+use liquid_core::runtime::{Interrupt, InterruptRegister, StackFrame};
+use liquid_core::{
+    BlockReflection, Error, Expression, Language, ParseBlock, Renderable, Result, Runtime,
+    TagBlock, TagTokenIter, Template,
+};
+
+/// Bounds on the work a single screen render may do, so one bad template
+/// can't hang or exhaust the server.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderLimits {
+    /// Wall-clock budget for a whole render, data sources included.
+    pub timeout: Duration,
+    /// Largest SVG a template may produce.
+    pub max_output_bytes: usize,
+    /// Total `for` loop iterations across one template render.
+    pub max_loop_iterations: usize,
+    /// Largest raster width or height, for devices that don't report a size.
+    pub max_raster_dimension: u32,
+}
+
+impl RenderLimits {
+    /// Reads `RENDER_TIMEOUT_MS` (default 10000), `RENDER_MAX_OUTPUT_BYTES`
+    /// (default 4 MiB), `RENDER_MAX_LOOP_ITERATIONS` (default 100000) and
+    /// `RENDER_MAX_RASTER_DIMENSION` (default 4096).
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+        Self {
+            timeout: Duration::from_millis(var("RENDER_TIMEOUT_MS", 10_000)),
+            max_output_bytes: var("RENDER_MAX_OUTPUT_BYTES", 4 * 1024 * 1024),
+            max_loop_iterations: var("RENDER_MAX_LOOP_ITERATIONS", 100_000),
+            max_raster_dimension: var("RENDER_MAX_RASTER_DIMENSION", 4096),
+        }
+    }
+
+    /// Largest raster to draw for a device: its own resolution when known,
+    /// capped at `max_raster_dimension`.
+    pub fn raster_size(&self, width: i64, height: i64) -> (u32, u32) {
+        let cap = self.max_raster_dimension;
+        let clamp = |size: i64| match u32::try_from(size) {
+            Ok(size) if size > 0 => size.min(cap),
+            _ => cap,
+        };
+        (clamp(width), clamp(height))
+    }
+
+    /// A data source timeout in whole seconds, at least one and no longer
+    /// than the whole render may take.
+    pub fn source_timeout_secs(&self, secs: i64) -> i64 {
+        secs.clamp(1, (self.timeout.as_secs() as i64).max(1))
+    }
+}
+
+pub fn render_limits() -> &'static RenderLimits {
+    static LIMITS: OnceLock<RenderLimits> = OnceLock::new();
+    LIMITS.get_or_init(RenderLimits::from_env)
+}
+
+/// Limits for the template render running on the current thread. The guarded
+/// `for` block and the output writer read it, since Liquid gives them no other
+/// way to reach per-render state from outside the template.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderBudget {
+    deadline: Option<Instant>,
+    timeout: Duration,
+    max_output_bytes: usize,
+    max_loop_iterations: usize,
+}
+
+impl RenderBudget {
+    pub fn new(limits: &RenderLimits, deadline: Option<Instant>) -> Self {
+        Self {
+            deadline,
+            timeout: limits.timeout,
+            max_output_bytes: limits.max_output_bytes,
+            max_loop_iterations: limits.max_loop_iterations,
+        }
+    }
+
+    /// Makes this budget current while `f` runs.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|c| c.replace(Some(*self)));
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// The budget set by `scope`, or the configured limits without a
+    /// deadline outside of one.
+    pub fn current() -> Self {
+        CURRENT
+            .with(Cell::get)
+            .unwrap_or_else(|| Self::new(render_limits(), None))
+    }
+
+    fn check_deadline(&self) -> Result<()> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(Error::with_msg(format!(
+                "Render exceeded its time budget of {:?}",
+                self.timeout
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<RenderBudget>> = const { Cell::new(None) };
+}
+
+struct Restore(Option<RenderBudget>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|c| c.set(self.0));
+    }
+}
+
+/// Collects template output, failing writes once the output grows past the
+/// budget or the deadline passes. Liquid replaces I/O errors with a generic
+/// message, so the reason is kept in `error` for the caller to report.
+pub struct LimitedWriter {
+    budget: RenderBudget,
+    buffer: Vec<u8>,
+    error: Option<Error>,
+}
+
+impl LimitedWriter {
+    pub fn new(budget: RenderBudget) -> Self {
+        Self {
+            budget,
+            buffer: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Why a write was refused, if one was.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    pub fn into_string(self) -> Result<String> {
+        String::from_utf8(self.buffer).map_err(|e| Error::with_msg(e.to_string()))
+    }
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let refused = if self.buffer.len() + buf.len() > self.budget.max_output_bytes {
+            Some(Error::with_msg(format!(
+                "Template output exceeded {} bytes",
+                self.budget.max_output_bytes
+            )))
+        } else {
+            self.budget.check_deadline().err()
+        };
+        if let Some(error) = refused {
+            let message = error.to_string();
+            self.error = Some(error);
+            return Err(io::Error::other(message));
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Iterations used so far in one render, kept in the runtime's registers.
+#[derive(Default)]
+struct LoopIterations(usize);
+
+/// Drop-in replacement for the standard `for` block that charges every loop
+/// against the render's iteration budget before running it, checks the
+/// deadline each iteration, and never materializes more of a counted range
+/// than `limit` and `offset` select.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GuardedForBlock;
+
+impl BlockReflection for GuardedForBlock {
+    fn start_tag(&self) -> &str {
+        "for"
+    }
+
+    fn end_tag(&self) -> &str {
+        "endfor"
+    }
+
+    fn description(&self) -> &str {
+        "Iterates over an array or range, within the render's loop budget."
+    }
+}
+
+impl ParseBlock for GuardedForBlock {
+    fn parse(
+        &self,
+        mut arguments: TagTokenIter<'_>,
+        mut tokens: TagBlock<'_, '_>,
+        options: &Language,
+    ) -> Result<Box<dyn Renderable>> {
+        let var_name = arguments
+            .expect_next("Identifier expected.")?
+            .expect_identifier()
+            .into_result()?;
+
+        arguments
+            .expect_next("\"in\" expected.")?
+            .expect_str("in")
+            .into_result_custom_msg("\"in\" expected.")?;
+
+        let range = arguments.expect_next("Array or range expected.")?;
+        let range = match range.expect_value() {
+            TryMatchToken::Matches(array) => RangeExpression::Array(array),
+            TryMatchToken::Fails(range) => match range.expect_range() {
+                TryMatchToken::Matches((start, stop)) => RangeExpression::Counted(start, stop),
+                TryMatchToken::Fails(range) => return range.raise_error().into_err(),
+            },
+        };
+
+        let mut limit = None;
+        let mut offset = None;
+        let mut reversed = false;
+        while let Some(token) = arguments.next() {
+            match token.as_str() {
+                "limit" => limit = Some(parse_attr(&mut arguments)?),
+                "offset" => offset = Some(parse_attr(&mut arguments)?),
+                "reversed" => reversed = true,
+                _ => {
+                    return token
+                        .raise_custom_error("\"limit\", \"offset\" or \"reversed\" expected.")
+                        .into_err();
+                }
+            }
+        }
+        arguments.expect_nothing()?;
+
+        let mut item_template = Vec::new();
+        let mut else_template = None;
+        while let Some(element) = tokens.next()? {
+            match element {
+                BlockElement::Tag(mut tag) => match tag.name() {
+                    "else" => {
+                        tag.tokens().expect_nothing()?;
+                        else_template = Some(tokens.parse_all(options)?);
+                        break;
+                    }
+                    _ => item_template.push(tag.parse(&mut tokens, options)?),
+                },
+                element => item_template.push(element.parse(&mut tokens, options)?),
+            }
+        }
+        tokens.assert_empty();
+
+        Ok(Box::new(For {
+            var_name: KString::from_ref(var_name),
+            range,
+            item_template: Template::new(item_template),
+            else_template: else_template.map(Template::new),
+            limit,
+            offset,
+            reversed,
+        }))
+    }
+
+    fn reflection(&self) -> &dyn BlockReflection {
+        self
+    }
+}
+
+fn parse_attr(arguments: &mut TagTokenIter<'_>) -> Result<Expression> {
+    arguments
+        .expect_next("\":\" expected.")?
+        .expect_str(":")
+        .into_result_custom_msg("\":\" expected.")?;
+    arguments
+        .expect_next("Value expected.")?
+        .expect_value()
+        .into_result()
+}
+
+#[derive(Debug)]
+enum RangeExpression {
+    Array(Expression),
+    Counted(Expression, Expression),
+}
+
+impl fmt::Display for RangeExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeExpression::Array(array) => write!(f, "{array}"),
+            RangeExpression::Counted(start, stop) => write!(f, "({start}..{stop})"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct For {
+    var_name: KString,
+    range: RangeExpression,
+    item_template: Template,
+    else_template: Option<Template>,
+    limit: Option<Expression>,
+    offset: Option<Expression>,
+    reversed: bool,
+}
+
+impl For {
+    fn trace(&self) -> String {
+        let mut parameters = vec![];
+        if let Some(limit) = &self.limit {
+            parameters.push(format!("limit:{limit}"));
+        }
+        if let Some(offset) = &self.offset {
+            parameters.push(format!("offset:{offset}"));
+        }
+        if self.reversed {
+            parameters.push("reversed".to_owned());
+        }
+        format!(
+            "{{% for {} in {} {} %}}",
+            self.var_name,
+            self.range,
+            parameters.join(", ")
+        )
+    }
+
+    /// The items to iterate over after `offset` and `limit`, in order.
+    /// Counted ranges are only expanded once they fit the iteration budget.
+    fn items<'r>(
+        &'r self,
+        runtime: &'r dyn Runtime,
+        remaining: usize,
+    ) -> Result<Vec<ValueCow<'r>>> {
+        let count = |attr: &Option<Expression>, name| {
+            attr.as_ref()
+                .map(|attr| evaluate_int(attr, runtime, name).map(|n| n.max(0) as usize))
+                .transpose()
+        };
+        let limit = count(&self.limit, "limit")?;
+        let offset = count(&self.offset, "offset")?.unwrap_or(0);
+        let select = |len: usize| {
+            let start = offset.min(len);
+            let end = limit.map_or(len, |limit| start.saturating_add(limit).min(len));
+            start..end
+        };
+        let too_many = || {
+            Error::with_msg(format!(
+                "Loop iteration limit of {} exceeded",
+                self.max_iterations()
+            ))
+        };
+
+        let mut items = match &self.range {
+            RangeExpression::Array(array) => {
+                let array = array.evaluate(runtime)?;
+                let values = array_values(array)?;
+                let range = select(values.len());
+                if range.len() > remaining {
+                    return Err(too_many());
+                }
+                values[range].to_vec()
+            }
+            RangeExpression::Counted(start, stop) => {
+                let start = evaluate_int(start, runtime, "start")?;
+                let stop = evaluate_int(stop, runtime, "end")?;
+                let len =
+                    usize::try_from(stop.saturating_sub(start).saturating_add(1)).unwrap_or(0);
+                let range = select(len);
+                if range.len() > remaining {
+                    return Err(too_many());
+                }
+                range
+                    .map(|i| ValueCow::Owned(Value::scalar(start + i as i64)))
+                    .collect()
+            }
+        };
+        if self.reversed {
+            items.reverse();
+        }
+        Ok(items)
+    }
+
+    fn max_iterations(&self) -> usize {
+        RenderBudget::current().max_loop_iterations
+    }
+}
+
+impl Renderable for For {
+    fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> Result<()> {
+        let budget = RenderBudget::current();
+        let used = runtime.registers().get_mut::<LoopIterations>().0;
+        let remaining = budget.max_loop_iterations.saturating_sub(used);
+        let items = self
+            .items(runtime, remaining)
+            .trace_with(|| self.trace().into())?;
+        runtime.registers().get_mut::<LoopIterations>().0 += items.len();
+
+        if items.is_empty() {
+            if let Some(template) = &self.else_template {
+                template
+                    .render_to(writer, runtime)
+                    .trace("{% else %}")
+                    .trace_with(|| self.trace().into())?;
+            }
+            return Ok(());
+        }
+
+        let parentloop = runtime
+            .try_get(&[Scalar::new("forloop")])
+            .map(|v| v.to_value());
+        let len = items.len() as i64;
+        for (i, item) in items.into_iter().enumerate() {
+            budget.check_deadline().trace_with(|| self.trace().into())?;
+
+            let i = i as i64;
+            let mut forloop = liquid::object!({
+                "length": len,
+                "index0": i,
+                "index": i + 1,
+                "rindex0": len - i - 1,
+                "rindex": len - i,
+                "first": i == 0,
+                "last": i == len - 1,
+            });
+            if let Some(parent) = &parentloop {
+                forloop.insert("parentloop".into(), parent.clone());
+            }
+            let forloop = Value::Object(forloop);
+
+            let mut root = std::collections::HashMap::<
+                liquid_core::model::KStringRef<'_>,
+                &dyn ValueView,
+            >::new();
+            root.insert("forloop".into(), &forloop);
+            root.insert(self.var_name.as_ref(), &item);
+
+            let scope = StackFrame::new(runtime, &root);
+            self.item_template
+                .render_to(writer, &scope)
+                .trace_with(|| self.trace().into())
+                .context_key("index")
+                .value_with(|| format!("{}", i + 1).into())?;
+
+            // `continue` just needs the interrupt cleared; `break` ends the loop.
+            let interrupt = scope.registers().get_mut::<InterruptRegister>().reset();
+            if let Some(Interrupt::Break) = interrupt {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn array_values(array: ValueCow<'_>) -> Result<Vec<ValueCow<'static>>> {
+    if let Some(array) = array.as_array() {
+        Ok(array
+            .values()
+            .map(|v| ValueCow::Owned(v.to_value()))
+            .collect())
+    } else if let Some(object) = array.as_object() {
+        Ok(object
+            .iter()
+            .map(|(k, v)| {
+                ValueCow::Owned(Value::Array(vec![
+                    Value::scalar(k.into_owned()),
+                    v.to_value(),
+                ]))
+            })
+            .collect())
+    } else if array.is_state() || array.is_nil() {
+        Ok(vec![])
+    } else {
+        Err(Error::with_msg("Expected an array").context("actual", array.type_name().to_string()))
+    }
+}
+
+fn evaluate_int(expression: &Expression, runtime: &dyn Runtime, name: &'static str) -> Result<i64> {
+    let value = expression.evaluate(runtime)?;
+    let int = value
+        .as_scalar()
+        .and_then(|s| s.to_integer())
+        .ok_or_else(|| {
+            Error::with_msg("Expected a whole number").context(name, value.to_kstr().into_owned())
+        })?;
+    Ok(int)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::server::template_with;
+
+    fn limits() -> RenderLimits {
+        RenderLimits {
+            timeout: Duration::from_secs(10),
+            max_output_bytes: 1024,
+            max_loop_iterations: 100,
+            max_raster_dimension: 4096,
+        }
+    }
+
+    #[test]
+    fn test_loops_behave_like_the_standard_for() {
+        let content = concat!(
+            "{% for row in rows %}{% for x in (1..3) reversed %}",
+            "{{ forloop.parentloop.index }}.{{ x }}{% if forloop.last %};{% endif %}",
+            "{% endfor %}{% endfor %}|",
+            "{% for x in (1..10) offset: 2 limit: 3 %}{{ x }}{% endfor %}|",
+            "{% for x in (-1..1) %}{{ x }}{% endfor %}|",
+            "{% for x in (1..5) %}{% if x == 2 %}{% continue %}{% endif %}",
+            "{% if x == 4 %}{% break %}{% endif %}{{ x }}{% endfor %}|",
+            "{% for x in empty %}{{ x }}{% else %}none{% endfor %}|",
+            "{% for pair in obj %}{{ pair[0] }}={{ pair[1] }}{% endfor %}",
+        );
+        let vars = liquid::object!({ "rows": [1, 2], "empty": [], "obj": { "a": 1 } });
+        let guarded = template_with(content).render(vars.clone()).expect("render");
+
+        let stdlib = liquid::ParserBuilder::with_stdlib()
+            .build()
+            .unwrap()
+            .parse(content)
+            .unwrap()
+            .render(&vars)
+            .unwrap();
+        assert_eq!(guarded, stdlib);
+        assert_eq!(guarded, "1.31.21.1;2.32.22.1;|345|-101|13|none|a=1");
+    }
+
+    #[test]
+    fn test_huge_ranges_fail_without_being_expanded() {
+        let budget = RenderBudget::new(&limits(), None);
+        let err = budget
+            .scope(|| {
+                template_with("{% for x in (1..1000000000000) %}{% endfor %}")
+                    .render(liquid::object!({}))
+            })
+            .expect_err("over the iteration limit");
+        assert!(
+            err.to_string()
+                .contains("Loop iteration limit of 100 exceeded"),
+            "{err}"
+        );
+
+        let out = budget
+            .scope(|| {
+                template_with("{% for x in (1..1000000000000) limit: 3 %}{{ x }}{% endfor %}")
+                    .render(liquid::object!({}))
+            })
+            .expect("only three iterations");
+        assert_eq!(out, "123");
+    }
+
+    #[test]
+    fn test_iteration_budget_is_shared_by_nested_loops() {
+        let budget = RenderBudget::new(&limits(), None);
+        let nested = "{% for a in (1..10) %}{% for b in (1..10) %}{% endfor %}{% endfor %}";
+        let err = budget
+            .scope(|| template_with(nested).render(liquid::object!({})))
+            .expect_err("110 iterations");
+        assert!(err.to_string().contains("Loop iteration limit"), "{err}");
+    }
+
+    #[test]
+    fn test_output_size_is_limited() {
+        let budget = RenderBudget::new(&limits(), None);
+        let err = budget
+            .scope(|| {
+                template_with("{% for x in (1..50) %}{{ text }}{% endfor %}")
+                    .render(liquid::object!({ "text": "0123456789012345678901234567890" }))
+            })
+            .expect_err("over 1 KiB");
+        assert!(
+            err.to_string()
+                .contains("Template output exceeded 1024 bytes"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_deadline_stops_render() {
+        let budget = RenderBudget::new(&limits(), Some(Instant::now()));
+        let err = budget
+            .scope(|| {
+                template_with("{% for x in (1..3) %}{{ x }}{% endfor %}")
+                    .render(liquid::object!({}))
+            })
+            .expect_err("deadline passed");
+        assert!(err.to_string().contains("time budget of 10s"), "{err}");
+    }
+
+    #[test]
+    fn test_raster_size_uses_device_resolution_within_cap() {
+        let limits = limits();
+        assert_eq!(limits.raster_size(800, 480), (800, 480));
+        assert_eq!(limits.raster_size(0, -1), (4096, 4096));
+        assert_eq!(limits.raster_size(10_000, 480), (4096, 480));
+    }
+
+    #[test]
+    fn test_source_timeouts_stay_within_the_render_budget() {
+        let limits = limits();
+        assert_eq!(limits.source_timeout_secs(0), 1);
+        assert_eq!(limits.source_timeout_secs(5), 5);
+        assert_eq!(limits.source_timeout_secs(i64::MAX), 10);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use axum_prometheus::metrics;
+
+use crate::models::{RenderStats, SourceKind, Template, TimingSummary};
+
+/// Steps of a screen render that are timed separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderStage {
+    /// Liquid render of the template to SVG.
+    Liquid,
+    /// usvg parse of the rendered SVG, including text layout.
+    SvgParse,
+    /// resvg drawing plus dithering to the panel's grays.
+    Rasterize,
+    /// BMP or PNG encoding.
+    Encode,
+}
+
+impl RenderStage {
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderStage::Liquid => "liquid",
+            RenderStage::SvgParse => "svg_parse",
+            RenderStage::Rasterize => "rasterize",
+            RenderStage::Encode => "encode",
+        }
+    }
+}
+
+/// Timings kept per stage and source for the editor summary.
+const WINDOW: usize = 100;
+
+#[derive(Default)]
+struct Samples(VecDeque<f64>);
+
+impl Samples {
+    fn push(&mut self, elapsed: Duration) {
+        if self.0.len() == WINDOW {
+            self.0.pop_front();
+        }
+        self.0.push_back(elapsed.as_secs_f64() * 1000.0);
+    }
+
+    fn summary(&self, name: String) -> TimingSummary {
+        let mut sorted: Vec<f64> = self.0.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        // Nearest-rank percentile.
+        let p95 = sorted
+            .get((count * 95).div_ceil(100).saturating_sub(1))
+            .copied()
+            .unwrap_or(0.0);
+        TimingSummary {
+            name,
+            count,
+            last_ms: self.0.back().copied().unwrap_or(0.0),
+            mean_ms: if count == 0 {
+                0.0
+            } else {
+                sorted.iter().sum::<f64>() / count as f64
+            },
+            p95_ms: p95,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TemplateStats {
+    stages: BTreeMap<RenderStage, Samples>,
+    /// Keyed by the source's template path, e.g. `http.weather`.
+    sources: BTreeMap<String, Samples>,
+}
+
+type Stats = Mutex<HashMap<i64, TemplateStats>>;
+
+/// Recent timings per template id since the server started.
+fn stats() -> &'static Stats {
+    static STATS: OnceLock<Stats> = OnceLock::new();
+    STATS.get_or_init(Default::default)
+}
+
+/// Records how long one stage of rendering `template` took, as the
+/// `render_stage_duration_seconds{template_id, stage}` histogram and in the
+/// template's recent timings.
+pub fn record_stage(template: &Template, stage: RenderStage, elapsed: Duration) {
+    metrics::histogram!(
+        "render_stage_duration_seconds",
+        "template_id" => template.id.to_string(),
+        "stage" => stage.label(),
+    )
+    .record(elapsed.as_secs_f64());
+    stats()
+        .lock()
+        .expect("render stats lock poisoned")
+        .entry(template.id)
+        .or_default()
+        .stages
+        .entry(stage)
+        .or_default()
+        .push(elapsed);
+}
+
+/// Records a data source fetch, successful or not, as the
+/// `data_source_fetch_duration_seconds{template_id, kind, source}` histogram and
+/// in the template's recent timings.
+pub fn record_fetch(template: &Template, kind: SourceKind, source: &str, elapsed: Duration) {
+    metrics::histogram!(
+        "data_source_fetch_duration_seconds",
+        "template_id" => template.id.to_string(),
+        "kind" => kind.label(),
+        "source" => source.to_string(),
+    )
+    .record(elapsed.as_secs_f64());
+    stats()
+        .lock()
+        .expect("render stats lock poisoned")
+        .entry(template.id)
+        .or_default()
+        .sources
+        .entry(format!("{}.{source}", kind.label()))
+        .or_default()
+        .push(elapsed);
+}
+
+/// Drops a template's recent timings, after it is deleted.
+pub fn forget_template(template_id: i64) {
+    stats()
+        .lock()
+        .expect("render stats lock poisoned")
+        .remove(&template_id);
+}
+
+/// Summary of a template's last `WINDOW` timings per stage and source.
+pub fn render_stats(template_id: i64) -> RenderStats {
+    let stats = stats().lock().expect("render stats lock poisoned");
+    let Some(template) = stats.get(&template_id) else {
+        return RenderStats::default();
+    };
+    RenderStats {
+        stages: template
+            .stages
+            .iter()
+            .map(|(stage, samples)| samples.summary(stage.label().to_string()))
+            .collect(),
+        sources: template
+            .sources
+            .iter()
+            .map(|(name, samples)| samples.summary(name.clone()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_covers_recent_window() {
+        let mut samples = Samples::default();
+        for ms in 1..=120 {
+            samples.push(Duration::from_millis(ms));
+        }
+        let summary = samples.summary("liquid".to_string());
+        assert_eq!(summary.count, WINDOW);
+        assert_eq!(summary.last_ms, 120.0);
+        // Oldest 20 dropped: 21..=120.
+        assert_eq!(summary.mean_ms, 70.5);
+        assert_eq!(summary.p95_ms, 115.0);
+    }
+
+    #[test]
+    fn test_stats_are_grouped_by_template_stage_and_source() {
+        let template = Template {
+            id: 8_001,
+            ..crate::models::server::template_with("")
+        };
+        record_stage(&template, RenderStage::Encode, Duration::from_millis(3));
+        record_stage(&template, RenderStage::Liquid, Duration::from_millis(2));
+        record_fetch(
+            &template,
+            SourceKind::Http,
+            "weather",
+            Duration::from_millis(40),
+        );
+
+        let stats = render_stats(8_001);
+        let stages: Vec<&str> = stats.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(stages, ["liquid", "encode"], "pipeline order");
+        assert_eq!(stats.sources.len(), 1);
+        assert_eq!(stats.sources[0].name, "http.weather");
+        assert_eq!(stats.sources[0].last_ms, 40.0);
+        assert_eq!(render_stats(8_002), RenderStats::default());
+
+        forget_template(8_001);
+        assert_eq!(render_stats(8_001), RenderStats::default());
+    }
+}
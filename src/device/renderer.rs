@@ -1,12 +1,29 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-use chrono::{DateTime, Utc};
+use axum_prometheus::metrics;
+use chrono::{DateTime, Datelike, SecondsFormat, Timelike, Utc};
 use chrono_tz::Tz;
 use dioxus::prelude::*;
+use futures::future::join_all;
 use liquid::Object;
+use liquid::model::{Value, ValueView};
 use thiserror::Error;
+use tokio::sync::Semaphore;
 
-use crate::models::RenderContext;
+use crate::device::assets::RenderAssets;
+use crate::device::dither::{DitherOptions, dither};
+use crate::device::fonts::{font_database, ui_family};
+use crate::device::locale::{Locale, render_locale};
+use crate::device::partials::RenderPartials;
+use crate::device::render_cache::{RenderKey, render_cache};
+use crate::device::render_limits::{RenderBudget, render_limits};
+use crate::device::render_stats::{RenderStage, record_fetch, record_stage};
+use crate::device::snapshots::resolve;
+use crate::device::text_layout::escape_xml;
+use crate::models::{Device, ImageFormat, RenderContext, SourceKind, Template, TemplateKind};
+use crate::time::{Clock, RealClock};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -22,126 +39,748 @@ pub enum Error {
     TzError(#[from] chrono_tz::ParseError),
     #[error("{0}")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    PngError(#[from] png::EncodingError),
+    #[error("Invalid image size {0}x{1}")]
+    ImageSizeError(u32, u32),
+    #[error("Render exceeded its time budget of {0:?}")]
+    TimeoutError(Duration),
+    #[error("{0}")]
+    JoinError(#[from] tokio::task::JoinError),
+    #[error("{0}")]
+    LayoutError(String),
 }
 
-pub async fn render_vars(render_context: &RenderContext) -> Result<Object, Error> {
-    let prometheus_queries = &render_context.prometheus_queries;
+/// What a template's data is fetched for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Fetch {
+    /// A render: results become each source's last good result and count
+    /// towards metrics and fetch timings.
+    Render,
+    /// A check of the template, which leaves no trace.
+    Check,
+}
 
-    let mut prometheus_data: HashMap<String, Vec<Object>> =
-        HashMap::with_capacity(prometheus_queries.len());
+/// Whether the template being rendered is its saved version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Content {
+    /// Loaded from the database, as devices render it; its parse is cached.
+    Saved,
+    /// Sent by the editor, and possibly not saved yet.
+    Unsaved,
+}
 
-    for query in prometheus_queries {
-        if let Ok(obj) = query.get_render_obj().await {
-            prometheus_data.insert(query.name.clone(), obj);
+/// Freshness and errors of one render's data sources, keyed by the object
+/// the source's data is under (`prometheus`, `http`, ...) and then its name,
+/// since sources of different kinds may share a name.
+struct SourceReport {
+    fetch: Fetch,
+    sources: Object,
+    errors: Object,
+}
+
+impl SourceReport {
+    fn new(fetch: Fetch) -> Self {
+        let by_kind = || {
+            [
+                SourceKind::Prometheus,
+                SourceKind::PrometheusRange,
+                SourceKind::Http,
+                SourceKind::Calendar,
+            ]
+            .into_iter()
+            .map(|kind| (kind.label().into(), Value::Object(Object::new())))
+            .collect()
+        };
+        Self {
+            fetch,
+            sources: by_kind(),
+            errors: by_kind(),
         }
     }
 
-    let range_queries = &render_context.range_queries;
-    let mut range_data: HashMap<String, Vec<Object>> = HashMap::with_capacity(range_queries.len());
+    /// Sets `report.<kind>.<name>` to `value`.
+    fn insert(report: &mut Object, kind: SourceKind, name: &str, value: Value) {
+        if let Some(Value::Object(by_name)) = report.get_mut(kind.label()) {
+            by_name.insert(name.to_string().into(), value);
+        }
+    }
 
-    for query in range_queries {
-        if let Ok(obj) = query.get_render_obj().await {
-            range_data.insert(query.name.clone(), obj);
+    /// Resolves a fetch against the last good result, recording its freshness
+    /// and any error. Returns the value to expose, if there is one.
+    async fn resolve(
+        &mut self,
+        kind: SourceKind,
+        id: Option<i64>,
+        name: &str,
+        fetched: Result<Value, String>,
+        now: i64,
+        tz: &Tz,
+    ) -> Option<Value> {
+        let keep = self.fetch == Fetch::Render;
+        if fetched.is_err() && keep {
+            metrics::counter!("data_source_errors_total", "kind" => kind.label()).increment(1);
+        }
+        match resolve(kind, id, fetched, now, keep).await {
+            Ok(source) => {
+                if let Some(error) = &source.error {
+                    Self::insert(&mut self.errors, kind, name, Value::scalar(error.clone()));
+                }
+                Self::insert(&mut self.sources, kind, name, source.meta(tz).into());
+                Some(source.value)
+            }
+            Err(error) => {
+                Self::insert(&mut self.errors, kind, name, Value::scalar(error));
+                None
+            }
         }
     }
+}
+
+/// The timezone renders happen in: `TZ`, or UTC when it isn't set.
+pub fn render_timezone() -> Result<Tz, chrono_tz::ParseError> {
+    std::env::var("TZ").unwrap_or("UTC".to_string()).parse()
+}
+
+/// The `now` object: the render time as a unix timestamp, ISO 8601 string and
+/// its calendar fields in the render timezone, with names in `locale`.
+fn now_obj(time: &DateTime<Tz>, locale: &Locale) -> Object {
+    let weekday = time.weekday().num_days_from_monday() as usize;
+    liquid::object!({
+        "timestamp": time.timestamp(),
+        "iso": time.to_rfc3339_opts(SecondsFormat::Secs, false),
+        "year": time.year(),
+        "month": time.month(),
+        "day": time.day(),
+        "hour": time.hour(),
+        "minute": time.minute(),
+        "second": time.second(),
+        "weekday": weekday + 1,
+        "weekday_name": locale.weekdays[weekday],
+        "month_name": locale.months[time.month0() as usize],
+        "day_of_year": time.ordinal(),
+        "week": time.iso_week().week(),
+        "timezone": time.format("%Z").to_string(),
+        "utc_offset": time.format("%:z").to_string(),
+    })
+}
+
+pub async fn render_vars(render_context: &RenderContext) -> Result<Object, Error> {
+    fetch_vars(render_context, Fetch::Render).await
+}
+
+/// The variables a template renders with, fetching its data sources for
+/// `fetch`.
+pub(crate) async fn fetch_vars(
+    render_context: &RenderContext,
+    fetch: Fetch,
+) -> Result<Object, Error> {
+    let tz = render_timezone()?;
+    let now = RealClock.now_secs();
+    let time_in_tz: DateTime<Tz> = Utc::now().with_timezone(&tz);
+    let mut report = SourceReport::new(fetch);
 
-    let http_sources = &render_context.http_sources;
-    let mut http_data: HashMap<String, liquid::model::Value> =
-        HashMap::with_capacity(http_sources.len());
+    // Fetch every source at once; results are resolved in order afterwards.
+    let permits = fetch_permits();
+    let prometheus = join_all(render_context.prometheus_queries.iter().map(|query| {
+        fetch_source(permits, query.timeout_secs, async {
+            query.get_render_obj().await.map(objects_to_value)
+        })
+    }));
+    let ranges = join_all(render_context.range_queries.iter().map(|query| {
+        fetch_source(permits, query.timeout_secs, async {
+            query.get_render_obj().await.map(objects_to_value)
+        })
+    }));
+    let http = join_all(
+        render_context
+            .http_sources
+            .iter()
+            .map(|source| fetch_source(permits, source.timeout_secs, source.get_render_obj())),
+    );
+    let calendars = join_all(render_context.calendar_sources.iter().map(|source| {
+        fetch_source(
+            permits,
+            source.timeout_secs,
+            source.get_render_obj(time_in_tz),
+        )
+    }));
+    let (prometheus, ranges, http, calendars) = tokio::join!(prometheus, ranges, http, calendars);
 
-    for source in http_sources {
-        if let Ok(obj) = source.get_render_obj().await {
-            http_data.insert(source.name.clone(), obj);
+    let mut prometheus_data = Object::new();
+    for (query, (fetched, elapsed)) in render_context.prometheus_queries.iter().zip(prometheus) {
+        let kind = SourceKind::Prometheus;
+        if let Some(elapsed) = elapsed.filter(|_| fetch == Fetch::Render) {
+            record_fetch(&render_context.template, kind, &query.name, elapsed);
+        }
+        if let Some(value) = report
+            .resolve(kind, query.id, &query.name, fetched, now, &tz)
+            .await
+        {
+            prometheus_data.insert(query.name.clone().into(), value);
         }
     }
 
-    let tz: Tz = std::env::var("TZ").unwrap_or("UTC".to_string()).parse()?;
+    let mut range_data = Object::new();
+    for (query, (fetched, elapsed)) in render_context.range_queries.iter().zip(ranges) {
+        let kind = SourceKind::PrometheusRange;
+        if let Some(elapsed) = elapsed.filter(|_| fetch == Fetch::Render) {
+            record_fetch(&render_context.template, kind, &query.name, elapsed);
+        }
+        if let Some(value) = report
+            .resolve(kind, query.id, &query.name, fetched, now, &tz)
+            .await
+        {
+            range_data.insert(query.name.clone().into(), value);
+        }
+    }
 
-    let utc_now: DateTime<Utc> = Utc::now();
-    let time_in_tz: DateTime<Tz> = utc_now.with_timezone(&tz);
+    let mut http_data = Object::new();
+    for (source, (fetched, elapsed)) in render_context.http_sources.iter().zip(http) {
+        let kind = SourceKind::Http;
+        if let Some(elapsed) = elapsed.filter(|_| fetch == Fetch::Render) {
+            record_fetch(&render_context.template, kind, &source.name, elapsed);
+        }
+        if let Some(value) = report
+            .resolve(kind, source.id, &source.name, fetched, now, &tz)
+            .await
+        {
+            http_data.insert(source.name.clone().into(), value);
+        }
+    }
+
+    let mut calendar_data = Object::new();
+    for (source, (fetched, elapsed)) in render_context.calendar_sources.iter().zip(calendars) {
+        let kind = SourceKind::Calendar;
+        if let Some(elapsed) = elapsed.filter(|_| fetch == Fetch::Render) {
+            record_fetch(&render_context.template, kind, &source.name, elapsed);
+        }
+        if let Some(value) = report
+            .resolve(kind, source.id, &source.name, fetched, now, &tz)
+            .await
+        {
+            calendar_data.insert(source.name.clone().into(), value);
+        }
+    }
 
     Ok(liquid::object!({
         "device": render_context.device.get_render_obj(),
         "time": time_in_tz.format("%I:%M %P").to_string(),
         "timezone": time_in_tz.format("%Z").to_string(),
         "date": time_in_tz.format("%Y-%m-%d").to_string(),
-        "prometheus": liquid::object!(prometheus_data),
-        "prometheus_range": liquid::object!(range_data),
-        "http": liquid::object!(http_data),
+        "now": now_obj(&time_in_tz, render_locale()),
+        "prometheus": prometheus_data,
+        "prometheus_range": range_data,
+        "http": http_data,
+        "calendar": calendar_data,
+        "sources": report.sources,
+        "errors": report.errors,
+    }))
+}
+
+/// One line per failed data source in `render_vars` output, named by its
+/// path such as `http.weather` and noting when the last good result was
+/// shown instead.
+fn source_warnings(vars: &Object) -> Vec<String> {
+    let Some(errors) = vars.get("errors").and_then(|e| e.as_object()) else {
+        return vec![];
+    };
+    let sources = vars.get("sources").and_then(|s| s.as_object());
+    let mut warnings = vec![];
+    for (kind, by_name) in errors.iter() {
+        let Some(by_name) = by_name.as_object() else {
+            continue;
+        };
+        for (name, error) in by_name.iter() {
+            let fetched_at = sources
+                .and_then(|s| s.get(kind.as_str()))
+                .and_then(|s| s.as_object())
+                .and_then(|s| s.get(name.as_str()))
+                .and_then(|s| s.as_object())
+                .and_then(|s| s.get("fetched_at"));
+            warnings.push(match fetched_at {
+                Some(fetched_at) => format!(
+                    "{kind}.{name}: {} (showing data fetched {})",
+                    error.render(),
+                    fetched_at.render()
+                ),
+                None => format!("{kind}.{name}: {}", error.render()),
+            });
+        }
+    }
+    warnings
+}
+
+/// Caps simultaneous data source fetches across all renders at
+/// `SOURCE_FETCH_CONCURRENCY` (default 8), so many devices refreshing at once
+/// don't flood the upstream servers.
+fn fetch_permits() -> &'static Semaphore {
+    static PERMITS: OnceLock<Semaphore> = OnceLock::new();
+    PERMITS.get_or_init(|| {
+        let permits = std::env::var("SOURCE_FETCH_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(8usize);
+        Semaphore::new(permits.max(1))
+    })
+}
+
+/// Runs one source fetch once a permit is free. The source's timeout covers
+/// both waiting for the permit and the fetch itself, so a slow source fails
+/// in time for the render to fall back to its last good result.
+///
+/// Also returns how long the fetch ran, excluding the wait for a permit, or
+/// `None` if it never started.
+async fn fetch_source<T, E: std::fmt::Display>(
+    permits: &Semaphore,
+    timeout_secs: i64,
+    fetch: impl Future<Output = Result<T, E>>,
+) -> (Result<T, String>, Option<Duration>) {
+    let timeout = Duration::from_secs(timeout_secs.max(1) as u64);
+    let now = tokio::time::Instant::now();
+    let deadline = now
+        .checked_add(timeout)
+        .unwrap_or(now + render_limits().timeout);
+    let Ok(permit) = tokio::time::timeout_at(deadline, permits.acquire()).await else {
+        return (Err(format!("No free fetch slot within {timeout:?}")), None);
+    };
+    let _permit = permit.expect("fetch semaphore is never closed");
+    let started = Instant::now();
+    let result = match tokio::time::timeout_at(deadline, fetch).await {
+        Ok(fetched) => fetched.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("Timed out after {timeout:?}")),
+    };
+    (result, Some(started.elapsed()))
+}
+
+fn objects_to_value(objects: Vec<Object>) -> Value {
+    Value::Array(objects.into_iter().map(Value::Object).collect())
+}
+
+/// A rendered screen reduced to the panel's gray levels: one level index per
+/// pixel, row-major, 0 being black and `levels - 1` white.
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub levels: u8,
+    pub pixels: Vec<u8>,
+}
+
+/// A rendered screen and warnings about data sources that failed while
+/// rendering it.
+pub struct RenderOutput {
+    pub image: Arc<Vec<u8>>,
+    pub warnings: Vec<String>,
+}
+
+/// Renders the device's screen in `format`, at the bit depth its panel
+/// supports. Identical SVG output with the same output settings is served from
+/// the render cache without rasterizing.
+pub async fn render_screen(
+    render_context: &RenderContext,
+    format: ImageFormat,
+) -> Result<Arc<Vec<u8>>, Error> {
+    let image = render(render_context, format, Content::Saved).await?.image;
+    remember_last_image(render_context.device.id, format, image.clone());
+    Ok(image)
+}
+
+async fn render(
+    render_context: &RenderContext,
+    format: ImageFormat,
+    content: Content,
+) -> Result<RenderOutput, Error> {
+    let limits = render_limits();
+    let deadline = Instant::now() + limits.timeout;
+
+    let (svg_data, warnings) = match render_context.template.kind {
+        TemplateKind::Liquid => render_svg(render_context, deadline, content).await?,
+        TemplateKind::Layout => render_layout(render_context, deadline).await?,
+    };
+
+    let levels = render_context.device.gray_levels();
+    let dither_options = DitherOptions::for_device(&render_context.device);
+    let key = RenderKey::new(&svg_data, levels, &dither_options, format);
+    let cache = render_cache();
+    let image = match cache.get(&key, RealClock.now_secs()) {
+        Some(image) => image,
+        None => {
+            if Instant::now() >= deadline {
+                return Err(Error::TimeoutError(limits.timeout));
+            }
+            let max_size =
+                limits.raster_size(render_context.device.width, render_context.device.height);
+            let (image, timings) = tokio::task::spawn_blocking(move || {
+                svg_to_image_timed(&svg_data, levels, &dither_options, format, max_size)
+            })
+            .await??;
+            for (stage, elapsed) in timings {
+                record_stage(&render_context.template, stage, elapsed);
+            }
+            let image = Arc::new(image);
+            cache.insert(key, image.clone(), RealClock.now_secs());
+            image
+        }
+    };
+    Ok(RenderOutput { image, warnings })
+}
+
+/// Fetches a Liquid template's data and renders it to SVG by `deadline`.
+/// Also returns warnings about data sources that failed.
+async fn render_svg(
+    render_context: &RenderContext,
+    deadline: Instant,
+    content: Content,
+) -> Result<(String, Vec<String>), Error> {
+    let vars = tokio::time::timeout_at(deadline.into(), render_vars(render_context))
+        .await
+        .map_err(|_| Error::TimeoutError(render_limits().timeout))??;
+    let warnings = source_warnings(&vars);
+
+    let (svg, elapsed) = render_liquid(render_context, vars, deadline, content).await?;
+    record_stage(&render_context.template, RenderStage::Liquid, elapsed);
+    Ok((svg, warnings))
+}
+
+/// Renders a Liquid template with `vars`, its assets and the saved partials
+/// by `deadline`. Also returns how long the Liquid render itself took.
+pub(crate) async fn render_liquid(
+    render_context: &RenderContext,
+    vars: Object,
+    deadline: Instant,
+    content: Content,
+) -> Result<(String, Duration), Error> {
+    let limits = render_limits();
+    let (assets, partials) = tokio::time::timeout_at(deadline.into(), async {
+        let assets = RenderAssets::load(render_context.template.id).await?;
+        let partials = RenderPartials::load().await?;
+        Ok::<_, Error>((assets, partials))
+    })
+    .await
+    .map_err(|_| Error::TimeoutError(limits.timeout))??;
+
+    // Render SVG from template. Liquid and resvg are CPU-bound, so they run on
+    // the blocking pool; the template render stops itself at the deadline.
+    let template = render_context.template.clone();
+    let budget = RenderBudget::new(limits, Some(deadline));
+    let (svg, elapsed) = tokio::task::spawn_blocking(move || {
+        let started = Instant::now();
+        let svg = budget.scope(|| {
+            assets.scope(|| {
+                partials.scope(|| match content {
+                    Content::Saved => template.render(vars),
+                    Content::Unsaved => template.render_unsaved(vars),
+                })
+            })
+        });
+        (svg, started.elapsed())
+    })
+    .await?;
+    Ok((svg?, elapsed))
+}
+
+/// Renders each region of a layout with its own template and data sources, on
+/// a device the size of the region, and nests the results in one SVG the size
+/// of the screen. Warnings are prefixed with the region's template name. A
+/// region whose template fails shows the error in its place, so the other
+/// regions still display.
+async fn render_layout(
+    render_context: &RenderContext,
+    deadline: Instant,
+) -> Result<(String, Vec<String>), Error> {
+    let device = &render_context.device;
+    let visible = render_context.regions.iter().filter(|region| {
+        let (_, _, width, height) = region.rect(device.width, device.height);
+        width > 0 && height > 0
+    });
+    let children = join_all(visible.map(|region| async move {
+        let rect = region.rect(device.width, device.height);
+        match crate::db::get_template_by_id(region.template_id).await {
+            Ok(template) => {
+                let name = template.name.clone();
+                (
+                    rect,
+                    name,
+                    render_region(template, rect, device, deadline).await,
+                )
+            }
+            Err(e) => (
+                rect,
+                format!("Template {}", region.template_id),
+                Err(e.into()),
+            ),
+        }
     }))
+    .await;
+
+    let (width, height) = (device.width, device.height);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}"><rect width="100%" height="100%" fill="white"/>"#
+    );
+    let mut warnings = vec![];
+    for ((x, y, width, height), name, child) in children {
+        match child {
+            Ok((child_svg, child_warnings)) => {
+                svg.push_str(&nest_svg(&child_svg, x, y, width, height));
+                warnings.extend(child_warnings.into_iter().map(|w| format!("{name}: {w}")));
+            }
+            Err(e) => {
+                let error = e.to_string();
+                let child_svg = region_error_svg(width, height, &name, &error);
+                svg.push_str(&nest_svg(&child_svg, x, y, width, height));
+                warnings.push(format!("{name}: {error}"));
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    Ok((svg, warnings))
+}
+
+/// Renders one region's template on a device the size of `rect`.
+async fn render_region(
+    template: Template,
+    (_, _, width, height): (i64, i64, i64, i64),
+    device: &Device,
+    deadline: Instant,
+) -> Result<(String, Vec<String>), Error> {
+    if template.kind == TemplateKind::Layout {
+        return Err(Error::LayoutError(format!(
+            "{} is a layout; layouts can't contain other layouts",
+            template.name
+        )));
+    }
+    let device = Device {
+        width,
+        height,
+        ..device.clone()
+    };
+    let child = RenderContext::load(device, template).await?;
+    render_svg(&child, deadline, Content::Saved).await
+}
+
+/// A framed box naming the region's template and its error, drawn in place of
+/// a region that failed to render.
+fn region_error_svg(width: i64, height: i64, name: &str, error: &str) -> String {
+    let family = escape_xml(&ui_family().unwrap_or_else(|| "sans-serif".to_string()));
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}"><rect x="1" y="1" width="{}" height="{}" fill="white" stroke="black" stroke-width="2"/><text x="8" y="20" font-family="{family}" font-size="14" font-weight="700">Render error: {}</text><text x="8" y="38" font-family="{family}" font-size="12">{}</text></svg>"#,
+        (width - 2).max(0),
+        (height - 2).max(0),
+        escape_xml(name),
+        escape_xml(error),
+    )
+}
+
+/// Places a rendered SVG document in a `width` x `height` viewport at `(x, y)`,
+/// clipping anything drawn outside it. The XML declaration and doctype, which
+/// can't appear inside another document, are dropped.
+fn nest_svg(svg: &str, x: i64, y: i64, width: i64, height: i64) -> String {
+    let root = svg.find("<svg").map_or(svg, |start| &svg[start..]);
+    format!(
+        r#"<svg x="{x}" y="{y}" width="{width}" height="{height}" overflow="hidden">{root}</svg>"#
+    )
+}
+
+type LastImages = Mutex<HashMap<(i64, ImageFormat), Arc<Vec<u8>>>>;
+
+/// The most recent successful screen render per device and format, kept in memory
+/// for devices that keep showing it when a later render fails.
+fn last_images() -> &'static LastImages {
+    static LAST: OnceLock<LastImages> = OnceLock::new();
+    LAST.get_or_init(Default::default)
+}
+
+fn remember_last_image(device_id: i64, format: ImageFormat, image: Arc<Vec<u8>>) {
+    last_images()
+        .lock()
+        .expect("last image lock poisoned")
+        .insert((device_id, format), image);
+}
+
+/// The last image `render_screen` produced for a device in `format`, if any
+/// since the server started.
+pub fn last_image(device_id: i64, format: ImageFormat) -> Option<Arc<Vec<u8>>> {
+    last_images()
+        .lock()
+        .expect("last image lock poisoned")
+        .get(&(device_id, format))
+        .cloned()
+}
+
+/// Rasterizes an SVG string and encodes it as `format` at `levels` grays.
+/// Anything beyond `max_size` is cropped off.
+pub(crate) fn svg_to_image(
+    svg_data: &str,
+    levels: u8,
+    dither_options: &DitherOptions,
+    format: ImageFormat,
+    max_size: (u32, u32),
+) -> Result<Vec<u8>, Error> {
+    svg_to_image_timed(svg_data, levels, dither_options, format, max_size).map(|(image, _)| image)
 }
 
-/// Renders a 1-bit BMP image for e-ink displays using SVG + Liquid templates
-pub async fn render_screen(render_context: &RenderContext) -> Result<Vec<u8>, Error> {
-    // Render SVG from template
-    let svg_data = render_context
-        .template
-        .render(render_vars(render_context).await?)?;
+/// Time spent in each stage of turning SVG into an encoded image.
+type StageTimings = Vec<(RenderStage, Duration)>;
 
-    Ok(svg_to_bmp(&svg_data)?)
+/// `svg_to_image`, also returning how long each step took.
+fn svg_to_image_timed(
+    svg_data: &str,
+    levels: u8,
+    dither_options: &DitherOptions,
+    format: ImageFormat,
+    max_size: (u32, u32),
+) -> Result<(Vec<u8>, StageTimings), Error> {
+    let (raster, mut timings) = svg_to_raster(svg_data, levels, dither_options, max_size)?;
+    let started = Instant::now();
+    let image = match format {
+        ImageFormat::Bmp => raster_to_bmp(&raster),
+        ImageFormat::Png => raster_to_png(&raster),
+    }?;
+    timings.push((RenderStage::Encode, started.elapsed()));
+    Ok((image, timings))
 }
 
-/// Converts SVG string to 1-bit BMP data
-/// Returns (bmp_data, width, height)
-fn svg_to_bmp(svg_data: &str) -> Result<Vec<u8>, Error> {
+/// Renders the same image as `render_screen` as a PNG, so the dashboard can
+/// show exactly the grays the panel will display, along with data source
+/// warnings for the editor.
+pub async fn render_preview(render_context: &RenderContext) -> Result<RenderOutput, Error> {
+    render(render_context, ImageFormat::Png, Content::Unsaved).await
+}
+
+/// Rasterizes an SVG string, cropped to `max_size`, and quantizes it to
+/// `levels` grays. Also returns the time spent parsing and rasterizing.
+fn svg_to_raster(
+    svg_data: &str,
+    levels: u8,
+    dither_options: &DitherOptions,
+    max_size: (u32, u32),
+) -> Result<(Raster, StageTimings), Error> {
     // Parse SVG
-    let mut opt = usvg::Options::default();
-    opt.fontdb_mut().load_system_fonts();
+    let started = Instant::now();
+    let opt = usvg::Options {
+        fontdb: font_database(),
+        ..Default::default()
+    };
 
     let tree = usvg::Tree::from_str(svg_data, &opt)?;
+    let parsed = Instant::now();
 
-    // Create pixmap for rendering
-    let pixmap_size = tree.size().to_int_size();
-    let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height())
-        .expect("Invalid image size");
+    // Create pixmap for rendering, no larger than the panel can show
+    let size = tree.size().to_int_size();
+    let width = size.width().min(max_size.0);
+    let height = size.height().min(max_size.1);
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).ok_or(Error::ImageSizeError(width, height))?;
 
     // Render SVG to pixmap
     resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    let raster = pixmap_to_raster(&pixmap, levels, dither_options);
 
-    // Convert to 1-bit BMP
-    let bmp_data = pixmap_to_bmp(&pixmap)?;
-    Ok(bmp_data)
+    let timings = vec![
+        (RenderStage::SvgParse, parsed - started),
+        (RenderStage::Rasterize, parsed.elapsed()),
+    ];
+    Ok((raster, timings))
 }
 
-/// Converts a pixmap to 1-bit BMP format
-fn pixmap_to_bmp(pixmap: &tiny_skia::Pixmap) -> Result<Vec<u8>, Error> {
+/// Converts a pixmap to luminance and dithers it down to `levels` grays.
+fn pixmap_to_raster(
+    pixmap: &tiny_skia::Pixmap,
+    levels: u8,
+    dither_options: &DitherOptions,
+) -> Raster {
     let width = pixmap.width() as usize;
     let height = pixmap.height() as usize;
 
-    // Convert RGBA pixmap to 1-bit format
-    let row_bytes = (width + 7) / 8; // Round up to nearest byte
-    let mut bit_data = vec![0u8; row_bytes * height];
-
-    // Pack pixels into 1-bit format
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = pixmap.pixel(x as u32, y as u32).unwrap();
-
-            // Convert to grayscale using standard luminance formula
-            let gray = (0.299 * pixel.red() as f32
-                + 0.587 * pixel.green() as f32
-                + 0.114 * pixel.blue() as f32) as u8;
-
-            // Apply threshold: >= 127 is white (1), < 127 is black (0)
-            if gray >= 127 {
-                let byte_index = y * row_bytes + x / 8;
-                let bit_index = 7 - (x % 8); // MSB first
-                bit_data[byte_index] |= 1 << bit_index;
-            }
+    // Convert to grayscale using standard luminance formula
+    let gray: Vec<f32> = pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            0.299 * pixel.red() as f32 + 0.587 * pixel.green() as f32 + 0.114 * pixel.blue() as f32
+        })
+        .collect();
+
+    Raster {
+        width,
+        height,
+        levels,
+        pixels: dither(&gray, width, height, levels, dither_options),
+    }
+}
+
+/// Bits per pixel needed to index `levels` palette entries (1, 2 or 4).
+fn bits_per_pixel(levels: u8) -> usize {
+    match levels {
+        0..=2 => 1,
+        3..=4 => 2,
+        _ => 4,
+    }
+}
+
+/// Packs each row of a raster MSB first at `bits_per_pixel(levels)`, rounding
+/// rows up to whole bytes. Returns `(bits_per_pixel, row_bytes, data)`.
+fn pack_rows(raster: &Raster) -> (usize, usize, Vec<u8>) {
+    let bpp = bits_per_pixel(raster.levels);
+    let per_byte = 8 / bpp;
+    let row_bytes = raster.width.div_ceil(per_byte); // Round up to nearest byte
+    let mut packed = vec![0u8; row_bytes * raster.height];
+
+    for y in 0..raster.height {
+        for x in 0..raster.width {
+            let level = raster.pixels[y * raster.width + x];
+            let shift = 8 - bpp * (x % per_byte + 1);
+            packed[y * row_bytes + x / per_byte] |= level << shift;
         }
     }
 
+    (bpp, row_bytes, packed)
+}
+
+/// Packs a raster into a palettized BMP (1, 2 or 4 bits per pixel)
+fn raster_to_bmp(raster: &Raster) -> Result<Vec<u8>, Error> {
+    // Palette index 0 is black
+    let (bpp, _, packed) = pack_rows(raster);
+
     // Create BMP file
-    create_bmp_file(width, height, &bit_data)
+    create_bmp_file(raster.width, raster.height, bpp, raster.levels, &packed)
 }
 
-/// Creates a 1-bit BMP file from bit data
-fn create_bmp_file(width: usize, height: usize, bit_data: &[u8]) -> Result<Vec<u8>, Error> {
+/// Encodes a raster as a grayscale PNG at 1, 2 or 4 bits per pixel. Level
+/// indices map directly onto PNG gray values, which run from black to white.
+fn raster_to_png(raster: &Raster) -> Result<Vec<u8>, Error> {
+    let (bpp, _, packed) = pack_rows(raster);
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, raster.width as u32, raster.height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(match bpp {
+        1 => png::BitDepth::One,
+        2 => png::BitDepth::Two,
+        _ => png::BitDepth::Four,
+    });
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&packed)?;
+    writer.finish()?;
+
+    Ok(png_data)
+}
+
+/// Creates a palettized BMP file from packed pixel data, with a gray ramp of
+/// `levels` colors from black to white
+fn create_bmp_file(
+    width: usize,
+    height: usize,
+    bits_per_pixel: usize,
+    levels: u8,
+    packed: &[u8],
+) -> Result<Vec<u8>, Error> {
     // BMP requires rows to be padded to 4-byte boundaries
-    let row_bytes = (width + 7) / 8;
-    let row_size = ((width + 31) / 32) * 4; // Round up to nearest 4 bytes
+    let row_bytes = (width * bits_per_pixel).div_ceil(8);
+    let row_size = (width * bits_per_pixel).div_ceil(32) * 4; // Round up to nearest 4 bytes
     let pixel_data_size = row_size * height;
-    let color_table_size = 8; // 2 colors * 4 bytes each
+    let colors = levels.max(2) as usize;
+    let color_table_size = colors * 4; // 4 bytes (BGRA) per color
     let header_size = 14 + 40; // BMP file header + DIB header
     let file_size = header_size + color_table_size + pixel_data_size;
 
@@ -158,24 +797,26 @@ fn create_bmp_file(width: usize, height: usize, bit_data: &[u8]) -> Result<Vec<u
     bmp_data.extend_from_slice(&(width as i32).to_le_bytes()); // Width
     bmp_data.extend_from_slice(&(height as i32).to_le_bytes()); // Height
     bmp_data.extend_from_slice(&1u16.to_le_bytes()); // Planes
-    bmp_data.extend_from_slice(&1u16.to_le_bytes()); // Bits per pixel
+    bmp_data.extend_from_slice(&(bits_per_pixel as u16).to_le_bytes()); // Bits per pixel
     bmp_data.extend_from_slice(&0u32.to_le_bytes()); // Compression (none)
     bmp_data.extend_from_slice(&(pixel_data_size as u32).to_le_bytes()); // Image size
     bmp_data.extend_from_slice(&0i32.to_le_bytes()); // X pixels per meter
     bmp_data.extend_from_slice(&0i32.to_le_bytes()); // Y pixels per meter
-    bmp_data.extend_from_slice(&2u32.to_le_bytes()); // Colors used (2)
-    bmp_data.extend_from_slice(&2u32.to_le_bytes()); // Important colors (2)
+    bmp_data.extend_from_slice(&(colors as u32).to_le_bytes()); // Colors used
+    bmp_data.extend_from_slice(&(colors as u32).to_le_bytes()); // Important colors
 
-    // Color Table (8 bytes: 2 colors * 4 bytes BGRA)
-    bmp_data.extend_from_slice(&[0, 0, 0, 0]); // Black (index 0)
-    bmp_data.extend_from_slice(&[255, 255, 255, 0]); // White (index 1)
+    // Color Table (gray ramp, BGRA): index 0 black, last index white
+    for i in 0..colors {
+        let v = (i * 255 / (colors - 1)) as u8;
+        bmp_data.extend_from_slice(&[v, v, v, 0]);
+    }
 
     // Pixel Data (bottom-up, padded rows)
     // BMP stores rows bottom-up, so we need to reverse
     for y in (0..height).rev() {
         let src_offset = y * row_bytes;
-        let src_end = (src_offset + row_bytes).min(bit_data.len());
-        bmp_data.extend_from_slice(&bit_data[src_offset..src_end]);
+        let src_end = (src_offset + row_bytes).min(packed.len());
+        bmp_data.extend_from_slice(&packed[src_offset..src_end]);
 
         // Add padding to reach 4-byte boundary
         let padding = row_size - row_bytes;
@@ -186,3 +827,403 @@ fn create_bmp_file(width: usize, height: usize, bit_data: &[u8]) -> Result<Vec<u
 
     Ok(bmp_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raster(levels: u8, pixels: Vec<u8>) -> Raster {
+        Raster {
+            width: pixels.len(),
+            height: 1,
+            levels,
+            pixels,
+        }
+    }
+
+    fn u16_at(bmp: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bmp[offset], bmp[offset + 1]])
+    }
+
+    fn u32_at(bmp: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bmp[offset..offset + 4].try_into().expect("4 bytes"))
+    }
+
+    #[test]
+    fn test_now_object_has_fields_in_timezone_and_locale() {
+        use chrono::TimeZone;
+
+        let tz: Tz = "Europe/Berlin".parse().expect("tz");
+        let time = tz.with_ymd_and_hms(2026, 3, 2, 7, 5, 9).unwrap();
+        let locale = crate::device::locale::locale("de").expect("de");
+        let now = now_obj(&time, locale);
+        let get = |key: &str| now.get(key).expect(key).to_kstr().to_string();
+        assert_eq!(get("timestamp"), time.timestamp().to_string());
+        assert_eq!(get("iso"), "2026-03-02T07:05:09+01:00");
+        assert_eq!(get("hour"), "7");
+        assert_eq!(get("weekday"), "1");
+        assert_eq!(get("weekday_name"), "Montag");
+        assert_eq!(get("month_name"), "März");
+        assert_eq!(get("day_of_year"), "61");
+        assert_eq!(get("week"), "10");
+        assert_eq!(get("timezone"), "CET");
+        assert_eq!(get("utc_offset"), "+01:00");
+    }
+
+    #[test]
+    fn test_two_level_raster_encodes_1bpp_black_white_bmp() {
+        let bmp = raster_to_bmp(&raster(2, vec![1, 0, 1, 1, 0, 0, 0, 1, 1])).expect("encode");
+        assert_eq!(u16_at(&bmp, 28), 1, "bits per pixel");
+        assert_eq!(u32_at(&bmp, 46), 2, "colors used");
+        assert_eq!(&bmp[54..62], &[0, 0, 0, 0, 255, 255, 255, 0]);
+        let pixels = &bmp[u32_at(&bmp, 10) as usize..];
+        assert_eq!(&pixels[..4], &[0b1011_0001, 0b1000_0000, 0, 0]);
+    }
+
+    #[test]
+    fn test_four_level_raster_encodes_2bpp_gray_bmp() {
+        let bmp = raster_to_bmp(&raster(4, vec![0, 1, 2, 3, 3])).expect("encode");
+        assert_eq!(u16_at(&bmp, 28), 2, "bits per pixel");
+        assert_eq!(u32_at(&bmp, 46), 4, "colors used");
+        assert_eq!(
+            &bmp[54..70],
+            &[
+                0, 0, 0, 0, 85, 85, 85, 0, 170, 170, 170, 0, 255, 255, 255, 0
+            ]
+        );
+        let pixels = &bmp[u32_at(&bmp, 10) as usize..];
+        assert_eq!(&pixels[..4], &[0b0001_1011, 0b1100_0000, 0, 0]);
+    }
+
+    #[test]
+    fn test_sixteen_level_raster_encodes_4bpp_gray_bmp() {
+        let bmp = raster_to_bmp(&raster(16, vec![0, 15, 7])).expect("encode");
+        assert_eq!(u16_at(&bmp, 28), 4, "bits per pixel");
+        assert_eq!(u32_at(&bmp, 46), 16, "colors used");
+        assert_eq!(u32_at(&bmp, 2) as usize, bmp.len(), "file size header");
+        let pixels = &bmp[u32_at(&bmp, 10) as usize..];
+        assert_eq!(&pixels[..4], &[0x0f, 0x70, 0, 0]);
+    }
+
+    fn png_bit_depth(png_data: &[u8]) -> png::BitDepth {
+        png::Decoder::new(std::io::Cursor::new(png_data))
+            .read_info()
+            .expect("read png header")
+            .info()
+            .bit_depth
+    }
+
+    #[test]
+    fn test_png_bit_depth_follows_gray_levels() {
+        let one_bit = raster_to_png(&raster(2, vec![0, 1, 1])).expect("encode");
+        assert_eq!(png_bit_depth(&one_bit), png::BitDepth::One);
+        let two_bit = raster_to_png(&raster(4, vec![0, 1, 2, 3])).expect("encode");
+        assert_eq!(png_bit_depth(&two_bit), png::BitDepth::Two);
+        let four_bit = raster_to_png(&raster(16, vec![0, 15])).expect("encode");
+        assert_eq!(png_bit_depth(&four_bit), png::BitDepth::Four);
+    }
+
+    #[tokio::test]
+    async fn test_failed_sources_are_reported_to_templates_and_preview() {
+        use crate::models::HttpSource;
+
+        let context = RenderContext {
+            device: crate::models::Device::virtual_device(),
+            template: crate::models::server::template_with(""),
+            prometheus_queries: vec![],
+            range_queries: vec![],
+            // Unsaved, so there is no last good result to fall back to.
+            http_sources: vec![HttpSource {
+                id: None,
+                name: "weather".to_string(),
+                template_id: 0,
+                url: "http://127.0.0.1:1/".to_string(),
+                timeout_secs: 5,
+                created_at: Default::default(),
+                updated_at: Default::default(),
+            }],
+            calendar_sources: vec![],
+            assets: vec![],
+            regions: vec![],
+        };
+
+        let vars = render_vars(&context).await.expect("render vars");
+        assert!(lookup(&vars, "errors.http.weather").is_some());
+        assert!(vars["http"].as_object().unwrap().get("weather").is_none());
+
+        let warnings = source_warnings(&vars);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("http.weather: "), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_warnings_mention_stale_data() {
+        let vars = liquid::object!({
+            "errors": { "prometheus": { "cpu": "connection refused" } },
+            "sources": {
+                "prometheus": { "cpu": { "stale": true, "fetched_at": "2026-01-01 10:42:00 +0000" } },
+            },
+        });
+        assert_eq!(
+            source_warnings(&vars),
+            vec![
+                "prometheus.cpu: connection refused (showing data fetched 2026-01-01 10:42:00 +0000)"
+            ]
+        );
+    }
+
+    /// The value at a dotted `path` in `object`, rendered.
+    fn lookup(object: &Object, path: &str) -> Option<String> {
+        let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+        let mut value = object.get(first)?.as_view();
+        for key in rest.split('.').filter(|key| !key.is_empty()) {
+            value = value.as_object()?.get(key)?;
+        }
+        Some(value.render().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_sources_of_different_kinds_may_share_a_name() {
+        let tz: Tz = "UTC".parse().expect("tz");
+        let mut report = SourceReport::new(Fetch::Check);
+        for (kind, now) in [
+            (SourceKind::Prometheus, 1_000),
+            (SourceKind::PrometheusRange, 2_000),
+        ] {
+            report
+                .resolve(kind, None, "cpu", Ok(Value::Nil), now, &tz)
+                .await
+                .expect("value");
+        }
+        assert_eq!(
+            lookup(&report.sources, "prometheus.cpu.fetched_at").as_deref(),
+            Some("1970-01-01 00:16:40 +0000")
+        );
+        assert_eq!(
+            lookup(&report.sources, "prometheus_range.cpu.fetched_at").as_deref(),
+            Some("1970-01-01 00:33:20 +0000")
+        );
+        assert_eq!(lookup(&report.sources, "http.cpu"), None);
+
+        for (kind, error) in [
+            (SourceKind::PrometheusRange, "bad step"),
+            (SourceKind::Prometheus, "connection refused"),
+        ] {
+            let fetched = Err(error.to_string());
+            assert!(
+                report
+                    .resolve(kind, None, "cpu", fetched, 3_000, &tz)
+                    .await
+                    .is_none()
+            );
+        }
+        assert_eq!(
+            lookup(&report.errors, "prometheus_range.cpu").as_deref(),
+            Some("bad step")
+        );
+        assert_eq!(
+            lookup(&report.errors, "prometheus.cpu").as_deref(),
+            Some("connection refused")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetches_are_concurrent_within_the_permit_cap() {
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, String>(1)
+        };
+
+        let permits = Semaphore::new(4);
+        let started = Instant::now();
+        let results = join_all((0..4).map(|_| fetch_source(&permits, 5, slow()))).await;
+        assert!(results.iter().all(|(r, _)| r == &Ok(1)));
+        assert!(
+            results
+                .iter()
+                .all(|(_, elapsed)| elapsed.unwrap() >= Duration::from_millis(200))
+        );
+        assert!(
+            started.elapsed() < Duration::from_millis(600),
+            "fetched in parallel"
+        );
+
+        let permits = Semaphore::new(1);
+        let started = Instant::now();
+        join_all((0..3).map(|_| fetch_source(&permits, 5, slow()))).await;
+        assert!(
+            started.elapsed() >= Duration::from_millis(600),
+            "one at a time"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_fetch_times_out() {
+        let permits = Semaphore::new(1);
+        let hang = std::future::pending::<Result<(), String>>();
+        let (result, elapsed) = fetch_source(&permits, 1, hang).await;
+        assert_eq!(result.unwrap_err(), "Timed out after 1s");
+        assert!(elapsed.is_some());
+
+        let _held = permits.acquire().await.unwrap();
+        let (result, elapsed) = fetch_source(&permits, 1, async { Ok::<_, String>(()) }).await;
+        assert_eq!(result.unwrap_err(), "No free fetch slot within 1s");
+        assert_eq!(elapsed, None, "never started");
+    }
+
+    #[test]
+    fn test_raster_is_cropped_to_max_size_and_rejects_empty_images() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20000" height="300"/>"#;
+        let (raster, _) = svg_to_raster(svg, 2, &DitherOptions::default(), (400, 480)).unwrap();
+        assert_eq!((raster.width, raster.height), (400, 300));
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300"/>"#;
+        let err = svg_to_raster(svg, 2, &DitherOptions::default(), (0, 480))
+            .err()
+            .expect("empty image");
+        assert!(matches!(err, Error::ImageSizeError(0, 300)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_layout_renders_each_region_at_its_size() {
+        use crate::db::{create_template, test_support::init_test_db};
+        use crate::models::{LayoutRegion, Region};
+
+        init_test_db().await;
+        // Fills its whole viewport and overdraws far beyond it.
+        let black = create_template(
+            "layout-black",
+            r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="{{ device.width }}" height="{{ device.height }}"><rect x="-500" y="-500" width="5000" height="5000" fill="black"/></svg>"#,
+            TemplateKind::Liquid,
+        )
+        .await
+        .expect("create template");
+        let layout = create_template("layout", "", TemplateKind::Layout)
+            .await
+            .expect("create layout");
+
+        let mut region = LayoutRegion::new(layout.id, black.id);
+        region.region = Region::BottomRight;
+        let mut device = Device::virtual_device();
+        (device.width, device.height) = (8, 4);
+        let mut context = RenderContext::load(device, layout.clone())
+            .await
+            .expect("load layout");
+        context.regions = vec![region];
+
+        let (svg, warnings) = render_layout(&context, Instant::now() + Duration::from_secs(5))
+            .await
+            .expect("render layout");
+        assert!(warnings.is_empty());
+        assert!(svg.contains(r#"width="4" height="2""#), "{svg}");
+        assert!(!svg.contains("<?xml"), "declaration dropped when nesting");
+
+        let (raster, _) = svg_to_raster(&svg, 2, &DitherOptions::default(), (8, 4)).unwrap();
+        let rows: Vec<&[u8]> = raster.pixels.chunks(8).collect();
+        assert_eq!(rows[0], [1; 8], "top stays white");
+        assert_eq!(rows[3], [1, 1, 1, 1, 0, 0, 0, 0], "bottom right is clipped");
+
+        context.regions[0].template_id = layout.id;
+        let (svg, warnings) = render_layout(&context, Instant::now() + Duration::from_secs(5))
+            .await
+            .expect("a nested layout only fails its region");
+        assert_eq!(
+            warnings,
+            ["layout: layout is a layout; layouts can't contain other layouts"]
+        );
+        assert!(svg.contains("Render error: layout"), "{svg}");
+    }
+
+    #[tokio::test]
+    async fn test_a_failing_region_leaves_the_others_displayed() {
+        use crate::db::{create_template, test_support::init_test_db};
+        use crate::models::{LayoutRegion, Region};
+
+        init_test_db().await;
+        let black = create_template(
+            "layout-region-ok",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{{ device.width }}" height="{{ device.height }}"><rect width="100%" height="100%" fill="black"/></svg>"#,
+            TemplateKind::Liquid,
+        )
+        .await
+        .expect("create template");
+        let broken = create_template(
+            "layout-region-broken",
+            r#"{{ 1 | battery: unit: "%" }}"#,
+            TemplateKind::Liquid,
+        )
+        .await
+        .expect("create template");
+        let layout = create_template("layout-partly-broken", "", TemplateKind::Layout)
+            .await
+            .expect("create layout");
+
+        let mut left = LayoutRegion::new(layout.id, black.id);
+        left.region = Region::LeftHalf;
+        let mut right = LayoutRegion::new(layout.id, broken.id);
+        right.region = Region::RightHalf;
+        let mut device = Device::virtual_device();
+        (device.width, device.height) = (80, 40);
+        let mut context = RenderContext::load(device, layout)
+            .await
+            .expect("load layout");
+        context.regions = vec![left, right];
+
+        let (svg, warnings) = render_layout(&context, Instant::now() + Duration::from_secs(5))
+            .await
+            .expect("render layout");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(
+            warnings[0].starts_with("layout-region-broken: ") && warnings[0].contains("battery"),
+            "{warnings:?}"
+        );
+        assert!(svg.contains("Render error: layout-region-broken"), "{svg}");
+
+        let (raster, _) = svg_to_raster(&svg, 2, &DitherOptions::default(), (80, 40)).unwrap();
+        let middle = &raster.pixels[20 * 80..21 * 80];
+        assert!(
+            middle[..40].iter().all(|&p| p == 0),
+            "left half still drawn"
+        );
+        assert!(
+            middle[41..79].contains(&1),
+            "right half shows the error box"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_screen_renders_become_the_last_image() {
+        use crate::db::{create_template, test_support::init_test_db};
+
+        init_test_db().await;
+        let template = create_template(
+            "last-image",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{{ device.width }}" height="{{ device.height }}"/>"#,
+            TemplateKind::Liquid,
+        )
+        .await
+        .expect("create template");
+        let mut device = Device::virtual_device();
+        (device.id, device.width, device.height) = (33_001, 8, 4);
+        let context = RenderContext::load(device, template)
+            .await
+            .expect("load context");
+
+        render_preview(&context).await.expect("preview");
+        assert!(last_image(33_001, ImageFormat::Png).is_none(), "preview");
+
+        let image = render_screen(&context, ImageFormat::Png)
+            .await
+            .expect("screen");
+        assert_eq!(last_image(33_001, ImageFormat::Png), Some(image));
+    }
+
+    #[test]
+    fn test_png_decodes_to_panel_grays() {
+        let png_data = raster_to_png(&raster(4, vec![0, 1, 2, 3])).expect("encode");
+        let decoded = image::load_from_memory(&png_data)
+            .expect("decode")
+            .to_luma8();
+        assert_eq!(decoded.into_raw(), vec![0, 85, 170, 255]);
+    }
+}
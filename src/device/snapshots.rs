@@ -0,0 +1,190 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use liquid::Object;
+use liquid::model::Value;
+
+use crate::db::{get_source_snapshot, save_source_snapshot};
+use crate::models::SourceKind;
+
+/// A data source's value for one render: either fetched just now or, when the
+/// fetch failed, the last good result stored in `source_snapshots`.
+#[derive(Debug)]
+pub struct SourceValue {
+    pub value: Value,
+    /// Why the live fetch failed, in which case `value` is the last good result.
+    pub error: Option<String>,
+    /// When `value` was fetched, in Unix seconds.
+    pub fetched_at: i64,
+}
+
+impl SourceValue {
+    /// The `sources.<kind>.<name>` object templates see. `fetched_at` is in the
+    /// render timezone in a form the `date` filter accepts.
+    pub fn meta(&self, tz: &Tz) -> Object {
+        let fetched_at = DateTime::from_timestamp(self.fetched_at, 0)
+            .unwrap_or_default()
+            .with_timezone(tz)
+            .format("%Y-%m-%d %H:%M:%S %z")
+            .to_string();
+        liquid::object!({
+            "stale": self.error.is_some(),
+            "fetched_at": fetched_at,
+        })
+    }
+}
+
+/// Stores a successful fetch as the source's last good result (unless `keep`
+/// is false), or falls back to the stored one when the fetch failed. Sources
+/// that haven't been saved yet (no id) are passed through. Storage problems
+/// are logged rather than failing the render.
+pub async fn resolve(
+    kind: SourceKind,
+    source_id: Option<i64>,
+    fetched: Result<Value, String>,
+    now: i64,
+    keep: bool,
+) -> Result<SourceValue, String> {
+    match fetched {
+        Ok(value) => {
+            if let Some(id) = source_id.filter(|_| keep) {
+                store(kind, id, &value, now).await;
+            }
+            Ok(SourceValue {
+                value,
+                error: None,
+                fetched_at: now,
+            })
+        }
+        Err(error) => {
+            let stored = match source_id {
+                Some(id) => load(kind, id).await,
+                None => None,
+            };
+            let Some((value, fetched_at)) = stored else {
+                return Err(error);
+            };
+            tracing::warn!(
+                "{kind:?} source {source_id:?} failed, using result from {fetched_at}: {error}"
+            );
+            Ok(SourceValue {
+                value,
+                error: Some(error),
+                fetched_at,
+            })
+        }
+    }
+}
+
+async fn store(kind: SourceKind, source_id: i64, value: &Value, now: i64) {
+    let json = match serde_json::to_string(value) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!("Unable to serialize {kind:?} source {source_id}: {e}");
+            return;
+        }
+    };
+    if let Err(e) = save_source_snapshot(kind, source_id, &json, now).await {
+        tracing::error!("Unable to save {kind:?} source {source_id}: {e}");
+    }
+}
+
+async fn load(kind: SourceKind, source_id: i64) -> Option<(Value, i64)> {
+    let (json, fetched_at) = match get_source_snapshot(kind, source_id).await {
+        Ok(snapshot) => snapshot?,
+        Err(e) => {
+            tracing::error!("Unable to load {kind:?} source {source_id}: {e}");
+            return None;
+        }
+    };
+    match serde_json::from_str(&json) {
+        Ok(value) => Some((value, fetched_at)),
+        Err(e) => {
+            tracing::error!("Unable to parse stored {kind:?} source {source_id}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::init_test_db;
+
+    fn sample() -> Value {
+        Value::Array(vec![Value::Object(liquid::object!({
+            "labels": liquid::object!({ "job": "node" }),
+            "value": 0.25,
+            "count": 3,
+            "name": "cpu",
+            "missing": Value::Nil,
+        }))])
+    }
+
+    #[tokio::test]
+    async fn test_failed_fetch_falls_back_to_last_good_value() {
+        init_test_db().await;
+        let kind = SourceKind::Prometheus;
+
+        let fresh = resolve(kind, Some(9001), Ok(sample()), 1_000, true)
+            .await
+            .unwrap();
+        assert_eq!(fresh.error, None);
+
+        let stale = resolve(
+            kind,
+            Some(9001),
+            Err("connection refused".into()),
+            2_000,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stale.error.as_deref(), Some("connection refused"));
+        assert_eq!(stale.fetched_at, 1_000);
+        assert_eq!(stale.value, sample());
+
+        let err = resolve(
+            kind,
+            Some(9002),
+            Err("connection refused".into()),
+            2_000,
+            true,
+        )
+        .await
+        .expect_err("nothing stored for a source that never succeeded");
+        assert_eq!(err, "connection refused");
+        assert!(
+            resolve(SourceKind::Http, Some(9001), Err("x".into()), 2_000, true)
+                .await
+                .is_err(),
+            "snapshots are per kind"
+        );
+
+        resolve(kind, Some(9003), Ok(sample()), 1_000, false)
+            .await
+            .unwrap();
+        assert!(
+            resolve(kind, Some(9003), Err("x".into()), 2_000, true)
+                .await
+                .is_err(),
+            "not kept"
+        );
+    }
+
+    #[test]
+    fn test_fetched_at_can_be_formatted_by_templates() {
+        let source = SourceValue {
+            value: Value::Nil,
+            error: Some("timed out".to_string()),
+            fetched_at: 1_700_000_000,
+        };
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        let template = crate::models::server::template_with(
+            r#"{% if source.stale %}as of {{ source.fetched_at | date: "%H:%M" }}{% endif %}"#,
+        );
+        let out = template
+            .render(liquid::object!({ "source": source.meta(&tz) }))
+            .expect("render");
+        assert_eq!(out, "as of 23:13");
+    }
+}
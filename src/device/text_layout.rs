@@ -0,0 +1,348 @@
+use usvg::fontdb::{Database, Family, Query, Weight};
+
+use crate::device::fonts::font_database;
+
+/// Font used to measure text; mirrors the SVG `font-family`, `font-size` and
+/// `font-weight` attributes of the `<text>` element it will be drawn in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Comma-separated family list, as in `font-family`.
+    pub family: String,
+    pub size: f32,
+    pub weight: u16,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            family: "sans-serif".to_string(),
+            size: 16.0,
+            weight: 400,
+        }
+    }
+}
+
+/// Calls `f` with a function giving the advance width of a string at
+/// `style.size`, using the face `usvg` would pick from the shared font
+/// database for the same style.
+///
+/// Widths are the sum of glyph advances without kerning, which matches what
+/// resvg draws for the pixel and UI fonts this is meant for to within a pixel
+/// or two per line.
+pub fn with_measure<T>(
+    style: &TextStyle,
+    f: impl FnOnce(&dyn Fn(&str) -> f32) -> T,
+) -> Result<T, String> {
+    measure_in(&font_database(), style, f)
+}
+
+/// Like `with_measure`, but when no installed font matches `style` every
+/// character is estimated at 0.6em wide instead of failing, for layouts that
+/// should still render without the exact font.
+pub fn with_measure_or_estimate<T>(style: &TextStyle, f: impl Fn(&dyn Fn(&str) -> f32) -> T) -> T {
+    with_measure(style, &f).unwrap_or_else(|_| {
+        let size = style.size;
+        f(&|text: &str| text.chars().count() as f32 * 0.6 * size)
+    })
+}
+
+fn measure_in<T>(
+    db: &Database,
+    style: &TextStyle,
+    f: impl FnOnce(&dyn Fn(&str) -> f32) -> T,
+) -> Result<T, String> {
+    let mut families: Vec<Family> = style
+        .family
+        .split(',')
+        .map(|name| name.trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|name| !name.is_empty())
+        .map(|name| match name {
+            "serif" => Family::Serif,
+            "sans-serif" => Family::SansSerif,
+            "cursive" => Family::Cursive,
+            "fantasy" => Family::Fantasy,
+            "monospace" => Family::Monospace,
+            name => Family::Name(name),
+        })
+        .collect();
+    // Same fallback as usvg.
+    families.push(Family::Serif);
+
+    let id = db
+        .query(&Query {
+            families: &families,
+            weight: Weight(style.weight),
+            ..Default::default()
+        })
+        .ok_or_else(|| format!("no font matches font-family {:?}", style.family))?;
+
+    db.with_face_data(id, |data, index| {
+        let face = ttf_parser::Face::parse(data, index).map_err(|e| e.to_string())?;
+        let scale = style.size / face.units_per_em() as f32;
+        // Characters the font lacks are drawn as .notdef (glyph 0).
+        let advance = |c: char| {
+            let glyph = face.glyph_index(c).unwrap_or(ttf_parser::GlyphId(0));
+            face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale
+        };
+        let measure = |text: &str| text.chars().map(advance).sum();
+        Ok(f(&measure))
+    })
+    .ok_or_else(|| format!("unable to read font for {:?}", style.family))?
+}
+
+/// Greedy word wrap to `max_width`. Explicit line breaks are kept, and words
+/// wider than a whole line are broken between characters. With `max_lines`,
+/// the last kept line is ellipsized if text was cut.
+pub fn wrap_lines(
+    text: &str,
+    max_width: f32,
+    max_lines: Option<usize>,
+    measure: &dyn Fn(&str) -> f32,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if measure(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Break words that don't fit on a line of their own.
+            for c in word.chars() {
+                line.push(c);
+                if measure(&line) > max_width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+
+    if let Some(max_lines) = max_lines
+        && lines.len() > max_lines
+    {
+        lines.truncate(max_lines.max(1));
+        if let Some(last) = lines.last_mut() {
+            *last = truncate(&format!("{last}…"), max_width, "…", measure);
+        }
+    }
+    lines
+}
+
+/// Shortens `text` so it fits in `max_width`, ending in `ellipsis` when cut.
+pub fn truncate(
+    text: &str,
+    max_width: f32,
+    ellipsis: &str,
+    measure: &dyn Fn(&str) -> f32,
+) -> String {
+    if measure(text) <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.trim_end_matches(ellipsis).chars().collect();
+    while !chars.is_empty() {
+        let candidate: String = chars.iter().collect::<String>().trim_end().to_string() + ellipsis;
+        if measure(&candidate) <= max_width {
+            return candidate;
+        }
+        chars.pop();
+    }
+    if measure(ellipsis) <= max_width {
+        ellipsis.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Largest whole font size between `min` and `max` at which `text` fits on one
+/// line of `max_width`, and whose size doesn't exceed `max_height`. Glyph
+/// advances scale linearly, so one measurement at size 1 is enough.
+pub fn fit_size(
+    text: &str,
+    max_width: f32,
+    max_height: Option<f32>,
+    min: f32,
+    max: f32,
+    measure_at_1px: &dyn Fn(&str) -> f32,
+) -> f32 {
+    let unit_width = measure_at_1px(text);
+    let mut size = max;
+    if unit_width > 0.0 {
+        size = size.min(max_width / unit_width);
+    }
+    if let Some(max_height) = max_height {
+        size = size.min(max_height);
+    }
+    size.floor().max(min)
+}
+
+/// Escapes text for use as SVG character data or attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Undoes `escape_xml` and Liquid's `escape`, so text that arrives escaped is
+/// measured as it is drawn and escaped only once on the way out.
+pub fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monospaced stand-in: every character is 10px wide.
+    fn mono(text: &str) -> f32 {
+        text.chars().count() as f32 * 10.0
+    }
+
+    #[test]
+    fn test_wrap_breaks_between_words() {
+        assert_eq!(
+            wrap_lines("the quick brown fox jumps", 100.0, None, &mono),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_keeps_line_breaks_and_splits_long_words() {
+        assert_eq!(
+            wrap_lines("ab\nabcdefghijklm", 50.0, None, &mono),
+            vec!["ab", "abcde", "fghij", "klm"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_ellipsizes_last_line_when_cut() {
+        assert_eq!(
+            wrap_lines("the quick brown fox jumps", 100.0, Some(2), &mono),
+            vec!["the quick", "brown fox…"]
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_pixel_width() {
+        assert_eq!(truncate("short", 100.0, "…", &mono), "short");
+        assert_eq!(truncate("a much longer title", 80.0, "…", &mono), "a much…");
+        assert_eq!(truncate("abc", 5.0, "…", &mono), "");
+    }
+
+    #[test]
+    fn test_escaping_round_trips_once() {
+        let text = r#"Q&A <live> "now" it's"#;
+        assert_eq!(unescape_xml(&escape_xml(text)), text);
+        assert_eq!(unescape_xml("Q&amp;A it&#39;s"), "Q&A it's");
+        assert_eq!(escape_xml(&unescape_xml("Q&amp;A")), "Q&amp;A");
+    }
+
+    #[test]
+    fn test_fit_size_is_limited_by_width_height_and_bounds() {
+        let unit = |text: &str| mono(text) / 10.0 * 0.5;
+        // "hello" is 2.5px wide per 1px of font size.
+        assert_eq!(fit_size("hello", 100.0, None, 6.0, 64.0, &unit), 40.0);
+        assert_eq!(fit_size("hello", 100.0, Some(24.0), 6.0, 64.0, &unit), 24.0);
+        assert_eq!(fit_size("hello", 1000.0, None, 6.0, 64.0, &unit), 64.0);
+        assert_eq!(fit_size("hello", 1.0, None, 6.0, 64.0, &unit), 6.0);
+    }
+
+    const NO_FONT: &str = "a system font is installed, such as fonts-dejavu";
+
+    /// Name of an installed font family, since the generic families resolve
+    /// to fonts (Arial, Times New Roman) that may not be installed.
+    fn installed_family(db: &Database) -> Option<String> {
+        db.faces()
+            .find_map(|face| face.families.first().map(|(name, _)| name.clone()))
+    }
+
+    #[test]
+    fn test_measurement_uses_installed_fonts() {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        let family = installed_family(&db).expect(NO_FONT);
+        let style = TextStyle {
+            family,
+            ..Default::default()
+        };
+        let (short, long) = measure_in(&db, &style, |m| (m("il"), m("WWWW"))).unwrap();
+        assert!(short > 0.0 && long > short * 2.0, "{short} vs {long}");
+
+        let double = TextStyle {
+            size: 32.0,
+            ..style.clone()
+        };
+        let doubled = measure_in(&db, &double, |m| m("WWWW")).unwrap();
+        assert!((doubled - long * 2.0).abs() < 0.01);
+
+        assert!(measure_in(&Database::new(), &style, |m| m("x")).is_err());
+    }
+
+    #[test]
+    fn test_filters_render_in_template() {
+        let family = installed_family(&font_database()).expect(NO_FONT);
+        let template = crate::models::server::template_with(&format!(
+            concat!(
+                r#"{{{{ "one two three four five six" | wrap_text: width: 60, x: 5, font_family: "{0}" }}}}|"#,
+                r#"{{{{ "Hi" | fit_text: width: 1000, height: 40, font_family: "{0}" }}}}|"#,
+                r#"{{{{ "one two three four five" | truncate_px: width: 60, font_family: "{0}" }}}}"#,
+            ),
+            family
+        ));
+        let out = template.render(liquid::object!({})).expect("render");
+        let parts: Vec<&str> = out.split('|').collect();
+
+        assert!(parts[0].starts_with(r#"<tspan x="5" dy="0">"#), "{out}");
+        assert!(parts[0].matches("<tspan").count() > 1, "{out}");
+        assert_eq!(parts[1], "40");
+        assert!(
+            parts[2].starts_with("one") && parts[2].ends_with('…'),
+            "{out}"
+        );
+
+        let escaped = crate::models::Template {
+            content: format!(
+                r#"{{{{ "Q&A <live>" | truncate_px: width: 1000, font_family: "{family}" }}}}"#
+            ),
+            ..template.clone()
+        }
+        .render(liquid::object!({}))
+        .expect("render");
+        assert_eq!(escaped, "Q&amp;A &lt;live&gt;");
+
+        // Already escaped input, such as from `escape`, isn't escaped twice.
+        let escaped_twice = crate::models::Template {
+            content: format!(
+                r#"{{{{ "Q&A" | escape | truncate_px: width: 1000, font_family: "{family}" }}}}"#
+            ),
+            ..template.clone()
+        }
+        .render(liquid::object!({}))
+        .expect("render");
+        assert_eq!(escaped_twice, "Q&amp;A");
+
+        let err = crate::models::Template {
+            content: r#"{{ "x" | wrap_text }}"#.to_string(),
+            ..template
+        }
+        .render(liquid::object!({}))
+        .expect_err("width is required");
+        assert!(err.to_string().contains("'width' is required"), "{err}");
+    }
+}
@@ -0,0 +1,870 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Instant;
+
+use liquid::Object;
+use liquid::model::ValueView;
+
+use crate::device::fonts::font_database;
+use crate::device::render_limits::render_limits;
+use crate::device::renderer::{Content, Error, Fetch, fetch_vars, render_liquid};
+use crate::models::server::liquid_parser;
+use crate::models::{RenderContext, TemplateDiagnostic, TemplateValidation};
+
+/// Warnings of one kind past this many are summed up in a last one.
+const MAX_WARNINGS: usize = 10;
+
+/// Words of Liquid expressions that aren't variables.
+const KEYWORDS: &[&str] = &[
+    "and", "or", "contains", "in", "true", "false", "nil", "null", "empty", "blank", "with", "as",
+    "for", "reversed",
+];
+
+/// Checks a Liquid template the way a device would render it: parses it,
+/// renders it with what its data sources return now and parses the SVG.
+/// An error from any step is reported at the template line and column it
+/// comes from, when that can be worked out. A template that renders is also
+/// checked for an SVG size other than the device's, variables its data
+/// doesn't have and text outside the screen.
+///
+/// Data is fetched without touching the sources' stored results, metrics or
+/// fetch timings. A check that can't run at all, such as data taking longer
+/// than the render budget, is reported as an error too.
+pub async fn validate_template(render_context: &RenderContext) -> TemplateValidation {
+    let mut validation = TemplateValidation::default();
+    if let Err(e) = check(render_context, &mut validation).await {
+        validation.errors.push(TemplateDiagnostic {
+            message: format!("Unable to check the template: {e}"),
+            line: None,
+            column: None,
+        });
+    }
+    validation
+}
+
+async fn check(
+    render_context: &RenderContext,
+    validation: &mut TemplateValidation,
+) -> Result<(), Error> {
+    let content = render_context.template.content.as_str();
+
+    if let Err(e) = liquid_parser().parse(content) {
+        validation
+            .errors
+            .push(liquid_diagnostic(content, &e.to_string()));
+        return Ok(());
+    }
+
+    let limits = render_limits();
+    let deadline = Instant::now() + limits.timeout;
+    let vars = tokio::time::timeout_at(deadline.into(), fetch_vars(render_context, Fetch::Check))
+        .await
+        .map_err(|_| Error::TimeoutError(limits.timeout))??;
+    validation
+        .warnings
+        .extend(unknown_variables(content, &vars));
+
+    let svg = match render_liquid(render_context, vars, deadline, Content::Unsaved).await {
+        Ok((svg, _)) => svg,
+        Err(e @ (Error::LiquidError(_) | Error::TimeoutError(_))) => {
+            validation
+                .errors
+                .push(liquid_diagnostic(content, &e.to_string()));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    let output = svg.clone();
+    let parsed = tokio::task::spawn_blocking(move || {
+        let opt = usvg::Options {
+            fontdb: font_database(),
+            ..Default::default()
+        };
+        usvg::Tree::from_str(&output, &opt).map(|tree| (tree.size(), texts(tree.root())))
+    })
+    .await?;
+    let (size, texts) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            validation.errors.push(svg_diagnostic(content, &svg, &e));
+            return Ok(());
+        }
+    };
+
+    let device = &render_context.device;
+    let (width, height) = (size.width().round() as i64, size.height().round() as i64);
+    if (width, height) != (device.width, device.height) {
+        let (line, column) = content
+            .find("<svg")
+            .map(|offset| line_column(content, offset))
+            .unzip();
+        validation.warnings.push(TemplateDiagnostic {
+            message: format!(
+                "The SVG is {width}×{height} but the device screen is {}×{}; \
+                 size it with {{{{ device.width }}}} and {{{{ device.height }}}}",
+                device.width, device.height
+            ),
+            line,
+            column,
+        });
+    }
+    validation
+        .warnings
+        .extend(text_outside(content, &svg, size, &texts));
+
+    Ok(())
+}
+
+/// A `{{ }}` or `{% %}` in a template: its byte range and the text between
+/// the delimiters, without whitespace control, starting at `inner_start`.
+/// A comment's range runs to its `endcomment`.
+struct Markup<'a> {
+    range: Range<usize>,
+    inner: &'a str,
+    inner_start: usize,
+    is_tag: bool,
+}
+
+impl Markup<'_> {
+    /// The tag's name and what follows it, with the offset of the latter.
+    fn tag(&self) -> (&str, &str, usize) {
+        let name_len = self
+            .inner
+            .find(char::is_whitespace)
+            .unwrap_or(self.inner.len());
+        let rest = self.inner[name_len..].trim_start();
+        let rest_start = self.inner_start + (self.inner.len() - rest.len());
+        (&self.inner[..name_len], rest, rest_start)
+    }
+}
+
+fn markup(content: &str) -> Vec<Markup<'_>> {
+    let mut found: Vec<Markup> = vec![];
+    let mut from = 0;
+    while let Some(start) = content[from..].find('{').map(|i| from + i) {
+        let close = match content[start..].get(..2) {
+            Some("{{") => "}}",
+            Some("{%") => "%}",
+            _ => {
+                from = start + 1;
+                continue;
+            }
+        };
+        let (inner_end, end) = match content[start + 2..].find(close) {
+            Some(i) => (start + 2 + i, start + 4 + i),
+            None => (content.len(), content.len()),
+        };
+        let raw = &content[start + 2..inner_end];
+        let trimmed = raw.trim_start_matches('-').trim_start();
+        let mut markup = Markup {
+            range: start..end,
+            inner: trimmed.trim_end().trim_end_matches('-').trim_end(),
+            inner_start: start + 2 + (raw.len() - trimmed.len()),
+            is_tag: close == "%}",
+        };
+        from = end;
+
+        // The bodies of comment and raw blocks aren't Liquid.
+        let (name, _, _) = markup.tag();
+        if markup.is_tag && (name == "comment" || name == "raw") {
+            let end_tag = format!("end{name}");
+            let mut search = end;
+            while let Some(i) = content[search..].find("{%") {
+                let tag = content[search + i + 2..]
+                    .trim_start_matches('-')
+                    .trim_start();
+                if tag.starts_with(&end_tag) {
+                    from = search + i;
+                    break;
+                }
+                search += i + 2;
+            }
+            if from == end {
+                from = content.len();
+            }
+            if name == "comment" {
+                markup.range.end = from;
+            }
+        }
+        found.push(markup);
+    }
+    found
+}
+
+/// 1-based line and column (in characters) of a byte offset.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Turns a Liquid parse or render error into a diagnostic. Parse errors
+/// carry their position; for render errors the variable, index or filter
+/// they name is looked up in the template's markup.
+fn liquid_diagnostic(content: &str, error: &str) -> TemplateDiagnostic {
+    let error = error.strip_prefix("liquid: ").unwrap_or(error);
+    let lines: Vec<&str> = error.lines().collect();
+    let context: Vec<(&str, &str)> = lines
+        .iter()
+        .skip(1)
+        .filter_map(|line| line.trim().split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        // Skip the source excerpt of parse errors: `2 | {% assign x = 1 %}`.
+        .filter(|(key, _)| {
+            !key.is_empty() && !key.contains('|') && !key.starts_with(|c: char| c.is_ascii_digit())
+        })
+        .collect();
+    let context_text = context
+        .iter()
+        .filter(|(key, value)| !key.starts_with("available") || value.len() <= 60)
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let get = |name: &str| {
+        context
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+
+    // Parse errors:  --> 2:7 | excerpt | = expected Literal
+    let (headline, position) = match lines
+        .first()
+        .and_then(|line| line.trim().strip_prefix("--> "))
+    {
+        Some(position) => {
+            let headline = lines
+                .iter()
+                .find_map(|line| line.trim().strip_prefix("= "))
+                .unwrap_or("Syntax error");
+            let position = position
+                .split_once(':')
+                .and_then(|(line, column)| Some((line.parse().ok()?, column.parse().ok()?)));
+            (headline, position)
+        }
+        None => {
+            let offset = match (get("variable"), get("requested index")) {
+                (Some(variable), Some(index)) => {
+                    locate_in_markup(content, &format!("{variable}.{index}"))
+                        .or_else(|| locate_in_markup(content, variable))
+                }
+                _ => None,
+            }
+            .or_else(|| get("requested variable").and_then(|v| locate_in_markup(content, v)))
+            .or_else(|| {
+                get("requested filter")
+                    .or_else(|| get("filter").and_then(|f| f.split_whitespace().next()))
+                    .and_then(|filter| locate_in_markup(content, filter))
+            });
+            (
+                lines.first().copied().unwrap_or_default(),
+                offset.map(|offset| line_column(content, offset)),
+            )
+        }
+    };
+
+    let message = if context_text.is_empty() {
+        headline.to_string()
+    } else {
+        format!("{headline} ({context_text})")
+    };
+    let (line, column) = position.unzip();
+    TemplateDiagnostic {
+        message,
+        line,
+        column,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Byte offset of the first whole-word `needle` inside `{{ }}` or `{% %}`.
+fn locate_in_markup(content: &str, needle: &str) -> Option<usize> {
+    markup(content).iter().find_map(|markup| {
+        let inner = &content[markup.inner_start..markup.inner_start + markup.inner.len()];
+        inner.match_indices(needle).find_map(|(i, _)| {
+            let before = inner[..i].chars().next_back();
+            let after = inner[i + needle.len()..].chars().next();
+            (!before.is_some_and(is_word_char) && !after.is_some_and(is_word_char))
+                .then_some(markup.inner_start + i)
+        })
+    })
+}
+
+/// Turns an error parsing the rendered SVG into a diagnostic at the
+/// template line that produced the offending output line.
+fn svg_diagnostic(content: &str, svg: &str, error: &usvg::Error) -> TemplateDiagnostic {
+    let error = error.to_string();
+    let detail = error
+        .strip_prefix("SVG data parsing failed cause ")
+        .unwrap_or(&error);
+    let output_position = detail.rsplit_once(" at ").and_then(|(detail, position)| {
+        let (row, column) = position.split_once(':')?;
+        Some((
+            detail,
+            row.parse::<usize>().ok()?,
+            column.parse::<usize>().ok()?,
+        ))
+    });
+    let Some((detail, row, column)) = output_position else {
+        return TemplateDiagnostic {
+            message: format!("Invalid SVG: {detail}"),
+            line: None,
+            column: None,
+        };
+    };
+    let mapped = svg
+        .split('\n')
+        .nth(row.saturating_sub(1))
+        .and_then(|output_line| source_position(content, output_line, column));
+    match mapped {
+        Some((line, column)) => TemplateDiagnostic {
+            message: format!("Invalid SVG: {detail}"),
+            line: Some(line),
+            column: Some(column),
+        },
+        None => TemplateDiagnostic {
+            message: format!("Invalid SVG: {detail} (line {row}, column {column} of the output)"),
+            line: None,
+            column: None,
+        },
+    }
+}
+
+/// Literal text of a template line found in an output line: its offset in
+/// the line, its offset in the output line and its length, in bytes.
+type LiteralMatch = (usize, usize, usize);
+
+/// The template line (and column) most likely to have produced `output_line`:
+/// the one whose literal text, outside markup, appears in it in order and
+/// covers the most of it. `output_column` is 1-based, in characters; it maps
+/// into the literal text it falls in, or to the markup after it.
+fn source_position(
+    content: &str,
+    output_line: &str,
+    output_column: usize,
+) -> Option<(usize, usize)> {
+    let mut in_markup = vec![false; content.len()];
+    for markup in markup(content) {
+        in_markup[markup.range].fill(true);
+    }
+
+    // (score, line index, line start, matches)
+    let mut best: Option<(usize, usize, usize, Vec<LiteralMatch>)> = None;
+    let mut line_start = 0;
+    for (index, line) in content.split('\n').enumerate() {
+        let mut segments = vec![];
+        let mut segment_start = None;
+        for (i, _) in line.char_indices().chain([(line.len(), ' ')]) {
+            let literal = i < line.len() && !in_markup[line_start + i];
+            match (literal, segment_start) {
+                (true, None) => segment_start = Some(i),
+                (false, Some(start)) => {
+                    let text = &line[start..i];
+                    let trimmed = text.trim_start();
+                    let start = start + text.len() - trimmed.len();
+                    let trimmed = trimmed.trim_end();
+                    if !trimmed.is_empty() {
+                        segments.push((start, trimmed));
+                    }
+                    segment_start = None;
+                }
+                _ => {}
+            }
+        }
+
+        let mut from = 0;
+        let mut matches = vec![];
+        for (start, text) in &segments {
+            let Some(i) = output_line[from..].find(text) else {
+                matches.clear();
+                break;
+            };
+            matches.push((*start, from + i, text.len()));
+            from += i + text.len();
+        }
+        let score = matches.iter().map(|(_, _, len)| len).sum::<usize>();
+        if score >= 3 && best.as_ref().is_none_or(|(best, ..)| score > *best) {
+            best = Some((score, index, line_start, matches));
+        }
+        line_start += line.len() + 1;
+    }
+
+    let (_, index, line_start, matches) = best?;
+    let line = &content[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let output_offset = output_line
+        .char_indices()
+        .nth(output_column.saturating_sub(1))
+        .map_or(output_line.len(), |(i, _)| i);
+    let source_offset = match matches
+        .iter()
+        .rev()
+        .find(|(_, out, _)| *out <= output_offset)
+    {
+        Some((source, out, len)) if output_offset < out + len => source + (output_offset - out),
+        Some((source, _, len)) => source + len,
+        None => line.len() - line.trim_start().len(),
+    };
+    let column = line[..source_offset].chars().count() + 1;
+    Some((index + 1, column))
+}
+
+/// Variable paths the template reads, like `["prometheus", "cpu"]`, with
+/// the byte offset of each, and the names it assigns, captures or loops
+/// over. Indexes that aren't quoted strings end a path.
+fn variable_references(content: &str) -> (Vec<(Vec<String>, usize)>, HashSet<String>) {
+    let mut references = vec![];
+    let mut defined: HashSet<String> = ["forloop", "tablerowloop"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    for markup in markup(content) {
+        if !markup.is_tag {
+            scan_expression(markup.inner, markup.inner_start, &mut references);
+            continue;
+        }
+        let (name, rest, rest_start) = markup.tag();
+        let first_word = || {
+            rest.split(|c: char| c.is_whitespace() || c == '=')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        };
+        match name {
+            "assign" => {
+                defined.insert(first_word());
+                if let Some(i) = rest.find('=') {
+                    scan_expression(&rest[i + 1..], rest_start + i + 1, &mut references);
+                }
+            }
+            "capture" | "increment" | "decrement" => {
+                defined.insert(first_word());
+            }
+            "for" | "tablerow" => {
+                defined.insert(first_word());
+                if let Some(i) = rest.find(" in ") {
+                    scan_expression(&rest[i + 4..], rest_start + i + 4, &mut references);
+                }
+            }
+            "include" | "render" => {
+                if let Some(i) = rest.find(" as ") {
+                    defined.insert(
+                        rest[i + 4..]
+                            .split_whitespace()
+                            .next()
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                }
+                scan_expression(rest, rest_start, &mut references);
+            }
+            "if" | "elsif" | "unless" | "case" | "when" | "cycle" | "echo" => {
+                scan_expression(rest, rest_start, &mut references);
+            }
+            _ => {}
+        }
+    }
+    (references, defined)
+}
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn identifier_end(bytes: &[u8], start: usize) -> usize {
+    let mut end = start;
+    while end < bytes.len()
+        && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_' || bytes[end] == b'-')
+    {
+        end += 1;
+    }
+    if bytes.get(end) == Some(&b'?') {
+        end += 1;
+    }
+    end
+}
+
+/// Adds the variable paths read by a Liquid expression to `references`,
+/// skipping filter names, named argument keys, keywords and literals.
+fn scan_expression(expression: &str, offset: usize, references: &mut Vec<(Vec<String>, usize)>) {
+    let bytes = expression.as_bytes();
+    let mut after_pipe = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'"' || c == b'\'' {
+            i += expression[i + 1..]
+                .find(c as char)
+                .map_or(bytes.len(), |end| end + 2);
+            continue;
+        }
+        if c == b'|' {
+            after_pipe = true;
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            while i < bytes.len()
+                && (bytes[i].is_ascii_digit()
+                    || bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+            {
+                i += 1;
+            }
+            continue;
+        }
+        if !is_identifier_start(c) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        i = identifier_end(bytes, i);
+        let mut path = vec![expression[start..i].to_string()];
+        loop {
+            if bytes.get(i) == Some(&b'.')
+                && bytes.get(i + 1).is_some_and(|c| is_identifier_start(*c))
+            {
+                let end = identifier_end(bytes, i + 1);
+                path.push(expression[i + 1..end].to_string());
+                i = end;
+            } else if bytes.get(i) == Some(&b'[') {
+                let inner = expression[i + 1..].trim_start();
+                let Some(quote) = inner.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                    break;
+                };
+                let Some((key, _)) = inner[1..].split_once(quote) else {
+                    break;
+                };
+                let Some(close) = expression[i..].find(']') else {
+                    break;
+                };
+                path.push(key.to_string());
+                i += close + 1;
+            } else {
+                break;
+            }
+        }
+
+        let is_filter = std::mem::take(&mut after_pipe);
+        let is_key = expression[i..].trim_start().starts_with(':');
+        if is_filter || is_key || path.len() == 1 && KEYWORDS.contains(&path[0].as_str()) {
+            continue;
+        }
+        references.push((path, offset + start));
+    }
+}
+
+/// Warnings for variables the template reads that its data doesn't have,
+/// such as a misspelt query name. Sources that failed to fetch are left
+/// alone, as their data can't be known.
+fn unknown_variables(content: &str, vars: &Object) -> Vec<TemplateDiagnostic> {
+    let (references, defined) = variable_references(content);
+    let failed = vars.get("errors").and_then(|errors| errors.as_object());
+    let mut seen = HashSet::new();
+    let mut warnings = vec![];
+    for (path, offset) in references {
+        if defined.contains(&path[0]) {
+            continue;
+        }
+        let Some(message) = missing(vars, &path) else {
+            continue;
+        };
+        // `errors.<kind>.<name>` marks `<kind>.<name>` as failed.
+        let source_failed = path.get(1).is_some_and(|name| {
+            failed
+                .and_then(|failed| failed.get(path[0].as_str()))
+                .and_then(|by_name| by_name.as_object())
+                .is_some_and(|by_name| by_name.contains_key(name.as_str()))
+        });
+        if source_failed {
+            continue;
+        }
+        if seen.insert(message.clone()) {
+            let (line, column) = line_column(content, offset);
+            warnings.push(TemplateDiagnostic {
+                message,
+                line: Some(line),
+                column: Some(column),
+            });
+        }
+    }
+    capped(warnings)
+}
+
+/// Why `path` can't be read from `vars`, if it can't. Only objects are
+/// followed; `size`, `first` and `last` are always there.
+fn missing(vars: &Object, path: &[String]) -> Option<String> {
+    let Some(mut value) = vars.get(path[0].as_str()).map(|v| v.as_view()) else {
+        return Some(format!("Unknown variable '{}'", path[0]));
+    };
+    for (depth, key) in path.iter().enumerate().skip(1) {
+        let object = value.as_object()?;
+        if matches!(key.as_str(), "size" | "first" | "last") {
+            return None;
+        }
+        match object.get(key.as_str()) {
+            Some(next) => value = next,
+            None => {
+                let mut keys: Vec<String> = object.keys().map(|k| k.to_string()).collect();
+                keys.sort();
+                let has = match keys.len() {
+                    0 => "it is empty".to_string(),
+                    1..=8 => format!("it has {}", keys.join(", ")),
+                    n => format!("it has {}, and {} more", keys[..8].join(", "), n - 8),
+                };
+                return Some(format!(
+                    "'{}' has no '{key}' ({has})",
+                    path[..depth].join(".")
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Text of every `<text>` element under `group` with the box it's drawn in.
+fn texts(group: &usvg::Group) -> Vec<(String, usvg::Rect)> {
+    let mut found = vec![];
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => found.extend(texts(group)),
+            usvg::Node::Text(text) => {
+                let content: String = text.chunks().iter().map(|chunk| chunk.text()).collect();
+                let content = content.trim();
+                if !content.is_empty() {
+                    found.push((content.to_string(), text.abs_bounding_box()));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Warnings for text drawn partly or wholly outside the `size` screen,
+/// placed where the template writes that text, if it can be found.
+fn text_outside(
+    content: &str,
+    svg: &str,
+    size: usvg::Size,
+    texts: &[(String, usvg::Rect)],
+) -> Vec<TemplateDiagnostic> {
+    let (width, height) = (size.width(), size.height());
+    let mut warnings = vec![];
+    for (text, bbox) in texts {
+        let shown: String = text.chars().take(40).collect();
+        let message = if bbox.right() <= 0.0
+            || bbox.bottom() <= 0.0
+            || bbox.left() >= width
+            || bbox.top() >= height
+        {
+            format!("Text \"{shown}\" is outside the {width}×{height} screen")
+        // Half a pixel of slack for antialiasing and rounding.
+        } else if bbox.left() < -0.5
+            || bbox.top() < -0.5
+            || bbox.right() > width + 0.5
+            || bbox.bottom() > height + 0.5
+        {
+            format!("Text \"{shown}\" runs past the edge of the {width}×{height} screen")
+        } else {
+            continue;
+        };
+
+        let escaped = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        // Only warnings that are kept are worth looking for.
+        let position = (warnings.len() < MAX_WARNINGS)
+            .then(|| {
+                svg.split('\n').find_map(|line| {
+                    let i = line.find(&escaped)?;
+                    source_position(content, line, line[..i].chars().count() + 1)
+                })
+            })
+            .flatten();
+        let (line, column) = position.unzip();
+        warnings.push(TemplateDiagnostic {
+            message,
+            line,
+            column,
+        });
+    }
+    capped(warnings)
+}
+
+/// The first `MAX_WARNINGS` warnings, and one saying how many more there
+/// were.
+fn capped(mut warnings: Vec<TemplateDiagnostic>) -> Vec<TemplateDiagnostic> {
+    if warnings.len() > MAX_WARNINGS {
+        let more = warnings.len() - MAX_WARNINGS;
+        warnings.truncate(MAX_WARNINGS);
+        warnings.push(TemplateDiagnostic {
+            message: format!("…and {more} more like these"),
+            line: None,
+            column: None,
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_template, test_support::init_test_db};
+    use crate::models::{Device, TemplateKind};
+
+    async fn validate(name: &str, content: &str) -> TemplateValidation {
+        init_test_db().await;
+        let template = create_template(name, content, TemplateKind::Liquid)
+            .await
+            .expect("create template");
+        let context = RenderContext::load(Device::virtual_device(), template)
+            .await
+            .expect("load context");
+        validate_template(&context).await
+    }
+
+    fn at(diagnostic: &TemplateDiagnostic) -> (Option<usize>, Option<usize>) {
+        (diagnostic.line, diagnostic.column)
+    }
+
+    #[test]
+    fn test_parse_errors_keep_their_position() {
+        let content = "<svg>\n<text>{{ now.year }</text>";
+        let Err(error) = liquid_parser().parse(content) else {
+            panic!("parsed");
+        };
+        let diagnostic = liquid_diagnostic(content, &error.to_string());
+        assert_eq!(at(&diagnostic), (Some(2), Some(10)));
+        assert!(
+            !diagnostic.message.contains(" | "),
+            "{}",
+            diagnostic.message
+        );
+    }
+
+    #[test]
+    fn test_render_errors_are_found_by_what_they_name() {
+        let content = "<svg>\n  {% for x in\n   missing %}{% endfor %}";
+        let diagnostic = liquid_diagnostic(
+            content,
+            "liquid: Unknown variable\n  with:\n    requested variable=missing\n",
+        );
+        assert_eq!(at(&diagnostic), (Some(3), Some(4)));
+        assert_eq!(
+            diagnostic.message,
+            "Unknown variable (requested variable=missing)"
+        );
+
+        let content = "{{ device.missing | upcase }}";
+        let diagnostic = liquid_diagnostic(
+            content,
+            "liquid: Unknown index\n  with:\n    variable=device\n    requested index=missing\n",
+        );
+        assert_eq!(at(&diagnostic), (Some(1), Some(4)));
+    }
+
+    #[test]
+    fn test_output_lines_map_back_to_the_template_line() {
+        let content = "<svg>\n<g>\n  <text x=\"{{ x }}\" y=\"2\">{{ label }}</tex>\n</g>";
+        let output = "  <text x=\"10\" y=\"2\">Hello</tex>";
+        let column = output.find("</tex>").unwrap() + 1;
+        let (line, column) = source_position(content, output, column).unwrap();
+        assert_eq!(line, 3);
+        assert_eq!(
+            &content.lines().nth(2).unwrap()[column - 1..],
+            "</tex>",
+            "a column in literal text maps into the same text"
+        );
+    }
+
+    #[test]
+    fn test_unknown_variables_point_at_the_missing_key() {
+        let vars = liquid::object!({
+            "prometheus": { "cpu": [], "load": [] },
+            "http": {},
+            "errors": { "http": { "weather": "timed out" }, "prometheus": { "weather": "down" } },
+        });
+        let content = r#"{% if prometheus.cpuu %}{% endif %}
+{% assign total = prometheus.cpu | size %}{% for row in prometheus.load %}{{ row.value | plus: total }}{% endfor %}
+{{ http.weather.temp }}{{ prometheus.weathr }}{{ "x" | append: suffix }}{{ prometheus["cpu"].first }}{% render "card", title: "t" %}"#;
+        let warnings = unknown_variables(content, &vars);
+        let messages: Vec<_> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "'prometheus' has no 'cpuu' (it has cpu, load)",
+                "'prometheus' has no 'weathr' (it has cpu, load)",
+                "Unknown variable 'suffix'"
+            ]
+        );
+        assert_eq!(at(&warnings[0]), (Some(1), Some(7)));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_svg_output_is_an_error_at_its_template_line() {
+        let validation = validate(
+            "validate-broken",
+            "<svg xmlns=\"http://www.w3.org/2000/svg\">\n  <text>{{ device.width }}</txt>\n</svg>",
+        )
+        .await;
+        assert_eq!(validation.errors.len(), 1);
+        let error = &validation.errors[0];
+        assert!(
+            error.message.starts_with("Invalid SVG: "),
+            "{}",
+            error.message
+        );
+        assert_eq!(error.line, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_warns_about_screen_size_and_text_off_screen() {
+        // Text in a family that isn't installed isn't drawn at all.
+        let family = crate::device::fonts::ui_family()
+            .expect("a system font is installed, such as fonts-dejavu");
+        let validation = validate(
+            "validate-warnings",
+            &format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300" font-family="{family}">
+<text x="10" y="40" font-size="20">Inside</text>
+<text x="-500" y="40" font-size="20">Gone</text>
+</svg>"#
+            ),
+        )
+        .await;
+        assert!(validation.errors.is_empty(), "{:?}", validation.errors);
+        let messages: Vec<_> = validation.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(
+            messages[0].starts_with(
+                "line 1, column 1: The SVG is 400×300 but the device screen is 800×480"
+            ),
+            "{messages:?}"
+        );
+        assert!(
+            messages[1].starts_with("line 3, column 38: Text \"Gone\" is outside"),
+            "{messages:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_valid_template_has_nothing_to_report() {
+        let validation = validate(
+            "validate-clean",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{{ device.width }}" height="{{ device.height }}">
+{% assign label = "Hi" %}<text x="10" y="40" font-size="20">{{ label }} {{ now.year }}</text>
+</svg>"#,
+        )
+        .await;
+        assert_eq!(validation, TemplateValidation::default());
+    }
+}
@@ -0,0 +1,568 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+use crate::device::charts::num;
+use crate::device::text_layout::{self, TextStyle, escape_xml};
+
+/// Widgets drawn from a single value by the widget filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WidgetKind {
+    Gauge,
+    ProgressBar,
+    Battery,
+    WifiBars,
+    BigNumber,
+}
+
+impl WidgetKind {
+    /// Name of the filter drawing this widget.
+    pub fn filter_name(self) -> &'static str {
+        match self {
+            WidgetKind::Gauge => "gauge",
+            WidgetKind::ProgressBar => "progress_bar",
+            WidgetKind::Battery => "battery",
+            WidgetKind::WifiBars => "wifi_bars",
+            WidgetKind::BigNumber => "big_number",
+        }
+    }
+
+    /// Default width and height.
+    pub fn default_size(self) -> (f64, f64) {
+        match self {
+            WidgetKind::Gauge => (100.0, 100.0),
+            WidgetKind::ProgressBar => (100.0, 12.0),
+            WidgetKind::Battery => (40.0, 20.0),
+            WidgetKind::WifiBars => (20.0, 16.0),
+            WidgetKind::BigNumber => (200.0, 100.0),
+        }
+    }
+}
+
+/// Where a widget is drawn. Sizes are rounded to whole pixels so edges land on
+/// pixel boundaries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Frame {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width: width.round().max(1.0),
+            height: height.round().max(1.0),
+        }
+    }
+
+    fn open(&self) -> String {
+        format!(
+            r#"<g transform="translate({} {})">"#,
+            num(self.x),
+            num(self.y)
+        )
+    }
+}
+
+/// Position of `value` between `min` and `max`, from 0 to 1.
+pub fn fraction(value: f64, min: f64, max: f64) -> f64 {
+    if max <= min || !value.is_finite() {
+        return 0.0;
+    }
+    ((value - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+/// Formats a value to `decimals` places, or to at most two without.
+pub fn format_value(value: f64, decimals: Option<usize>) -> String {
+    match decimals {
+        Some(decimals) => format!("{value:.decimals$}"),
+        None => num(value),
+    }
+}
+
+/// Outline of a 1px-stroked box, inset by half a pixel so it stays sharp.
+fn outline(x: f64, y: f64, width: f64, height: f64) -> String {
+    format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white" stroke="black" stroke-width="1" shape-rendering="crispEdges"/>"#,
+        num(x + 0.5),
+        num(y + 0.5),
+        num((width - 1.0).max(0.0)),
+        num((height - 1.0).max(0.0))
+    )
+}
+
+fn solid(x: f64, y: f64, width: f64, height: f64) -> String {
+    format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black" shape-rendering="crispEdges"/>"#,
+        num(x),
+        num(y),
+        num(width.max(0.0)),
+        num(height.max(0.0))
+    )
+}
+
+// ─── Gauge ────────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GaugeOptions {
+    pub min: f64,
+    pub max: f64,
+    /// Values marked with a tick across the band.
+    pub thresholds: Vec<f64>,
+    /// Text in the middle, usually the formatted value.
+    pub label: Option<String>,
+    pub font_family: String,
+    pub font_size: Option<f64>,
+}
+
+/// The gauge's band opens at the bottom, running clockwise through 240°.
+const GAUGE_START: f64 = 150.0;
+const GAUGE_SWEEP: f64 = 240.0;
+
+/// Draws `value` as a band filled clockwise from the lower left, inside an
+/// outline of the whole band. The gauge is a circle of the frame's smaller
+/// side, centred in it.
+pub fn gauge_svg(value: f64, frame: Frame, options: &GaugeOptions) -> String {
+    let size = frame.width.min(frame.height);
+    let (cx, cy) = (frame.width / 2.0, frame.height / 2.0);
+    let thickness = (size / 8.0).round().max(2.0);
+    let outer = size / 2.0 - 3.0;
+    let inner = (outer - thickness).max(1.0);
+
+    let point = |fraction: f64, radius: f64| {
+        let angle = (GAUGE_START + GAUGE_SWEEP * fraction) * PI / 180.0;
+        format!(
+            "{} {}",
+            num(cx + radius * angle.cos()),
+            num(cy + radius * angle.sin())
+        )
+    };
+    let band = |to: f64| {
+        let large = u8::from(GAUGE_SWEEP * to > 180.0);
+        format!(
+            "M{} A{o} {o} 0 {large} 1 {} L{} A{i} {i} 0 {large} 0 {} Z",
+            point(0.0, outer),
+            point(to, outer),
+            point(to, inner),
+            point(0.0, inner),
+            o = num(outer),
+            i = num(inner),
+        )
+    };
+
+    let mut svg = frame.open();
+    let _ = write!(
+        svg,
+        r#"<path d="{}" fill="white" stroke="black" stroke-width="1"/>"#,
+        band(1.0)
+    );
+    let filled = fraction(value, options.min, options.max);
+    if filled > 0.0 {
+        let _ = write!(svg, r#"<path d="{}" fill="black"/>"#, band(filled));
+    }
+    for &threshold in &options.thresholds {
+        let at = fraction(threshold, options.min, options.max);
+        let _ = write!(
+            svg,
+            r#"<path d="M{} L{}" stroke="black" stroke-width="2"/>"#,
+            point(at, inner - 3.0),
+            point(at, outer + 3.0)
+        );
+    }
+    if let Some(label) = &options.label {
+        let font_size = options.font_size.unwrap_or((size / 5.0).round());
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-family="{}" font-size="{}" font-weight="700" fill="black">{}</text>"#,
+            num(cx),
+            num(cy + font_size * 0.35),
+            escape_xml(&options.font_family),
+            num(font_size),
+            escape_xml(label)
+        );
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+// ─── Progress bar ─────────────────────────────────────────────────────────────
+
+/// Draws an outlined bar filled from the left to `fraction`, with a 1px white
+/// gap between the outline and the fill.
+pub fn progress_bar_svg(fraction: f64, frame: Frame) -> String {
+    let inner = (frame.width - 4.0).max(0.0);
+    let mut svg = frame.open();
+    svg.push_str(&outline(0.0, 0.0, frame.width, frame.height));
+    if fraction > 0.0 {
+        svg.push_str(&solid(
+            2.0,
+            2.0,
+            (inner * fraction.clamp(0.0, 1.0)).round(),
+            frame.height - 4.0,
+        ));
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+// ─── Battery ──────────────────────────────────────────────────────────────────
+
+/// Draws a battery icon with its terminal on the right, filled to `percent`.
+/// An unknown charge leaves it empty.
+pub fn battery_svg(percent: Option<f64>, frame: Frame) -> String {
+    let terminal = (frame.width / 10.0).round().max(2.0);
+    let body = (frame.width - terminal).max(1.0);
+    let terminal_height = (frame.height / 2.0).round();
+
+    let mut svg = frame.open();
+    svg.push_str(&outline(0.0, 0.0, body, frame.height));
+    svg.push_str(&solid(
+        body,
+        ((frame.height - terminal_height) / 2.0).round(),
+        terminal,
+        terminal_height,
+    ));
+    if let Some(percent) = percent {
+        let level = ((body - 4.0).max(0.0) * fraction(percent, 0.0, 100.0)).round();
+        if level > 0.0 {
+            svg.push_str(&solid(2.0, 2.0, level, frame.height - 4.0));
+        }
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+// ─── Wi-Fi bars ───────────────────────────────────────────────────────────────
+
+const WIFI_BARS: u32 = 4;
+
+/// Bars lit for a signal of `rssi` dBm: -55 and up is excellent, below -85
+/// is unusable.
+pub fn signal_bars(rssi: f64) -> u32 {
+    [-85.0, -75.0, -67.0, -55.0]
+        .iter()
+        .filter(|&&floor| rssi >= floor)
+        .count() as u32
+}
+
+/// Draws four rising bars, the first `signal_bars(rssi)` of them filled and
+/// the rest outlined. An unknown signal lights none.
+pub fn wifi_bars_svg(rssi: Option<f64>, frame: Frame) -> String {
+    let lit = rssi.map_or(0, signal_bars);
+    let gap = (frame.width / 10.0).round().max(1.0);
+    let bar = ((frame.width - gap * (WIFI_BARS - 1) as f64) / WIFI_BARS as f64)
+        .floor()
+        .max(1.0);
+
+    let mut svg = frame.open();
+    for i in 0..WIFI_BARS {
+        let height = (frame.height * (i + 1) as f64 / WIFI_BARS as f64).round();
+        let x = i as f64 * (bar + gap);
+        let y = frame.height - height;
+        svg.push_str(&if i < lit {
+            solid(x, y, bar, height)
+        } else {
+            outline(x, y, bar, height)
+        });
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+// ─── Big number ───────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigNumberOptions {
+    /// Caption above the number.
+    pub label: Option<String>,
+    /// How the value compares with the previous one, drawn as an arrow.
+    pub trend: Option<Ordering>,
+    pub border: bool,
+    pub font_family: String,
+    /// Size of the number; fitted to the tile when not given.
+    pub font_size: Option<f64>,
+}
+
+const TILE_PADDING: f64 = 4.0;
+
+/// Draws `text` as large as fits in a tile, with an optional caption above it
+/// and a trend arrow to its right: a triangle pointing up or down, or a bar
+/// when unchanged.
+pub fn big_number_svg(text: &str, frame: Frame, options: &BigNumberOptions) -> String {
+    let family = escape_xml(&options.font_family);
+    let caption_size = (frame.height / 5.0).round().clamp(6.0, 14.0);
+    let top = TILE_PADDING
+        + match options.label {
+            Some(_) => caption_size + TILE_PADDING,
+            None => 0.0,
+        };
+    let available_height = (frame.height - top - TILE_PADDING).max(1.0);
+    let arrow = match options.trend {
+        Some(_) => (available_height * 0.4).round(),
+        None => 0.0,
+    };
+    let available_width = (frame.width - TILE_PADDING * 2.0 - arrow - TILE_PADDING).max(1.0);
+    let font_size = options.font_size.unwrap_or_else(|| {
+        fit_number(
+            text,
+            &options.font_family,
+            available_width,
+            available_height,
+        )
+    });
+
+    let mut svg = frame.open();
+    if options.border {
+        svg.push_str(&outline(0.0, 0.0, frame.width, frame.height));
+    }
+    if let Some(label) = &options.label {
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="{family}" font-size="{}" fill="black">{}</text>"#,
+            num(TILE_PADDING),
+            num(TILE_PADDING + caption_size * 0.8),
+            num(caption_size),
+            escape_xml(label)
+        );
+    }
+    // Digits have no descenders, so the baseline sits on the bottom padding.
+    let baseline = frame.height - TILE_PADDING - (available_height - font_size).max(0.0) / 2.0;
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" font-family="{family}" font-size="{}" font-weight="700" fill="black">{}</text>"#,
+        num(TILE_PADDING),
+        num(baseline.round()),
+        num(font_size),
+        escape_xml(text)
+    );
+    if let Some(trend) = options.trend {
+        let left = frame.width - TILE_PADDING - arrow;
+        let top = (baseline - font_size / 2.0 - arrow / 2.0).round();
+        let (right, bottom, middle) = (left + arrow, top + arrow, left + arrow / 2.0);
+        let shape = match trend {
+            Ordering::Greater => format!(
+                r#"<path d="M{} {} L{} {} L{} {} Z" fill="black"/>"#,
+                num(left),
+                num(bottom),
+                num(middle),
+                num(top),
+                num(right),
+                num(bottom)
+            ),
+            Ordering::Less => format!(
+                r#"<path d="M{} {} L{} {} L{} {} Z" fill="black"/>"#,
+                num(left),
+                num(top),
+                num(middle),
+                num(bottom),
+                num(right),
+                num(top)
+            ),
+            Ordering::Equal => solid(
+                left,
+                (top + arrow / 2.0 - 1.0).round(),
+                arrow,
+                (arrow / 6.0).round().max(2.0),
+            ),
+        };
+        svg.push_str(&shape);
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+/// Largest bold size at which `text` fits the box.
+fn fit_number(text: &str, family: &str, width: f64, height: f64) -> f64 {
+    let style = TextStyle {
+        family: family.to_string(),
+        size: 1.0,
+        weight: 700,
+    };
+    let (width, height) = (width as f32, height as f32);
+    text_layout::with_measure_or_estimate(&style, |measure| {
+        text_layout::fit_size(text, width, Some(height), 6.0, height, measure)
+    }) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: f64, height: f64) -> Frame {
+        Frame::new(0.0, 0.0, width, height)
+    }
+
+    #[test]
+    fn test_fraction_and_formatting() {
+        assert_eq!(fraction(25.0, 0.0, 50.0), 0.5);
+        assert_eq!(fraction(-5.0, 0.0, 50.0), 0.0);
+        assert_eq!(fraction(500.0, 0.0, 50.0), 1.0);
+        assert_eq!(fraction(1.0, 5.0, 5.0), 0.0);
+        assert_eq!(fraction(f64::NAN, 0.0, 1.0), 0.0);
+        assert_eq!(format_value(21.456, None), "21.46");
+        assert_eq!(format_value(21.456, Some(1)), "21.5");
+        assert_eq!(format_value(21.0, Some(0)), "21");
+    }
+
+    #[test]
+    fn test_gauge_fills_band_and_marks_thresholds() {
+        let options = GaugeOptions {
+            min: 0.0,
+            max: 100.0,
+            thresholds: vec![50.0, 80.0],
+            label: Some("42%".to_string()),
+            font_family: "sans-serif".to_string(),
+            font_size: None,
+        };
+        let svg = gauge_svg(42.0, frame(100.0, 100.0), &options);
+        assert_eq!(svg.matches("<path").count(), 4, "{svg}");
+        assert!(svg.contains(r#"fill="black"/>"#), "{svg}");
+        assert!(svg.contains(">42%</text>"), "{svg}");
+        assert!(!svg.contains("NaN"));
+
+        // Half way is straight up, with the band's outer edge at radius 47.
+        let svg = gauge_svg(50.0, frame(100.0, 100.0), &options);
+        assert!(svg.contains("A47 47 0 0 1 50 3 L50 16"), "{svg}");
+
+        let empty = gauge_svg(
+            0.0,
+            frame(100.0, 100.0),
+            &GaugeOptions {
+                thresholds: vec![],
+                label: None,
+                ..options
+            },
+        );
+        assert_eq!(empty.matches("<path").count(), 1, "{empty}");
+    }
+
+    #[test]
+    fn test_progress_bar_and_battery_fill_whole_pixels() {
+        let svg = progress_bar_svg(0.5, frame(101.0, 10.0));
+        assert!(
+            svg.contains(r#"<rect x="0.5" y="0.5" width="100" height="9""#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r#"<rect x="2" y="2" width="49" height="6" fill="black""#),
+            "{svg}"
+        );
+
+        let svg = battery_svg(Some(75.0), frame(40.0, 20.0));
+        // A 36px body and a 4px terminal, filled to 75% of the 32px inside.
+        assert!(
+            svg.contains(r#"<rect x="36" y="5" width="4" height="10""#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r#"<rect x="2" y="2" width="24" height="16""#),
+            "{svg}"
+        );
+
+        let unknown = battery_svg(None, frame(40.0, 20.0));
+        assert_eq!(unknown.matches("<rect").count(), 2, "{unknown}");
+    }
+
+    #[test]
+    fn test_wifi_bars_follow_signal_strength() {
+        assert_eq!(signal_bars(-50.0), 4);
+        assert_eq!(signal_bars(-67.0), 3);
+        assert_eq!(signal_bars(-80.0), 1);
+        assert_eq!(signal_bars(-90.0), 0);
+
+        let svg = wifi_bars_svg(Some(-70.0), frame(20.0, 16.0));
+        assert_eq!(svg.matches(r#"fill="black""#).count(), 2, "{svg}");
+        assert_eq!(svg.matches(r#"fill="white""#).count(), 2, "{svg}");
+        // 3px bars with 2px gaps.
+        assert!(
+            svg.contains(r#"<rect x="0" y="12" width="3" height="4""#),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"<rect x="15.5" y="0.5""#), "{svg}");
+    }
+
+    #[test]
+    fn test_big_number_shows_caption_and_trend() {
+        let options = BigNumberOptions {
+            label: Some("Temp".to_string()),
+            trend: Some(Ordering::Greater),
+            border: true,
+            font_family: "sans-serif".to_string(),
+            font_size: Some(40.0),
+        };
+        let svg = big_number_svg("21.5°", frame(200.0, 100.0), &options);
+        assert!(svg.contains(">Temp</text>"), "{svg}");
+        assert!(
+            svg.contains(r#"font-size="40" font-weight="700" fill="black">21.5°</text>"#),
+            "{svg}"
+        );
+        // Upwards triangle: the apex is above the base.
+        let up = svg.split("<path d=\"").nth(1).unwrap();
+        let coords: Vec<f64> = up
+            .split(['M', 'L', 'Z', ' ', '"'])
+            .filter_map(|s| s.parse().ok())
+            .take(6)
+            .collect();
+        assert!(coords[3] < coords[1], "{svg}");
+
+        let flat = big_number_svg(
+            "3",
+            frame(200.0, 100.0),
+            &BigNumberOptions {
+                trend: Some(Ordering::Equal),
+                label: None,
+                font_size: None,
+                ..options
+            },
+        );
+        assert!(!flat.contains("<path"), "{flat}");
+        assert_eq!(flat.matches("<rect").count(), 2, "{flat}");
+    }
+
+    #[test]
+    fn test_widget_filters_render_in_template() {
+        let template = crate::models::server::template_with(concat!(
+            r#"{{ 90 | gauge: thresholds: "50,80", unit: "%" }}|"#,
+            r#"{{ 25 | progress_bar: width: 104, max: 50 }}|"#,
+            r#"{{ device.battery_percent_charged | battery }}|"#,
+            r#"{{ device.rssi | wifi_bars }}|"#,
+            r#"{{ series | big_number: label: "Load", decimals: 1, font_size: 30 }}"#,
+        ));
+        let globals = liquid::object!({
+            "device": { "battery_percent_charged": liquid::model::Value::Nil, "rssi": "-60" },
+            "series": { "first": 2.0, "last": 1.26 },
+        });
+        let out = template.render(globals).expect("render");
+        let parts: Vec<&str> = out.split('|').collect();
+
+        assert!(parts[0].contains(">90%</text>"), "{out}");
+        assert_eq!(parts[0].matches(r#"stroke-width="2""#).count(), 2, "{out}");
+        assert!(
+            parts[1].contains(r#"width="50" height="8" fill="black""#),
+            "{out}"
+        );
+        assert_eq!(
+            parts[2].matches("<rect").count(),
+            2,
+            "no charge level: {out}"
+        );
+        assert_eq!(parts[3].matches(r#"fill="black""#).count(), 3, "{out}");
+        assert!(parts[4].contains(">1.3</text>"), "{out}");
+        assert!(parts[4].contains(">Load</text>"), "{out}");
+
+        let err = crate::models::Template {
+            content: r#"{{ 1 | battery: unit: "%" }}"#.to_string(),
+            ..template
+        }
+        .render(liquid::object!({}))
+        .expect_err("unknown argument");
+        assert!(
+            err.to_string().contains("battery: unknown argument 'unit'"),
+            "{err}"
+        );
+    }
+}
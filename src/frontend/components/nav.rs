@@ -16,6 +16,8 @@ pub fn Nav() -> Element {
                     NavLink { to: Route::Dashboard {}, label: "Dashboard" }
                     NavLink { to: Route::Devices {}, label: "Devices" }
                     NavLink { to: Route::Templates {}, label: "Templates" }
+                    NavLink { to: Route::Partials {}, label: "Partials" }
+                    NavLink { to: Route::Fonts {}, label: "Fonts" }
                     NavLink { to: Route::Users {}, label: "Users" }
                 }
                 form {
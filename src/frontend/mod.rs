@@ -7,7 +7,10 @@ mod test_harness;
 
 use dioxus::prelude::*;
 
-use pages::{Dashboard, DeviceDetail, Devices, Login, Setup, TemplateEditor, Templates, Users};
+use pages::{
+    Dashboard, DeviceDetail, Devices, Fonts, Login, Partials, Setup, TemplateEditor, Templates,
+    Users,
+};
 use store::AppStore;
 
 #[derive(Routable, Clone, PartialEq, Debug)]
@@ -28,6 +31,10 @@ enum Route {
         Templates {},
         #[route("/template/:id")]
         TemplateEditor { id: i64 },
+        #[route("/partials")]
+        Partials {},
+        #[route("/fonts")]
+        Fonts {},
         #[route("/users")]
         Users {},
     #[end_layout]
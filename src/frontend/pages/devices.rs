@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 
 use crate::frontend::server_fns::get_screen_preview_for_template;
 use crate::frontend::store::AppStore;
-use crate::models::{Device, DeviceLog};
+use crate::models::{Device, DeviceLog, Dither};
 
 #[component]
 pub fn Devices() -> Element {
@@ -221,6 +221,8 @@ pub fn DeviceDetail(id: i64) -> Element {
 
     let screen = use_resource(move || {
         let tid = selected_template_id();
+        // Re-render the preview when the device's output settings change.
+        let _ = device().map(|d| (d.dither, d.dither_threshold, d.gamma));
         async move {
             match tid {
                 Some(tid) => get_screen_preview_for_template(id, tid).await,
@@ -270,6 +272,11 @@ pub fn DeviceDetail(id: i64) -> Element {
                                     _ => "\u{2014}".to_string(),
                                 }
                             }
+                            DetailRow { label: "Gray Levels", value: device.gray_levels().to_string() }
+                            DetailRow {
+                                label: "Image Format",
+                                value: device.image_format().extension().to_uppercase()
+                            }
                             DetailRow {
                                 label: "RSSI",
                                 value: device.rssi.clone().unwrap_or("\u{2014}".to_string())
@@ -284,6 +291,13 @@ pub fn DeviceDetail(id: i64) -> Element {
                             selected_template_id: selected_template_id,
                         }
                         MaxCompatibilityToggle { device_id: device.id, current_value: device.maximum_compatibility }
+                        KeepLastImageToggle { device_id: device.id, current_value: device.keep_last_image_on_error }
+                        DitherSettings {
+                            device_id: device.id,
+                            current_dither: device.dither,
+                            current_threshold: device.dither_threshold,
+                            current_gamma: device.gamma,
+                        }
                     }
 
                     div { class: "bg-white rounded-xl shadow-sm border border-gray-100 p-6",
@@ -292,7 +306,7 @@ pub fn DeviceDetail(id: i64) -> Element {
                             Some(Ok(b64)) if !b64.is_empty() => rsx! {
                                 img {
                                     class: "w-full rounded border border-gray-100",
-                                    src: "data:image/bmp;base64,{b64}",
+                                    src: "data:image/png;base64,{b64}",
                                     alt: "Screen preview",
                                 }
                             },
@@ -736,7 +750,149 @@ fn MaxCompatibilityToggle(device_id: i64, current_value: bool) -> Element {
                     None => rsx! {},
                 }
             }
-            p { class: "text-xs text-gray-400 mt-2", "Enable if the device requires compatibility mode for rendering." }
+            p { class: "text-xs text-gray-400 mt-2", "Enable if the device requires compatibility mode for rendering. Also forces BMP screens instead of PNG." }
+        }
+    }
+}
+
+#[component]
+fn KeepLastImageToggle(device_id: i64, current_value: bool) -> Element {
+    let store = use_context::<AppStore>();
+    let mut checked = use_signal(move || current_value);
+    let mut save_status = use_signal(|| None::<Result<(), String>>);
+
+    rsx! {
+        div { class: "mt-4 pt-4 border-t border-gray-100",
+            h2 { class: "text-xs font-semibold text-gray-400 uppercase tracking-wider mb-3", "On Render Error" }
+            div { class: "flex items-center gap-3",
+                label { class: "relative inline-flex items-center cursor-pointer",
+                    input {
+                        r#type: "checkbox",
+                        class: "sr-only peer",
+                        checked: checked(),
+                        onchange: move |evt| {
+                            let val = evt.checked();
+                            checked.set(val);
+                            save_status.set(None);
+                            spawn(async move {
+                                match store.update_device_keep_last_image_on_error(device_id, val).await {
+                                    Ok(()) => save_status.set(Some(Ok(()))),
+                                    Err(e) => save_status.set(Some(Err(e.to_string()))),
+                                }
+                            });
+                        },
+                    }
+                    div { class: "w-9 h-5 bg-gray-200 peer-focus:outline-none peer-focus:ring-2 peer-focus:ring-gray-300 rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-white after:border-gray-300 after:border after:rounded-full after:h-4 after:w-4 after:transition-all peer-checked:bg-gray-900" }
+                }
+                match save_status() {
+                    Some(Ok(())) => rsx! {
+                        span { class: "text-sm text-green-600", "Saved!" }
+                    },
+                    Some(Err(e)) => rsx! {
+                        span { class: "text-sm text-red-500", "Error: {e}" }
+                    },
+                    None => rsx! {},
+                }
+            }
+            p { class: "text-xs text-gray-400 mt-2", "Keep showing the last successfully rendered screen when the template fails, instead of an error screen with a link to the editor." }
+        }
+    }
+}
+
+#[component]
+fn DitherSettings(
+    device_id: i64,
+    current_dither: Dither,
+    current_threshold: i64,
+    current_gamma: f64,
+) -> Element {
+    let store = use_context::<AppStore>();
+    let mut dither = use_signal(move || current_dither);
+    let mut threshold = use_signal(move || current_threshold.to_string());
+    let mut gamma = use_signal(move || current_gamma.to_string());
+    let mut save_status = use_signal(|| None::<Result<(), String>>);
+
+    rsx! {
+        div { class: "mt-4 pt-4 border-t border-gray-100",
+            h2 { class: "text-xs font-semibold text-gray-400 uppercase tracking-wider mb-3", "Dithering" }
+            div { class: "flex flex-wrap items-end gap-3",
+                div {
+                    label { class: "block text-xs text-gray-400 mb-1", "Algorithm" }
+                    select {
+                        class: "text-sm border border-gray-200 rounded-lg px-3 py-1.5 text-gray-700 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                        onchange: move |evt| {
+                            if let Ok(d) = evt.value().parse::<Dither>() {
+                                dither.set(d);
+                                save_status.set(None);
+                            }
+                        },
+                        for d in Dither::ALL {
+                            option {
+                                value: d.as_str(),
+                                selected: d == dither(),
+                                {d.label()}
+                            }
+                        }
+                    }
+                }
+                div {
+                    label { class: "block text-xs text-gray-400 mb-1", "Threshold" }
+                    input {
+                        class: "w-20 text-sm border border-gray-200 rounded-lg px-3 py-1.5 text-gray-700 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                        r#type: "number",
+                        min: "0",
+                        max: "255",
+                        value: "{threshold}",
+                        oninput: move |evt| {
+                            threshold.set(evt.value());
+                            save_status.set(None);
+                        },
+                    }
+                }
+                div {
+                    label { class: "block text-xs text-gray-400 mb-1", "Gamma" }
+                    input {
+                        class: "w-20 text-sm border border-gray-200 rounded-lg px-3 py-1.5 text-gray-700 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                        r#type: "number",
+                        min: "0.1",
+                        step: "0.1",
+                        value: "{gamma}",
+                        oninput: move |evt| {
+                            gamma.set(evt.value());
+                            save_status.set(None);
+                        },
+                    }
+                }
+                button {
+                    class: "px-3 py-1.5 bg-gray-900 text-white text-sm font-medium rounded-lg hover:bg-gray-700 transition-colors",
+                    onclick: move |_| {
+                        save_status.set(None);
+                        let (Ok(t), Ok(g)) = (threshold().parse::<i64>(), gamma().parse::<f64>()) else {
+                            save_status.set(Some(Err("Threshold and gamma must be numbers".to_string())));
+                            return;
+                        };
+                        spawn(async move {
+                            match store.update_device_dithering(device_id, dither(), t, g).await {
+                                Ok(()) => save_status.set(Some(Ok(()))),
+                                Err(e) => save_status.set(Some(Err(e.to_string()))),
+                            }
+                        });
+                    },
+                    "Save"
+                }
+                match save_status() {
+                    Some(Ok(())) => rsx! {
+                        span { class: "text-sm text-green-600", "Saved!" }
+                    },
+                    Some(Err(e)) => rsx! {
+                        span { class: "text-sm text-red-500", "Error: {e}" }
+                    },
+                    None => rsx! {},
+                }
+            }
+            p { class: "text-xs text-gray-400 mt-2",
+                "Dithering renders photos and gradients as patterns of dots. Threshold sets the black/white cut-off (0\u{2013}255); gamma above 1.0 brightens midtones."
+            }
         }
     }
 }
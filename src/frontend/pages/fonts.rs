@@ -0,0 +1,128 @@
+use base64::Engine;
+use dioxus::prelude::*;
+
+use crate::frontend::store::AppStore;
+use crate::models::Font;
+
+#[component]
+pub fn Fonts() -> Element {
+    let store = use_context::<AppStore>();
+    let fonts = store.fonts;
+    let fonts_loaded = store.fonts_loaded;
+    let mut uploading = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    use_effect(move || {
+        spawn(store.fetch_fonts());
+    });
+
+    let handle_upload = move |evt: FormEvent| {
+        let files = evt.files();
+        uploading.set(true);
+        error.set(None);
+        spawn(async move {
+            for file in files {
+                let result = match file.read_bytes().await {
+                    Ok(bytes) => {
+                        let data = base64::engine::general_purpose::STANDARD.encode(&bytes);
+                        store.upload_font(file.name(), data).await.map(|_| ())
+                    }
+                    Err(e) => Err(ServerFnError::new(format!(
+                        "Unable to read {}: {e}",
+                        file.name()
+                    ))),
+                };
+                if let Err(e) = result {
+                    error.set(Some(e.to_string()));
+                }
+            }
+            uploading.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "mb-8 flex items-center justify-between",
+            div {
+                h1 { class: "text-3xl font-bold text-gray-900 tracking-tight", "Fonts" }
+                p { class: "text-gray-500 mt-1",
+                    "Upload TTF or OTF fonts to use in templates with font-family"
+                }
+            }
+            label {
+                class: "inline-flex items-center gap-2 px-4 py-2 bg-gray-900 text-white text-sm font-medium rounded-lg hover:bg-gray-700 transition-colors cursor-pointer",
+                if uploading() { "Uploading..." } else { "Upload Font" }
+                input {
+                    r#type: "file",
+                    class: "hidden",
+                    accept: ".ttf,.otf,.ttc",
+                    multiple: true,
+                    disabled: uploading(),
+                    onchange: handle_upload,
+                }
+            }
+        }
+
+        if let Some(e) = error() {
+            div { class: "mb-6 px-4 py-3 bg-red-50 border border-red-100 rounded-lg text-sm text-red-600",
+                "{e}"
+            }
+        }
+
+        if !fonts_loaded() {
+            div { class: "flex flex-col items-center justify-center py-12 gap-3",
+                div { class: "w-6 h-6 border-2 border-gray-200 border-t-gray-900 rounded-full animate-spin" }
+                p { class: "text-sm text-gray-400", "Loading..." }
+            }
+        } else if fonts().is_empty() {
+            div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+                div { class: "py-16 text-center",
+                    p { class: "text-gray-400 text-lg", "No fonts uploaded" }
+                    p { class: "text-gray-300 text-sm mt-2",
+                        "Templates can use the system fonts listed in the template editor"
+                    }
+                }
+            }
+        } else {
+            div { class: "bg-white rounded-xl shadow-sm border border-gray-100",
+                div { class: "divide-y divide-gray-100",
+                    for font in fonts() {
+                        FontRow { key: "{font.id}", font: font.clone() }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn FontRow(font: Font) -> Element {
+    let store = use_context::<AppStore>();
+    let mut deleting = use_signal(|| false);
+    let id = font.id;
+    let size_kib = (font.size + 1023) / 1024;
+
+    rsx! {
+        div { class: "px-6 py-4 flex items-center justify-between",
+            div {
+                p { class: "font-medium text-gray-900", "{font.family}" }
+                p { class: "text-xs text-gray-400 mt-0.5",
+                    "{font.filename} · {size_kib} KiB · uploaded {font.created_at}"
+                }
+            }
+            button {
+                class: "px-3 py-1.5 text-xs font-medium text-red-600 border border-red-200 rounded-lg hover:bg-red-50 transition-colors disabled:opacity-50",
+                disabled: deleting(),
+                onclick: move |_| {
+                    deleting.set(true);
+                    spawn(async move {
+                        if let Err(e) = store.delete_font(id).await {
+                            tracing::error!("Failed to delete font: {e}");
+                        }
+                        deleting.set(false);
+                    });
+                },
+                "Delete"
+            }
+        }
+    }
+}
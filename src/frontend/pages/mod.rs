@@ -1,6 +1,8 @@
 mod dashboard;
 mod devices;
+mod fonts;
 mod login;
+mod partials;
 mod setup;
 mod template_editor;
 mod templates;
@@ -8,7 +10,9 @@ mod users;
 
 pub use dashboard::Dashboard;
 pub use devices::{DeviceDetail, Devices};
+pub use fonts::Fonts;
 pub use login::Login;
+pub use partials::Partials;
 pub use setup::Setup;
 pub use template_editor::TemplateEditor;
 pub use templates::Templates;
@@ -0,0 +1,179 @@
+use dioxus::prelude::*;
+
+use crate::frontend::store::AppStore;
+use crate::models::Partial;
+
+#[component]
+pub fn Partials() -> Element {
+    let store = use_context::<AppStore>();
+    let partials = store.partials;
+    let partials_loaded = store.partials_loaded;
+    let mut adding = use_signal(|| false);
+
+    use_effect(move || {
+        spawn(store.fetch_partials());
+    });
+
+    rsx! {
+        div { class: "mb-8 flex items-center justify-between",
+            div {
+                h1 { class: "text-3xl font-bold text-gray-900 tracking-tight", "Partials" }
+                p { class: "text-gray-500 mt-1",
+                    "Snippets shared by every template, drawn with {{% render \"name\" %}}"
+                }
+            }
+            button {
+                class: "inline-flex items-center gap-2 px-4 py-2 bg-gray-900 text-white text-sm font-medium rounded-lg hover:bg-gray-700 transition-colors disabled:opacity-50",
+                disabled: adding(),
+                onclick: move |_| adding.set(true),
+                "New Partial"
+            }
+        }
+
+        if adding() {
+            div { class: "mb-6 bg-white rounded-xl shadow-sm border border-gray-100",
+                PartialRow {
+                    partial: Partial::default(),
+                    on_done: move |_| adding.set(false),
+                }
+            }
+        }
+
+        if !partials_loaded() {
+            div { class: "flex flex-col items-center justify-center py-12 gap-3",
+                div { class: "w-6 h-6 border-2 border-gray-200 border-t-gray-900 rounded-full animate-spin" }
+                p { class: "text-sm text-gray-400", "Loading..." }
+            }
+        } else if partials().is_empty() {
+            div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+                div { class: "py-16 text-center",
+                    p { class: "text-gray-400 text-lg", "No partials yet" }
+                    p { class: "text-gray-300 text-sm mt-2",
+                        "Move a header or footer here to share it between templates"
+                    }
+                }
+            }
+        } else {
+            div { class: "bg-white rounded-xl shadow-sm border border-gray-100",
+                div { class: "divide-y divide-gray-100",
+                    for partial in partials() {
+                        PartialRow {
+                            key: "{partial.id:?}",
+                            partial: partial.clone(),
+                            on_done: move |_| {},
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One partial, shown by name until edited. A new partial (without an id)
+/// starts in the editor, and `on_done` fires when it is saved or dropped.
+#[component]
+fn PartialRow(partial: Partial, on_done: EventHandler) -> Element {
+    let store = use_context::<AppStore>();
+    let is_new = partial.id.is_none();
+    let mut editing = use_signal(move || is_new);
+    let mut name = use_signal(|| partial.name.clone());
+    let mut content = use_signal(|| partial.content.clone());
+    let mut saving = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+    let id = partial.id;
+
+    let lines = partial.content.lines().count();
+    let can_save = !name().trim().is_empty() && !saving();
+    let save_partial = partial.clone();
+
+    rsx! {
+        if editing() {
+            div { class: "p-4 bg-gray-50 flex flex-col gap-3",
+                div {
+                    label { class: "block text-xs font-medium text-gray-500 mb-1", "Name" }
+                    input {
+                        class: "w-64 text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                        placeholder: "header",
+                        value: "{name}",
+                        oninput: move |evt| name.set(evt.value()),
+                    }
+                }
+                textarea {
+                    class: "w-full h-48 p-3 font-mono text-sm text-gray-800 bg-white border border-gray-200 rounded-lg focus:outline-none focus:ring-1 focus:ring-gray-300 resize-y",
+                    spellcheck: false,
+                    value: "{content}",
+                    oninput: move |evt| content.set(evt.value()),
+                }
+                div { class: "flex items-center gap-2",
+                    button {
+                        class: "inline-flex items-center px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors disabled:opacity-50",
+                        disabled: !can_save,
+                        onclick: move |_| {
+                            let partial = Partial {
+                                name: name(),
+                                content: content(),
+                                ..save_partial.clone()
+                            };
+                            saving.set(true);
+                            error.set(None);
+                            spawn(async move {
+                                match store.save_partial(partial).await {
+                                    Ok(_) => {
+                                        editing.set(false);
+                                        on_done(());
+                                    }
+                                    Err(e) => error.set(Some(e.to_string())),
+                                }
+                                saving.set(false);
+                            });
+                        },
+                        if saving() { "Saving..." } else { "Save" }
+                    }
+                    button {
+                        class: "inline-flex items-center px-3 py-1.5 text-gray-600 text-xs font-medium rounded-lg border border-gray-300 hover:bg-gray-100 transition-colors disabled:opacity-50",
+                        disabled: saving(),
+                        onclick: move |_| {
+                            name.set(partial.name.clone());
+                            content.set(partial.content.clone());
+                            error.set(None);
+                            editing.set(false);
+                            on_done(());
+                        },
+                        "Cancel"
+                    }
+                    if let Some(id) = id {
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 bg-red-600 text-white text-xs font-medium rounded-lg hover:bg-red-500 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                spawn(async move {
+                                    if let Err(e) = store.delete_partial(id).await {
+                                        error.set(Some(e.to_string()));
+                                    }
+                                });
+                            },
+                            "Delete"
+                        }
+                    }
+                    if let Some(ref err) = error() {
+                        span { class: "text-xs text-red-500", "{err}" }
+                    }
+                }
+            }
+        } else {
+            div { class: "px-6 py-4 flex items-center justify-between",
+                div {
+                    p { class: "font-medium text-gray-900 font-mono", "{partial.name}" }
+                    p { class: "text-xs text-gray-400 mt-0.5",
+                        "{lines} lines · updated {partial.updated_at}"
+                    }
+                }
+                button {
+                    class: "text-xs text-gray-400 hover:text-gray-600 transition-colors",
+                    onclick: move |_| editing.set(true),
+                    "Edit"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use base64::Engine;
+use dioxus::prelude::*;
+
+use crate::{
+    frontend::server_fns::{delete_asset, get_asset_thumbnail, upload_asset},
+    models::{Asset, RenderContext, RenderContextStoreExt, TemplateStoreExt},
+};
+
+/// Markup that embeds an asset; inserted into the template by the picker.
+fn asset_snippet(name: &str) -> String {
+    format!(r#"<image href="{{{{ "{name}" | asset }}}}" x="0" y="0" width="100" height="100"/>"#)
+}
+
+/// Inserts `snippet` just before the closing `</svg>` tag, or at the end if
+/// there isn't one.
+fn insert_before_svg_end(content: &str, snippet: &str) -> String {
+    match content.rfind("</svg>") {
+        Some(i) => format!("{}  {snippet}\n{}", &content[..i], &content[i..]),
+        None => format!("{content}{snippet}\n"),
+    }
+}
+
+#[component]
+pub fn Assets(render_context: WriteStore<RenderContext>) -> Element {
+    let mut assets = render_context.assets();
+    let mut uploading = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    let handle_upload = move |evt: FormEvent| {
+        let files = evt.files();
+        let template_id = render_context.template().id().cloned();
+        uploading.set(true);
+        error.set(None);
+        spawn(async move {
+            for file in files {
+                let result = match file.read_bytes().await {
+                    Ok(bytes) => {
+                        let data = base64::engine::general_purpose::STANDARD.encode(&bytes);
+                        upload_asset(template_id, file.name(), data).await
+                    }
+                    Err(e) => Err(ServerFnError::new(format!(
+                        "Unable to read {}: {e}",
+                        file.name()
+                    ))),
+                };
+                match result {
+                    Ok(asset) => assets.push(asset),
+                    Err(e) => error.set(Some(e.to_string())),
+                }
+            }
+            uploading.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+            div { class: "p-4 border-b border-gray-100 flex items-center justify-between",
+                span { class: "text-sm font-medium text-gray-700",
+                    "Images ({assets().len()})"
+                }
+                label {
+                    class: "inline-flex items-center gap-2 px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors cursor-pointer",
+                    if uploading() { "Uploading..." } else { "Upload Image" }
+                    input {
+                        r#type: "file",
+                        class: "hidden",
+                        accept: "image/png,image/jpeg,image/svg+xml",
+                        multiple: true,
+                        disabled: uploading(),
+                        onchange: handle_upload,
+                    }
+                }
+            }
+            if let Some(e) = error() {
+                p { class: "px-4 pt-3 text-xs text-red-500", "{e}" }
+            }
+            if !assets().is_empty() {
+                div { class: "divide-y divide-gray-100",
+                    for asset in assets() {
+                        AssetRow {
+                            key: "{asset.id}",
+                            asset: asset.clone(),
+                            on_insert: move |name: String| {
+                                let mut content = render_context.template().content();
+                                let updated = insert_before_svg_end(&content.read(), &asset_snippet(&name));
+                                content.set(updated);
+                            },
+                            on_delete: move |id: i64| {
+                                assets.write().retain(|a| a.id != id);
+                                spawn(async move { delete_asset(id).await.ok(); });
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn AssetRow(
+    asset: Asset,
+    on_insert: EventHandler<String>,
+    on_delete: EventHandler<i64>,
+) -> Element {
+    let id = asset.id;
+    let thumbnail = use_resource(move || get_asset_thumbnail(id));
+    let size_kib = (asset.size + 1023) / 1024;
+    let name = asset.name.clone();
+
+    rsx! {
+        div { class: "p-4 flex items-center gap-4",
+            div { class: "w-16 h-12 shrink-0 flex items-center justify-center bg-gray-50 rounded",
+                if let Some(Ok(uri)) = thumbnail() {
+                    img { class: "max-w-full max-h-full", src: "{uri}" }
+                }
+            }
+            div { class: "flex-1 min-w-0",
+                span { class: "text-sm font-medium text-gray-900", "{asset.name}" }
+                p { class: "text-xs text-gray-400 mt-0.5",
+                    "{asset.content_type} · {size_kib} KiB"
+                }
+                code { class: "text-xs text-blue-700 bg-blue-50 px-1 rounded",
+                    {format!("{{{{ \"{}\" | asset }}}}", asset.name)}
+                }
+            }
+            div { class: "flex items-center gap-2",
+                button {
+                    class: "inline-flex items-center px-3 py-1.5 text-gray-600 text-xs font-medium rounded-lg border border-gray-300 hover:bg-gray-100 transition-colors",
+                    onclick: move |_| on_insert(name.clone()),
+                    "Insert"
+                }
+                button {
+                    class: "inline-flex items-center px-3 py-1.5 bg-red-600 text-white text-xs font-medium rounded-lg hover:bg-red-500 transition-colors",
+                    onclick: move |_| on_delete(id),
+                    "Delete"
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_is_inserted_before_closing_svg_tag() {
+        let content = "<svg>\n  <rect/>\n</svg>\n";
+        assert_eq!(
+            insert_before_svg_end(content, &asset_snippet("logo")),
+            "<svg>\n  <rect/>\n  <image href=\"{{ \"logo\" | asset }}\" x=\"0\" y=\"0\" width=\"100\" height=\"100\"/>\n</svg>\n"
+        );
+        assert_eq!(insert_before_svg_end("", "<x/>"), "<x/>\n");
+    }
+}
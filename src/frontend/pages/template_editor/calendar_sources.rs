@@ -0,0 +1,258 @@
+use dioxus::prelude::*;
+
+use crate::{
+    frontend::server_fns::{
+        MAX_CALENDAR_BYTES, delete_calendar_source, execute_calendar_source, save_calendar_source,
+    },
+    models::{CalendarSource, Template},
+};
+
+#[component]
+pub fn CalendarSources(
+    sources: Store<Vec<CalendarSource>>,
+    template: ReadSignal<Template>,
+) -> Element {
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+            div { class: "p-4 border-b border-gray-100 flex items-center justify-between",
+                span { class: "text-sm font-medium text-gray-700",
+                    "Calendars ({sources().len()})"
+                }
+                div { class: "flex items-center gap-2",
+                    button {
+                        class: "inline-flex items-center gap-2 px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors",
+                        onclick: move |_| sources.push(CalendarSource::new(template().id)),
+                        "Add Calendar"
+                    }
+                }
+            }
+            if !sources().is_empty() {
+                div { class: "divide-y divide-gray-100",
+                    for source in sources.iter() {
+                        CalendarSourceRow {
+                            key: "{source.peek().id.map(|id| id.to_string()).unwrap_or_else(|| source.peek().created_at.and_utc().timestamp_nanos_opt().unwrap_or(0).to_string())}",
+                            source: source,
+                            on_delete: move |_| {
+                                let s = source.peek().clone();
+                                sources.write().retain(|x| x.ne(&s));
+                                if let Some(id) = s.id {
+                                    spawn(async move { delete_calendar_source(id).await.ok(); });
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn CalendarSourceRow(source: WriteStore<CalendarSource>, on_delete: EventHandler) -> Element {
+    let is_new = use_memo(move || source().id.is_none());
+    let mut editing = use_signal(|| is_new.cloned());
+    let mut saving = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+    let mut original = use_signal(|| None::<CalendarSource>);
+    // File chosen since the last save, as (file name, contents).
+    let mut upload = use_signal(|| None::<(String, String)>);
+
+    // Uploaded files are read from the database, so only saved ones preview.
+    let result = use_resource(move || {
+        let source = source();
+        async move {
+            match source.url.is_empty() && source.id.is_none() {
+                true => None,
+                false => Some(execute_calendar_source(source).await),
+            }
+        }
+    });
+
+    let handle_upload = move |evt: FormEvent| {
+        let Some(file) = evt.files().into_iter().next() else {
+            return;
+        };
+        error.set(None);
+        spawn(async move {
+            match file.read_bytes().await {
+                Ok(bytes) if bytes.len() > MAX_CALENDAR_BYTES => error.set(Some(format!(
+                    "{} is larger than {} MiB",
+                    file.name(),
+                    MAX_CALENDAR_BYTES / 1024 / 1024
+                ))),
+                Ok(bytes) => {
+                    let content = String::from_utf8_lossy(&bytes).into_owned();
+                    upload.set(Some((file.name(), content)));
+                    source.write().url = String::new();
+                }
+                Err(e) => error.set(Some(format!("Unable to read {}: {e}", file.name()))),
+            }
+        });
+    };
+
+    let has_calendar = !source().url.trim().is_empty() || upload().is_some() || source().size > 0;
+    let can_save = !source().name.trim().is_empty() && has_calendar;
+    let file_label = match (upload(), source().size) {
+        (Some((name, _)), _) => name,
+        (None, 0) => "No file".to_string(),
+        (None, size) => format!("Uploaded file ({} KiB)", (size + 1023) / 1024),
+    };
+
+    rsx! {
+        if editing() {
+            div { class: "p-4 bg-gray-50",
+                div { class: "flex flex-col gap-3",
+                    div { class: "flex gap-3",
+                        div { class: "flex-1",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Name" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                value: "{source().name}",
+                                oninput: move |evt| source.write().name = evt.value(),
+                            }
+                        }
+                        div { class: "flex-[2]",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "URL" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                placeholder: "https:// or webcal://",
+                                value: "{source().url}",
+                                oninput: move |evt| {
+                                    upload.set(None);
+                                    source.write().url = evt.value();
+                                },
+                            }
+                        }
+                        div { class: "w-20",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Days" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                r#type: "number",
+                                min: "1",
+                                max: "366",
+                                value: "{source().days}",
+                                oninput: move |evt| {
+                                    if let Ok(days) = evt.value().parse::<i64>() {
+                                        source.write().days = days.clamp(1, 366);
+                                    }
+                                },
+                            }
+                        }
+                        div { class: "w-24",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Timeout (s)" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                r#type: "number",
+                                min: "1",
+                                value: "{source().timeout_secs}",
+                                oninput: move |evt| {
+                                    if let Ok(secs) = evt.value().parse::<i64>() {
+                                        source.write().timeout_secs = secs.max(1);
+                                    }
+                                },
+                            }
+                        }
+                    }
+                    if source().url.trim().is_empty() {
+                        div { class: "flex items-center gap-3",
+                            label {
+                                class: "inline-flex items-center px-3 py-1.5 text-gray-600 text-xs font-medium rounded-lg border border-gray-300 hover:bg-gray-100 transition-colors cursor-pointer",
+                                "Upload .ics"
+                                input {
+                                    r#type: "file",
+                                    class: "hidden",
+                                    accept: ".ics,text/calendar",
+                                    onchange: handle_upload,
+                                }
+                            }
+                            span { class: "text-xs text-gray-500", "{file_label}" }
+                        }
+                    }
+                    div { class: "flex items-center gap-2",
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors disabled:opacity-50",
+                            disabled: !can_save || saving(),
+                            onclick: move |_| {
+                                saving.set(true);
+                                error.set(None);
+                                spawn(async move {
+                                    let content = upload().map(|(_, content)| content);
+                                    match save_calendar_source(source().clone(), content).await {
+                                        Ok(s) => {
+                                            *source.write() = s;
+                                            upload.set(None);
+                                            editing.set(false);
+                                        }
+                                        Err(e) => error.set(Some(e.to_string())),
+                                    }
+                                    saving.set(false);
+                                });
+                            },
+                            if saving() { "Saving..." } else { "Save" }
+                        }
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 text-gray-600 text-xs font-medium rounded-lg border border-gray-300 hover:bg-gray-100 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                if is_new() {
+                                    on_delete(());
+                                } else if let Some(orig) = original() {
+                                    *source.write() = orig;
+                                    upload.set(None);
+                                    editing.set(false);
+                                }
+                            },
+                            "Cancel"
+                        }
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 bg-red-600 text-white text-xs font-medium rounded-lg hover:bg-red-500 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                on_delete(());
+                            },
+                            "Delete"
+                        }
+                        if let Some(ref err) = error() {
+                            span { class: "text-xs text-red-500", "{err}" }
+                        }
+                    }
+                }
+            }
+        } else {
+            div { class: "p-4",
+                div { class: "flex items-start justify-between gap-4 mb-1",
+                    div {
+                        span { class: "text-sm font-medium text-gray-900", "{source().name}" }
+                        p { class: "text-xs text-gray-400 mt-0.5 truncate max-w-md",
+                            if source().url.is_empty() { "{file_label}" } else { "{source().url}" }
+                            " · next {source().days} days"
+                        }
+                    }
+                    div { class: "flex items-center gap-2",
+                        button {
+                            class: "text-xs text-gray-400 hover:text-gray-600 transition-colors",
+                            onclick: move |_| {
+                                original.set(Some(source().clone()));
+                                editing.set(true);
+                            },
+                            "Edit"
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(Some(Ok(res))) = result() {
+            if let Some(ref err) = res.error {
+                div { class: "px-4 pb-3",
+                    p { class: "text-xs text-red-500", "Error: {err}" }
+                }
+            } else if let Some(ref data) = res.data {
+                div { class: "px-4 pb-3 overflow-x-auto",
+                    pre { class: "text-xs text-gray-600 bg-gray-50 rounded p-2 max-h-32 overflow-y-auto",
+                        {serde_json::to_string_pretty(data).unwrap_or_default()}
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use dioxus::prelude::*;
+
+use crate::frontend::server_fns::get_font_families;
+
+#[component]
+pub fn FontFamilies() -> Element {
+    let families = use_resource(get_font_families);
+    let mut open = use_signal(|| false);
+
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+            button {
+                class: "w-full px-4 py-2.5 flex items-center justify-between text-left hover:bg-gray-50 transition-colors",
+                onclick: move |_| open.set(!open()),
+                span { class: "text-xs font-medium text-gray-600", "Available Fonts" }
+                span { class: "text-xs text-gray-400", if open() { "▲" } else { "▼" } }
+            }
+            if open() {
+                div { class: "border-t border-gray-100 p-4",
+                    match families() {
+                        None => rsx! {
+                            div { class: "w-3 h-3 border-2 border-gray-200 border-t-gray-400 rounded-full animate-spin" }
+                        },
+                        Some(Err(e)) => rsx! {
+                            p { class: "text-xs text-red-500", "{e}" }
+                        },
+                        Some(Ok(families)) => rsx! {
+                            p { class: "text-xs text-gray-400 mb-2",
+                                "Use in SVG as font-family=\"…\". Upload more on the "
+                                Link {
+                                    to: crate::frontend::Route::Fonts {},
+                                    class: "underline hover:text-gray-600",
+                                    "Fonts"
+                                }
+                                " page."
+                            }
+                            div { class: "flex flex-wrap gap-1.5",
+                                for family in families {
+                                    code {
+                                        key: "{family}",
+                                        class: "text-xs text-blue-700 bg-blue-50 px-1 rounded",
+                                        "{family}"
+                                    }
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
@@ -78,6 +78,20 @@ fn HttpSourceRow(source: WriteStore<HttpSource>, on_delete: EventHandler) -> Ele
                                 oninput: move |evt| source.write().url = evt.value(),
                             }
                         }
+                        div { class: "w-24",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Timeout (s)" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                r#type: "number",
+                                min: "1",
+                                value: "{source().timeout_secs}",
+                                oninput: move |evt| {
+                                    if let Ok(secs) = evt.value().parse::<i64>() {
+                                        source.write().timeout_secs = secs.max(1);
+                                    }
+                                },
+                            }
+                        }
                     }
                     div { class: "flex items-center gap-2",
                         button {
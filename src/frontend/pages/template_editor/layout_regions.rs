@@ -0,0 +1,240 @@
+use dioxus::prelude::*;
+
+use crate::{
+    frontend::{
+        server_fns::{delete_layout_region, save_layout_region},
+        store::AppStore,
+    },
+    models::{Device, LayoutRegion, Region, Template, TemplateKind},
+};
+
+#[component]
+pub fn LayoutRegions(
+    regions: Store<Vec<LayoutRegion>>,
+    template: ReadSignal<Template>,
+    device: ReadSignal<Device>,
+) -> Element {
+    let store = use_context::<AppStore>();
+    let templates = store.templates;
+
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
+            div { class: "p-4 border-b border-gray-100 flex items-center justify-between",
+                span { class: "text-sm font-medium text-gray-700",
+                    "Regions ({regions().len()})"
+                }
+                div { class: "flex items-center gap-2",
+                    button {
+                        class: "inline-flex items-center gap-2 px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors",
+                        onclick: move |_| {
+                            let first = templates()
+                                .iter()
+                                .find(|t| t.kind == TemplateKind::Liquid)
+                                .map_or(0, |t| t.id);
+                            regions.push(LayoutRegion::new(template().id, first));
+                        },
+                        "Add Region"
+                    }
+                }
+            }
+            if !regions().is_empty() {
+                div { class: "divide-y divide-gray-100",
+                    for region in regions.iter() {
+                        RegionRow {
+                            key: "{region.peek().id.map(|id| id.to_string()).unwrap_or_else(|| region.peek().created_at.and_utc().timestamp_nanos_opt().unwrap_or(0).to_string())}",
+                            region: region,
+                            device,
+                            on_delete: move |_| {
+                                let r = region.peek().clone();
+                                regions.write().retain(|x| x.ne(&r));
+                                if let Some(id) = r.id {
+                                    spawn(async move { delete_layout_region(id).await.ok(); });
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn RegionRow(
+    region: WriteStore<LayoutRegion>,
+    device: ReadSignal<Device>,
+    on_delete: EventHandler,
+) -> Element {
+    let store = use_context::<AppStore>();
+    let templates = store.templates;
+    let is_new = use_memo(move || region().id.is_none());
+    let mut editing = use_signal(|| is_new.cloned());
+    let mut saving = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+    let mut original = use_signal(|| None::<LayoutRegion>);
+
+    let template_name = templates()
+        .iter()
+        .find(|t| t.id == region().template_id)
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| "No template".to_string());
+    let (x, y, width, height) = region().rect(device().width, device().height);
+
+    rsx! {
+        if editing() {
+            div { class: "p-4 bg-gray-50",
+                div { class: "flex flex-col gap-3",
+                    div { class: "flex gap-3",
+                        div { class: "flex-[2]",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Template" }
+                            select {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                onchange: move |evt| {
+                                    if let Ok(id) = evt.value().parse::<i64>() {
+                                        region.write().template_id = id;
+                                    }
+                                },
+                                for t in templates().iter().filter(|t| t.kind == TemplateKind::Liquid) {
+                                    option {
+                                        value: "{t.id}",
+                                        selected: t.id == region().template_id,
+                                        "{t.name}"
+                                    }
+                                }
+                            }
+                        }
+                        div { class: "flex-1",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Region" }
+                            select {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                onchange: move |evt| {
+                                    if let Ok(r) = evt.value().parse::<Region>() {
+                                        region.write().region = r;
+                                    }
+                                },
+                                for r in Region::ALL {
+                                    option {
+                                        value: r.as_str(),
+                                        selected: r == region().region,
+                                        {r.label()}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if region().region == Region::Custom {
+                        div { class: "flex gap-3",
+                            RectField {
+                                label: "X",
+                                value: region().x,
+                                onchange: move |v| region.write().x = v,
+                            }
+                            RectField {
+                                label: "Y",
+                                value: region().y,
+                                onchange: move |v| region.write().y = v,
+                            }
+                            RectField {
+                                label: "Width",
+                                value: region().width,
+                                onchange: move |v| region.write().width = v,
+                            }
+                            RectField {
+                                label: "Height",
+                                value: region().height,
+                                onchange: move |v| region.write().height = v,
+                            }
+                        }
+                    }
+                    div { class: "flex items-center gap-2",
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 bg-green-700 text-white text-xs font-medium rounded-lg hover:bg-green-600 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                saving.set(true);
+                                error.set(None);
+                                spawn(async move {
+                                    match save_layout_region(region().clone()).await {
+                                        Ok(r) => {
+                                            *region.write() = r;
+                                            editing.set(false);
+                                        }
+                                        Err(e) => error.set(Some(e.to_string())),
+                                    }
+                                    saving.set(false);
+                                });
+                            },
+                            if saving() { "Saving..." } else { "Save" }
+                        }
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 text-gray-600 text-xs font-medium rounded-lg border border-gray-300 hover:bg-gray-100 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                if is_new() {
+                                    on_delete(());
+                                } else if let Some(orig) = original() {
+                                    *region.write() = orig;
+                                    editing.set(false);
+                                }
+                            },
+                            "Cancel"
+                        }
+                        button {
+                            class: "inline-flex items-center px-3 py-1.5 bg-red-600 text-white text-xs font-medium rounded-lg hover:bg-red-500 transition-colors disabled:opacity-50",
+                            disabled: saving(),
+                            onclick: move |_| {
+                                on_delete(());
+                            },
+                            "Delete"
+                        }
+                        if let Some(ref err) = error() {
+                            span { class: "text-xs text-red-500", "{err}" }
+                        }
+                    }
+                }
+            }
+        } else {
+            div { class: "p-4",
+                div { class: "flex items-start justify-between gap-4",
+                    div {
+                        span { class: "text-sm font-medium text-gray-900", "{template_name}" }
+                        p { class: "text-xs text-gray-400 mt-0.5",
+                            "{region().region.label()} \u{2014} {width}\u{00d7}{height} at {x},{y}"
+                        }
+                    }
+                    div { class: "flex items-center gap-2",
+                        button {
+                            class: "text-xs text-gray-400 hover:text-gray-600 transition-colors",
+                            onclick: move |_| {
+                                original.set(Some(region().clone()));
+                                editing.set(true);
+                            },
+                            "Edit"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Pixel coordinate or size of a custom region.
+#[component]
+fn RectField(label: &'static str, value: i64, onchange: EventHandler<i64>) -> Element {
+    rsx! {
+        div { class: "w-24",
+            label { class: "block text-xs font-medium text-gray-500 mb-1", "{label}" }
+            input {
+                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                r#type: "number",
+                min: "0",
+                value: "{value}",
+                oninput: move |evt| {
+                    if let Ok(v) = evt.value().parse::<i64>() {
+                        onchange(v.max(0));
+                    }
+                },
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 
 use crate::frontend::server_fns::{get_render_context_for_template, get_virtual_render_context};
 use crate::frontend::store::AppStore;
-use crate::models::{Device, RenderContext, RenderContextStoreExt};
+use crate::models::{Device, RenderContext, RenderContextStoreExt, TemplateKind};
 
 pub mod template_preview;
 use template_preview::TemplatePreview;
@@ -16,12 +16,36 @@ use range_queries::RangeQueries;
 pub mod http_sources;
 use http_sources::HttpSources;
 
+pub mod calendar_sources;
+use calendar_sources::CalendarSources;
+
+pub mod assets;
+use assets::Assets;
+
+pub mod layout_regions;
+use layout_regions::LayoutRegions;
+
+pub mod render_performance;
+use render_performance::RenderPerformance;
+
+pub mod revision_history;
+use revision_history::RevisionHistory;
+
 pub mod template_variables;
 use template_variables::TemplateVariables;
 
 pub mod template_form;
 use template_form::TemplateForm;
 
+pub mod font_families;
+use font_families::FontFamilies;
+
+pub mod icon_names;
+use icon_names::IconNames;
+
+pub mod partial_names;
+use partial_names::PartialNames;
+
 #[component]
 pub fn TemplateEditor(id: i64) -> Element {
     let store = use_context::<AppStore>();
@@ -79,9 +103,17 @@ pub fn TemplateEditor(id: i64) -> Element {
                 }
             }
 
-            PrometheusQueries { queries: render_context.prometheus_queries(), template: render_context.template() }
-            RangeQueries { queries: render_context.range_queries(), template: render_context.template() }
-            HttpSources { sources: render_context.http_sources(), template: render_context.template() }
+            RenderPerformance { render_context }
+            RevisionHistory { render_context }
+            if render_context.template().read().kind == TemplateKind::Layout {
+                LayoutRegions { regions: render_context.regions(), template: render_context.template(), device: render_context.device() }
+            } else {
+                PrometheusQueries { queries: render_context.prometheus_queries(), template: render_context.template() }
+                RangeQueries { queries: render_context.range_queries(), template: render_context.template() }
+                HttpSources { sources: render_context.http_sources(), template: render_context.template() }
+                CalendarSources { sources: render_context.calendar_sources(), template: render_context.template() }
+                Assets { render_context }
+            }
 
         }
 
@@ -87,6 +87,20 @@ fn QueryRow(query: WriteStore<PrometheusQuery>, on_delete: EventHandler) -> Elem
                                 oninput: move |evt| query.write().addr = evt.value(),
                             }
                         }
+                        div { class: "w-24",
+                            label { class: "block text-xs font-medium text-gray-500 mb-1", "Timeout (s)" }
+                            input {
+                                class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                                r#type: "number",
+                                min: "1",
+                                value: "{query().timeout_secs}",
+                                oninput: move |evt| {
+                                    if let Ok(secs) = evt.value().parse::<i64>() {
+                                        query.write().timeout_secs = secs.max(1);
+                                    }
+                                },
+                            }
+                        }
                     }
                     div {
                         label { class: "block text-xs font-medium text-gray-500 mb-1", "PromQL Query" }
@@ -0,0 +1,85 @@
+use dioxus::prelude::*;
+
+use crate::{
+    frontend::server_fns::get_render_stats,
+    models::{RenderContext, RenderStats, TimingSummary},
+};
+
+/// Recent render stage and data source timings for the template, refreshed
+/// whenever the preview is re-rendered while the panel is open.
+#[component]
+pub fn RenderPerformance(render_context: ReadSignal<RenderContext>) -> Element {
+    let mut open = use_signal(|| false);
+    let mut stats = use_signal(RenderStats::default);
+    let mut refresh = use_signal(|| 0);
+
+    use_resource(move || {
+        let template_id = render_context().template.id;
+        let (open, _) = (open(), refresh());
+        async move {
+            if open && let Ok(s) = get_render_stats(template_id).await {
+                stats.set(s);
+            }
+        }
+    });
+
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden mt-6",
+            button {
+                class: "w-full px-4 py-2.5 flex items-center justify-between text-left hover:bg-gray-50 transition-colors",
+                onclick: move |_| open.set(!open()),
+                span { class: "text-xs font-medium text-gray-600", "Render Performance" }
+                span { class: "text-xs text-gray-400", if open() { "▲" } else { "▼" } }
+            }
+            if open() {
+                div { class: "border-t border-gray-100 p-4 flex flex-col gap-4",
+                    if stats().stages.is_empty() && stats().sources.is_empty() {
+                        p { class: "text-xs text-gray-400", "No renders of this template since the server started." }
+                    } else {
+                        TimingTable { heading: "Stage", rows: stats().stages }
+                        if !stats().sources.is_empty() {
+                            TimingTable { heading: "Data source", rows: stats().sources }
+                        }
+                    }
+                    div {
+                        button {
+                            class: "text-xs text-gray-400 hover:text-gray-600 transition-colors",
+                            onclick: move |_| refresh += 1,
+                            "Refresh"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn TimingTable(heading: String, rows: Vec<TimingSummary>) -> Element {
+    rsx! {
+        table { class: "w-full text-xs",
+            thead {
+                tr { class: "text-left text-gray-400 border-b border-gray-100",
+                    th { class: "pb-1.5 font-medium pr-4", "{heading}" }
+                    th { class: "pb-1.5 font-medium pr-4 text-right", "Last" }
+                    th { class: "pb-1.5 font-medium pr-4 text-right", "Mean" }
+                    th { class: "pb-1.5 font-medium pr-4 text-right", "p95" }
+                    th { class: "pb-1.5 font-medium text-right", "Samples" }
+                }
+            }
+            tbody { class: "divide-y divide-gray-50",
+                for row in rows {
+                    tr {
+                        td { class: "py-1.5 pr-4",
+                            code { class: "text-gray-700", "{row.name}" }
+                        }
+                        td { class: "py-1.5 pr-4 text-right text-gray-600", "{row.last_ms:.1} ms" }
+                        td { class: "py-1.5 pr-4 text-right text-gray-600", "{row.mean_ms:.1} ms" }
+                        td { class: "py-1.5 pr-4 text-right text-gray-600", "{row.p95_ms:.1} ms" }
+                        td { class: "py-1.5 text-right text-gray-400", "{row.count}" }
+                    }
+                }
+            }
+        }
+    }
+}
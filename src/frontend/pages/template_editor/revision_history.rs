@@ -0,0 +1,466 @@
+use dioxus::prelude::*;
+
+use crate::{
+    frontend::{
+        server_fns::{
+            get_render_context_for_template, get_template_preview, get_template_revisions,
+            get_virtual_render_context, restore_template_revision,
+        },
+        store::AppStore,
+    },
+    models::{RenderContext, RevisionSources, TemplateRevision},
+};
+
+/// The template's saved revisions, a side-by-side diff and previews of two of
+/// them, and a button to restore any one.
+#[component]
+pub fn RevisionHistory(render_context: WriteStore<RenderContext>) -> Element {
+    let store = use_context::<AppStore>();
+    let mut open = use_signal(|| false);
+    let mut refresh = use_signal(|| 0);
+    let mut revisions = use_signal(Vec::<TemplateRevision>::new);
+    let mut error = use_signal(|| None::<String>);
+    // Ids of the older and newer revisions being compared.
+    let mut older = use_signal(|| None::<i64>);
+    let mut newer = use_signal(|| None::<i64>);
+    let mut restoring = use_signal(|| None::<i64>);
+    let mut restore_warnings = use_signal(Vec::<String>::new);
+
+    // Saving updates the store's copy of the template, so the list follows.
+    use_resource(move || {
+        let template_id = render_context.peek().template.id;
+        let saved = store
+            .templates
+            .read()
+            .iter()
+            .find(|t| t.id == template_id)
+            .map(|t| t.content.clone());
+        let (open, _, _) = (open(), refresh(), saved);
+        async move {
+            if !open {
+                return;
+            }
+            match get_template_revisions(template_id).await {
+                Ok(list) => {
+                    newer.set(list.first().map(|r| r.id));
+                    older.set(list.get(1).or(list.first()).map(|r| r.id));
+                    revisions.set(list);
+                    error.set(None);
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    let find = move |id: Option<i64>| {
+        id.and_then(|id| revisions.read().iter().find(|r| r.id == id).cloned())
+    };
+    let older_revision = find(older());
+    let newer_revision = find(newer());
+
+    let mut restore = move |revision_id: i64| {
+        restoring.set(Some(revision_id));
+        error.set(None);
+        restore_warnings.set(vec![]);
+        spawn(async move {
+            let template_id = render_context.peek().template.id;
+            let device_id = render_context.peek().device.id;
+            let result = match restore_template_revision(revision_id).await {
+                Ok(warnings) => {
+                    restore_warnings.set(warnings);
+                    if device_id == 0 {
+                        get_virtual_render_context(template_id).await
+                    } else {
+                        get_render_context_for_template(device_id, template_id).await
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(restored) => {
+                    *render_context.write() = restored;
+                    refresh += 1;
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            restoring.set(None);
+        });
+    };
+
+    rsx! {
+        div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden mt-6",
+            button {
+                class: "w-full px-4 py-2.5 flex items-center justify-between text-left hover:bg-gray-50 transition-colors",
+                onclick: move |_| open.set(!open()),
+                span { class: "text-xs font-medium text-gray-600", "Revision History" }
+                span { class: "text-xs text-gray-400", if open() { "▲" } else { "▼" } }
+            }
+            if open() {
+                div { class: "border-t border-gray-100 p-4 flex flex-col gap-4",
+                    if let Some(ref err) = error() {
+                        p { class: "text-xs text-red-500", "{err}" }
+                    }
+                    for warning in restore_warnings() {
+                        p { class: "text-xs text-amber-600", "{warning}" }
+                    }
+                    if revisions().is_empty() {
+                        p { class: "text-xs text-gray-400", "No revisions yet. One is recorded each time the template is saved." }
+                    } else {
+                        table { class: "w-full text-xs",
+                            thead {
+                                tr { class: "text-left text-gray-400",
+                                    th { class: "font-medium pb-1 w-10", "Old" }
+                                    th { class: "font-medium pb-1 w-10", "New" }
+                                    th { class: "font-medium pb-1", "Saved" }
+                                    th { class: "font-medium pb-1", "By" }
+                                    th { class: "font-medium pb-1", "" }
+                                    th { class: "font-medium pb-1", "" }
+                                }
+                            }
+                            tbody {
+                                for (i , revision) in revisions().into_iter().enumerate() {
+                                    tr { key: "{revision.id}", class: "border-t border-gray-50 text-gray-600",
+                                        td { class: "py-1",
+                                            input {
+                                                r#type: "radio",
+                                                name: "older-revision",
+                                                checked: older() == Some(revision.id),
+                                                onchange: move |_| older.set(Some(revision.id)),
+                                            }
+                                        }
+                                        td { class: "py-1",
+                                            input {
+                                                r#type: "radio",
+                                                name: "newer-revision",
+                                                checked: newer() == Some(revision.id),
+                                                onchange: move |_| newer.set(Some(revision.id)),
+                                            }
+                                        }
+                                        td { class: "py-1 font-mono", "#{revision.id} · {revision.created_at}" }
+                                        td { class: "py-1",
+                                            {revision.author.clone().unwrap_or_else(|| "—".to_string())}
+                                        }
+                                        td { class: "py-1 text-gray-400",
+                                            {revision_note(&revision, i == 0)}
+                                        }
+                                        td { class: "py-1 text-right",
+                                            if i > 0 {
+                                                button {
+                                                    class: "px-2 py-0.5 text-xs text-gray-600 border border-gray-200 rounded hover:bg-gray-50 transition-colors disabled:opacity-50",
+                                                    disabled: restoring().is_some(),
+                                                    onclick: move |_| restore(revision.id),
+                                                    if restoring() == Some(revision.id) { "Restoring..." } else { "Restore #{revision.id}" }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        if let (Some(old), Some(new)) = (older_revision, newer_revision) {
+                            RevisionComparison { render_context, older: old, newer: new }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn RevisionComparison(
+    render_context: ReadSignal<RenderContext>,
+    older: TemplateRevision,
+    newer: TemplateRevision,
+) -> Element {
+    let content = diff_lines(&older.content, &newer.content);
+    let sources = diff_lines(
+        &source_lines(&older.sources).join("\n"),
+        &source_lines(&newer.sources).join("\n"),
+    );
+
+    rsx! {
+        div { class: "flex flex-col gap-3",
+            p { class: "text-xs font-medium text-gray-600",
+                "#{older.id} ({older.created_at}) → #{newer.id} ({newer.created_at})"
+            }
+            DiffTable { rows: content }
+            if sources.iter().any(|row| row.changed) {
+                p { class: "text-xs font-medium text-gray-600", "Data sources" }
+                DiffTable { rows: sources }
+            }
+            div { class: "flex flex-wrap gap-4",
+                RevisionPreview { render_context, revision: older }
+                RevisionPreview { render_context, revision: newer }
+            }
+        }
+    }
+}
+
+#[component]
+fn DiffTable(rows: Vec<DiffRow>) -> Element {
+    if !rows.iter().any(|row| row.changed) {
+        return rsx! {
+            p { class: "text-xs text-gray-400", "No differences." }
+        };
+    }
+    let cell = |changed: bool, side: &'static str| match (changed, side) {
+        (false, _) => "px-2 font-mono whitespace-pre text-gray-600",
+        (true, "old") => "px-2 font-mono whitespace-pre text-red-800 bg-red-50",
+        (true, _) => "px-2 font-mono whitespace-pre text-green-800 bg-green-50",
+    };
+
+    rsx! {
+        div { class: "overflow-x-auto max-h-96 overflow-y-auto border border-gray-100 rounded",
+            table { class: "w-full text-xs",
+                tbody {
+                    for (i , row) in rows.into_iter().enumerate() {
+                        tr { key: "{i}",
+                            td { class: "px-1 text-right text-gray-300 select-none",
+                                {row.old.as_ref().map(|(n, _)| n.to_string())}
+                            }
+                            td { class: cell(row.changed && row.old.is_some(), "old"),
+                                {row.old.as_ref().map(|(_, line)| line.clone())}
+                            }
+                            td { class: "px-1 text-right text-gray-300 select-none border-l border-gray-100",
+                                {row.new.as_ref().map(|(n, _)| n.to_string())}
+                            }
+                            td { class: cell(row.changed && row.new.is_some(), "new"),
+                                {row.new.as_ref().map(|(_, line)| line.clone())}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The revision rendered on the editor's preview device, with its own
+/// content and data sources.
+#[component]
+fn RevisionPreview(
+    render_context: ReadSignal<RenderContext>,
+    revision: TemplateRevision,
+) -> Element {
+    let id = revision.id;
+    let preview = use_resource(use_reactive!(|revision| {
+        let context = render_context.peek().at_revision(&revision);
+        async move { get_template_preview(context).await }
+    }));
+    let device = render_context.peek().device.clone();
+
+    rsx! {
+        div { class: "flex flex-col gap-1",
+            span { class: "text-xs text-gray-400", "#{id}" }
+            div {
+                class: "flex items-center justify-center bg-white border border-gray-200 rounded shadow-sm",
+                style: "width: {device.width}px; height: {device.height}px;",
+                match preview() {
+                    None => rsx! {
+                        div { class: "w-3 h-3 border-2 border-gray-200 border-t-gray-400 rounded-full animate-spin" }
+                    },
+                    Some(Err(e)) => rsx! {
+                        p { class: "text-xs text-red-500 p-2", "{e}" }
+                    },
+                    Some(Ok(preview)) => rsx! {
+                        img {
+                            src: "data:image/png;base64,{preview.image}",
+                            alt: "Revision #{id}",
+                            class: "max-w-none",
+                            style: "image-rendering: pixelated;",
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// "current" for the latest revision, and which revision a restore came from.
+fn revision_note(revision: &TemplateRevision, latest: bool) -> String {
+    let current = latest.then(|| "current".to_string());
+    let restored = revision
+        .restored_from
+        .map(|from| format!("restored from #{from}"));
+    current
+        .into_iter()
+        .chain(restored)
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// One row of a side-by-side diff: a line number and line from each side,
+/// either of which is missing where a line was only added or removed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffRow {
+    pub old: Option<(usize, String)>,
+    pub new: Option<(usize, String)>,
+    pub changed: bool,
+}
+
+/// Longest-common-subsequence line diff of `old` and `new`. Runs of removed
+/// and added lines between unchanged ones are paired up row by row. Inputs
+/// too large to compare line by line show their differing middle as changed.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffRow> {
+    const MAX_CELLS: usize = 4_000_000;
+
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // `common[i][j]`: length of the longest common subsequence of a[i..], b[j..].
+    let mut matches = vec![];
+    if a.len() * b.len() <= MAX_CELLS {
+        let mut common = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                common[i][j] = match a[i] == b[j] {
+                    true => common[i + 1][j + 1] + 1,
+                    false => common[i + 1][j].max(common[i][j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                matches.push((i, j));
+                (i, j) = (i + 1, j + 1);
+            } else if common[i + 1][j] >= common[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    matches.push((a.len(), b.len()));
+
+    let row = |i: usize, j: usize| DiffRow {
+        old: Some((i + 1, old[i].to_string())),
+        new: Some((j + 1, new[j].to_string())),
+        changed: false,
+    };
+    let mut rows: Vec<DiffRow> = (0..prefix).map(|i| row(i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matches {
+        let (removed, added) = (next_i - i, next_j - j);
+        for k in 0..removed.max(added) {
+            rows.push(DiffRow {
+                old: (k < removed).then(|| (prefix + i + k + 1, a[i + k].to_string())),
+                new: (k < added).then(|| (prefix + j + k + 1, b[j + k].to_string())),
+                changed: true,
+            });
+        }
+        if next_i < a.len() {
+            rows.push(row(prefix + next_i, prefix + next_j));
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    for k in 0..suffix {
+        rows.push(row(old.len() - suffix + k, new.len() - suffix + k));
+    }
+    rows
+}
+
+/// A line per data source and layout region, for diffing two revisions'
+/// configuration.
+fn source_lines(sources: &RevisionSources) -> Vec<String> {
+    let prometheus = sources.prometheus_queries.iter().map(|q| {
+        format!(
+            "prometheus.{}: {} @ {} ({}s)",
+            q.name, q.query, q.addr, q.timeout_secs
+        )
+    });
+    let range = sources.range_queries.iter().map(|q| {
+        format!(
+            "prometheus_range.{}: {} @ {} over {} step {} ({}s)",
+            q.name, q.query, q.addr, q.duration, q.step, q.timeout_secs
+        )
+    });
+    let http = sources
+        .http_sources
+        .iter()
+        .map(|s| format!("http.{}: {} ({}s)", s.name, s.url, s.timeout_secs));
+    let calendar = sources.calendar_sources.iter().map(|s| {
+        let from = match s.url.is_empty() {
+            true => "uploaded file".to_string(),
+            false => s.url.clone(),
+        };
+        format!(
+            "calendar.{}: {from}, {} days ({}s)",
+            s.name, s.days, s.timeout_secs
+        )
+    });
+    let regions = sources.regions.iter().map(|r| {
+        format!(
+            "region {:?} ({}, {}, {}×{}): template {}",
+            r.region, r.x, r.y, r.width, r.height, r.template_id
+        )
+    });
+    prometheus
+        .chain(range)
+        .chain(http)
+        .chain(calendar)
+        .chain(regions)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sides(rows: &[DiffRow]) -> Vec<(Option<usize>, Option<usize>, bool)> {
+        rows.iter()
+            .map(|row| {
+                (
+                    row.old.as_ref().map(|(n, _)| *n),
+                    row.new.as_ref().map(|(n, _)| *n),
+                    row.changed,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lines_pairs_changed_runs_between_unchanged_lines() {
+        let rows = diff_lines("a\nb\nc\nd\ne", "a\nB\nc\nd\nx\ny\ne");
+        assert_eq!(
+            sides(&rows),
+            [
+                (Some(1), Some(1), false),
+                (Some(2), Some(2), true),
+                (Some(3), Some(3), false),
+                (Some(4), Some(4), false),
+                (None, Some(5), true),
+                (None, Some(6), true),
+                (Some(5), Some(7), false),
+            ]
+        );
+        assert_eq!(rows[1].old, Some((2, "b".to_string())));
+        assert_eq!(rows[1].new, Some((2, "B".to_string())));
+    }
+
+    #[test]
+    fn test_diff_lines_handles_removals_and_identical_input() {
+        assert_eq!(
+            sides(&diff_lines("a\nb\nc", "c")),
+            [
+                (Some(1), None, true),
+                (Some(2), None, true),
+                (Some(3), Some(1), false),
+            ]
+        );
+        assert!(diff_lines("a\nb", "a\nb").iter().all(|row| !row.changed));
+        assert_eq!(sides(&diff_lines("", "a")), [(None, Some(1), true)]);
+    }
+}
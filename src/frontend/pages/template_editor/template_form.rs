@@ -1,8 +1,14 @@
+use base64::Engine;
 use dioxus::prelude::*;
 
-use crate::frontend::pages::template_editor::TemplateVariables;
+use crate::frontend::pages::template_editor::{
+    FontFamilies, IconNames, PartialNames, TemplateVariables,
+};
+use crate::frontend::server_fns::export_template;
 use crate::frontend::store::AppStore;
-use crate::models::{RenderContext, RenderContextStoreExt, TemplateStoreExt};
+use crate::models::{
+    RenderContext, RenderContextStoreExt, TemplateKind, TemplateStoreExt, TemplateValidation,
+};
 
 #[component]
 pub fn TemplateForm(
@@ -14,10 +20,32 @@ pub fn TemplateForm(
     let devices = store.devices;
     let mut save_status = use_signal(|| None::<Result<(), String>>);
     let mut copy_status = use_signal(|| None::<Result<(), String>>);
+    let mut export_link = use_signal(|| None::<Result<String, String>>);
     let mut delete_confirming = use_signal(|| false);
     let mut delete_error = use_signal(|| None::<String>);
+    let mut validation = use_signal(|| None::<TemplateValidation>);
     let nav = use_navigator();
 
+    let mut save = move |force: bool| {
+        save_status.set(None);
+        export_link.set(None);
+        let id = *render_context.template().id().read();
+        let name = render_context.template().name().read().clone();
+        let content = render_context.template().content().read().clone();
+        spawn(async move {
+            match store.save_template(id, name, content, force).await {
+                Ok(checked) => {
+                    if checked.errors.is_empty() || force {
+                        save_status.set(Some(Ok(())));
+                    }
+                    let clean = checked.errors.is_empty() && checked.warnings.is_empty();
+                    validation.set((!clean).then_some(checked));
+                }
+                Err(e) => save_status.set(Some(Err(e.to_string()))),
+            }
+        });
+    };
+
     rsx! {
         div { class: "flex-1 min-w-0 flex flex-col gap-4",
             div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
@@ -55,14 +83,20 @@ pub fn TemplateForm(
                     }
                 }
 
-                textarea {
-                    class: "w-full h-96 p-4 font-mono text-sm text-gray-800 bg-gray-50 border-0 focus:outline-none focus:ring-0 resize-y",
-                    spellcheck: false,
-                    value: render_context.template().content(),
-                    oninput: move |evt| {
-                        save_status.set(None);
-                        *render_context.template().content().write() = evt.value();
+                if render_context.template().read().kind == TemplateKind::Layout {
+                    p { class: "p-4 text-sm text-gray-500",
+                        "This layout is drawn from the templates placed in its regions below. Each one renders with its own data sources, at the size of its region."
+                    }
+                } else {
+                    textarea {
+                        class: "w-full h-96 p-4 font-mono text-sm text-gray-800 bg-gray-50 border-0 focus:outline-none focus:ring-0 resize-y",
+                        spellcheck: false,
+                        value: render_context.template().content(),
+                        oninput: move |evt| {
+                            save_status.set(None);
+                            *render_context.template().content().write() = evt.value();
 
+                        }
                     }
                 }
 
@@ -71,20 +105,17 @@ pub fn TemplateForm(
             div { class: "flex items-center gap-3",
                 button {
                     class: "inline-flex items-center gap-2 px-4 py-2 bg-green-700 text-white text-sm font-medium rounded-lg hover:bg-green-600 transition-colors",
-                    onclick: move |_| {
-                        save_status.set(None);
-                        let id = render_context.template().id().read().clone();
-                        let name = render_context.template().name().read().clone();
-                        let content = render_context.template().content().read().clone();
-                        spawn(async move {
-                            match store.save_template(id, name, content).await {
-                                Ok(()) => save_status.set(Some(Ok(()))),
-                                Err(e) => save_status.set(Some(Err(e.to_string()))),
-                            }
-                        });
-                    },
+                    onclick: move |_| save(false),
                     "Save"
                 }
+                if validation().is_some_and(|v| !v.errors.is_empty()) {
+                    button {
+                        class: "px-3 py-1.5 text-sm text-red-600 border border-red-200 rounded-lg hover:bg-red-50 transition-colors",
+                        title: "Save despite the errors; devices will show an error screen",
+                        onclick: move |_| save(true),
+                        "Save anyway"
+                    }
+                }
                 match save_status() {
                     Some(Ok(())) => rsx! {
                         span { class: "text-sm text-green-600", "Saved!" }
@@ -132,6 +163,38 @@ pub fn TemplateForm(
                         },
                         _ => rsx! {},
                     }
+                    match export_link() {
+                        Some(Ok(href)) => rsx! {
+                            a {
+                                class: "px-3 py-1.5 text-sm text-blue-600 border border-blue-200 rounded-lg hover:bg-blue-50 transition-colors",
+                                href: "{href}",
+                                download: "{bundle_file_name(&render_context.template().name().read())}",
+                                "Download bundle"
+                            }
+                        },
+                        _ => rsx! {
+                            button {
+                                class: "px-3 py-1.5 text-sm text-gray-700 border border-gray-200 rounded-lg hover:bg-gray-50 transition-colors",
+                                title: "Export the saved template with its data sources, assets and partials",
+                                onclick: move |_| {
+                                    let template_id = *render_context.template().id().read();
+                                    spawn(async move {
+                                        match export_template(template_id).await {
+                                            Ok(json) => {
+                                                let data = base64::engine::general_purpose::STANDARD.encode(json);
+                                                export_link.set(Some(Ok(format!("data:application/json;base64,{data}"))));
+                                            }
+                                            Err(e) => export_link.set(Some(Err(e.to_string()))),
+                                        }
+                                    });
+                                },
+                                "Export"
+                            }
+                        },
+                    }
+                    if let Some(Err(e)) = export_link() {
+                        span { class: "text-sm text-red-500", "Error: {e}" }
+                    }
                     if delete_confirming() {
                         div { class: "flex items-center gap-2",
                             span { class: "text-sm text-gray-500", "Delete this template?" }
@@ -175,8 +238,47 @@ pub fn TemplateForm(
                     }
                 }
             }
+            if let Some(validation) = validation() {
+                div { class: "bg-white rounded-xl shadow-sm border border-gray-100 px-4 py-3 flex flex-col gap-1",
+                    if !validation.errors.is_empty() {
+                        p { class: "text-sm font-medium text-red-600",
+                            if save_status().is_some() { "Saved with errors" } else { "Not saved: the template doesn't render" }
+                        }
+                    }
+                    for error in validation.errors.iter() {
+                        p { class: "text-xs font-mono text-red-600", "{error}" }
+                    }
+                    for warning in validation.warnings.iter() {
+                        p { class: "text-xs font-mono text-amber-600", "{warning}" }
+                    }
+                }
+            }
             TemplateVariables { render_context }
+            PartialNames {}
+            FontFamilies {}
+            IconNames {}
         }
 
     }
 }
+
+/// `<name>.json` with anything but letters, digits, `-` and `_` in the
+/// template name replaced by `-`.
+fn bundle_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        "template.json".to_string()
+    } else {
+        format!("{stem}.json")
+    }
+}
@@ -8,6 +8,7 @@ pub fn TemplatePreview(
     render_error: WriteStore<Option<String>>,
 ) -> Element {
     let mut image = use_store(|| None::<String>);
+    let mut warnings = use_signal(Vec::<String>::new);
     let mut preview_loading = use_signal(|| false);
     let (tx, mut rx) = tokio::sync::watch::channel(true);
 
@@ -24,10 +25,11 @@ pub fn TemplatePreview(
             preview_loading.set(true);
 
             match get_template_preview(render_context().clone()).await {
-                Ok(i) => {
+                Ok(preview) => {
                     render_error.set(None);
 
-                    image.set(Some(i))
+                    warnings.set(preview.warnings);
+                    image.set(Some(preview.image))
                 }
                 Err(e) => {
                     render_error.set(Some(format!("{:?}", e)));
@@ -48,6 +50,13 @@ pub fn TemplatePreview(
                     }
                 }
             }
+            if !warnings().is_empty() {
+                div { class: "px-4 py-3 bg-amber-50 border-b border-amber-100",
+                    for warning in warnings() {
+                        p { class: "text-xs text-amber-700 font-mono", "{warning}" }
+                    }
+                }
+            }
             div { class: "p-4 bg-gray-50",
                 div {
                     class: "flex items-center justify-center bg-white border border-gray-200 rounded shadow-sm",
@@ -56,7 +65,7 @@ pub fn TemplatePreview(
                     match image() {
                         Some(b64) => rsx! {
                             img {
-                                src: "data:image/bmp;base64,{b64}",
+                                src: "data:image/png;base64,{b64}",
                                 alt: "Template preview",
                                 class: "max-w-none",
                                 style: "image-rendering: pixelated;",
@@ -6,6 +6,7 @@ use crate::{frontend::server_fns::get_template_context, models::RenderContext};
 pub fn TemplateVariables(render_context: ReadStore<RenderContext>) -> Element {
     let mut vars_loading = use_signal(|| true);
     let mut vars = use_signal(|| vec![]);
+    let mut error_count = use_signal(|| 0);
 
     use_resource(move || {
         vars_loading.set(true);
@@ -13,8 +14,9 @@ pub fn TemplateVariables(render_context: ReadStore<RenderContext>) -> Element {
             let tv = get_template_context(render_context()).await;
             vars_loading.set(false);
             match tv {
-                Ok(v) => {
-                    vars.set(v);
+                Ok(context) => {
+                    vars.set(context.vars);
+                    error_count.set(context.error_count);
                 }
                 Err(_) => {}
             }
@@ -29,6 +31,11 @@ pub fn TemplateVariables(render_context: ReadStore<RenderContext>) -> Element {
                 onclick: move |_| vars_open.set(!vars_open()),
                 div { class: "flex items-center gap-2",
                     span { class: "text-xs font-medium text-gray-600", "Available Template Variables" }
+                    if error_count() > 0 {
+                        span { class: "text-xs font-medium text-red-600 bg-red-50 px-1.5 rounded",
+                            if error_count() == 1 { "1 source error" } else { "{error_count} source errors" }
+                        }
+                    }
                     if vars_loading() {
                         div { class: "w-3 h-3 border-2 border-gray-200 border-t-gray-400 rounded-full animate-spin" }
                     }
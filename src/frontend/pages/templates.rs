@@ -1,17 +1,26 @@
 use dioxus::prelude::*;
 
 use crate::frontend::store::AppStore;
+use crate::models::{ImportConflict, ImportOptions, ImportReport, TemplateKind};
 
 #[component]
 pub fn Templates() -> Element {
     let store = use_context::<AppStore>();
     let templates = store.templates;
     let templates_loaded = store.templates_loaded;
+    let mut importing = use_signal(|| false);
     let nav = use_navigator();
 
-    let handle_new = move |_| {
+    let handle_new = move |kind: TemplateKind| {
+        let name = match kind {
+            TemplateKind::Liquid => "New Template",
+            TemplateKind::Layout => "New Layout",
+        };
         spawn(async move {
-            match store.create_template("New Template".to_string(), String::new()).await {
+            match store
+                .create_template(name.to_string(), String::new(), kind)
+                .await
+            {
                 Ok(t) => {
                     nav.push(super::super::Route::TemplateEditor { id: t.id });
                 }
@@ -31,9 +40,20 @@ pub fn Templates() -> Element {
             div { class: "flex items-center gap-3",
                 button {
                     class: "inline-flex items-center gap-2 px-4 py-2 bg-gray-900 text-white text-sm font-medium rounded-lg hover:bg-gray-700 transition-colors",
-                    onclick: handle_new,
+                    onclick: move |_| handle_new(TemplateKind::Liquid),
                     "New Template"
                 }
+                button {
+                    class: "inline-flex items-center gap-2 px-4 py-2 text-sm font-medium text-gray-700 border border-gray-200 rounded-lg hover:bg-gray-50 transition-colors",
+                    onclick: move |_| handle_new(TemplateKind::Layout),
+                    "New Layout"
+                }
+                button {
+                    class: "inline-flex items-center gap-2 px-4 py-2 text-sm font-medium text-gray-700 border border-gray-200 rounded-lg hover:bg-gray-50 transition-colors disabled:opacity-50",
+                    disabled: importing(),
+                    onclick: move |_| importing.set(true),
+                    "Import"
+                }
                 button {
                     class: "inline-flex items-center gap-2 px-4 py-2 text-sm font-medium text-gray-700 border border-gray-200 rounded-lg hover:bg-gray-50 transition-colors",
                     onclick: move |_| { spawn(store.fetch_templates()); },
@@ -42,6 +62,10 @@ pub fn Templates() -> Element {
             }
         }
 
+        if importing() {
+            ImportBundle { on_close: move |_| importing.set(false) }
+        }
+
         if !templates_loaded() {
             div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden",
                 div { class: "flex flex-col items-center justify-center py-12 gap-3",
@@ -65,7 +89,12 @@ pub fn Templates() -> Element {
                         class: "block group",
                         div { class: "bg-white rounded-xl shadow-sm border border-gray-100 overflow-hidden group-hover:shadow-md group-hover:border-gray-200 transition-all",
                             div { class: "p-5",
-                                h3 { class: "font-medium text-gray-900 mb-1", "{template.name}" }
+                                div { class: "flex items-center gap-2 mb-1",
+                                    h3 { class: "font-medium text-gray-900", "{template.name}" }
+                                    if template.kind == TemplateKind::Layout {
+                                        span { class: "text-xs text-gray-500 bg-gray-100 rounded px-1.5 py-0.5", "Layout" }
+                                    }
+                                }
                                 p { class: "text-xs text-gray-400",
                                     "Updated {template.updated_at}"
                                 }
@@ -77,3 +106,135 @@ pub fn Templates() -> Element {
         }
     }
 }
+
+/// Imports a bundle exported from another server, with the choices of what
+/// to do when its names are taken and of a Prometheus server to use instead.
+#[component]
+fn ImportBundle(on_close: EventHandler) -> Element {
+    let store = use_context::<AppStore>();
+    let mut file = use_signal(|| None::<(String, String)>);
+    let mut conflict = use_signal(ImportConflict::default);
+    let mut prometheus_addr = use_signal(String::new);
+    let mut running = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+    let mut report = use_signal(|| None::<ImportReport>);
+
+    let handle_file = move |evt: FormEvent| {
+        let Some(selected) = evt.files().into_iter().next() else {
+            return;
+        };
+        error.set(None);
+        report.set(None);
+        spawn(async move {
+            match selected.read_bytes().await {
+                Ok(bytes) => match String::from_utf8(bytes.to_vec()) {
+                    Ok(text) => file.set(Some((selected.name(), text))),
+                    Err(_) => error.set(Some(format!("{} is not a text file", selected.name()))),
+                },
+                Err(e) => error.set(Some(format!("Unable to read {}: {e}", selected.name()))),
+            }
+        });
+    };
+
+    let handle_import = move |_| {
+        let Some((_, bundle)) = file() else {
+            return;
+        };
+        let options = ImportOptions {
+            conflict: conflict(),
+            prometheus_addr: Some(prometheus_addr()),
+        };
+        running.set(true);
+        error.set(None);
+        spawn(async move {
+            match store.import_template(bundle, options).await {
+                Ok(r) => {
+                    file.set(None);
+                    report.set(Some(r));
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            running.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "mb-6 bg-white rounded-xl shadow-sm border border-gray-100 p-4 flex flex-col gap-3",
+            div { class: "flex items-center justify-between",
+                h2 { class: "text-sm font-medium text-gray-900", "Import a template bundle" }
+                button {
+                    class: "text-xs text-gray-400 hover:text-gray-600 transition-colors",
+                    onclick: move |_| on_close(()),
+                    "Close"
+                }
+            }
+            div { class: "flex flex-wrap items-end gap-3",
+                label {
+                    class: "inline-flex items-center px-3 py-1.5 text-sm text-gray-700 border border-gray-200 rounded-lg hover:bg-gray-50 transition-colors cursor-pointer",
+                    match file() {
+                        Some((name, _)) => rsx! { "{name}" },
+                        None => rsx! { "Choose file..." },
+                    }
+                    input {
+                        r#type: "file",
+                        class: "hidden",
+                        accept: ".json,application/json",
+                        disabled: running(),
+                        onchange: handle_file,
+                    }
+                }
+                div {
+                    label { class: "block text-xs font-medium text-gray-500 mb-1", "If the name is taken" }
+                    select {
+                        class: "text-sm border border-gray-200 rounded-lg px-2 py-1.5 text-gray-600",
+                        onchange: move |evt| {
+                            conflict.set(match evt.value().as_str() {
+                                "replace" => ImportConflict::Replace,
+                                _ => ImportConflict::Rename,
+                            });
+                        },
+                        option { value: "rename", selected: conflict() == ImportConflict::Rename,
+                            "Import under a new name, keep existing partials"
+                        }
+                        option { value: "replace", selected: conflict() == ImportConflict::Replace,
+                            "Replace the existing template and partials"
+                        }
+                    }
+                }
+                div { class: "flex-1 min-w-48",
+                    label { class: "block text-xs font-medium text-gray-500 mb-1", "Prometheus URL" }
+                    input {
+                        class: "w-full text-sm border border-gray-200 rounded-lg px-3 py-1.5 focus:outline-none focus:ring-1 focus:ring-gray-300",
+                        placeholder: "Keep the bundle's",
+                        value: "{prometheus_addr}",
+                        oninput: move |evt| prometheus_addr.set(evt.value()),
+                    }
+                }
+                button {
+                    class: "inline-flex items-center px-3 py-1.5 bg-gray-900 text-white text-sm font-medium rounded-lg hover:bg-gray-700 transition-colors disabled:opacity-50",
+                    disabled: file().is_none() || running(),
+                    onclick: handle_import,
+                    if running() { "Importing..." } else { "Import" }
+                }
+            }
+            if let Some(e) = error() {
+                p { class: "text-sm text-red-500", "{e}" }
+            }
+            if let Some(report) = report() {
+                div { class: "text-sm text-gray-600 flex flex-col gap-1",
+                    p {
+                        "Imported "
+                        Link {
+                            to: super::super::Route::TemplateEditor { id: report.template.id },
+                            class: "font-medium text-blue-600 hover:underline",
+                            "{report.template.name}"
+                        }
+                    }
+                    for warning in report.warnings.iter() {
+                        p { class: "text-xs text-amber-600", "{warning}" }
+                    }
+                }
+            }
+        }
+    }
+}
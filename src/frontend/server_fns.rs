@@ -2,8 +2,10 @@ use dioxus::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::models::{
-    AuthenticatedUser, Device, DeviceLog, HttpSource, HttpSourceResult, PrometheusQuery,
-    PrometheusQueryResult, RangeQuery, RangeQueryResult, RenderContext, Template,
+    Asset, AuthenticatedUser, CalendarSource, CalendarSourceResult, Device, DeviceLog, Dither,
+    Font, HttpSource, HttpSourceResult, ImportOptions, ImportReport, LayoutRegion, Partial,
+    PrometheusQuery, PrometheusQueryResult, RangeQuery, RangeQueryResult, RenderContext,
+    RenderStats, Template, TemplateKind, TemplateRevision, TemplateValidation,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -21,6 +23,21 @@ pub struct TemplateVar {
     pub is_error: bool,
 }
 
+/// Variables available to a template, and how many of its data sources failed
+/// to fetch (their messages are under `errors`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct TemplateContext {
+    pub vars: Vec<TemplateVar>,
+    pub error_count: usize,
+}
+
+/// A base64 PNG preview and the data source warnings from rendering it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PreviewImage {
+    pub image: String,
+    pub warnings: Vec<String>,
+}
+
 #[cfg(feature = "server")]
 mod utils {
     use itertools::Itertools;
@@ -185,9 +202,10 @@ pub async fn get_screen_preview(device_id: i64) -> Result<String, ServerFnError>
         .await
         .map_err(|e| ServerFnError::new(format!("Unablle to query db: {:?}", e)))?;
 
-    match crate::device::renderer::render_screen(&render_context).await {
-        Ok(bmp_bytes) => {
-            let encoded = base64::engine::general_purpose::STANDARD.encode(&bmp_bytes);
+    match crate::device::renderer::render_preview(&render_context).await {
+        Ok(preview) => {
+            let encoded =
+                base64::engine::general_purpose::STANDARD.encode(preview.image.as_slice());
             Ok(encoded)
         }
         Err(e) => {
@@ -205,22 +223,26 @@ pub async fn get_screen_preview_for_template(
     use base64::Engine;
 
     let render_context = get_render_context_for_template(device_id, template_id).await?;
-    let bmp_bytes = crate::device::renderer::render_screen(&render_context)
+    let preview = crate::device::renderer::render_preview(&render_context)
         .await
         .map_err(|e| ServerFnError::new(format!("{:?}", e)))?;
-    Ok(base64::engine::general_purpose::STANDARD.encode(&bmp_bytes))
+    Ok(base64::engine::general_purpose::STANDARD.encode(preview.image.as_slice()))
 }
 
 #[server]
-pub async fn get_template_preview(render_context: RenderContext) -> Result<String, ServerFnError> {
+pub async fn get_template_preview(
+    render_context: RenderContext,
+) -> Result<PreviewImage, ServerFnError> {
     use base64::Engine;
 
-    let bmp_bytes = crate::device::renderer::render_screen(&render_context)
+    let preview = crate::device::renderer::render_preview(&render_context)
         .await
         .map_err(|e| ServerFnError::new(format!("Unable to render screen: {}", e)))?;
 
-    let encoded = base64::engine::general_purpose::STANDARD.encode(&bmp_bytes);
-    Ok(encoded)
+    Ok(PreviewImage {
+        image: base64::engine::general_purpose::STANDARD.encode(preview.image.as_slice()),
+        warnings: preview.warnings,
+    })
 }
 
 #[server]
@@ -247,8 +269,12 @@ pub async fn get_template_by_id(id: i64) -> Result<Template, ServerFnError> {
 }
 
 #[server]
-pub async fn create_template(name: String, content: String) -> Result<Template, ServerFnError> {
-    crate::db::create_template(&name, &content)
+pub async fn create_template(
+    name: String,
+    content: String,
+    kind: TemplateKind,
+) -> Result<Template, ServerFnError> {
+    crate::db::create_template(&name, &content, kind)
         .await
         .map_err(|e| ServerFnError::new(format!("Unable to create template: {:?}", e)))
 }
@@ -260,20 +286,301 @@ pub async fn copy_template(id: i64) -> Result<Template, ServerFnError> {
         .map_err(|e| ServerFnError::new(format!("Unable to copy template: {:?}", e)))
 }
 
+/// The template as a bundle file (pretty JSON) to import on another server.
+#[server]
+pub async fn export_template(id: i64) -> Result<String, ServerFnError> {
+    let bundle = crate::db::export_template(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to export template: {:?}", e)))?;
+    serde_json::to_string_pretty(&bundle)
+        .map_err(|e| ServerFnError::new(format!("Unable to export template: {e}")))
+}
+
+/// Imports a bundle written by `export_template`. Its assets and partials
+/// are checked as uploads and saved partials are, and the imported state is
+/// recorded as a revision; a replaced template's previous state is recorded
+/// first.
+#[server]
+pub async fn import_template(
+    bundle: String,
+    options: ImportOptions,
+) -> Result<ImportReport, ServerFnError> {
+    use crate::device::partials;
+    use crate::models::{BUNDLE_VERSION, ImportConflict, TemplateBundle};
+    use base64::Engine;
+
+    let author = require_auth().await.ok().map(|user| user.username);
+    let map_err = |e| ServerFnError::new(format!("Unable to import template: {:?}", e));
+
+    let mut bundle: TemplateBundle = serde_json::from_str(&bundle)
+        .map_err(|e| ServerFnError::new(format!("Not a template bundle: {e}")))?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(ServerFnError::new(format!(
+            "The bundle has format version {}, newer than this server reads ({BUNDLE_VERSION})",
+            bundle.version
+        )));
+    }
+    if bundle.name.trim().is_empty() {
+        return Err(ServerFnError::new("The bundle's template has no name"));
+    }
+    let mut asset_names = std::collections::HashSet::new();
+    for asset in &bundle.assets {
+        let name = asset.name.trim();
+        if name.is_empty() || name != asset.name || name.contains(['/', '\\']) {
+            return Err(ServerFnError::new(format!(
+                "Invalid asset name '{}'",
+                asset.name
+            )));
+        }
+        if !asset_names.insert(name) {
+            return Err(ServerFnError::new(format!(
+                "The bundle has two assets named '{name}'"
+            )));
+        }
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&asset.data)
+            .map_err(|e| ServerFnError::new(format!("Invalid asset '{}': {e}", asset.name)))?;
+        if data.len() > MAX_ASSET_BYTES {
+            return Err(ServerFnError::new(format!(
+                "Asset '{}' is larger than {} MiB",
+                asset.name,
+                MAX_ASSET_BYTES / 1024 / 1024
+            )));
+        }
+        if crate::device::assets::detect_content_type(&data).is_none() {
+            return Err(ServerFnError::new(format!(
+                "Asset '{}' is not a PNG, JPEG or SVG image",
+                asset.name
+            )));
+        }
+    }
+    for (name, content) in &bundle.calendar_files {
+        check_calendar_file(content)
+            .map_err(|e| ServerFnError::new(format!("Invalid calendar '{name}': {e}")))?;
+    }
+    let limits = crate::device::render_limits::render_limits();
+    let sources = &mut bundle.sources;
+    for timeout_secs in (sources
+        .prometheus_queries
+        .iter_mut()
+        .map(|q| &mut q.timeout_secs))
+    .chain(
+        sources
+            .range_queries
+            .iter_mut()
+            .map(|q| &mut q.timeout_secs),
+    )
+    .chain(sources.http_sources.iter_mut().map(|s| &mut s.timeout_secs))
+    .chain(
+        sources
+            .calendar_sources
+            .iter_mut()
+            .map(|s| &mut s.timeout_secs),
+    ) {
+        *timeout_secs = limits.source_timeout_secs(*timeout_secs);
+    }
+    let saved = crate::db::get_partials().await.map_err(map_err)?;
+    let mut contents: std::collections::HashMap<_, _> = saved
+        .iter()
+        .map(|p| (p.name.clone(), p.content.clone()))
+        .collect();
+    for partial in &bundle.partials {
+        partials::check_name(&partial.name).map_err(ServerFnError::new)?;
+        crate::models::server::liquid_parser()
+            .parse(&partial.content)
+            .map_err(|e| {
+                ServerFnError::new(format!("Unable to parse partial '{}': {e}", partial.name))
+            })?;
+        if options.conflict == ImportConflict::Replace || !contents.contains_key(&partial.name) {
+            contents.insert(partial.name.clone(), partial.content.clone());
+        }
+    }
+    for partial in &bundle.partials {
+        if let Some(cycle) = partials::find_cycle(&contents, &partial.name) {
+            return Err(ServerFnError::new(format!(
+                "'{}' would include itself: {}",
+                partial.name,
+                cycle.join(" → ")
+            )));
+        }
+    }
+    let options = ImportOptions {
+        prometheus_addr: options
+            .prometheus_addr
+            .map(|addr| addr.trim().to_string())
+            .filter(|addr| !addr.is_empty()),
+        ..options
+    };
+
+    if options.conflict == ImportConflict::Replace {
+        let templates = crate::db::get_templates().await.map_err(map_err)?;
+        if let Some(existing) = templates
+            .iter()
+            .find(|t| t.name == bundle.name && t.kind == bundle.kind)
+        {
+            record_revision(existing.id, &existing.content, None, None)
+                .await
+                .map_err(map_err)?;
+        }
+    }
+    let report = crate::db::import_template(&bundle, &options)
+        .await
+        .map_err(map_err)?;
+    crate::models::server::invalidate_template(report.template.id);
+    for partial in &bundle.partials {
+        partials::invalidate_partial(&partial.name);
+    }
+    record_revision(
+        report.template.id,
+        &report.template.content,
+        author.as_deref(),
+        None,
+    )
+    .await
+    .map_err(map_err)?;
+
+    Ok(report)
+}
+
 #[server]
 pub async fn delete_template(id: i64) -> Result<(), ServerFnError> {
     crate::db::delete_template(id)
         .await
-        .map_err(|e| ServerFnError::new(format!("Unable to delete template: {:?}", e)))
+        .map_err(|e| ServerFnError::new(format!("Unable to delete template: {:?}", e)))?;
+    crate::models::server::invalidate_template(id);
+    crate::device::render_stats::forget_template(id);
+
+    Ok(())
+}
+
+/// Records the template's saved content and its current data sources as a
+/// revision, unless they match the latest one.
+#[cfg(feature = "server")]
+async fn record_revision(
+    template_id: i64,
+    content: &str,
+    author: Option<&str>,
+    restored_from: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let sources = crate::db::get_revision_sources(template_id).await?;
+    let latest = crate::db::get_latest_template_revision(template_id).await?;
+    if latest.is_some_and(|r| r.content == content && r.sources == sources) {
+        return Ok(());
+    }
+    crate::db::create_template_revision(template_id, content, &sources, author, restored_from)
+        .await?;
+    Ok(())
 }
 
+/// Checks a template and, unless that finds errors and `force` isn't set,
+/// saves it and records the result as a revision. The first save also
+/// records the version the template had before it. Liquid templates are
+/// checked by rendering them on the virtual device with their current data.
 #[server]
-pub async fn save_template(id: i64, name: String, content: String) -> Result<(), ServerFnError> {
+pub async fn save_template(
+    id: i64,
+    name: String,
+    content: String,
+    force: bool,
+) -> Result<TemplateValidation, ServerFnError> {
+    let author = require_auth().await.ok().map(|user| user.username);
+    let map_err = |e| ServerFnError::new(format!("Unable to save template: {:?}", e));
+
+    let saved = crate::db::get_template_by_id(id).await.map_err(map_err)?;
+    let validation = match saved.kind {
+        TemplateKind::Liquid => {
+            let template = Template {
+                content: content.clone(),
+                ..saved
+            };
+            match RenderContext::load(Device::virtual_device(), template).await {
+                Ok(render_context) => {
+                    crate::device::validation::validate_template(&render_context).await
+                }
+                Err(e) => TemplateValidation {
+                    errors: vec![crate::models::TemplateDiagnostic {
+                        message: format!("Unable to check the template: {e}"),
+                        line: None,
+                        column: None,
+                    }],
+                    warnings: vec![],
+                },
+            }
+        }
+        TemplateKind::Layout => TemplateValidation::default(),
+    };
+    if !validation.errors.is_empty() && !force {
+        return Ok(validation);
+    }
+
+    if crate::db::get_latest_template_revision(id)
+        .await
+        .map_err(map_err)?
+        .is_none()
+    {
+        let before = crate::db::get_template_by_id(id).await.map_err(map_err)?;
+        record_revision(id, &before.content, None, None)
+            .await
+            .map_err(map_err)?;
+    }
     crate::db::update_template(id, &name, &content)
         .await
-        .map_err(|e| ServerFnError::new(format!("Unable to save template: {:?}", e)))?;
+        .map_err(map_err)?;
+    crate::models::server::invalidate_template(id);
+    record_revision(id, &content, author.as_deref(), None)
+        .await
+        .map_err(map_err)?;
 
-    Ok(())
+    Ok(validation)
+}
+
+/// A template's revisions, newest first.
+#[server]
+pub async fn get_template_revisions(
+    template_id: i64,
+) -> Result<Vec<TemplateRevision>, ServerFnError> {
+    crate::db::get_template_revisions(template_id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to get revisions: {:?}", e)))
+}
+
+/// Puts a template's content, data sources and layout regions back as they
+/// were at a revision. The state being replaced is recorded first, unless it
+/// already is, and the restore is recorded as a new revision. Returns
+/// warnings about uploaded calendar files that couldn't be brought back.
+#[server]
+pub async fn restore_template_revision(revision_id: i64) -> Result<Vec<String>, ServerFnError> {
+    let author = require_auth().await.ok().map(|user| user.username);
+    let map_err = |e| ServerFnError::new(format!("Unable to restore revision: {:?}", e));
+
+    let revision = crate::db::get_template_revision(revision_id)
+        .await
+        .map_err(map_err)?;
+    let template = crate::db::get_template_by_id(revision.template_id)
+        .await
+        .map_err(map_err)?;
+    record_revision(template.id, &template.content, author.as_deref(), None)
+        .await
+        .map_err(map_err)?;
+
+    crate::db::update_template(template.id, &template.name, &revision.content)
+        .await
+        .map_err(map_err)?;
+    let warnings =
+        crate::db::restore_revision_sources(template.id, &revision.sources, &Default::default())
+            .await
+            .map_err(map_err)?;
+    crate::models::server::invalidate_template(template.id);
+    record_revision(
+        template.id,
+        &revision.content,
+        author.as_deref(),
+        Some(revision.id),
+    )
+    .await
+    .map_err(map_err)?;
+
+    Ok(warnings)
 }
 
 #[server]
@@ -293,6 +600,162 @@ pub async fn update_device_maximum_compatibility(
         .map_err(|e| ServerFnError::new(format!("Unable to update maximum compatibility: {:?}", e)))
 }
 
+#[server]
+pub async fn update_device_keep_last_image_on_error(
+    device_id: i64,
+    keep_last_image_on_error: bool,
+) -> Result<(), ServerFnError> {
+    crate::db::update_device_keep_last_image_on_error(device_id, keep_last_image_on_error)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to update render error setting: {:?}", e)))
+}
+
+#[server]
+pub async fn update_device_dithering(
+    device_id: i64,
+    dither: Dither,
+    dither_threshold: i64,
+    gamma: f64,
+) -> Result<(), ServerFnError> {
+    if !(0..=255).contains(&dither_threshold) {
+        return Err(ServerFnError::new("Threshold must be between 0 and 255"));
+    }
+    if !gamma.is_finite() || gamma <= 0.0 {
+        return Err(ServerFnError::new("Gamma must be a finite number greater than 0"));
+    }
+    crate::db::update_device_dithering(device_id, dither, dither_threshold, gamma)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to update dithering: {:?}", e)))
+}
+
+/// Largest font file accepted by `upload_font`.
+#[cfg(feature = "server")]
+pub const MAX_FONT_BYTES: usize = 10 * 1024 * 1024;
+
+#[server]
+pub async fn get_fonts() -> Result<Vec<Font>, ServerFnError> {
+    crate::db::get_fonts()
+        .await
+        .map_err(|e: sqlx::Error| ServerFnError::new(e.to_string()))
+}
+
+/// Families usable in `font-family`: system fonts plus uploads.
+#[server]
+pub async fn get_font_families() -> Result<Vec<String>, ServerFnError> {
+    Ok(crate::device::fonts::font_families())
+}
+
+/// Names of the icons the `icon` filter can draw.
+#[server]
+pub async fn get_icon_names() -> Result<Vec<String>, ServerFnError> {
+    Ok(crate::device::icons::icon_names()
+        .map(String::from)
+        .collect())
+}
+
+#[server]
+pub async fn get_partials() -> Result<Vec<Partial>, ServerFnError> {
+    crate::db::get_partials()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to get partials: {:?}", e)))
+}
+
+/// Saves a partial. The name must be unused, and the content must parse and
+/// must not include the partial again, directly or through other partials.
+#[server]
+pub async fn save_partial(partial: Partial) -> Result<Partial, ServerFnError> {
+    use crate::device::partials;
+
+    let name = partial.name.trim();
+    partials::check_name(name).map_err(ServerFnError::new)?;
+    let saved = crate::db::get_partials()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to get partials: {:?}", e)))?;
+    if saved.iter().any(|p| p.name == name && p.id != partial.id) {
+        return Err(ServerFnError::new(format!(
+            "A partial named '{name}' already exists"
+        )));
+    }
+    crate::models::server::liquid_parser()
+        .parse(&partial.content)
+        .map_err(|e| ServerFnError::new(format!("Unable to parse partial: {e}")))?;
+    let contents = saved
+        .iter()
+        .filter(|p| p.id != partial.id)
+        .map(|p| (p.name.clone(), p.content.clone()))
+        .chain([(name.to_string(), partial.content.clone())])
+        .collect();
+    if let Some(cycle) = partials::find_cycle(&contents, name) {
+        return Err(ServerFnError::new(format!(
+            "'{name}' would include itself: {}",
+            cycle.join(" → ")
+        )));
+    }
+
+    let result = match partial.id {
+        Some(id) => {
+            if let Some(old) = saved.iter().find(|p| p.id == Some(id)) {
+                partials::invalidate_partial(&old.name);
+            }
+            crate::db::update_partial(id, name, &partial.content).await
+        }
+        None => crate::db::create_partial(name, &partial.content).await,
+    };
+    result.map_err(|e| ServerFnError::new(format!("Unable to save partial: {:?}", e)))
+}
+
+#[server]
+pub async fn delete_partial(id: i64) -> Result<(), ServerFnError> {
+    let partials = crate::db::get_partials()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to get partials: {:?}", e)))?;
+    crate::db::delete_partial(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to delete partial: {:?}", e)))?;
+    if let Some(partial) = partials.iter().find(|p| p.id == Some(id)) {
+        crate::device::partials::invalidate_partial(&partial.name);
+    }
+    Ok(())
+}
+
+/// Stores a TTF/OTF font sent as base64 and reloads the shared font database.
+#[server]
+pub async fn upload_font(filename: String, data: String) -> Result<Font, ServerFnError> {
+    use base64::Engine;
+
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| ServerFnError::new(format!("Invalid upload: {e}")))?;
+    if data.len() > MAX_FONT_BYTES {
+        return Err(ServerFnError::new(format!(
+            "Font is larger than {} MiB",
+            MAX_FONT_BYTES / 1024 / 1024
+        )));
+    }
+    let family = crate::device::fonts::families_in_font_data(&data)
+        .into_iter()
+        .next()
+        .ok_or_else(|| ServerFnError::new(format!("{filename} is not a TTF or OTF font")))?;
+
+    let font = crate::db::create_font(&family, &filename, &data)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to save font: {:?}", e)))?;
+    crate::device::fonts::reload_fonts()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to reload fonts: {:?}", e)))?;
+    Ok(font)
+}
+
+#[server]
+pub async fn delete_font(id: i64) -> Result<(), ServerFnError> {
+    crate::db::delete_font(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to delete font: {:?}", e)))?;
+    crate::device::fonts::reload_fonts()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to reload fonts: {:?}", e)))
+}
+
 #[server]
 pub async fn get_devices() -> Result<Vec<Device>, ServerFnError> {
     crate::db::get_devices()
@@ -300,34 +763,18 @@ pub async fn get_devices() -> Result<Vec<Device>, ServerFnError> {
         .map_err(|e: sqlx::Error| ServerFnError::new(e.to_string()))
 }
 
-/// Load a template's data sources (prometheus, range, and HTTP) and assemble a
-/// `RenderContext` for the given device + template. Shared by the three
-/// `get_*_render_context` server functions, which differ only in how they
-/// resolve the device and template.
+/// Load a template's data sources (prometheus, range, and HTTP), assets and
+/// layout regions and assemble a `RenderContext` for the given device +
+/// template. Shared by the three `get_*_render_context` server functions,
+/// which differ only in how they resolve the device and template.
 #[cfg(feature = "server")]
 async fn assemble_render_context(
     device: Device,
     template: Template,
 ) -> Result<RenderContext, ServerFnError> {
-    let to_err = |e: sqlx::Error| ServerFnError::new(e.to_string());
-
-    let prometheus_queries = crate::db::get_prometheus_queries(template.id)
-        .await
-        .map_err(to_err)?;
-    let range_queries = crate::db::get_range_queries(template.id)
-        .await
-        .map_err(to_err)?;
-    let http_sources = crate::db::get_http_sources(template.id)
+    RenderContext::load(device, template)
         .await
-        .map_err(to_err)?;
-
-    Ok(RenderContext {
-        device,
-        template,
-        prometheus_queries,
-        range_queries,
-        http_sources,
-    })
+        .map_err(|e| ServerFnError::new(e.to_string()))
 }
 
 #[server]
@@ -392,18 +839,28 @@ pub async fn delete_device(id: i64) -> Result<(), ServerFnError> {
 
 #[server]
 pub async fn save_prometheus_query(pq: PrometheusQuery) -> Result<PrometheusQuery, ServerFnError> {
+    let pq = PrometheusQuery {
+        timeout_secs: crate::device::render_limits::render_limits()
+            .source_timeout_secs(pq.timeout_secs),
+        ..pq
+    };
     match pq.id {
         Some(id) => {
-            crate::db::update_prometheus_query(id, &pq.name, &pq.addr, &pq.query)
+            crate::db::update_prometheus_query(id, &pq.name, &pq.addr, &pq.query, pq.timeout_secs)
                 .await
                 .map_err(|e| ServerFnError::new(format!("Unable to update query: {:?}", e)))?;
             Ok(pq)
         }
         None => {
-            let f =
-                crate::db::create_prometheus_query(pq.template_id, &pq.name, &pq.addr, &pq.query)
-                    .await
-                    .map_err(|e| ServerFnError::new(format!("Unable to create query: {:?}", e)))?;
+            let f = crate::db::create_prometheus_query(
+                pq.template_id,
+                &pq.name,
+                &pq.addr,
+                &pq.query,
+                pq.timeout_secs,
+            )
+            .await
+            .map_err(|e| ServerFnError::new(format!("Unable to create query: {:?}", e)))?;
             Ok(f)
         }
     }
@@ -418,6 +875,11 @@ pub async fn delete_prometheus_query(id: i64) -> Result<(), ServerFnError> {
 
 #[server]
 pub async fn save_range_query(rq: RangeQuery) -> Result<RangeQuery, ServerFnError> {
+    let rq = RangeQuery {
+        timeout_secs: crate::device::render_limits::render_limits()
+            .source_timeout_secs(rq.timeout_secs),
+        ..rq
+    };
     match rq.id {
         Some(id) => {
             crate::db::update_range_query(
@@ -427,6 +889,7 @@ pub async fn save_range_query(rq: RangeQuery) -> Result<RangeQuery, ServerFnErro
                 &rq.query,
                 &rq.duration,
                 &rq.step,
+                rq.timeout_secs,
             )
             .await
             .map_err(|e| ServerFnError::new(format!("Unable to update range query: {:?}", e)))?;
@@ -440,6 +903,7 @@ pub async fn save_range_query(rq: RangeQuery) -> Result<RangeQuery, ServerFnErro
                 &rq.query,
                 &rq.duration,
                 &rq.step,
+                rq.timeout_secs,
             )
             .await
             .map_err(|e| ServerFnError::new(format!("Unable to create range query: {:?}", e)))?;
@@ -596,9 +1060,14 @@ pub async fn execute_prometheus_queries(
 
 #[server]
 pub async fn save_http_source(source: HttpSource) -> Result<HttpSource, ServerFnError> {
+    let source = HttpSource {
+        timeout_secs: crate::device::render_limits::render_limits()
+            .source_timeout_secs(source.timeout_secs),
+        ..source
+    };
     match source.id {
         Some(id) => {
-            crate::db::update_http_source(id, &source.name, &source.url)
+            crate::db::update_http_source(id, &source.name, &source.url, source.timeout_secs)
                 .await
                 .map_err(|e| {
                     ServerFnError::new(format!("Unable to update http source: {:?}", e))
@@ -606,11 +1075,14 @@ pub async fn save_http_source(source: HttpSource) -> Result<HttpSource, ServerFn
             Ok(source)
         }
         None => {
-            let f = crate::db::create_http_source(source.template_id, &source.name, &source.url)
-                .await
-                .map_err(|e| {
-                    ServerFnError::new(format!("Unable to create http source: {:?}", e))
-                })?;
+            let f = crate::db::create_http_source(
+                source.template_id,
+                &source.name,
+                &source.url,
+                source.timeout_secs,
+            )
+            .await
+            .map_err(|e| ServerFnError::new(format!("Unable to create http source: {:?}", e)))?;
             Ok(f)
         }
     }
@@ -623,6 +1095,211 @@ pub async fn delete_http_source(id: i64) -> Result<(), ServerFnError> {
         .map_err(|e| ServerFnError::new(format!("Unable to delete http source: {:?}", e)))
 }
 
+/// Largest iCalendar file accepted by `save_calendar_source`.
+pub const MAX_CALENDAR_BYTES: usize = 2 * 1024 * 1024;
+
+/// Checks an uploaded iCalendar file's size and that it parses.
+#[cfg(feature = "server")]
+fn check_calendar_file(content: &str) -> Result<(), String> {
+    if content.len() > MAX_CALENDAR_BYTES {
+        return Err(format!(
+            "Calendar is larger than {} MiB",
+            MAX_CALENDAR_BYTES / 1024 / 1024
+        ));
+    }
+    crate::device::calendar::parse(content).map(|_| ())
+}
+
+/// Saves a calendar source. `content` replaces the uploaded file when given;
+/// a source with a URL keeps no file.
+#[server]
+pub async fn save_calendar_source(
+    source: CalendarSource,
+    content: Option<String>,
+) -> Result<CalendarSource, ServerFnError> {
+    let source = CalendarSource {
+        timeout_secs: crate::device::render_limits::render_limits()
+            .source_timeout_secs(source.timeout_secs),
+        ..source
+    };
+    let content = match (source.url.trim().is_empty(), content) {
+        (false, _) => Some(String::new()),
+        (true, Some(content)) => {
+            check_calendar_file(&content).map_err(ServerFnError::new)?;
+            Some(content)
+        }
+        (true, None) if source.size == 0 => {
+            return Err(ServerFnError::new(
+                "Enter a calendar URL or upload an .ics file",
+            ));
+        }
+        (true, None) => None,
+    };
+    let url = source.url.trim();
+
+    match source.id {
+        Some(id) => {
+            crate::db::update_calendar_source(
+                id,
+                &source.name,
+                url,
+                content.as_deref(),
+                source.days,
+                source.timeout_secs,
+            )
+            .await
+            .map_err(|e| {
+                ServerFnError::new(format!("Unable to update calendar source: {:?}", e))
+            })?;
+            Ok(CalendarSource {
+                url: url.to_string(),
+                size: content.map_or(source.size, |c| c.len() as i64),
+                ..source
+            })
+        }
+        None => crate::db::create_calendar_source(
+            source.template_id,
+            &source.name,
+            url,
+            content.as_deref().unwrap_or_default(),
+            source.days,
+            source.timeout_secs,
+        )
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to create calendar source: {:?}", e))),
+    }
+}
+
+#[server]
+pub async fn delete_calendar_source(id: i64) -> Result<(), ServerFnError> {
+    crate::db::delete_calendar_source(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to delete calendar source: {:?}", e)))
+}
+
+/// Fetches a saved calendar source and returns its upcoming events as
+/// templates see them.
+#[server]
+pub async fn execute_calendar_source(
+    source: CalendarSource,
+) -> Result<CalendarSourceResult, ServerFnError> {
+    let tz = crate::device::renderer::render_timezone()
+        .map_err(|e| ServerFnError::new(format!("Invalid TZ: {e}")))?;
+    let now = chrono::Utc::now().with_timezone(&tz);
+
+    Ok(match source.get_render_obj(now).await {
+        Ok(value) => CalendarSourceResult {
+            source_name: source.name,
+            data: serde_json::to_value(&value).ok(),
+            error: None,
+        },
+        Err(e) => CalendarSourceResult {
+            source_name: source.name,
+            data: None,
+            error: Some(e),
+        },
+    })
+}
+
+/// Places a template in a region of a layout. The template can't be a layout
+/// itself, so layouts never nest.
+#[server]
+pub async fn save_layout_region(region: LayoutRegion) -> Result<LayoutRegion, ServerFnError> {
+    let template = crate::db::get_template_by_id(region.template_id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to query db: {:?}", e)))?;
+    if template.kind == TemplateKind::Layout {
+        return Err(ServerFnError::new(format!(
+            "{} is a layout; layouts can't contain other layouts",
+            template.name
+        )));
+    }
+    if region.region == crate::models::Region::Custom && (region.width <= 0 || region.height <= 0) {
+        return Err(ServerFnError::new(
+            "Width and height must be greater than 0",
+        ));
+    }
+
+    match region.id {
+        Some(id) => {
+            crate::db::update_layout_region(id, &region)
+                .await
+                .map_err(|e| {
+                    ServerFnError::new(format!("Unable to update layout region: {:?}", e))
+                })?;
+            Ok(region)
+        }
+        None => crate::db::create_layout_region(region.layout_id, &region)
+            .await
+            .map_err(|e| ServerFnError::new(format!("Unable to create layout region: {:?}", e))),
+    }
+}
+
+#[server]
+pub async fn delete_layout_region(id: i64) -> Result<(), ServerFnError> {
+    crate::db::delete_layout_region(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to delete layout region: {:?}", e)))
+}
+
+/// Largest image accepted by `upload_asset`.
+#[cfg(feature = "server")]
+pub const MAX_ASSET_BYTES: usize = 5 * 1024 * 1024;
+
+/// Stores a PNG, JPEG or SVG image sent as base64 as an asset of the template,
+/// named after the file without its extension.
+#[server]
+pub async fn upload_asset(
+    template_id: i64,
+    filename: String,
+    data: String,
+) -> Result<Asset, ServerFnError> {
+    use base64::Engine;
+
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| ServerFnError::new(format!("Invalid upload: {e}")))?;
+    if data.len() > MAX_ASSET_BYTES {
+        return Err(ServerFnError::new(format!(
+            "Image is larger than {} MiB",
+            MAX_ASSET_BYTES / 1024 / 1024
+        )));
+    }
+    let content_type = crate::device::assets::detect_content_type(&data)
+        .ok_or_else(|| ServerFnError::new(format!("{filename} is not a PNG, JPEG or SVG image")))?;
+    let name = std::path::Path::new(&filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ServerFnError::new("Asset needs a file name"))?;
+
+    crate::db::create_asset(template_id, &name, content_type, &data)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to save asset: {:?}", e)))
+}
+
+#[server]
+pub async fn delete_asset(id: i64) -> Result<(), ServerFnError> {
+    crate::db::delete_asset(id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Unable to delete asset: {:?}", e)))
+}
+
+/// Small data URI preview of an asset for the template editor's picker.
+#[server]
+pub async fn get_asset_thumbnail(id: i64) -> Result<String, ServerFnError> {
+    use crate::device::assets::{EmbedOptions, data_uri};
+
+    let (content_type, data) = crate::db::get_asset_data_by_id(id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let options = EmbedOptions {
+        height: Some(48),
+        ..Default::default()
+    };
+    data_uri(&content_type, &data, &options).map_err(ServerFnError::new)
+}
+
 #[server]
 pub async fn execute_http_source(source: HttpSource) -> Result<HttpSourceResult, ServerFnError> {
     use crate::models::server::http_client;
@@ -657,8 +1334,9 @@ pub async fn execute_http_source(source: HttpSource) -> Result<HttpSourceResult,
 #[server]
 pub async fn get_template_context(
     render_context: RenderContext,
-) -> Result<Vec<TemplateVar>, ServerFnError> {
+) -> Result<TemplateContext, ServerFnError> {
     use crate::{device::renderer::render_vars, frontend::server_fns::utils::obj_to_template_var};
+    use liquid::model::ValueView;
 
     let device_obj = render_vars(&render_context)
         .await
@@ -666,8 +1344,28 @@ pub async fn get_template_context(
 
     let mut vars: Vec<TemplateVar> = vec![];
     obj_to_template_var(&"".to_string(), &mut vars, &device_obj);
+    for var in vars.iter_mut().filter(|v| v.path.starts_with("errors.")) {
+        var.is_error = true;
+    }
+    // Errors are keyed by kind, then source name.
+    let error_count = device_obj
+        .get("errors")
+        .and_then(|e| e.as_object())
+        .map_or(0, |e| {
+            e.values()
+                .filter_map(|by_name| by_name.as_object())
+                .map(|by_name| by_name.size() as usize)
+                .sum()
+        });
 
-    Ok(vars)
+    Ok(TemplateContext { vars, error_count })
+}
+
+/// Timings of the template's recent renders and data source fetches since the
+/// server started.
+#[server]
+pub async fn get_render_stats(template_id: i64) -> Result<RenderStats, ServerFnError> {
+    Ok(crate::device::render_stats::render_stats(template_id))
 }
 
 #[server]
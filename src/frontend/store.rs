@@ -1,7 +1,10 @@
 use dioxus::prelude::*;
 
 use crate::frontend::server_fns::{self, ServerInfo};
-use crate::models::{AuthenticatedUser, Device, Template};
+use crate::models::{
+    AuthenticatedUser, Device, Dither, Font, ImportOptions, ImportReport, Partial, Template,
+    TemplateKind, TemplateValidation,
+};
 
 #[derive(Clone, Copy)]
 pub struct AppStore {
@@ -14,12 +17,16 @@ pub struct AppStore {
     pub devices: Signal<Vec<Device>>,
     pub templates: Signal<Vec<Template>>,
     pub users: Signal<Vec<AuthenticatedUser>>,
+    pub fonts: Signal<Vec<Font>>,
+    pub partials: Signal<Vec<Partial>>,
     pub server_info: Signal<Option<ServerInfo>>,
 
     // Distinguish loading-spinner from genuinely-empty lists
     pub devices_loaded: Signal<bool>,
     pub templates_loaded: Signal<bool>,
     pub users_loaded: Signal<bool>,
+    pub fonts_loaded: Signal<bool>,
+    pub partials_loaded: Signal<bool>,
 }
 
 impl AppStore {
@@ -31,10 +38,14 @@ impl AppStore {
             devices: Signal::new(vec![]),
             templates: Signal::new(vec![]),
             users: Signal::new(vec![]),
+            fonts: Signal::new(vec![]),
+            partials: Signal::new(vec![]),
             server_info: Signal::new(None),
             devices_loaded: Signal::new(false),
             templates_loaded: Signal::new(false),
             users_loaded: Signal::new(false),
+            fonts_loaded: Signal::new(false),
+            partials_loaded: Signal::new(false),
         }
     }
 
@@ -118,6 +129,34 @@ impl AppStore {
         Ok(())
     }
 
+    pub async fn update_device_keep_last_image_on_error(
+        mut self,
+        device_id: i64,
+        val: bool,
+    ) -> Result<(), ServerFnError> {
+        server_fns::update_device_keep_last_image_on_error(device_id, val).await?;
+        if let Some(d) = self.devices.write().iter_mut().find(|d| d.id == device_id) {
+            d.keep_last_image_on_error = val;
+        }
+        Ok(())
+    }
+
+    pub async fn update_device_dithering(
+        mut self,
+        device_id: i64,
+        dither: Dither,
+        dither_threshold: i64,
+        gamma: f64,
+    ) -> Result<(), ServerFnError> {
+        server_fns::update_device_dithering(device_id, dither, dither_threshold, gamma).await?;
+        if let Some(d) = self.devices.write().iter_mut().find(|d| d.id == device_id) {
+            d.dither = dither;
+            d.dither_threshold = dither_threshold;
+            d.gamma = gamma;
+        }
+        Ok(())
+    }
+
     // --- Templates ---
 
     pub async fn fetch_templates(mut self) {
@@ -132,8 +171,9 @@ impl AppStore {
         mut self,
         name: String,
         content: String,
+        kind: TemplateKind,
     ) -> Result<Template, ServerFnError> {
-        let t = server_fns::create_template(name, content).await?;
+        let t = server_fns::create_template(name, content, kind).await?;
         self.templates.write().push(t.clone());
         Ok(t)
     }
@@ -144,18 +184,39 @@ impl AppStore {
         Ok(t)
     }
 
+    pub async fn import_template(
+        mut self,
+        bundle: String,
+        options: ImportOptions,
+    ) -> Result<ImportReport, ServerFnError> {
+        let report = server_fns::import_template(bundle, options).await?;
+        let mut templates = self.templates.write();
+        match templates.iter_mut().find(|t| t.id == report.template.id) {
+            Some(t) => *t = report.template.clone(),
+            None => templates.push(report.template.clone()),
+        }
+        Ok(report)
+    }
+
+    /// Saves a template unless checking it finds errors and `force` isn't
+    /// set; either way returns what the check found.
     pub async fn save_template(
         mut self,
         id: i64,
         name: String,
         content: String,
-    ) -> Result<(), ServerFnError> {
-        server_fns::save_template(id, name.clone(), content.clone()).await?;
+        force: bool,
+    ) -> Result<TemplateValidation, ServerFnError> {
+        let validation =
+            server_fns::save_template(id, name.clone(), content.clone(), force).await?;
+        if !validation.errors.is_empty() && !force {
+            return Ok(validation);
+        }
         if let Some(t) = self.templates.write().iter_mut().find(|t| t.id == id) {
             t.name = name;
             t.content = content;
         }
-        Ok(())
+        Ok(validation)
     }
 
     pub async fn delete_template(mut self, id: i64) -> Result<(), ServerFnError> {
@@ -179,4 +240,57 @@ impl AppStore {
         self.users.write().retain(|u| u.id != user_id);
         Ok(())
     }
+
+    // --- Fonts ---
+
+    pub async fn fetch_fonts(mut self) {
+        match server_fns::get_fonts().await {
+            Ok(list) => self.fonts.set(list),
+            Err(e) => tracing::error!("fetch_fonts failed: {e}"),
+        }
+        self.fonts_loaded.set(true);
+    }
+
+    pub async fn upload_font(
+        mut self,
+        filename: String,
+        data: String,
+    ) -> Result<Font, ServerFnError> {
+        let font = server_fns::upload_font(filename, data).await?;
+        let mut fonts = self.fonts.write();
+        fonts.push(font.clone());
+        fonts.sort_by(|a, b| (&a.family, &a.filename).cmp(&(&b.family, &b.filename)));
+        Ok(font)
+    }
+
+    pub async fn delete_font(mut self, id: i64) -> Result<(), ServerFnError> {
+        server_fns::delete_font(id).await?;
+        self.fonts.write().retain(|f| f.id != id);
+        Ok(())
+    }
+
+    // --- Partials ---
+
+    pub async fn fetch_partials(mut self) {
+        match server_fns::get_partials().await {
+            Ok(list) => self.partials.set(list),
+            Err(e) => tracing::error!("fetch_partials failed: {e}"),
+        }
+        self.partials_loaded.set(true);
+    }
+
+    pub async fn save_partial(mut self, partial: Partial) -> Result<Partial, ServerFnError> {
+        let saved = server_fns::save_partial(partial).await?;
+        let mut partials = self.partials.write();
+        partials.retain(|p| p.id != saved.id);
+        partials.push(saved.clone());
+        partials.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(saved)
+    }
+
+    pub async fn delete_partial(mut self, id: i64) -> Result<(), ServerFnError> {
+        server_fns::delete_partial(id).await?;
+        self.partials.write().retain(|p| p.id != Some(id));
+        Ok(())
+    }
 }
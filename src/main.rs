@@ -15,7 +15,8 @@ mod tls;
 #[cfg(feature = "server")]
 async fn build_router(tls_enabled: bool) -> axum::Router {
     use axum::routing::get;
-    use axum_prometheus::PrometheusMetricLayer;
+    use axum_prometheus::metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+    use axum_prometheus::{PrometheusMetricLayerBuilder, utils::SECONDS_DURATION_BUCKETS};
     use tower_http::{
         cors::{Any, CorsLayer},
         trace::TraceLayer,
@@ -29,6 +30,10 @@ async fn build_router(tls_enabled: bool) -> axum::Router {
         .expect("Failed to run database migrations");
     tracing::info!("Database initialized and migrations applied");
 
+    crate::device::fonts::reload_fonts()
+        .await
+        .expect("Failed to load uploaded fonts");
+
     // Session store for auth
     let session_store = tower_sessions_sqlx_store::SqliteStore::new(db.clone());
     session_store
@@ -42,7 +47,28 @@ async fn build_router(tls_enabled: bool) -> axum::Router {
     let auth_layer =
         axum_login::AuthManagerLayerBuilder::new(auth_backend, session_layer).build();
 
-    let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
+    // Export every `*_duration_seconds` metric (HTTP requests, render stages,
+    // data source fetches) as a histogram rather than a summary.
+    let (prometheus_layer, metric_handle) = PrometheusMetricLayerBuilder::new()
+        .with_metrics_from_fn(|| {
+            let handle = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Suffix("_duration_seconds".to_string()),
+                    SECONDS_DURATION_BUCKETS,
+                )
+                .expect("duration buckets are valid")
+                .install_recorder()
+                .expect("Failed to install metrics recorder");
+            let upkeep = handle.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    upkeep.run_upkeep();
+                }
+            });
+            handle
+        })
+        .build_pair();
     let device_api = crate::device::api::router(tls_enabled);
     let auth_api = crate::auth::router();
 
@@ -11,6 +11,9 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "server")]
 pub mod server;
 
+/// Fetch timeout for new data sources; matches the column default.
+pub const DEFAULT_SOURCE_TIMEOUT_SECS: i64 = 5;
+
 #[cfg_attr(feature = "server", derive(FromRow))]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct PrometheusQuery {
@@ -19,6 +22,8 @@ pub struct PrometheusQuery {
     pub template_id: i64,
     pub addr: String,
     pub query: String,
+    /// How long a fetch may take before the source counts as failed.
+    pub timeout_secs: i64,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -31,6 +36,7 @@ impl PrometheusQuery {
             name: "".to_string(),
             addr: "".to_string(),
             query: "".to_string(),
+            timeout_secs: DEFAULT_SOURCE_TIMEOUT_SECS,
             created_at: Utc::now().naive_utc(),
             updated_at: Utc::now().naive_utc(),
         }
@@ -49,6 +55,8 @@ pub struct RangeQuery {
     pub duration: String,
     /// Resolution between points, Prometheus-style (e.g. "60s", "5m").
     pub step: String,
+    /// How long a fetch may take before the source counts as failed.
+    pub timeout_secs: i64,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -63,6 +71,7 @@ impl RangeQuery {
             query: "".to_string(),
             duration: "1h".to_string(),
             step: "60s".to_string(),
+            timeout_secs: DEFAULT_SOURCE_TIMEOUT_SECS,
             created_at: Utc::now().naive_utc(),
             updated_at: Utc::now().naive_utc(),
         }
@@ -132,10 +141,163 @@ pub struct Template {
     pub id: i64,
     pub name: String,
     pub content: String,
+    pub kind: TemplateKind,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+/// Whether a template is rendered from its Liquid content or composed from
+/// other templates placed in regions of the screen.
+#[cfg_attr(feature = "server", derive(sqlx::Type))]
+#[cfg_attr(feature = "server", sqlx(rename_all = "snake_case"))]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    #[default]
+    Liquid,
+    /// Content is unused; the screen is made of the layout's `LayoutRegion`s.
+    Layout,
+}
+
+/// Part of the screen a layout region covers. Presets scale with the device's
+/// resolution; `Custom` uses the region's own pixel rectangle.
+#[cfg_attr(feature = "server", derive(sqlx::Type))]
+#[cfg_attr(feature = "server", sqlx(rename_all = "snake_case"))]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Region {
+    #[default]
+    Full,
+    TopHalf,
+    BottomHalf,
+    LeftHalf,
+    RightHalf,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Custom,
+}
+
+impl Region {
+    pub const ALL: [Region; 10] = [
+        Region::Full,
+        Region::TopHalf,
+        Region::BottomHalf,
+        Region::LeftHalf,
+        Region::RightHalf,
+        Region::TopLeft,
+        Region::TopRight,
+        Region::BottomLeft,
+        Region::BottomRight,
+        Region::Custom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Region::Full => "full",
+            Region::TopHalf => "top_half",
+            Region::BottomHalf => "bottom_half",
+            Region::LeftHalf => "left_half",
+            Region::RightHalf => "right_half",
+            Region::TopLeft => "top_left",
+            Region::TopRight => "top_right",
+            Region::BottomLeft => "bottom_left",
+            Region::BottomRight => "bottom_right",
+            Region::Custom => "custom",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Region::Full => "Full screen",
+            Region::TopHalf => "Top half",
+            Region::BottomHalf => "Bottom half",
+            Region::LeftHalf => "Left half",
+            Region::RightHalf => "Right half",
+            Region::TopLeft => "Top left quarter",
+            Region::TopRight => "Top right quarter",
+            Region::BottomLeft => "Bottom left quarter",
+            Region::BottomRight => "Bottom right quarter",
+            Region::Custom => "Custom",
+        }
+    }
+}
+
+impl std::str::FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|r| r.as_str() == s)
+            .ok_or_else(|| format!("unknown region {s:?}"))
+    }
+}
+
+/// A template placed in one region of a layout template.
+#[cfg_attr(feature = "server", derive(FromRow))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LayoutRegion {
+    pub id: Option<i64>,
+    pub layout_id: i64,
+    /// The template rendered into the region; never itself a layout.
+    pub template_id: i64,
+    pub region: Region,
+    /// Pixel rectangle of a `Custom` region; ignored for presets.
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl LayoutRegion {
+    pub fn new(layout_id: i64, template_id: i64) -> Self {
+        Self {
+            id: None,
+            layout_id,
+            template_id,
+            region: Region::default(),
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            created_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// `(x, y, width, height)` of the region on a `width` x `height` screen.
+    /// Halves and quarters of odd sizes give the extra pixel to the bottom and
+    /// right; custom rectangles are clipped to the screen.
+    pub fn rect(&self, width: i64, height: i64) -> (i64, i64, i64, i64) {
+        let (half_w, half_h) = (width / 2, height / 2);
+        match self.region {
+            Region::Full => (0, 0, width, height),
+            Region::TopHalf => (0, 0, width, half_h),
+            Region::BottomHalf => (0, half_h, width, height - half_h),
+            Region::LeftHalf => (0, 0, half_w, height),
+            Region::RightHalf => (half_w, 0, width - half_w, height),
+            Region::TopLeft => (0, 0, half_w, half_h),
+            Region::TopRight => (half_w, 0, width - half_w, half_h),
+            Region::BottomLeft => (0, half_h, half_w, height - half_h),
+            Region::BottomRight => (half_w, half_h, width - half_w, height - half_h),
+            Region::Custom => {
+                let x = self.x.clamp(0, width);
+                let y = self.y.clamp(0, height);
+                (
+                    x,
+                    y,
+                    self.width.clamp(0, width - x),
+                    self.height.clamp(0, height - y),
+                )
+            }
+        }
+    }
+}
+
 #[cfg_attr(feature = "server", derive(FromRow))]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Store)]
 pub struct Device {
@@ -151,10 +313,71 @@ pub struct Device {
     pub rssi: Option<String>,
     pub template_id: i64,
     pub maximum_compatibility: bool,
+    pub dither: Dither,
+    /// Luminance cut-off (0-255) between black and white.
+    pub dither_threshold: i64,
+    /// Gamma applied to luminance before dithering; values above 1.0 brighten midtones.
+    pub gamma: f64,
+    /// When a render fails, show the last image rendered successfully instead
+    /// of the error screen.
+    pub keep_last_image_on_error: bool,
     pub last_seen_at: String,
     pub created_at: String,
 }
 
+/// How the renderer reduces a grayscale raster to the panel's black and white.
+#[cfg_attr(feature = "server", derive(sqlx::Type))]
+#[cfg_attr(feature = "server", sqlx(rename_all = "snake_case"))]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Dither {
+    /// Hard cut at the threshold; best for crisp text and line art.
+    #[default]
+    Threshold,
+    FloydSteinberg,
+    Atkinson,
+    /// Ordered dithering with an 8x8 Bayer matrix.
+    Bayer,
+}
+
+impl Dither {
+    pub const ALL: [Dither; 4] = [
+        Dither::Threshold,
+        Dither::FloydSteinberg,
+        Dither::Atkinson,
+        Dither::Bayer,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dither::Threshold => "threshold",
+            Dither::FloydSteinberg => "floyd_steinberg",
+            Dither::Atkinson => "atkinson",
+            Dither::Bayer => "bayer",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dither::Threshold => "Threshold",
+            Dither::FloydSteinberg => "Floyd\u{2013}Steinberg",
+            Dither::Atkinson => "Atkinson",
+            Dither::Bayer => "Ordered (Bayer)",
+        }
+    }
+}
+
+impl std::str::FromStr for Dither {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.as_str() == s)
+            .ok_or_else(|| format!("unknown dither algorithm {s:?}"))
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Store)]
 pub struct RenderContext {
     pub device: Device,
@@ -162,6 +385,152 @@ pub struct RenderContext {
     pub prometheus_queries: Vec<PrometheusQuery>,
     pub range_queries: Vec<RangeQuery>,
     pub http_sources: Vec<HttpSource>,
+    pub calendar_sources: Vec<CalendarSource>,
+    pub assets: Vec<Asset>,
+    /// Regions of a layout template; empty for Liquid templates.
+    pub regions: Vec<LayoutRegion>,
+}
+
+impl RenderContext {
+    /// This context with the template's content, data sources and regions as
+    /// they were at `revision`, to preview it on the same device.
+    pub fn at_revision(&self, revision: &TemplateRevision) -> Self {
+        let sources = revision.sources.clone();
+        Self {
+            template: Template {
+                content: revision.content.clone(),
+                updated_at: revision.created_at,
+                ..self.template.clone()
+            },
+            prometheus_queries: sources.prometheus_queries,
+            range_queries: sources.range_queries,
+            http_sources: sources.http_sources,
+            calendar_sources: sources.calendar_sources,
+            regions: sources.regions,
+            ..self.clone()
+        }
+    }
+}
+
+/// A problem found in a template when it is saved, at a 1-based line and
+/// column of its content when that could be worked out.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TemplateDiagnostic {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl std::fmt::Display for TemplateDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {line}, column {column}: ")?,
+            (Some(line), None) => write!(f, "line {line}: ")?,
+            _ => {}
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// What checking a template on save found: errors that would stop it
+/// rendering on a device, and warnings about likely mistakes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct TemplateValidation {
+    pub errors: Vec<TemplateDiagnostic>,
+    pub warnings: Vec<TemplateDiagnostic>,
+}
+
+/// A saved version of a template: its content, data sources and layout
+/// regions, recorded each time the template is saved.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TemplateRevision {
+    pub id: i64,
+    pub template_id: i64,
+    pub content: String,
+    pub sources: RevisionSources,
+    /// Username of whoever saved it; none for the version a template had
+    /// before its first recorded save.
+    pub author: Option<String>,
+    /// The revision this one was restored from, if it was a restore.
+    pub restored_from: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Data source configuration and layout regions kept with a revision.
+/// Uploaded calendar files are not part of it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RevisionSources {
+    pub prometheus_queries: Vec<PrometheusQuery>,
+    pub range_queries: Vec<RangeQuery>,
+    pub http_sources: Vec<HttpSource>,
+    pub calendar_sources: Vec<CalendarSource>,
+    pub regions: Vec<LayoutRegion>,
+}
+
+/// Version of the bundle format written by template export.
+#[cfg(feature = "server")]
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A template with its data sources, assets and the partials it uses, as a
+/// single file for moving it to another server. Ids are those of the
+/// exporting server; import creates everything afresh.
+#[cfg(feature = "server")]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TemplateBundle {
+    pub version: u32,
+    pub name: String,
+    pub kind: TemplateKind,
+    pub content: String,
+    pub sources: RevisionSources,
+    /// Uploaded iCalendar files, by calendar source name.
+    #[serde(default)]
+    pub calendar_files: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    pub assets: Vec<BundleAsset>,
+    /// Partials the template includes or renders by name, and the ones
+    /// those use in turn.
+    #[serde(default)]
+    pub partials: Vec<Partial>,
+    /// Names of the templates placed in a layout's regions, by their id in
+    /// `sources.regions`. Import places the template of the same name.
+    #[serde(default)]
+    pub region_templates: std::collections::BTreeMap<i64, String>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundleAsset {
+    pub name: String,
+    pub content_type: String,
+    /// Base64 of the image data.
+    pub data: String,
+}
+
+/// What import does when the bundle's template or one of its partials has
+/// the name of an existing one.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ImportConflict {
+    /// Import the template under a new name and keep existing partials.
+    #[default]
+    Rename,
+    /// Overwrite the existing template and partials.
+    Replace,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ImportOptions {
+    pub conflict: ImportConflict,
+    /// Prometheus server to use for every instant and range query instead
+    /// of the `addr` in the bundle.
+    pub prometheus_addr: Option<String>,
+}
+
+/// The template a bundle was imported into, and what could not be imported
+/// as it was.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImportReport {
+    pub template: Template,
+    pub warnings: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -184,6 +553,8 @@ pub struct HttpSource {
     pub name: String,
     pub template_id: i64,
     pub url: String,
+    /// How long a fetch may take before the source counts as failed.
+    pub timeout_secs: i64,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -195,12 +566,65 @@ impl HttpSource {
             template_id,
             name: "".to_string(),
             url: "".to_string(),
+            timeout_secs: DEFAULT_SOURCE_TIMEOUT_SECS,
             created_at: Utc::now().naive_utc(),
             updated_at: Utc::now().naive_utc(),
         }
     }
 }
 
+/// An iCalendar feed, fetched from `url` or uploaded, whose upcoming events
+/// are exposed as `calendar.<name>.events`. As with assets, an uploaded file
+/// is only loaded for rendering.
+#[cfg_attr(feature = "server", derive(FromRow))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CalendarSource {
+    pub id: Option<i64>,
+    pub name: String,
+    pub template_id: i64,
+    /// `http(s)://` or `webcal://` feed; empty when a file was uploaded.
+    pub url: String,
+    /// Size of the uploaded file in bytes; 0 for a feed.
+    pub size: i64,
+    /// Number of days from today whose events are exposed.
+    pub days: i64,
+    pub timeout_secs: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl CalendarSource {
+    pub fn new(template_id: i64) -> Self {
+        Self {
+            id: None,
+            template_id,
+            name: "".to_string(),
+            url: "".to_string(),
+            size: 0,
+            days: 7,
+            timeout_secs: DEFAULT_SOURCE_TIMEOUT_SECS,
+            created_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// A PNG, JPEG or SVG image uploaded to a template and embedded with the
+/// `asset` filter. As with fonts, the image data is only loaded for rendering.
+#[cfg_attr(feature = "server", derive(FromRow))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Asset {
+    pub id: i64,
+    pub template_id: i64,
+    /// Name used in templates: `{{ "logo" | asset }}`.
+    pub name: String,
+    pub content_type: String,
+    /// Size of the image data in bytes.
+    pub size: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HttpSourceResult {
     pub source_name: String,
@@ -208,6 +632,14 @@ pub struct HttpSourceResult {
     pub error: Option<String>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CalendarSourceResult {
+    pub source_name: String,
+    /// The events, as templates see them.
+    pub data: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
 #[cfg_attr(feature = "server", derive(FromRow))]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct DeviceLog {
@@ -267,6 +699,140 @@ pub struct AuthenticatedUser {
     pub username: String,
 }
 
+/// An uploaded TTF/OTF font. The font data itself stays in the database and is
+/// only read when the shared font database is rebuilt.
+#[cfg_attr(feature = "server", derive(FromRow))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Font {
+    pub id: i64,
+    /// Family name read from the font file, as used in `font-family`.
+    pub family: String,
+    pub filename: String,
+    /// Size of the font data in bytes.
+    pub size: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A snippet shared by all templates, rendered with `{% render "name" %}` or
+/// `{% include "name" %}`.
+#[cfg_attr(feature = "server", derive(FromRow))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Partial {
+    pub id: Option<i64>,
+    pub name: String,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Default for Partial {
+    fn default() -> Self {
+        Self {
+            id: None,
+            name: "".to_string(),
+            content: "".to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// Kind of data source a stored last-known-good result belongs to.
+#[cfg(feature = "server")]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum SourceKind {
+    Prometheus,
+    PrometheusRange,
+    Http,
+    Calendar,
+}
+
+#[cfg(feature = "server")]
+impl SourceKind {
+    /// Name of the template object the kind's results are exposed under, also
+    /// used as a metrics label.
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceKind::Prometheus => "prometheus",
+            SourceKind::PrometheusRange => "prometheus_range",
+            SourceKind::Http => "http",
+            SourceKind::Calendar => "calendar",
+        }
+    }
+}
+
+/// Recent timings of one render stage or data source, in milliseconds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TimingSummary {
+    /// Stage name (`liquid`, `svg_parse`, `rasterize`, `encode`) or source
+    /// path (`http.weather`).
+    pub name: String,
+    pub count: usize,
+    pub last_ms: f64,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+}
+
+/// Timings of a template's recent renders, for the template editor.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RenderStats {
+    pub stages: Vec<TimingSummary>,
+    pub sources: Vec<TimingSummary>,
+}
+
+/// Encodings a device can be sent its screen in.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Bmp,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Png => "png",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Png => "image/png",
+        }
+    }
+}
+
+/// First firmware release that can decode PNG screens; older firmware only
+/// understands BMP.
+const PNG_MIN_FIRMWARE: (u32, u32, u32) = (1, 6, 0);
+
+/// Parses a `major.minor.patch` firmware version, tolerating a leading `v` and
+/// missing minor/patch components.
+fn parse_fw_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map(str::parse).transpose().ok()?.unwrap_or(0);
+    let patch = parts
+        .next()
+        .map(|p| {
+            // Ignore pre-release/build suffixes such as "2-beta".
+            let digits: String = p.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse()
+        })
+        .transpose()
+        .ok()?
+        .unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Gray levels each panel can display, keyed by the `Model` header the firmware
+/// reports. Models not listed here are driven as 1-bit black and white.
+const MODEL_GRAY_LEVELS: &[(&str, u8)] = &[("og", 2), ("v2", 4), ("x", 16)];
+
 impl Device {
     pub fn virtual_device() -> Self {
         Device {
@@ -282,11 +848,38 @@ impl Device {
             rssi: Some("-65".to_string()),
             template_id: 0,
             maximum_compatibility: false,
+            dither: Dither::default(),
+            dither_threshold: 127,
+            gamma: 1.0,
+            keep_last_image_on_error: false,
             last_seen_at: String::new(),
             created_at: String::new(),
         }
     }
 
+    /// Number of gray levels (2, 4 or 16) the renderer quantizes to for this
+    /// device, from its model's capabilities.
+    pub fn gray_levels(&self) -> u8 {
+        MODEL_GRAY_LEVELS
+            .iter()
+            .find(|(model, _)| model.eq_ignore_ascii_case(&self.model))
+            .map(|(_, levels)| *levels)
+            .unwrap_or(2)
+    }
+
+    /// Format `/api/display` links the device to: PNG (much smaller over slow
+    /// Wi-Fi) when its firmware can decode it, BMP otherwise or when maximum
+    /// compatibility is enabled.
+    pub fn image_format(&self) -> ImageFormat {
+        if self.maximum_compatibility {
+            return ImageFormat::Bmp;
+        }
+        match self.fw_version.as_deref().and_then(parse_fw_version) {
+            Some(version) if version >= PNG_MIN_FIRMWARE => ImageFormat::Png,
+            _ => ImageFormat::Bmp,
+        }
+    }
+
     pub fn percent_charged(&self) -> Option<f32> {
         self.battery_voltage.map(|battery_voltage| {
             let pct_charged = (battery_voltage - 3.) / 0.012;
@@ -325,6 +918,65 @@ mod tests {
         assert_eq!(series.points.len(), 3, "points should be preserved");
     }
 
+    #[test]
+    fn test_gray_levels_follow_model_capabilities() {
+        let mut device = Device::virtual_device();
+        assert_eq!(device.gray_levels(), 2, "unknown models are 1-bit");
+        device.model = "v2".to_string();
+        assert_eq!(device.gray_levels(), 4);
+        device.model = "X".to_string();
+        assert_eq!(device.gray_levels(), 16, "model lookup ignores case");
+    }
+
+    #[test]
+    fn test_image_format_follows_firmware_and_compatibility() {
+        let mut device = Device::virtual_device();
+        device.fw_version = Some("1.5.9".to_string());
+        assert_eq!(
+            device.image_format(),
+            ImageFormat::Bmp,
+            "old firmware gets BMP"
+        );
+        device.fw_version = Some("1.6.0".to_string());
+        assert_eq!(device.image_format(), ImageFormat::Png);
+        device.fw_version = Some("v1.10.2-beta".to_string());
+        assert_eq!(
+            device.image_format(),
+            ImageFormat::Png,
+            "versions compare numerically"
+        );
+        device.maximum_compatibility = true;
+        assert_eq!(
+            device.image_format(),
+            ImageFormat::Bmp,
+            "maximum compatibility forces BMP"
+        );
+        device.maximum_compatibility = false;
+        device.fw_version = None;
+        assert_eq!(
+            device.image_format(),
+            ImageFormat::Bmp,
+            "unknown firmware gets BMP"
+        );
+    }
+
+    #[test]
+    fn test_layout_regions_split_the_screen() {
+        let mut region = LayoutRegion::new(1, 2);
+        let rect = |region: &LayoutRegion| region.rect(801, 481);
+        assert_eq!(rect(&region), (0, 0, 801, 481));
+        region.region = Region::TopHalf;
+        assert_eq!(rect(&region), (0, 0, 801, 240));
+        region.region = Region::BottomHalf;
+        assert_eq!(rect(&region), (0, 240, 801, 241), "odd pixel goes last");
+        region.region = Region::BottomRight;
+        assert_eq!(rect(&region), (400, 240, 401, 241));
+
+        region.region = Region::Custom;
+        (region.x, region.y, region.width, region.height) = (700, -10, 200, 100);
+        assert_eq!(rect(&region), (700, 0, 101, 100), "clipped to the screen");
+    }
+
     #[test]
     fn test_range_series_from_points_empty_reports_zeros() {
         let series = RangeSeries::from_points(HashMap::new(), vec![]);
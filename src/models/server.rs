@@ -1,12 +1,26 @@
-use std::sync::OnceLock;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
 
+use axum_prometheus::metrics;
+use chrono::NaiveDateTime;
 use dioxus::prelude::*;
-use liquid::ParserBuilder;
-use liquid::{Error, Object};
+use liquid::{Error, Object, Parser, ParserBuilder};
 
-use crate::device::liquid_filters::{QrcodeFilterParser, QrcodeWifiFilterParser};
+use crate::device::charts::ChartKind;
+use crate::device::formatting::FormatKind;
+use crate::device::liquid_filters::{
+    AgendaFilterParser, AssetFilterParser, ChartFilterParser, FitTextFilterParser,
+    FormatFilterParser, IconFilterParser, MonthCalendarFilterParser, QrcodeFilterParser,
+    QrcodeWifiFilterParser, StrftimeFilterParser, TruncatePxFilterParser, WidgetFilterParser,
+    WrapTextFilterParser,
+};
+use crate::device::partials::CurrentPartials;
+use crate::device::render_limits::{GuardedForBlock, LimitedWriter, RenderBudget};
+use crate::device::widgets::WidgetKind;
 use crate::models::{
-    Device, HttpSource, PrometheusQuery, RangePoint, RangeQuery, RangeSeries, Template,
+    CalendarSource, Device, HttpSource, PrometheusQuery, RangePoint, RangeQuery, RangeSeries,
+    RenderContext, Template, TemplateKind,
 };
 use crate::time::{Clock, RealClock};
 
@@ -15,16 +29,152 @@ pub fn http_client() -> &'static reqwest::Client {
     CLIENT.get_or_init(reqwest::Client::new)
 }
 
-impl Template {
-    pub fn render(&self, globals: Object) -> Result<String, Error> {
-        let parser = ParserBuilder::with_stdlib()
+/// The Liquid parser with the standard library plus this crate's filters,
+/// guarded `for` block and the shared partials. Building it registers every
+/// filter, so it's done once.
+pub fn liquid_parser() -> &'static Parser {
+    static PARSER: OnceLock<Parser> = OnceLock::new();
+    PARSER.get_or_init(|| {
+        ParserBuilder::with_stdlib()
             .filter(QrcodeFilterParser)
             .filter(QrcodeWifiFilterParser)
-            .build()?;
+            .filter(AssetFilterParser)
+            .filter(WrapTextFilterParser)
+            .filter(FitTextFilterParser)
+            .filter(TruncatePxFilterParser)
+            .filter(ChartFilterParser(ChartKind::Sparkline))
+            .filter(ChartFilterParser(ChartKind::Line))
+            .filter(ChartFilterParser(ChartKind::Bar))
+            .filter(ChartFilterParser(ChartKind::Area))
+            .filter(WidgetFilterParser(WidgetKind::Gauge))
+            .filter(WidgetFilterParser(WidgetKind::ProgressBar))
+            .filter(WidgetFilterParser(WidgetKind::Battery))
+            .filter(WidgetFilterParser(WidgetKind::WifiBars))
+            .filter(WidgetFilterParser(WidgetKind::BigNumber))
+            .filter(IconFilterParser)
+            .filter(AgendaFilterParser)
+            .filter(MonthCalendarFilterParser)
+            .filter(FormatFilterParser(FormatKind::Number))
+            .filter(FormatFilterParser(FormatKind::Si))
+            .filter(FormatFilterParser(FormatKind::Iec))
+            .filter(FormatFilterParser(FormatKind::Duration))
+            .filter(FormatFilterParser(FormatKind::TimeAgo))
+            .filter(FormatFilterParser(FormatKind::Percent))
+            .filter(FormatFilterParser(FormatKind::Convert))
+            .filter(StrftimeFilterParser)
+            .block(GuardedForBlock)
+            .partials(CurrentPartials)
+            .build()
+            .expect("built-in Liquid filters register")
+    })
+}
+
+struct CompiledTemplate {
+    /// Hash of the parsed content. The editor previews unsaved content under
+    /// the saved template's id and `updated_at`, so a hit must also match it.
+    content_hash: u64,
+    template: Arc<liquid::Template>,
+}
+
+type TemplateCache = Mutex<HashMap<(i64, NaiveDateTime), CompiledTemplate>>;
+
+/// Parsed templates keyed by template id and `updated_at`, holding at most one
+/// entry per template.
+fn template_cache() -> &'static TemplateCache {
+    static CACHE: OnceLock<TemplateCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Drops the parsed copy of a template, after it is saved or deleted.
+pub fn invalidate_template(id: i64) {
+    template_cache()
+        .lock()
+        .expect("template cache lock poisoned")
+        .retain(|(cached_id, _), _| *cached_id != id);
+}
+
+impl Template {
+    /// Renders the saved template, parsing it once per version.
+    pub fn render(&self, globals: Object) -> Result<String, Error> {
+        Self::render_parsed(self.compiled(true)?, globals)
+    }
+
+    /// Renders content that may not be saved yet, such as the editor's
+    /// preview. The saved version's parse is reused when the content matches,
+    /// but any other parse isn't cached, since it would be filed under the
+    /// saved template's version and miss on every device render.
+    pub fn render_unsaved(&self, globals: Object) -> Result<String, Error> {
+        Self::render_parsed(self.compiled(false)?, globals)
+    }
 
-        let template = parser.parse(&self.content)?;
+    fn render_parsed(template: Arc<liquid::Template>, globals: Object) -> Result<String, Error> {
+        // Output and loops are bounded by the render budget of the current
+        // thread; see `RenderBudget::scope`.
+        let mut output = LimitedWriter::new(RenderBudget::current());
+        if let Err(e) = template.render_to(&mut output, &globals) {
+            return Err(output.take_error().unwrap_or(e));
+        }
+        output.into_string()
+    }
 
-        Ok(template.render(&globals)?)
+    /// The parsed template, from the cache when this version was parsed
+    /// before. A new parse replaces the template's cached copy only when
+    /// `cache` says the content is the saved version.
+    fn compiled(&self, cache: bool) -> Result<Arc<liquid::Template>, Error> {
+        let mut hasher = DefaultHasher::new();
+        self.content.hash(&mut hasher);
+        let content_hash = hasher.finish();
+        let key = (self.id, self.updated_at);
+
+        if let Some(cached) = template_cache()
+            .lock()
+            .expect("template cache lock poisoned")
+            .get(&key)
+            .filter(|cached| cached.content_hash == content_hash)
+        {
+            metrics::counter!("template_cache_hits_total").increment(1);
+            return Ok(cached.template.clone());
+        }
+        metrics::counter!("template_cache_misses_total").increment(1);
+
+        let template = Arc::new(liquid_parser().parse(&self.content)?);
+        if !cache {
+            return Ok(template);
+        }
+        invalidate_template(self.id);
+        template_cache()
+            .lock()
+            .expect("template cache lock poisoned")
+            .insert(
+                key,
+                CompiledTemplate {
+                    content_hash,
+                    template: template.clone(),
+                },
+            );
+        Ok(template)
+    }
+}
+
+impl RenderContext {
+    /// Loads the template's data sources, assets and, for a layout, its
+    /// regions, to render it on `device`.
+    pub async fn load(device: Device, template: Template) -> Result<Self, sqlx::Error> {
+        let regions = match template.kind {
+            TemplateKind::Liquid => vec![],
+            TemplateKind::Layout => crate::db::get_layout_regions(template.id).await?,
+        };
+
+        Ok(RenderContext {
+            prometheus_queries: crate::db::get_prometheus_queries(template.id).await?,
+            range_queries: crate::db::get_range_queries(template.id).await?,
+            http_sources: crate::db::get_http_sources(template.id).await?,
+            calendar_sources: crate::db::get_calendar_sources(template.id).await?,
+            assets: crate::db::get_assets(template.id).await?,
+            regions,
+            device,
+            template,
+        })
     }
 }
 
@@ -57,6 +207,57 @@ impl HttpSource {
     }
 }
 
+impl CalendarSource {
+    /// Events from the start of `now`'s day through the next `days` days, as
+    /// `{ "events": [...] }`. Feed, parse and database errors surface as a
+    /// `String` so the renderer can skip the source and the editor can show it.
+    pub async fn get_render_obj(
+        &self,
+        now: chrono::DateTime<chrono_tz::Tz>,
+    ) -> Result<liquid::model::Value, String> {
+        let ics = self.ics().await?;
+        let days = self.days.clamp(1, 366) as u64;
+        // Parsing and expanding recurrences is CPU-bound and a feed can be
+        // large, so it runs on the blocking pool.
+        tokio::task::spawn_blocking(move || {
+            let events = crate::device::calendar::parse(&ics)?;
+            let events: Vec<liquid::model::Value> =
+                crate::device::calendar::upcoming(&events, now, days)
+                    .iter()
+                    .map(|occurrence| occurrence.to_object(now).into())
+                    .collect();
+            Ok(liquid::object!({ "events": events }).into())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    /// The uploaded file, or the feed at `url` (`webcal://` is read as
+    /// `https://`).
+    async fn ics(&self) -> Result<String, String> {
+        if self.url.is_empty() {
+            let id = self.id.ok_or("no calendar file uploaded")?;
+            return crate::db::get_calendar_content(id)
+                .await
+                .map_err(|e| e.to_string());
+        }
+        let url = match self.url.strip_prefix("webcal://") {
+            Some(rest) => format!("https://{rest}"),
+            None => self.url.clone(),
+        };
+        http_client()
+            .get(url)
+            .header("Accept", "text/calendar")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
 pub fn json_to_liquid(value: &serde_json::Value) -> liquid::model::Value {
     match value {
         serde_json::Value::Null => liquid::model::Value::Nil,
@@ -154,22 +355,116 @@ fn range_series_to_object(s: &RangeSeries) -> Object {
     })
 }
 
+/// An unsaved template around `content`, for tests that render through it.
 #[cfg(test)]
-mod range_render_tests {
+pub(crate) fn template_with(content: &str) -> Template {
+    Template {
+        id: 1,
+        name: "t".into(),
+        content: content.into(),
+        kind: Default::default(),
+        created_at: chrono::Utc::now().naive_utc(),
+        updated_at: chrono::Utc::now().naive_utc(),
+    }
+}
+
+#[cfg(test)]
+mod template_cache_tests {
     use super::*;
-    use crate::models::{RangePoint, RangeSeries, Template};
-    use std::collections::HashMap;
 
-    fn template_with(content: &str) -> Template {
-        Template {
-            id: 1,
-            name: "t".into(),
-            content: content.into(),
-            created_at: chrono::Utc::now().naive_utc(),
-            updated_at: chrono::Utc::now().naive_utc(),
-        }
+    #[test]
+    fn test_templates_are_parsed_once_per_version() {
+        let saved = Template {
+            id: 7_001,
+            ..template_with("{{ n }}")
+        };
+        let first = saved.compiled(true).unwrap();
+        assert!(Arc::ptr_eq(&first, &saved.compiled(true).unwrap()));
+        assert_eq!(saved.render(liquid::object!({ "n": 1 })).unwrap(), "1");
+
+        // Unsaved edits previewed under the same id and timestamp don't
+        // replace the saved copy.
+        let edited = Template {
+            content: "n={{ n }}".to_string(),
+            ..saved.clone()
+        };
+        assert_eq!(
+            edited.render_unsaved(liquid::object!({ "n": 2 })).unwrap(),
+            "n=2"
+        );
+        assert!(Arc::ptr_eq(&first, &saved.compiled(true).unwrap()));
+        assert!(Arc::ptr_eq(&first, &saved.compiled(false).unwrap()));
+
+        let newer = Template {
+            updated_at: chrono::DateTime::from_timestamp(1_700_000_000, 0)
+                .unwrap()
+                .naive_utc(),
+            ..saved.clone()
+        };
+        assert!(!Arc::ptr_eq(&first, &newer.compiled(true).unwrap()));
+        assert_eq!(
+            template_cache()
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|(id, _)| *id == 7_001)
+                .count(),
+            1,
+            "one entry per template"
+        );
+
+        invalidate_template(7_001);
+        assert!(
+            !template_cache()
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|(id, _)| *id == 7_001)
+        );
     }
 
+    #[test]
+    fn test_a_preview_before_any_render_leaves_the_cache_to_devices() {
+        let saved = Template {
+            id: 7_002,
+            ..template_with("{{ n }}")
+        };
+        let edited = Template {
+            content: "n={{ n }}".to_string(),
+            ..saved.clone()
+        };
+        assert_eq!(
+            edited.render_unsaved(liquid::object!({ "n": 1 })).unwrap(),
+            "n=1"
+        );
+        assert!(
+            !template_cache()
+                .lock()
+                .unwrap()
+                .contains_key(&(7_002, saved.updated_at))
+        );
+
+        // The device render that follows caches the saved content and hits
+        // it from then on.
+        assert_eq!(saved.render(liquid::object!({ "n": 2 })).unwrap(), "2");
+        let cached = saved.compiled(true).unwrap();
+        assert!(Arc::ptr_eq(&cached, &saved.compiled(true).unwrap()));
+        assert_eq!(
+            edited.render_unsaved(liquid::object!({ "n": 3 })).unwrap(),
+            "n=3"
+        );
+        assert!(Arc::ptr_eq(&cached, &saved.compiled(true).unwrap()));
+
+        invalidate_template(7_002);
+    }
+}
+
+#[cfg(test)]
+mod range_render_tests {
+    use super::*;
+    use crate::models::{RangePoint, RangeSeries};
+    use std::collections::HashMap;
+
     #[test]
     fn test_range_series_is_drawable_from_a_template() {
         let series = RangeSeries::from_points(
@@ -207,6 +502,172 @@ mod range_render_tests {
             .expect("template should render range data");
         assert_eq!(out, "n=3 min=10 max=30 last=20 pts=10;30;20;");
     }
+
+    #[test]
+    fn test_range_series_is_charted_by_the_chart_filters() {
+        let series = RangeSeries::from_points(
+            HashMap::new(),
+            vec![
+                RangePoint {
+                    t: 1.0,
+                    value: 10.0,
+                },
+                RangePoint {
+                    t: 2.0,
+                    value: 30.0,
+                },
+            ],
+        );
+        let globals = liquid::object!({
+            "prometheus_range": liquid::object!({
+                "cpu": vec![liquid::model::Value::Object(range_series_to_object(&series))],
+            }),
+        });
+        let out = template_with(
+            "{{ prometheus_range.cpu[0] | sparkline: width: 100, height: 20 }}|\
+             {{ prometheus_range.cpu | bar_chart: width: 100, height: 30, axes: true }}",
+        )
+        .render(globals.clone())
+        .expect("charts render");
+        let (sparkline, bars) = out.split_once('|').unwrap();
+        assert!(sparkline.contains(r#"d="M0 20 L100 0""#), "{out}");
+        assert_eq!(bars.matches("<rect").count(), 2, "{out}");
+        assert!(bars.contains(r#"d="M0 0V30H100""#), "{out}");
+
+        let err = template_with("{{ prometheus_range.cpu[0] | line_chart: width: 10 }}")
+            .render(globals.clone())
+            .expect_err("height is required");
+        assert!(
+            err.to_string().contains("line_chart: 'height' is required"),
+            "{err}"
+        );
+        let err = template_with(
+            "{{ prometheus_range.cpu[0] | area_chart: width: 1, height: 1, colour: 1 }}",
+        )
+        .render(globals)
+        .expect_err("unknown argument");
+        assert!(
+            err.to_string().contains("unknown argument 'colour'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_values_are_formatted_by_the_formatting_filters() {
+        let globals = liquid::object!({
+            "prometheus": { "disk": [{ "value": 1234000000.0 }] },
+            "http": { "temp": "20", "uptime": 11520, "blank": "" },
+        });
+        let out = template_with(
+            "{{ 1234567.891 | number: decimals: 2 }}|\
+             {{ prometheus.disk[0].value | si: unit: \"B\" }}|\
+             {{ prometheus.disk[0].value | iec: unit: \"B\" }}|\
+             {{ http.uptime | duration }}|\
+             {{ 1760000000 | time_ago: now: 1760000300 }}|\
+             {{ 0.4234 | percent: decimals: 1 }}|\
+             {{ http.temp | convert: from: \"c\", to: \"f\" | round }}|\
+             {{ http.blank | si }}",
+        )
+        .render(globals.clone())
+        .expect("formatting filters render");
+        assert_eq!(
+            out,
+            "1,234,567.89|1.2 GB|1.1 GiB|3h 12m|5 min ago|42.3%|68|"
+        );
+
+        let err = template_with("{{ \"abc\" | number }}")
+            .render(globals.clone())
+            .expect_err("not a number");
+        assert!(
+            err.to_string()
+                .contains("number filter: expected a number, got 'abc'"),
+            "{err}"
+        );
+        let err = template_with("{{ 1 | convert: to: \"f\" }}")
+            .render(globals)
+            .expect_err("from is required");
+        assert!(
+            err.to_string().contains("convert: 'from' is required"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_dates_are_formatted_by_strftime_with_localized_names() {
+        let globals = liquid::object!({ "http": { "day": "2026-03-02", "blank": "" } });
+        let out = template_with(
+            "{{ http.day | strftime: \"%A %-d. %B\", locale: \"de\" }}|\
+             {{ \"2026-03-02 14:05:00\" | strftime: \"%^a %H:%M\", locale: \"fr\" }}|\
+             {{ http.day | strftime: \"%Y-%m-%d\" }}|\
+             {{ http.blank | strftime: \"%A\" }}",
+        )
+        .render(globals.clone())
+        .expect("strftime renders");
+        assert_eq!(out, "Montag 2. März|LUN. 14:05|2026-03-02|");
+
+        let err = template_with("{{ http.day | strftime }}")
+            .render(globals.clone())
+            .expect_err("format is required");
+        assert!(
+            err.to_string().contains("strftime: a format is required"),
+            "{err}"
+        );
+        let err = template_with("{{ http.day | strftime: \"%A\", locale: \"xx\" }}")
+            .render(globals)
+            .expect_err("unknown locale");
+        assert!(
+            err.to_string()
+                .contains("strftime filter: unknown locale 'xx'"),
+            "{err}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod calendar_render_tests {
+    use crate::device::calendar;
+    use crate::models::server::template_with;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_calendar_events_are_drawn_by_the_calendar_filters() {
+        let tz: chrono_tz::Tz = "Europe/Berlin".parse().unwrap();
+        let now = tz.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let events = calendar::parse(concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Standup\r\n",
+            "DTSTART;TZID=Europe/Berlin:20261012T090000\r\nDURATION:PT15M\r\n",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:2\r\nSUMMARY:Brunch & chat\r\n",
+            "DTSTART;TZID=Europe/Berlin:20261018T110000\r\nDURATION:PT2H\r\nEND:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        ))
+        .unwrap();
+        let events: Vec<liquid::model::Value> = calendar::upcoming(&events, now, 7)
+            .iter()
+            .map(|o| o.to_object(now).into())
+            .collect();
+        let globals = liquid::object!({
+            "date": "2026-10-18",
+            "calendar": { "work": { "events": events } },
+        });
+        let template = template_with(
+            "{{ calendar.work.events.size }}|\
+             {{ calendar.work.events[0].start_time }} {{ calendar.work.events[0].ongoing }}|\
+             {{ calendar.work.events | agenda: width: 300 }}|\
+             {{ calendar.work | month_calendar: width: 280, height: 240 }}",
+        );
+        let out = template.render(globals).expect("calendar filters render");
+        let parts: Vec<&str> = out.split('|').collect();
+        // Brunch, then standups on Monday and Friday.
+        assert_eq!(parts[0], "3");
+        assert_eq!(parts[1], "11:00 true");
+        assert!(parts[2].contains(">Today</text>"), "{out}");
+        assert!(parts[2].contains(">Brunch &amp; chat</text>"), "{out}");
+        assert!(parts[2].contains(">Fri 23 Oct</text>"), "{out}");
+        assert!(parts[3].contains(">October 2026</text>"), "{out}");
+        assert!(parts[3].contains(r#"fill="white">18</text>"#), "{out}");
+    }
 }
 
 impl PrometheusQuery {
@@ -232,3 +693,66 @@ impl PrometheusQuery {
             .unwrap_or_else(|| vec![]))
     }
 }
+
+#[cfg(test)]
+mod partial_render_tests {
+    use crate::device::partials::RenderPartials;
+    use crate::models::server::template_with;
+
+    #[test]
+    fn test_partials_are_resolved_by_include_and_render() {
+        let partials = RenderPartials::new([
+            (
+                "header".to_string(),
+                "<text>{{ device.friendly_id }} {{ title }}</text>".to_string(),
+            ),
+            ("footer".to_string(), "<text>{{ note }}</text>".to_string()),
+            (
+                "page".to_string(),
+                r#"{% include "header" title: "Hi" %}{% render "footer", note: "n" %}"#.to_string(),
+            ),
+        ]);
+        let globals = liquid::object!({ "device": { "friendly_id": "ABC" } });
+        let out = partials
+            .scope(|| template_with(r#"{% include "page" %}"#).render(globals.clone()))
+            .expect("partials render");
+        // `render` only sees the variables passed to it.
+        assert_eq!(out, "<text>ABC Hi</text><text>n</text>");
+
+        let err = partials
+            .scope(|| template_with(r#"{% render "sidebar" %}"#).render(globals))
+            .expect_err("unknown partial");
+        assert!(
+            err.to_string()
+                .contains("unknown partial 'sidebar' (known: footer, header, page)"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_partial_cycles_fail_the_render() {
+        let partials = RenderPartials::new([
+            ("a".to_string(), r#"{% include "b" %}"#.to_string()),
+            ("b".to_string(), "{% include name %}".to_string()),
+            ("c".to_string(), "c".to_string()),
+        ]);
+        let err = partials
+            .scope(|| {
+                template_with(r#"{% include "a" %}"#).render(liquid::object!({ "name": "a" }))
+            })
+            .expect_err("cycle");
+        assert!(
+            err.to_string().contains("partial cycle: a → b → a"),
+            "{err}"
+        );
+
+        // The same partial may still appear twice side by side.
+        let out = partials
+            .scope(|| {
+                template_with(r#"{% include "b" %}{% include "b" %}"#)
+                    .render(liquid::object!({ "name": "c" }))
+            })
+            .expect("no cycle");
+        assert_eq!(out, "cc");
+    }
+}